@@ -0,0 +1,60 @@
+/*
+ * C ABI of the Transparente background removal engine (libalphasvg).
+ *
+ * Generated from src/ffi.rs, regenerate after changing it with:
+ *     cbindgen --config cbindgen.toml --output include/alphasvg.h
+ */
+
+#ifndef ALPHASVG_H
+#define ALPHASVG_H
+
+/* Generated by cbindgen, do not edit by hand. */
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Removes the background of the image at `input` and writes a transparent PNG to `output`.
+ *
+ * `opts_json` may be null for the defaults, or an object with any of:
+ *
+ * ```text
+ * {"model": "auto", "max_megapixels": 40.0, "upscale_mask": false,
+ *  "preprocess": {"auto_levels": false, "white_balance": false,
+ *                 "exposure_ev": 0.0, "deblock": false, "denoise": 0.0,
+ *                 "transform": {"quarter_turns": 0, "flip_horizontal": false,
+ *                               "flip_vertical": false, "straighten": 0.0}},
+ *  "roi": {"x": 0, "y": 0, "width": 512, "height": 512}}
+ * ```
+ *
+ * Returns 0 on success, 2 for invalid arguments, 3 when the image cannot be
+ * decoded or encoded, 4 when the model fails to load, 5 when it cannot be
+ * downloaded, 6 when inference fails, 7 when a file cannot be read or written
+ * and 101 on an internal error; [`alphasvg_last_error`] then describes the
+ * failure. `output` is only replaced once the whole image is written.
+ *
+ * # Safety
+ *
+ * `input` and `output` must be valid NUL-terminated strings, and `opts_json`
+ * either null or a valid NUL-terminated string.
+ */
+int alphasvg_remove_background(const char *input, const char *output, const char *opts_json);
+
+/**
+ * Message of the last failed call on this thread, or null after a success.
+ *
+ * The string is owned by the library and valid until the next call on this thread.
+ */
+const char *alphasvg_last_error(void);
+
+/**
+ * Version of the library, as a static string.
+ */
+const char *alphasvg_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ALPHASVG_H */
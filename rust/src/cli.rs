@@ -2,24 +2,155 @@
 //!
 //! Handles batch image processing when run from the command line.
 
-use std::path::Path;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use image::DynamicImage;
 use walkdir::WalkDir;
-use anyhow::Result;
+use anyhow::{Result, Context};
 
-use crate::generators::{self, LogOutput, ModelState, ModelType};
+use crate::dedup::{self, DedupMode};
+use crate::error::AlphasvgError;
+use crate::estimate::{self, SizeEstimates};
+use crate::generators::{self, AlphaOptions, LogOutput, ModelType, SvgOptions};
+use crate::generators::alpha::AlphaOutput;
+use crate::generators::{confidence, desktop_thumbnail, preprocess};
+use crate::generators::{denoise, fidelity, ocr, text_regions, upscale};
+use crate::generators::bokeh::{self, BokehOptions};
+use crate::generators::params::Params;
+use crate::generators::registry::{self, GenerateContext, Section};
+use crate::generators::watermark::{self, WatermarkOptions};
+use crate::generators::rasterize::{self, RasterScale};
+use crate::generators::path_model;
+use crate::generators::metadata::{ImageMetadata, MetadataMapping};
+use crate::journal::{Journal, JournalEntry};
+use crate::gui::processing;
 use crate::lang::LanguageManager;
+use crate::naming::{OutputKind, OutputNaming};
+use crate::organize::OrganizeRules;
+use crate::power;
+use crate::provenance::Provenance;
+use crate::report::{BatchReport, Coverage, FileIssue, ImageReport, QualityCheck, SummaryFormat};
+use crate::settings::Settings;
+use crate::stats;
+use crate::temp;
+use crate::upload::{self, Destination};
+
+/// Options for a CLI batch run.
+pub struct BatchOptions {
+    /// Only list the outputs with their estimated sizes, see [`crate::estimate`].
+    pub dry_run: bool,
+    /// Skip inputs already recorded in the output directory's job journal.
+    pub resume: bool,
+    /// Leave near-duplicate inputs out of the batch, see [`crate::dedup`].
+    pub dedup: Option<DedupMode>,
+    /// Largest hash distance, in bits, at which two inputs count as duplicates.
+    pub dedup_distance: u32,
+    /// Cut-outs that may wait for their outputs while the next image runs
+    /// inference on another thread; 0 processes one image at a time.
+    pub pipeline_depth: usize,
+    /// Model to run, or `ModelType::Auto` to choose per image.
+    pub model: ModelType,
+    pub alpha: AlphaOptions,
+    pub svg: SvgOptions,
+    /// Also render every SVG output to PNG at this scale.
+    pub rasterize: Option<RasterScale>,
+    /// Also write every SVG output as a JSON path model, see [`path_model`].
+    pub path_json: bool,
+    /// Vector outputs trace the input as-is instead of the cut-out.
+    pub vectorize_original: bool,
+    /// Skip the SVG generators for cut-outs flagged by the quality check.
+    pub skip_suspect: bool,
+    /// Score every vector output against its source, see [`fidelity`].
+    pub fidelity: bool,
+    /// Wait before each image while on battery or throttling, see [`power`].
+    pub pause_on_battery: bool,
+    /// Write a mask confidence heatmap per image and add its stats to the report.
+    pub confidence: bool,
+    /// Also write freedesktop thumbnails of the outputs for Linux file managers.
+    pub desktop_thumbnails: bool,
+    /// Also write the original with its background blurred.
+    pub bokeh: Option<BokehOptions>,
+    /// Outputs to write, see [`registry`].
+    pub generators: Vec<OutputKind>,
+    /// Generator parameters from `--set`, see [`crate::generators::params`].
+    pub params: Params,
+    /// Watermark stamped on the PNG/JPEG outputs.
+    pub watermark: Option<WatermarkOptions>,
+    /// Super-resolution factor (2 or 4) applied before the SVG generators.
+    pub upscale: Option<u32>,
+    /// Extra denoise strength for individual generators' input.
+    pub denoise_for: Vec<(OutputKind, f32)>,
+    /// End-of-batch summary format.
+    pub summary: SummaryFormat,
+    /// Output name template, see [`crate::naming`].
+    pub name_template: String,
+    /// Moves originals and outputs after each image, see [`crate::organize`].
+    pub organize: Option<OrganizeRules>,
+    /// Remote destination the outputs are uploaded to after the batch.
+    pub upload: Option<Destination>,
+    /// Parallel uploads.
+    pub upload_jobs: usize,
+    /// Input metadata carried over to the SVG outputs.
+    pub metadata_map: MetadataMapping,
+}
+
+/// Outcome of a batch run, mapped to the process exit code.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BatchStatus {
+    Success = 0,
+    InvalidInput = 2,
+    FileErrors = 3,
+    ModelError = 4,
+    ModelDownload = 5,
+    Inference = 6,
+    Io = 7,
+    /// A panic, reported by the C ABI; a panicking CLI exits with the same code.
+    Internal = 101,
+}
+
+impl BatchStatus {
+    /// Status for a run that failed with `error`; unclassified failures count as file errors.
+    pub fn for_error(error: &anyhow::Error) -> Self {
+        match AlphasvgError::find(error) {
+            Some(AlphasvgError::ModelDownload { .. }) => BatchStatus::ModelDownload,
+            Some(AlphasvgError::ModelLoad { .. }) => BatchStatus::ModelError,
+            Some(AlphasvgError::Inference { .. }) => BatchStatus::Inference,
+            Some(AlphasvgError::Io { .. }) => BatchStatus::Io,
+            Some(AlphasvgError::Decode { .. } | AlphasvgError::Encode { .. }) | None => BatchStatus::FileErrors,
+        }
+    }
+}
+
+impl From<BatchStatus> for ExitCode {
+    fn from(status: BatchStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
 
 /// Processes all images in a directory.
-pub fn process_batch(input_dir: &str, output_dir: &str, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+///
+/// Per-file failures are logged and the batch continues; a model failure aborts it.
+pub fn process_batch(input_dir: &str, output_dir: &str, opts: &BatchOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<BatchStatus> {
     let input_path = Path::new(input_dir);
     let output_path = Path::new(output_dir);
 
-    if !input_path.exists() {
-        println!("❌ Input directory not found: {}", input_dir);
-        return Ok(());
+    if !input_path.is_dir() {
+        logger.error(lang.t_args("cli_input_not_found", &[input_dir]));
+        return Ok(BatchStatus::InvalidInput);
     }
 
-    std::fs::create_dir_all(output_path)?;
+    if !opts.dry_run {
+        std::fs::create_dir_all(output_path)?;
+        // Leftovers of runs that were killed; recent ones may belong to a run still going
+        match temp::clean(output_path, &temp::CleanOptions::default()) {
+            Ok(removed) if !removed.is_empty() => logger.verbose(lang.t_args("cli_temp_cleaned", &[removed.len()])),
+            Ok(_) => {}
+            Err(e) => logger.error(format!("{:#}", e)),
+        }
+    }
 
     let extensions = ["png", "jpg", "jpeg"];
     let mut files = Vec::new();
@@ -31,7 +162,7 @@ pub fn process_batch(input_dir: &str, output_dir: &str, lang: &LanguageManager,
             if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
                 if extensions.contains(&ext.to_lowercase().as_str()) {
                     let name = path.file_name().unwrap().to_str().unwrap();
-                    if !name.contains(".temp.") && !name.contains(".vtrace_temp.") {
+                    if !temp::is_temp(name) {
                         files.push(path.to_path_buf());
                     }
                 }
@@ -40,46 +171,540 @@ pub fn process_batch(input_dir: &str, output_dir: &str, lang: &LanguageManager,
     }
 
     if files.is_empty() {
-        println!("ℹ️ No image files found in {}", input_dir);
-        return Ok(());
+        logger.error(lang.t_args("cli_no_images", &[input_dir]));
+        return Ok(BatchStatus::InvalidInput);
     }
 
-    println!("🚀 Processing {} images modularly...", files.len());
+    let model_type = opts.model;
+    let model_name = model_type.name();
+    let mut report = BatchReport { model: model_name.clone(), generators: opts.generators.clone(), total: files.len(), ..Default::default() };
+
+    // Preflight: reject files whose headers cannot be decoded before spending time on them
+    let files: Vec<PathBuf> = files.into_iter()
+        .filter(|file_path| match preflight(file_path) {
+            Ok(()) => true,
+            Err(e) => {
+                logger.error(lang.t_args("cli_quarantined", &[lang.format_file_name(file_path), format!("{:#}", e)]));
+                report.quarantine.push(FileIssue { input: file_path.clone(), reason: format!("{:#}", e) });
+                false
+            }
+        })
+        .collect();
 
-    for file_path in files {
-        process_single_image(&file_path, output_path, lang, logger)?;
+    // Near-duplicates are dropped here; in link mode they get the outputs of their group later
+    let mut files = files;
+    if opts.dedup.is_some() {
+        logger.send(lang.t_args("cli_dedup_scan", &[files.len()]));
+        report.duplicates = dedup::find_duplicates(&files, opts.dedup_distance);
+        for group in &report.duplicates {
+            let names: Vec<String> = group.duplicates.iter().map(|d| lang.format_file_name(d)).collect();
+            logger.send(lang.t_args("cli_dedup_group", &[group.duplicates.len().to_string(), lang.format_file_name(&group.keep), names.join(", ")]));
+            files.retain(|f| !group.duplicates.contains(f));
+            report.skipped += group.duplicates.len();
+        }
+    }
+
+    if opts.dry_run {
+        dry_run(&files, output_path, opts, lang, logger)?;
+        return Ok(BatchStatus::Success);
+    }
+    logger.send(lang.t_args("cli_processing_n", &[files.len()]));
+
+    let mut journal = Journal::open(output_path)?;
+    // Statistics are informational; failing to remember the folder must not fail the batch
+    let _ = stats::remember_folder(output_path);
+    if let Some(rules) = &opts.organize {
+        logger.verbose(lang.t_args("cli_job_id", &[&rules.job_id]));
+    }
+
+    if opts.resume {
+        files.retain(|file_path| {
+            let completed = journal.is_completed(file_path);
+            if completed {
+                logger.verbose(lang.t_args("cli_skipping", &[lang.format_file_name(file_path)]));
+                report.skipped += 1;
+            }
+            !completed
+        });
+    }
+
+    let start = Instant::now();
+    let mut status = BatchStatus::Success;
+    std::thread::scope(|scope| -> Result<()> {
+        // With a pipeline depth, inference runs ahead on its own thread while this one writes the outputs
+        let cut_outs: Box<dyn Iterator<Item = StagedCutOut>> = if opts.pipeline_depth > 0 {
+            let (tx, rx) = mpsc::sync_channel(opts.pipeline_depth);
+            scope.spawn(move || {
+                for file_path in files {
+                    let staged = StagedCutOut::run(file_path, output_path, model_type, opts, lang, logger);
+                    let aborts = staged.result.as_ref().err().and_then(AlphasvgError::find).is_some_and(AlphasvgError::aborts_batch);
+                    // The receiver is gone once the batch has been aborted
+                    if tx.send(staged).is_err() || aborts {
+                        break;
+                    }
+                }
+            });
+            Box::new(rx.into_iter())
+        } else {
+            Box::new(files.into_iter().map(|file_path| StagedCutOut::run(file_path, output_path, model_type, opts, lang, logger)))
+        };
+
+        for StagedCutOut { file_path, result, inference } in cut_outs {
+            let outputs_start = Instant::now();
+            match result.and_then(|cut_out| write_outputs(&file_path, output_path, cut_out, opts, lang, logger)) {
+                Ok(mut image) => {
+                    let elapsed = inference + outputs_start.elapsed();
+                    logger.verbose(lang.t_args("cli_image_done", &[lang.format_file_name(&file_path), lang.format_duration(elapsed)]));
+                    let entry = JournalEntry::new(&file_path, &model_name, elapsed.as_millis() as u64, image.outputs.clone())?;
+                    journal.record(&entry)?;
+                    image.duration_ms = entry.duration_ms;
+                    if opts.dedup == Some(DedupMode::Link) {
+                        link_duplicates(&file_path, &image.outputs, &report.duplicates, &opts.name_template, lang, logger);
+                    }
+                    match opts.organize.as_ref().map(|rules| rules.archive_input(&file_path)) {
+                        Some(Ok(Some(moved))) => logger.verbose(lang.t_args("cli_original_moved", &[lang.format_file_name(&file_path), moved.display().to_string()])),
+                        Some(Err(e)) => logger.error(lang.t_args("cli_original_not_moved", &[lang.format_file_name(&file_path), format!("{:#}", e)])),
+                        Some(Ok(None)) | None => {}
+                    }
+                    report.processed.push(image);
+                }
+                Err(e) if AlphasvgError::find(&e).is_some_and(AlphasvgError::aborts_batch) => {
+                    logger.error(lang.t_args("cli_batch_aborted", &[format!("{:#}", e)]));
+                    log_hint(&e, lang, logger);
+                    status = BatchStatus::for_error(&e);
+                    break;
+                }
+                Err(e) => {
+                    logger.error(lang.t_args("cli_file_error", &[lang.format_file_name(&file_path), format!("{:#}", e)]));
+                    log_hint(&e, lang, logger);
+                    report.failed.push(FileIssue { input: file_path, reason: format!("{:#}", e) });
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    if let Some(destination) = &opts.upload {
+        let files: Vec<PathBuf> = report.processed.iter().flat_map(|image| image.outputs.iter().cloned()).collect();
+        if !files.is_empty() {
+            logger.send(lang.t_args("cli_uploading", &[files.len()]));
+            let (uploaded, failed) = upload::upload_all(&files, output_path, destination, opts.upload_jobs);
+            for remote in &uploaded {
+                logger.verbose(lang.t_args("cli_uploaded", &[lang.format_file_name(&remote.local), remote.url.clone()]));
+            }
+            for (file, reason) in failed {
+                logger.error(lang.t_args("cli_upload_failed", &[lang.format_file_name(&file), reason.clone()]));
+                report.upload_failed.push(FileIssue { input: file, reason });
+            }
+            logger.send(lang.t_args("cli_upload_done", &[uploaded.len(), files.len()]));
+            report.uploads = uploaded;
+        }
+    }
+
+    if status == BatchStatus::Success && !(report.failed.is_empty() && report.upload_failed.is_empty()) {
+        status = BatchStatus::FileErrors;
+    }
+
+    if !report.quarantine.is_empty() {
+        logger.error(format!("\n{}", lang.t_args("cli_quarantine_summary", &[report.quarantine.len(), report.total])));
+    }
+    if !report.failed.is_empty() {
+        logger.error(lang.t_args("cli_failed_summary", &[report.failed.len(), report.total]));
+    }
+    let suspect: Vec<String> = report.processed.iter().filter(|i| i.quality.suspect).map(|i| lang.format_file_name(&i.input)).collect();
+    if !suspect.is_empty() {
+        logger.error(lang.t_args("cli_suspect_summary", &[suspect.len().to_string(), suspect.join(", ")]));
+    }
+    let low_fidelity: Vec<String> = report.processed.iter()
+        .flat_map(|i| i.fidelity.iter().filter(|f| f.low))
+        .map(|f| lang.format_file_name(&f.output))
+        .collect();
+    if !low_fidelity.is_empty() {
+        logger.error(lang.t_args("cli_fidelity_summary", &[low_fidelity.len().to_string(), low_fidelity.join(", ")]));
+    }
+    if !report.processed.is_empty() {
+        logger.send(format!("\n{}", report.summary_table(lang)));
+    }
+    logger.send(lang.t_args("processed_n", &[report.processed.len().to_string(), lang.format_duration(start.elapsed())]));
+
+    report.provenance = provenance(opts);
+    let report_path = report.write(output_path)?;
+    logger.verbose(lang.t_args("cli_report_written", &[report_path.display()]));
+    if opts.summary == SummaryFormat::Csv {
+        let csv_path = report.write_summary_csv(output_path)?;
+        logger.send(lang.t_args("cli_summary_written", &[csv_path.display()]));
+    }
+
+    Ok(status)
+}
+
+/// Links the outputs of `kept` for each of its near-duplicates; failures are logged.
+fn link_duplicates(kept: &Path, outputs: &[PathBuf], groups: &[dedup::DuplicateGroup], template: &str, lang: &LanguageManager, logger: &LogOutput) {
+    let Some(group) = groups.iter().find(|group| group.keep == kept) else { return };
+    for duplicate in &group.duplicates {
+        match dedup::link_outputs(kept, duplicate, outputs, template) {
+            Ok(linked) => logger.verbose(lang.t_args("cli_dedup_linked", &[linked.len().to_string(), lang.format_file_name(duplicate)])),
+            Err(e) => logger.error(lang.t_args("cli_dedup_link_failed", &[lang.format_file_name(duplicate), format!("{:#}", e)])),
+        }
+    }
+}
+
+/// Lists the outputs each of `files` would get, with the sizes estimated from earlier runs.
+fn dry_run(files: &[PathBuf], output_dir: &Path, opts: &BatchOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    let estimates = SizeEstimates::load();
+    let mut kinds = opts.generators.clone();
+    kinds.extend(opts.confidence.then_some(OutputKind::Confidence));
+    kinds.extend(opts.bokeh.is_some().then_some(OutputKind::Bokeh));
+
+    logger.send(lang.t_args("cli_dry_run", &[files.len()]));
+    let mut total = 0;
+    let mut unknown = 0;
+    for file in files {
+        let megapixels = estimate::megapixels(file).unwrap_or_default();
+        logger.send(lang.t_args("cli_dry_run_file", &[lang.format_file_name(file), lang.format_number(megapixels, 1)]));
+        for (kind, path) in output_paths(file, output_dir, &opts.name_template, &kinds)? {
+            let path = match registry::get(kind) {
+                Some(generator) => path.with_extension(generator.extension(&opts.params)),
+                None => path,
+            };
+            let size = match estimates.estimate(kind, megapixels) {
+                Some(bytes) => {
+                    total += bytes;
+                    format!("~{}", lang.format_size(bytes))
+                }
+                None => {
+                    unknown += 1;
+                    "?".to_string()
+                }
+            };
+            logger.send(format!("   {:<40} {:>10}", lang.format_file_name(&path), size));
+        }
     }
+    logger.send(lang.t_args("cli_dry_run_total", &[lang.format_size(total)]));
+    if unknown > 0 {
+        logger.send(lang.t_args("cli_dry_run_unknown", &[unknown]));
+    }
+    Ok(())
+}
 
-    println!("\n✅ All image processing complete.");
+/// Checks that a file's format can be detected and its header decoded.
+fn preflight(path: &Path) -> Result<()> {
+    image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_dimensions()
+        .context("Unreadable image header")?;
     Ok(())
 }
 
-/// Processes a single image through all generation pipelines.
-fn process_single_image(input_path: &Path, output_dir: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
-    let file_name = input_path.file_stem().unwrap().to_str().unwrap();
-    let base_name = format!("{}_alpha", file_name);
+/// Replays the last job started from the GUI without opening a window.
+pub fn run_last(lang: &LanguageManager, logger: &LogOutput) -> Result<BatchStatus> {
+    let Some(config) = Settings::load().last_job else {
+        logger.error(lang.t("cli_no_last_job"));
+        return Ok(BatchStatus::InvalidInput);
+    };
+
+    if !config.input.is_file() || !config.output.is_dir() {
+        logger.error(lang.t("error_invalid_paths"));
+        return Ok(BatchStatus::InvalidInput);
+    }
+
+    logger.send(lang.t_args("cli_replaying", &[config.input.display(), config.output.display()]));
+    let start = Instant::now();
+    let result = processing::run_pipeline(&config, lang, logger);
+    processing::record_history(config, start.elapsed(), result.as_ref().err().map(|e| e.to_string()));
+    match result {
+        Ok(()) => Ok(BatchStatus::Success),
+        Err(e) => {
+            logger.error(lang.t_args("cli_error", &[format!("{:#}", e)]));
+            log_hint(&e, lang, logger);
+            Ok(BatchStatus::for_error(&e))
+        }
+    }
+}
 
-    let alpha_path = output_dir.join(format!("{}.png", base_name));
-    let gray_path = output_dir.join(format!("{}_gray.svg", base_name));
-    let halftone_path = output_dir.join(format!("{}_halftone.svg", base_name));
-    let lineart_path = output_dir.join(format!("{}_lineart.svg", base_name));
-    let color_logo_path = output_dir.join(format!("{}_color_logo.svg", base_name));
-    let color_illus_path = output_dir.join(format!("{}_color_illus.svg", base_name));
-    let thumb_path = output_dir.join(format!("{}_thumb.png", base_name));
+/// Logs how to fix `error` when its kind has a remediation hint.
+fn log_hint(error: &anyhow::Error, lang: &LanguageManager, logger: &LogOutput) {
+    if let Some(key) = AlphasvgError::find(error).and_then(AlphasvgError::hint_key) {
+        logger.error(lang.t(key));
+    }
+}
 
-    println!("\n📦 Processing: {:?}...", input_path.file_name().unwrap());
+/// Returns the output paths the CLI produces for one input, in registry order.
+pub fn output_paths(input_path: &Path, output_dir: &Path, template: &str, kinds: &[OutputKind]) -> Result<Vec<(OutputKind, PathBuf)>> {
+    let naming = OutputNaming::new(input_path, None, template)?;
+    Ok(registry::REGISTRY.iter()
+        .map(|g| g.kind())
+        .filter(|kind| kinds.contains(kind))
+        .map(|kind| (kind, naming.path(output_dir, kind)))
+        .collect())
+}
 
-    // 1. Generate the AI-processed Alpha PNG first
-    let dummy_status = std::sync::Arc::new(std::sync::Mutex::new(ModelState::Unloaded));
-    let img = generators::generate_alpha_png(input_path, Some(&alpha_path), lang, logger, &dummy_status, ModelType::default())?;
+/// Source image for one generator, with its extra denoise pass if configured.
+fn generator_input<'a>(img: &'a DynamicImage, kind: OutputKind, denoise_for: &[(OutputKind, f32)]) -> Cow<'a, DynamicImage> {
+    match denoise_for.iter().rev().find(|(k, _)| *k == kind) {
+        Some(&(_, strength)) if strength > 0.0 => Cow::Owned(denoise::denoise(img, strength)),
+        _ => Cow::Borrowed(img),
+    }
+}
 
-    // 2. Use the processed Alpha PNG as source for everything else
-    generators::generate_grayscale_svg(&img, &gray_path, 8, lang, logger)?;
-    generators::generate_halftone_svg(&img, &halftone_path, lang, logger)?;
-    generators::generate_lineart_svg(&img, &lineart_path, lang, logger)?;
-    generators::generate_logo(&img, &color_logo_path, lang, logger)?;
-    generators::generate_illustration(&img, &color_illus_path, lang, logger)?;
-    generators::generate_thumbnail(&img, &thumb_path, lang, logger)?;
+/// Provenance of the batch so far; the arguments reproduce it from the command line.
+fn provenance(opts: &BatchOptions) -> Provenance {
+    Provenance::collect(std::env::args().skip(1).collect(), serde_json::json!({
+        "model": opts.model.name(),
+        "params": opts.params,
+        "preprocess": opts.alpha.preprocess,
+        "roi": opts.alpha.roi,
+        "mask": opts.alpha.mask,
+        "upscale": opts.upscale,
+        "vectorize_original": opts.vectorize_original,
+    }))
+}
 
-    Ok(())
+/// First stage of an image: the cut-out and the paths of the outputs still to write.
+struct CutOut {
+    outputs: Vec<(OutputKind, PathBuf)>,
+    /// `None` when no output needs it; `original` is set then.
+    alpha: Option<AlphaOutput>,
+    /// Corrected input, for `--vectorize-original`.
+    original: Option<DynamicImage>,
+}
+
+/// A cut-out handed from the inference stage to the output stage.
+struct StagedCutOut {
+    file_path: PathBuf,
+    result: Result<CutOut>,
+    /// Time the first stage took.
+    inference: Duration,
+}
+
+impl StagedCutOut {
+    fn run(file_path: PathBuf, output_dir: &Path, model_type: ModelType, opts: &BatchOptions, lang: &LanguageManager, logger: &LogOutput) -> Self {
+        if opts.pause_on_battery {
+            power::wait_while_constrained(lang, logger);
+        }
+        let start = Instant::now();
+        let result = cut_out(&file_path, output_dir, model_type, opts, lang, logger);
+        Self { file_path, result, inference: start.elapsed() }
+    }
+}
+
+/// Runs the model on an image, writing the Alpha PNG if it is requested.
+/// Inference is skipped when no output needs the cut-out.
+#[tracing::instrument(name = "cut_out", skip_all, fields(file = %input_path.display()))]
+fn cut_out(input_path: &Path, output_dir: &Path, model_type: ModelType, opts: &BatchOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<CutOut> {
+    let outputs = output_paths(input_path, output_dir, &opts.name_template, &opts.generators)?;
+
+    logger.send(format!("\n{}", lang.t_args("cli_processing_file", &[lang.format_file_name(input_path)])));
+    logger.debug(lang.t_args("cli_outputs", &[format!("{:?}", outputs.iter().map(|(_, p)| p).collect::<Vec<_>>())]));
+
+    let vector_only = |kind: &OutputKind| opts.vectorize_original && registry::get(*kind).is_some_and(|g| g.is_vector());
+    let needs_cutout = opts.confidence || opts.bokeh.is_some()
+        || outputs.iter().any(|(kind, _)| !vector_only(kind) && registry::get(*kind).is_none_or(|g| g.needs_cutout()));
+    let original = if opts.vectorize_original || !needs_cutout {
+        Some(preprocess::open(input_path, &opts.alpha.preprocess, lang, logger)?)
+    } else {
+        None
+    };
+    if !needs_cutout {
+        logger.verbose(lang.t("cli_cutout_skipped"));
+        return Ok(CutOut { outputs, alpha: None, original });
+    }
+
+    let alpha_path = outputs.iter().find(|(kind, _)| *kind == OutputKind::Alpha).map(|(_, p)| p.as_path());
+    let alpha = generators::generate_alpha_png(input_path, alpha_path, &opts.alpha, lang, logger, model_type)?;
+    Ok(CutOut { outputs, alpha: Some(alpha), original })
+}
+
+/// Writes every other output of an image from its cut-out.
+/// Returns its report entry; the caller fills in the duration.
+#[tracing::instrument(name = "image", skip_all, fields(file = %input_path.display()))]
+fn write_outputs(input_path: &Path, output_dir: &Path, cut_out: CutOut, opts: &BatchOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<ImageReport> {
+    let CutOut { mut outputs, alpha, original } = cut_out;
+    let alpha_path = outputs.iter().find(|(kind, _)| *kind == OutputKind::Alpha).map(|(_, path)| path.clone());
+    let mut produced: Vec<PathBuf> = Vec::new();
+    let model = alpha.as_ref().and_then(|a| a.model.clone());
+    let downscale_retry = alpha.as_ref().and_then(|a| a.retried_at);
+    let inference = alpha.as_ref().map_or(Duration::ZERO, |a| a.inference);
+    let has_cutout = alpha.is_some();
+    // Without a cut-out every output reads the corrected input
+    let (img, original) = match alpha {
+        Some(alpha) => (alpha.image, original),
+        None => (original.context("No cut-out or input image to write the outputs from")?, None),
+    };
+
+    // Flag blank, full or shattered cut-outs before the expensive vector outputs
+    let (coverage, quality) = if has_cutout {
+        let coverage = Coverage::measure(&img);
+        let quality = QualityCheck::measure(&img, &coverage);
+        (coverage, quality)
+    } else {
+        (Coverage::default(), QualityCheck::default())
+    };
+    if quality.suspect {
+        let issues: Vec<String> = quality.issues.iter().map(|issue| lang.t(issue.label_key())).collect();
+        logger.error(lang.t_args("cli_suspect", &[lang.format_file_name(input_path), issues.join(", ")]));
+        if opts.skip_suspect {
+            outputs.retain(|(kind, _)| registry::get(*kind).is_none_or(|g| !g.is_vector()));
+            logger.verbose(lang.t("cli_suspect_skipped"));
+        }
+    }
+
+    // 1. Use the processed Alpha PNG as source for everything else
+    let any_vector = outputs.iter().filter_map(|(kind, _)| registry::get(*kind)).any(|g| g.is_vector());
+    let vector_base = original.as_ref().unwrap_or(&img);
+    let upscaled = match opts.upscale {
+        Some(factor) if any_vector => upscale::upscale(vector_base, factor, opts.svg.max_working_px, lang, logger)?,
+        _ => None,
+    };
+    let svg_source = upscaled.as_ref().unwrap_or(vector_base);
+    let metadata = match ImageMetadata::read(input_path) {
+        Ok(meta) => opts.metadata_map.apply(&meta),
+        Err(e) => {
+            logger.verbose(lang.t_args("cli_metadata_unreadable", &[lang.format_file_name(input_path), format!("{:#}", e)]));
+            None
+        }
+    };
+    if let Some(title) = metadata.as_ref().and_then(|m| m.title.as_ref()) {
+        logger.verbose(lang.t_args("cli_metadata_title", &[title]));
+    }
+    let text_regions = match opts.svg.text {
+        Some(_) if any_vector => tracing::info_span!("text_regions").in_scope(|| text_regions::detect(svg_source)),
+        _ => Vec::new(),
+    };
+    if !text_regions.is_empty() {
+        logger.error(lang.t_args("cli_text_regions", &[lang.format_file_name(input_path), text_regions.len().to_string()]));
+    }
+    // Recognized text is optional: a failing recognizer leaves the outputs without it
+    let ocr_lines = match (&opts.svg.ocr, outputs.first()) {
+        (Some(language), Some((_, path))) if any_vector => tracing::info_span!("ocr").in_scope(|| ocr::recognize(svg_source, language, path))
+            .unwrap_or_else(|e| {
+                logger.error(lang.t_args("cli_ocr_failed", &[lang.format_file_name(input_path), format!("{:#}", e)]));
+                Vec::new()
+            }),
+        _ => Vec::new(),
+    };
+    let svg_opts = SvgOptions {
+        metadata: metadata.clone(),
+        provenance: Some(provenance(opts)),
+        text_regions: text_regions.clone(),
+        ocr_lines: ocr_lines.clone(),
+        ..opts.svg.clone()
+    };
+    let ctx = GenerateContext { input: input_path, transform: opts.alpha.preprocess.transform, cutout: &img, svg: &svg_opts, params: &opts.params, lang, logger };
+    // Outputs by kind, for the size estimates of later dry runs
+    let mut sized: Vec<(OutputKind, PathBuf)> = Vec::new();
+    for (kind, path) in outputs {
+        let Some(generator) = registry::get(kind) else { continue };
+        let _span = tracing::info_span!("generate", kind = ?kind).entered();
+        let path = path.with_extension(generator.extension(&opts.params));
+        let path = if opts.svg.compress && generator.is_vector() { path.with_extension("svgz") } else { path };
+        let source = generator_input(if generator.is_vector() { svg_source } else { &img }, kind, &opts.denoise_for);
+        generator.generate(&source, &path, &ctx)?;
+        sized.push((kind, path.clone()));
+        produced.push(path);
+    }
+
+    // 2. Optional fidelity scores, raster renders and path models of the vector outputs
+    let mut fidelities = Vec::new();
+    if opts.fidelity {
+        for (kind, path) in &sized {
+            let Some(generator) = registry::get(*kind).filter(|g| g.is_vector() && fidelity::is_measured(*kind)) else { continue };
+            // The score is informative: an output that cannot be rendered back does not fail the image
+            let fidelity = match tracing::info_span!("fidelity").in_scope(|| fidelity::measure(path, svg_source, generator.section() == Section::SvgColor)) {
+                Ok(fidelity) => fidelity,
+                Err(e) => {
+                    logger.error(lang.t_args("cli_fidelity_failed", &[lang.format_file_name(path), format!("{:#}", e)]));
+                    continue;
+                }
+            };
+            let args = [lang.format_file_name(path), lang.format_number(fidelity.ssim, 2), lang.format_number(fidelity.delta_e, 1)];
+            if fidelity.low {
+                logger.error(lang.t_args("cli_fidelity_low", &args));
+            } else {
+                logger.verbose(lang.t_args("cli_fidelity", &args));
+            }
+            fidelities.push(fidelity);
+        }
+    }
+    let svgs: Vec<PathBuf> = produced.iter().filter(|p| p.extension().is_some_and(|e| e == "svg" || e == "svgz")).cloned().collect();
+    if opts.path_json {
+        for svg in &svgs {
+            let json = path_model::export_svg(svg)?;
+            logger.verbose(lang.t_args("cli_path_model", &[lang.format_file_name(&json)]));
+            produced.push(json);
+        }
+    }
+    if let Some(scale) = opts.rasterize {
+        for svg in svgs {
+            let png = rasterize::rasterize_svg(&svg, scale)?;
+            logger.verbose(lang.t_args("cli_rendered", &[lang.format_file_name(&png), scale.to_string()]));
+            produced.push(png);
+        }
+    }
+
+    // 3. Optional extras from the original photo and the cut-out
+    if let Some(bokeh_opts) = &opts.bokeh {
+        let path = OutputNaming::new(input_path, None, &opts.name_template)?.path(output_dir, OutputKind::Bokeh);
+        let original = opts.alpha.preprocess.transform.apply(image::open(input_path).map_err(|e| AlphasvgError::decode(input_path, e))?);
+        bokeh::generate_bokeh(&original, &img, &path, bokeh_opts, lang, logger)?;
+        sized.push((OutputKind::Bokeh, path.clone()));
+        produced.push(path);
+    }
+
+    // The alpha stage stamped its PNG while writing it, keeping the cut-out clean
+    if let Some(mark) = &opts.watermark {
+        for path in produced.iter().filter(|p| Some(*p) != alpha_path.as_ref() && watermark::is_stampable(p)) {
+            watermark::stamp_file(path, mark)?;
+        }
+        logger.verbose(lang.t("cli_watermark_applied"));
+    }
+
+    // 4. Optional confidence diagnostic from the cut-out's alpha channel
+    let mut stats = None;
+    if opts.confidence {
+        let path = OutputNaming::new(input_path, None, &opts.name_template)?.path(output_dir, OutputKind::Confidence);
+        let s = confidence::write_confidence_overlay(&img, &path)?;
+        logger.verbose(lang.t_args("cli_confidence", &[
+            lang.format_number((s.mean_edge_confidence * 100.0) as f64, 0),
+            lang.format_number(s.ambiguous_percent as f64, 1),
+        ]));
+        sized.push((OutputKind::Confidence, path.clone()));
+        produced.push(path);
+        stats = Some(s);
+    }
+    if let Some(megapixels) = estimate::megapixels(input_path) {
+        // Estimates are informational; failing to store them must not fail the image
+        let _ = SizeEstimates::record(&sized, megapixels);
+    }
+
+    // 5. Rename and move the outputs, then the optional file manager previews of their final paths
+    if let Some(rules) = &opts.organize {
+        produced = rules.place_outputs(&produced, output_dir)?;
+    }
+    if opts.desktop_thumbnails {
+        let previewable = ["png", "jpg", "webp", "svg", "svgz"];
+        for path in produced.iter().filter(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| previewable.contains(&e))) {
+            if let Err(e) = desktop_thumbnail::write_thumbnails(path) {
+                logger.error(lang.t_args("cli_thumbnail_failed", &[lang.format_file_name(path), e.to_string()]));
+            }
+        }
+    }
+
+    let output_bytes = produced.iter().filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum();
+    Ok(ImageReport {
+        input: input_path.to_path_buf(),
+        model,
+        downscale_retry,
+        inference_ms: inference.as_millis() as u64,
+        duration_ms: 0,
+        outputs: produced,
+        output_bytes,
+        confidence: stats,
+        coverage,
+        quality,
+        metadata,
+        text_regions,
+        ocr: ocr_lines,
+        fidelity: fidelities,
+    })
 }
@@ -3,3 +3,17 @@ pub const TOLERANCE: u8 = 15;
 pub const THUMB_WIDTH: u32 = 150;
 pub const DESPILL_STRENGTH: f32 = 0.6;
 pub const MIN_ALPHA: u8 = 8;
+pub const MAX_MEGAPIXELS: f32 = 40.0;
+pub const MAX_VECTOR_PX: u32 = 2048;
+pub const SVG_DPI: f32 = 96.0;
+pub const QA_MIN_COVERAGE: f32 = 1.0;
+pub const QA_MAX_COVERAGE: f32 = 99.0;
+pub const QA_MAX_FRAGMENTS: usize = 150;
+pub const QA_MIN_LARGEST_FRAGMENT: f32 = 40.0;
+pub const FIDELITY_MIN_SSIM: f64 = 0.75;
+pub const FIDELITY_MAX_DELTA_E: f64 = 12.0;
+pub const DEDUP_MAX_DISTANCE: u32 = 6;
+pub const PIPELINE_DEPTH: usize = 1;
+pub const HOLE_FILL_BLUR_DIVISOR: f32 = 40.0;
+pub const TEMP_STALE_SECS: u64 = 3600;
+pub const POWER_POLL_SECS: u64 = 30;
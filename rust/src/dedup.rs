@@ -0,0 +1,106 @@
+//! Near-duplicate detection for `--dedup`.
+//!
+//! Every input gets a 64-bit difference hash: the image is shrunk to 9×8
+//! grayscale pixels and each bit records whether a pixel is brighter than its
+//! right neighbour. Burst frames of the same shot differ in a handful of bits,
+//! so inputs within [`config::DEDUP_MAX_DISTANCE`] bits of the first input of
+//! a group join that group and only the first one is processed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use image::imageops::FilterType;
+use serde::Serialize;
+
+use crate::error::AlphasvgError;
+use crate::naming::OutputNaming;
+
+/// What happens to the near-duplicates of a processed input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum DedupMode {
+    /// Leave them out of the batch.
+    #[default]
+    Skip,
+    /// Leave them out, but give each one links to the outputs of the input it duplicates.
+    Link,
+}
+
+/// Inputs that look the same; only `keep` is processed.
+#[derive(Serialize, Clone, Debug)]
+pub struct DuplicateGroup {
+    pub keep: PathBuf,
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// Difference hash of the image at `path`.
+pub fn dhash(path: &Path) -> Result<u64> {
+    let img = image::open(path).map_err(|e| AlphasvgError::decode(path, e))?;
+    let small = img.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | u64::from(small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]);
+        }
+    }
+    Ok(hash)
+}
+
+/// Groups `files` whose hashes are at most `max_distance` bits apart, in input
+/// order. Files that cannot be hashed are left alone; only groups with
+/// duplicates are returned.
+pub fn find_duplicates(files: &[PathBuf], max_distance: u32) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<(u64, DuplicateGroup)> = Vec::new();
+    for file in files {
+        let Ok(hash) = dhash(file) else { continue };
+        match groups.iter_mut().find(|(first, _)| (first ^ hash).count_ones() <= max_distance) {
+            Some((_, group)) => group.duplicates.push(file.clone()),
+            None => groups.push((hash, DuplicateGroup { keep: file.clone(), duplicates: Vec::new() })),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).filter(|group| !group.duplicates.is_empty()).collect()
+}
+
+/// Gives `duplicate` the outputs of `kept`: every output named by `template`
+/// for `kept` is hard-linked (or copied, where links are not supported) under
+/// the name `template` gives it for `duplicate`. Existing files are left as
+/// they are. Returns the paths created.
+pub fn link_outputs(kept: &Path, duplicate: &Path, outputs: &[PathBuf], template: &str) -> Result<Vec<PathBuf>> {
+    let (kept_naming, duplicate_naming) = (OutputNaming::new(kept, None, template)?, OutputNaming::new(duplicate, None, template)?);
+    let mut linked = Vec::new();
+    for output in outputs {
+        let Some(name) = output.file_name().map(|n| n.to_string_lossy().into_owned()) else { continue };
+        let Some(target_name) = kept_naming.rename(&name, &duplicate_naming) else { continue };
+        let target = output.with_file_name(target_name);
+        if target.exists() {
+            continue;
+        }
+        if fs::hard_link(output, &target).is_err() {
+            fs::copy(output, &target).map_err(AlphasvgError::io(&target))?;
+        }
+        linked.push(target);
+    }
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linked_outputs_are_named_by_the_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = "{suffix}_{stem}";
+        // The kept stem also appears in the suffix part of the name
+        let outputs: Vec<PathBuf> = ["logo_logo.svg", "alpha_logo.png", "gray_logo.svgz", "notes_logo.txt"].iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for output in &outputs {
+            fs::write(output, "x").unwrap();
+        }
+
+        let linked = link_outputs(Path::new("/in/logo.jpg"), Path::new("/in/burst.jpg"), &outputs, template).unwrap();
+        let mut names: Vec<String> = linked.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        names.sort();
+        assert_eq!(names, ["alpha_burst.png", "gray_burst.svgz", "logo_burst.svg"]);
+    }
+}
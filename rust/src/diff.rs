@@ -0,0 +1,130 @@
+//! Visual comparison of two images (`alphasvg diff`).
+//!
+//! Writes a per-pixel difference heatmap and an alpha-only difference next to
+//! each other and scores the pair with SSIM, so outputs can be compared after a
+//! parameter change or checked against golden files.
+
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context, anyhow};
+use image::{GrayImage, Luma, Rgb, RgbImage, RgbaImage};
+
+/// SSIM window size and stride, in pixels.
+const WINDOW: u32 = 8;
+const STRIDE: u32 = 4;
+/// SSIM stabilizing constants for 8-bit data.
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Result of comparing two images.
+#[derive(Debug)]
+pub struct DiffResult {
+    /// Structural similarity of the images composited over mid gray (1 = identical).
+    pub ssim: f64,
+    /// Structural similarity of the alpha channels alone.
+    pub alpha_ssim: f64,
+    /// Mean absolute difference over all RGBA channels, 0..255.
+    pub mean_abs_diff: f64,
+    /// Share of pixels that differ at all, in percent.
+    pub changed_percent: f64,
+    pub heatmap: PathBuf,
+    pub alpha_heatmap: PathBuf,
+}
+
+/// Compares `a` and `b`, writing `{a}_vs_{b}_diff.png` and `..._alpha_diff.png` to `output_dir`.
+pub fn compare(a: &Path, b: &Path, output_dir: &Path) -> Result<DiffResult> {
+    let img_a = image::open(a).with_context(|| format!("Failed to open {:?}", a))?.into_rgba8();
+    let img_b = image::open(b).with_context(|| format!("Failed to open {:?}", b))?.into_rgba8();
+    if img_a.dimensions() != img_b.dimensions() {
+        return Err(anyhow!(
+            "Images differ in size: {}x{} vs {}x{}",
+            img_a.width(), img_a.height(), img_b.width(), img_b.height()
+        ));
+    }
+
+    let (width, height) = img_a.dimensions();
+    let mut heat = RgbImage::new(width, height);
+    let mut alpha_heat = RgbImage::new(width, height);
+    let mut total = 0u64;
+    let mut changed = 0usize;
+    for (x, y, pa) in img_a.enumerate_pixels() {
+        let pb = img_b.get_pixel(x, y);
+        let diffs: [u8; 4] = [0, 1, 2, 3].map(|c| pa[c].abs_diff(pb[c]));
+        let max = *diffs.iter().max().unwrap();
+        total += diffs.iter().map(|&d| d as u64).sum::<u64>();
+        if max > 0 {
+            changed += 1;
+        }
+        heat.put_pixel(x, y, heat_color(max));
+        alpha_heat.put_pixel(x, y, heat_color(diffs[3]));
+    }
+
+    let stem = |p: &Path| p.file_stem().and_then(|s| s.to_str()).unwrap_or("image").to_string();
+    let base = format!("{}_vs_{}", stem(a), stem(b));
+    let heatmap = output_dir.join(format!("{}_diff.png", base));
+    let alpha_heatmap = output_dir.join(format!("{}_alpha_diff.png", base));
+    heat.save(&heatmap)?;
+    alpha_heat.save(&alpha_heatmap)?;
+
+    let pixels = (width as f64 * height as f64).max(1.0);
+    Ok(DiffResult {
+        ssim: ssim(&composite_luma(&img_a), &composite_luma(&img_b)),
+        alpha_ssim: ssim(&alpha(&img_a), &alpha(&img_b)),
+        mean_abs_diff: total as f64 / (pixels * 4.0),
+        changed_percent: changed as f64 * 100.0 / pixels,
+        heatmap,
+        alpha_heatmap,
+    })
+}
+
+/// Black for no change, through red and yellow, to white for the largest change.
+fn heat_color(diff: u8) -> Rgb<u8> {
+    let v = diff as u32 * 3;
+    Rgb([v.min(255) as u8, v.saturating_sub(255).min(255) as u8, v.saturating_sub(510).min(255) as u8])
+}
+
+/// Luma of the image composited over mid gray, so transparency changes count too.
+pub(crate) fn composite_luma(img: &RgbaImage) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y);
+        let a = p[3] as f32 / 255.0;
+        let luma = p[0] as f32 * 0.299 + p[1] as f32 * 0.587 + p[2] as f32 * 0.114;
+        Luma([(luma * a + 128.0 * (1.0 - a)).round() as u8])
+    })
+}
+
+fn alpha(img: &RgbaImage) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| Luma([img.get_pixel(x, y)[3]]))
+}
+
+/// Mean SSIM over square windows; images smaller than a window use one window.
+pub(crate) fn ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    let (width, height) = a.dimensions();
+    let (ww, wh) = (WINDOW.min(width), WINDOW.min(height));
+    if ww == 0 || wh == 0 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    let mut windows = 0usize;
+    for y0 in (0..=height - wh).step_by(STRIDE as usize) {
+        for x0 in (0..=width - ww).step_by(STRIDE as usize) {
+            let n = (ww * wh) as f64;
+            let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in y0..y0 + wh {
+                for x in x0..x0 + ww {
+                    let (va, vb) = (a.get_pixel(x, y)[0] as f64, b.get_pixel(x, y)[0] as f64);
+                    sa += va;
+                    sb += vb;
+                    saa += va * va;
+                    sbb += vb * vb;
+                    sab += va * vb;
+                }
+            }
+            let (ma, mb) = (sa / n, sb / n);
+            let (va, vb, cov) = (saa / n - ma * ma, sbb / n - mb * mb, sab / n - ma * mb);
+            sum += ((2.0 * ma * mb + C1) * (2.0 * cov + C2)) / ((ma * ma + mb * mb + C1) * (va + vb + C2));
+            windows += 1;
+        }
+    }
+    sum / windows.max(1) as f64
+}
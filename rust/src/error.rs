@@ -0,0 +1,153 @@
+//! Failure kinds the GUI, the CLI and the C ABI act on.
+//!
+//! Library functions keep returning `anyhow::Result` so context can be added on
+//! the way up; only the points where a failure is first classified (decoding,
+//! encoding, file access, model download, loading and inference) raise an
+//! [`AlphasvgError`]. The frontends find it again in the error chain with
+//! [`AlphasvgError::find`] to pick a remediation hint, the actions the GUI error
+//! dialog offers, or an exit code. Other failures stay unclassified.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use image::ImageError;
+
+#[derive(Debug)]
+pub enum AlphasvgError {
+    /// A model could not be downloaded.
+    ModelDownload { model: String, reason: String },
+    /// The model file at `path` could not be loaded into an ONNX Runtime session.
+    ModelLoad { model: String, path: PathBuf, reason: String },
+    /// An input image could not be decoded.
+    Decode { path: PathBuf, reason: String },
+    /// An output image could not be encoded.
+    Encode { path: PathBuf, reason: String },
+    /// A loaded model failed while running on an image.
+    Inference { model: String, reason: String },
+    /// Reading or writing a file failed.
+    Io { path: PathBuf, source: io::Error },
+}
+
+/// What the user can do about a failure, offered by the GUI error dialog.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Remedy {
+    /// Run the job again, e.g. once the network is back or space is freed.
+    Retry,
+    /// Leave the input and go on with the next queued one.
+    Skip,
+    /// Open the folder of the file involved.
+    OpenFolder,
+    /// Pick another model in the form, e.g. one already downloaded.
+    ChooseModel,
+}
+
+impl AlphasvgError {
+    /// Classifies a failure to read the image at `path`.
+    pub fn decode(path: &Path, error: ImageError) -> Self {
+        match error {
+            ImageError::IoError(source) => Self::Io { path: path.to_path_buf(), source },
+            e => Self::Decode { path: path.to_path_buf(), reason: e.to_string() },
+        }
+    }
+
+    /// Classifies a failure to write the image at `path`.
+    pub fn encode(path: &Path, error: ImageError) -> Self {
+        match error {
+            ImageError::IoError(source) => Self::Io { path: path.to_path_buf(), source },
+            e => Self::Encode { path: path.to_path_buf(), reason: e.to_string() },
+        }
+    }
+
+    /// Adapter for `map_err` on I/O results concerning `path`.
+    pub fn io(path: &Path) -> impl FnOnce(io::Error) -> Self + '_ {
+        move |source| Self::Io { path: path.to_path_buf(), source }
+    }
+
+    /// The classified failure in the chain of `error`, if any.
+    pub fn find(error: &anyhow::Error) -> Option<&Self> {
+        error.chain().find_map(|e| e.downcast_ref::<Self>())
+    }
+
+    /// Locale key of a hint telling the user how to fix the failure.
+    pub fn hint_key(&self) -> Option<&'static str> {
+        match self {
+            Self::ModelDownload { .. } => Some("hint_check_network"),
+            Self::ModelLoad { .. } => Some("hint_model_corrupt"),
+            Self::Decode { .. } => Some("hint_unsupported_image"),
+            Self::Inference { .. } => Some("hint_try_other_model"),
+            Self::Io { source, .. } => match source.kind() {
+                io::ErrorKind::StorageFull => Some("hint_free_disk_space"),
+                io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => Some("hint_check_permissions"),
+                _ => None,
+            },
+            Self::Encode { .. } => None,
+        }
+    }
+
+    /// Locale key of the name of the kind of failure.
+    pub fn kind_key(&self) -> &'static str {
+        match self {
+            Self::ModelDownload { .. } => "error_kind_model_download",
+            Self::ModelLoad { .. } => "error_kind_model_load",
+            Self::Decode { .. } => "error_kind_decode",
+            Self::Encode { .. } => "error_kind_encode",
+            Self::Inference { .. } => "error_kind_inference",
+            Self::Io { .. } => "error_kind_io",
+        }
+    }
+
+    /// File the failure concerns, when it names one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::ModelLoad { path, .. } | Self::Decode { path, .. } | Self::Encode { path, .. } | Self::Io { path, .. } => Some(path),
+            Self::ModelDownload { .. } | Self::Inference { .. } => None,
+        }
+    }
+
+    /// What the user can do about the failure, most useful first.
+    pub fn remedies(&self) -> &'static [Remedy] {
+        match self {
+            Self::ModelDownload { .. } => &[Remedy::Retry, Remedy::ChooseModel],
+            // Deleting the damaged file makes the next run download it again
+            Self::ModelLoad { .. } => &[Remedy::OpenFolder, Remedy::ChooseModel, Remedy::Retry],
+            Self::Decode { .. } => &[Remedy::Skip, Remedy::OpenFolder],
+            Self::Encode { .. } => &[Remedy::Retry, Remedy::Skip],
+            Self::Inference { .. } => &[Remedy::Skip, Remedy::ChooseModel, Remedy::Retry],
+            Self::Io { source, .. } => match source.kind() {
+                io::ErrorKind::StorageFull | io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => &[Remedy::OpenFolder, Remedy::Retry],
+                _ => &[Remedy::Retry, Remedy::Skip, Remedy::OpenFolder],
+            },
+        }
+    }
+
+    /// True when the next image would fail the same way, so a batch should stop.
+    pub fn aborts_batch(&self) -> bool {
+        match self {
+            Self::ModelDownload { .. } | Self::ModelLoad { .. } => true,
+            Self::Io { source, .. } => source.kind() == io::ErrorKind::StorageFull,
+            Self::Decode { .. } | Self::Encode { .. } | Self::Inference { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for AlphasvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ModelDownload { model, reason } => write!(f, "Failed to download model {}: {}", model, reason),
+            Self::ModelLoad { model, reason, .. } => write!(f, "Failed to load ONNX model {}: {}", model, reason),
+            Self::Decode { path, reason } => write!(f, "Failed to decode {}: {}", path.display(), reason),
+            Self::Encode { path, reason } => write!(f, "Failed to encode {}: {}", path.display(), reason),
+            Self::Inference { model, reason } => write!(f, "Inference with model {} failed: {}", model, reason),
+            Self::Io { path, source } => write!(f, "Failed to access {}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for AlphasvgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Output size estimates from earlier runs.
+//!
+//! After every image, the size of each output is added to `output_sizes.json`
+//! in the config directory, per kind of output, together with the megapixels
+//! of the input it came from. An estimate is the average bytes per megapixel
+//! of a kind times the megapixels of the next input; kinds never produced
+//! before have no estimate. A file that cannot be parsed is left as it is
+//! rather than replaced by the sizes of a single run.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AlphasvgError;
+use crate::naming::OutputKind;
+use crate::settings::Settings;
+use crate::temp;
+
+const SIZES_FILE: &str = "output_sizes.json";
+
+/// Totals over the outputs of one kind written so far.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct KindSizes {
+    megapixels: f64,
+    bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SizeEstimates {
+    kinds: HashMap<OutputKind, KindSizes>,
+}
+
+impl SizeEstimates {
+    pub fn load() -> Self {
+        Settings::config_dir()
+            .and_then(|dir| Self::read(&dir.join(SIZES_FILE)).ok())
+            .unwrap_or_default()
+    }
+
+    /// The estimates saved at `path`, empty when there is no file yet.
+    fn read(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(AlphasvgError::io(path)(e).into()),
+        }
+    }
+
+    /// Expected size of a `kind` output of an input of `megapixels`.
+    pub fn estimate(&self, kind: OutputKind, megapixels: f64) -> Option<u64> {
+        let sizes = self.kinds.get(&kind).filter(|s| s.megapixels > 0.0)?;
+        Some((sizes.bytes as f64 / sizes.megapixels * megapixels) as u64)
+    }
+
+    /// Estimates for `kinds`: the total of the known ones and the kinds without an estimate.
+    pub fn total(&self, kinds: &[OutputKind], megapixels: f64) -> (u64, Vec<OutputKind>) {
+        let mut total = 0;
+        let mut unknown = Vec::new();
+        for &kind in kinds {
+            match self.estimate(kind, megapixels) {
+                Some(bytes) => total += bytes,
+                None => unknown.push(kind),
+            }
+        }
+        (total, unknown)
+    }
+
+    /// Adds the sizes of `outputs`, written from an input of `megapixels`.
+    ///
+    /// An unreadable file is an error and is not overwritten. The new totals are
+    /// written through a temporary file, so an interrupted write keeps the old ones.
+    pub fn record(outputs: &[(OutputKind, PathBuf)], megapixels: f64) -> Result<()> {
+        let dir = Settings::config_dir().context("Could not find config directory")?;
+        let file = dir.join(SIZES_FILE);
+        let mut estimates = Self::read(&file)?;
+        for (kind, path) in outputs {
+            let Ok(meta) = fs::metadata(path) else { continue };
+            let sizes = estimates.kinds.entry(*kind).or_default();
+            sizes.megapixels += megapixels;
+            sizes.bytes += meta.len();
+        }
+        fs::create_dir_all(&dir).map_err(AlphasvgError::io(&dir))?;
+        let mut temp = temp::file_beside(&file, "tmp")?;
+        temp.write_all(serde_json::to_string_pretty(&estimates)?.as_bytes()).map_err(AlphasvgError::io(&file))?;
+        temp.persist(&file).map_err(|e| e.error).map_err(AlphasvgError::io(&file))?;
+        Ok(())
+    }
+}
+
+/// Megapixels of the image at `path`, read from its header.
+pub fn megapixels(path: &Path) -> Option<f64> {
+    let (width, height) = image::image_dimensions(path).ok()?;
+    Some(megapixels_of(width, height))
+}
+
+pub fn megapixels_of(width: u32, height: u32) -> f64 {
+    f64::from(width) * f64::from(height) / 1_000_000.0
+}
@@ -0,0 +1,110 @@
+//! Application event bus.
+//!
+//! Background work (processing jobs, model loading, the update check) publishes
+//! typed [`AppEvent`]s instead of sharing `Arc<Mutex<..>>` state with the GUI,
+//! which drains them once per frame in `update()`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use image::{GrayImage, RgbaImage};
+
+use crate::error::{AlphasvgError, Remedy};
+use crate::generators::ModelState;
+use crate::lang::LanguageManager;
+use crate::naming::OutputKind;
+
+#[derive(Clone, Debug)]
+pub enum AppEvent {
+    /// A line for the terminal log.
+    LogLine(String),
+    /// `done` of `total` pipeline steps of the running job are complete.
+    Progress { done: usize, total: usize },
+    ModelStateChanged(ModelState),
+    /// The running job ended, with what went wrong if it failed.
+    JobFinished { failure: Option<JobFailure> },
+    /// A newer release was found (version, release page).
+    UpdateAvailable { version: String, url: String },
+    /// Files to queue, given on the command line or handed over by a second launch.
+    OpenFiles(Vec<PathBuf>),
+    /// Model mask of `input`, before the mask adjustments, for the live preview.
+    MaskReady { input: PathBuf, mask: Arc<GrayImage> },
+    /// A quick preview of `input` ended, with the outputs it could render, or its error.
+    PreviewReady { input: PathBuf, images: Vec<(OutputKind, Arc<RgbaImage>)>, error: Option<String> },
+    /// An entry of the tray icon's menu was chosen.
+    Tray(TrayAction),
+}
+
+/// Quick actions of the tray icon, see `gui::tray`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrayAction {
+    /// Bring the window to the front.
+    ShowWindow,
+    /// Pick images to queue with the current form.
+    QueueFiles,
+    /// Stop or resume starting queued images; the running job finishes.
+    TogglePause,
+    Quit,
+}
+
+/// A failed job, as the GUI error dialog shows it.
+#[derive(Clone, Debug)]
+pub struct JobFailure {
+    pub message: String,
+    /// Localized name of the kind of failure.
+    pub kind: String,
+    /// File the failure concerns: the one the error names, or the job's input.
+    pub path: PathBuf,
+    /// Input of the failed job.
+    pub input: PathBuf,
+    /// Whether the next queued inputs would fail the same way.
+    pub aborts_queue: bool,
+    /// Localized hint on how to fix it, when the kind of failure is known.
+    pub hint: Option<String>,
+    pub remedies: Vec<Remedy>,
+}
+
+impl JobFailure {
+    /// Describes `error`, raised by the job on `input`, in the language of `lang`.
+    pub fn new(error: &anyhow::Error, input: &Path, lang: &LanguageManager) -> Self {
+        let classified = AlphasvgError::find(error);
+        Self {
+            message: error.to_string(),
+            kind: lang.t(classified.map_or("error_kind_other", AlphasvgError::kind_key)),
+            path: classified.and_then(AlphasvgError::path).unwrap_or(input).to_path_buf(),
+            input: input.to_path_buf(),
+            aborts_queue: classified.is_some_and(AlphasvgError::aborts_batch),
+            hint: classified.and_then(AlphasvgError::hint_key).map(|key| lang.t(key)),
+            remedies: classified.map_or(&[Remedy::Retry, Remedy::Skip][..], AlphasvgError::remedies).to_vec(),
+        }
+    }
+}
+
+/// Publishing side of the bus; cheap to clone into worker threads.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: Sender<AppEvent>,
+    wake: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl EventBus {
+    /// Creates a bus and the receiver its events are drained from.
+    pub fn new() -> (Self, Receiver<AppEvent>) {
+        let (tx, rx) = mpsc::channel();
+        (Self { tx, wake: None }, rx)
+    }
+
+    /// Calls `wake` after each published event, e.g. to request a repaint.
+    pub fn with_waker(mut self, wake: impl Fn() + Send + Sync + 'static) -> Self {
+        self.wake = Some(Arc::new(wake));
+        self
+    }
+
+    pub fn publish(&self, event: AppEvent) {
+        // The receiver is gone only while the app shuts down
+        let _ = self.tx.send(event);
+        if let Some(wake) = &self.wake {
+            wake();
+        }
+    }
+}
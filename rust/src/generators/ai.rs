@@ -2,95 +2,181 @@
 //!
 //! Handles ONNX model loading, session management, and inference.
 
-use image::{DynamicImage, Luma, imageops::FilterType};
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, imageops::{self, FilterType}};
 use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
 use std::fs;
 use anyhow::{Result, Context, anyhow};
-use ort::{inputs, session::Session, value::Value};
-use ndarray::Array4;
-use std::sync::{Mutex, Arc};
+use ort::{inputs, session::Session, value::TensorRef};
+use std::sync::Mutex;
 
+use crate::error::AlphasvgError;
 use crate::lang::LanguageManager;
 use crate::generators::{LogOutput, ModelState, ModelType};
 use super::models::{ModelConfig, get_model_config};
+use super::tensor::{fill_planar_tensor, transform_output};
 
-static SESSION: Mutex<Option<(ModelType, Session)>> = Mutex::new(None);
+/// Loaded sessions keyed by model name, most recently used last. Two are kept so
+/// ensemble runs (or a mask model plus the upscaler) do not reload a model for every image.
+static SESSIONS: Mutex<Vec<(String, Session)>> = Mutex::new(Vec::new());
+const MAX_SESSIONS: usize = 2;
+
+/// Model files a session was loaded from and their licenses, keyed by model name, for the run provenance.
+static MODEL_FILES: Mutex<BTreeMap<String, (PathBuf, &'static str)>> = Mutex::new(BTreeMap::new());
+
+/// No execution provider is registered on the session builder, so ONNX Runtime runs on the CPU.
+pub const EXECUTION_PROVIDER: &str = "CPU";
+
+/// Scratch buffers reused across inferences, keyed by model input resolution.
+static SCRATCH_POOL: Mutex<BTreeMap<u32, ScratchBuffers>> = Mutex::new(BTreeMap::new());
+
+/// Lower input resolutions tried, in order, when a model runs out of memory at its own.
+const RETRY_RESOLUTIONS: [u32; 2] = [768, 512];
+
+/// Input tensor and raw mask buffers for one model resolution.
+#[derive(Default)]
+struct ScratchBuffers {
+    input: Vec<f32>,
+    mask: Vec<u8>,
+}
+
+/// Mask returned by [`get_model_mask`].
+pub struct ModelMask {
+    pub mask: image::ImageBuffer<Luma<u8>, Vec<u8>>,
+    /// Lower input resolution the model ran at after running out of memory at its own.
+    pub retried_at: Option<u32>,
+}
 
 /// Performs AI inference to get a transparency mask (saliency map).
-/// Returns a Luma image of the mask.
+///
+/// With `allow_downscale_retry`, an allocation failure on a 1024px model whose
+/// input takes any height and width is retried at the next lower resolution in
+/// [`RETRY_RESOLUTIONS`] instead of failing.
+#[tracing::instrument(skip_all)]
 pub fn get_model_mask(
     img: &DynamicImage, 
     lang: &LanguageManager, 
     logger: &LogOutput, 
-    status: &Arc<Mutex<ModelState>>,
     model_type: ModelType,
-) -> Result<image::ImageBuffer<Luma<u8>, Vec<u8>>> {
+    allow_downscale_retry: bool,
+) -> Result<ModelMask> {
     
     let config = get_model_config(model_type);
-    let model_path = prepare_model(lang, logger, status, &config)?;
-    let mut session_guard = SESSION.lock().map_err(|_| anyhow!("Failed to lock session mutex"))?;
+    let model_path = prepare_model(lang, logger, &config)?;
 
-    process_model_mask(img, lang, logger, status, model_type, &config, &model_path, &mut session_guard)
+    with_session(lang, logger, &config, &model_path, |session| {
+        let mut res = config.resolution;
+        loop {
+            let err = match process_model_mask(img, lang, logger, &config, session, res) {
+                Ok(mask) => return Ok(ModelMask { mask, retried_at: (res != config.resolution).then_some(res) }),
+                Err(e) => e,
+            };
+            let next = RETRY_RESOLUTIONS.iter().copied().find(|&r| r < res);
+            match next {
+                Some(next) if allow_downscale_retry && config.resolution >= 1024 && is_allocation_failure(&err) && has_dynamic_size(session) => {
+                    // Give the buffers sized for the failed resolution back before retrying
+                    if let Ok(mut pool) = SCRATCH_POOL.lock() {
+                        pool.remove(&res);
+                    }
+                    logger.send(lang.t_args("log_downscale_retry", &[config.name.clone(), res.to_string(), next.to_string()]));
+                    res = next;
+                }
+                _ => return Err(err),
+            }
+        }
+    })
 }
 
-fn process_model_mask(
-    img: &DynamicImage,
+/// Whether an inference error is ONNX Runtime failing to allocate memory.
+fn is_allocation_failure(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err).to_lowercase();
+    ["failed to allocate", "bad_alloc", "out of memory", "allocation failed"].iter().any(|pattern| message.contains(pattern))
+}
+
+/// Whether the model's `[N, C, H, W]` input takes any height and width, so it
+/// can run below its own resolution; a fixed size would only fail differently.
+fn has_dynamic_size(session: &Session) -> bool {
+    session.inputs().first()
+        .and_then(|input| input.dtype().tensor_shape())
+        .is_some_and(|shape| shape.len() == 4 && shape[2] < 0 && shape[3] < 0)
+}
+
+/// Drops the loaded sessions, so the next inference builds them again.
+pub(crate) fn clear_sessions() {
+    if let Ok(mut sessions) = SESSIONS.lock() {
+        sessions.clear();
+    }
+}
+
+/// Runs `f` with the cached session for `config`, loading it from `model_path` if needed.
+pub(crate) fn with_session<R>(
     lang: &LanguageManager,
     logger: &LogOutput,
-    status: &Arc<Mutex<ModelState>>,
-    model_type: ModelType,
     config: &ModelConfig,
     model_path: &Path,
-    session_guard: &mut Option<(ModelType, Session)>,
-) -> Result<image::ImageBuffer<Luma<u8>, Vec<u8>>> {
-    
-    // Ensure the session is initialized for the correct model
-    let is_correct_model = if let Some((current_type, _)) = session_guard {
-        *current_type == model_type
-    } else {
-        false
-    };
+    f: impl FnOnce(&mut Session) -> Result<R>,
+) -> Result<R> {
+    let mut sessions = SESSIONS.lock().map_err(|_| anyhow!("Failed to lock session mutex"))?;
 
-    if !is_correct_model {
-        {
-            let mut s = status.lock().unwrap();
-            *s = ModelState::Loading;
-        }
+    // Ensure a session is loaded for the model and mark it most recently used
+    if let Some(pos) = sessions.iter().position(|(name, _)| *name == config.name) {
+        let entry = sessions.remove(pos);
+        sessions.push(entry);
+    } else {
+        logger.model_state(ModelState::Loading);
         logger.send(lang.t("log_loading_model"));
         
-        let new_session = Session::builder()?
+        let mut builder = Session::builder()?;
+        if let Some(threads) = crate::priority::worker_limit() {
+            builder = builder.with_intra_threads(threads)?;
+        }
+        let new_session = builder
             .commit_from_file(model_path)
-            .map_err(|e| anyhow!("Failed to load ONNX model {}: {}", config.name, e))?;
+            .map_err(|e| AlphasvgError::ModelLoad { model: config.name.clone(), path: model_path.to_path_buf(), reason: e.to_string() })?;
             
-        *session_guard = Some((model_type, new_session));
+        if sessions.len() >= MAX_SESSIONS {
+            sessions.remove(0);
+        }
+        sessions.push((config.name.clone(), new_session));
+        if let Ok(mut files) = MODEL_FILES.lock() {
+            files.insert(config.name.clone(), (model_path.to_path_buf(), config.license));
+        }
     }
 
-    let (_, session) = session_guard.as_mut().unwrap();
+    let (_, session) = sessions.last_mut().unwrap();
     
-    {
-        let mut s = status.lock().unwrap();
-        *s = ModelState::Ready(config.name.clone());
-    }
+    logger.model_state(ModelState::Ready(config.name.clone()));
+
+    // Failures already classified inside `f`, e.g. reading a file, keep their kind
+    f(session).map_err(|e| match AlphasvgError::find(&e) {
+        Some(_) => e,
+        None => AlphasvgError::Inference { model: config.name.clone(), reason: format!("{:#}", e) }.into(),
+    })
+}
+
+fn process_model_mask(
+    img: &DynamicImage,
+    lang: &LanguageManager,
+    logger: &LogOutput,
+    config: &ModelConfig,
+    session: &mut Session,
+    res: u32,
+) -> Result<image::ImageBuffer<Luma<u8>, Vec<u8>>> {
+    let (width, height) = img.dimensions();
 
-    let rgba = img.to_rgba8();
-    let (width, height) = rgba.dimensions();
-    let res = config.resolution;
+    // Reuse the scratch buffers for this resolution across batch items
+    let mut pool = SCRATCH_POOL.lock().map_err(|_| anyhow!("Failed to lock scratch buffer pool"))?;
+    let scratch = pool.entry(res).or_default();
 
     // 1. Pre-process
     let resized = img.resize_exact(res, res, FilterType::Lanczos3);
-    let mut input_array = Array4::<f32>::zeros((1, 3, res as usize, res as usize));
-    
-    for (x, y, pixel) in resized.to_rgb8().enumerate_pixels() {
-        input_array[[0, 0, y as usize, x as usize]] = (pixel[0] as f32 / 255.0 - 0.485) / 0.229;
-        input_array[[0, 1, y as usize, x as usize]] = (pixel[1] as f32 / 255.0 - 0.456) / 0.224;
-        input_array[[0, 2, y as usize, x as usize]] = (pixel[2] as f32 / 255.0 - 0.406) / 0.225;
-    }
+    fill_planar_tensor(&resized, &config.normalization, &mut scratch.input);
+    drop(resized);
 
     // 2. Inference
     logger.send(lang.t("log_inference"));
     let shape = vec![1, 3, res as usize, res as usize];
-    let data = input_array.into_raw_vec_and_offset().0.into_boxed_slice();
-    let input_tensor = Value::from_array((shape, data))?;
+    let input_tensor = TensorRef::from_array_view((shape, scratch.input.as_slice()))?;
     
     let input_name = session.inputs()[0].name().to_string();
     let output_name = session.outputs()[0].name().to_string();
@@ -98,27 +184,42 @@ fn process_model_mask(
     let input_map = inputs![input_name => input_tensor];
     let outputs = session.run(input_map)?;
     
-    let (_mask_shape, mask_slice) = outputs[output_name].try_extract_tensor::<f32>()?;
+    let (mask_shape, mask_slice) = outputs[output_name].try_extract_tensor::<f32>()?;
 
     // 3. Post-process mask
-    let mut mask_img = image::ImageBuffer::new(res, res);
-    for y in 0..res {
-        for x in 0..res {
-            let val = mask_slice[(y * res + x) as usize];
-            let pixel_val = (val * 255.0).clamp(0.0, 255.0) as u8;
-            mask_img.put_pixel(x, y, Luma([pixel_val]));
-        }
+    let plane = (res * res) as usize;
+    // Outputs are [1, C, H, W] or [1, H, W]; anything else is treated as one channel
+    let channels = if mask_shape.len() == 4 { mask_shape[1].max(1) as usize } else { 1 };
+    if mask_slice.len() < plane * channels {
+        return Err(anyhow!("Model output is smaller than the expected mask"));
     }
+    let mut mask_buf = std::mem::take(&mut scratch.mask);
+    transform_output(mask_slice, channels, plane, &config.output, &mut mask_buf);
+    let mask_img = ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(res, res, mask_buf)
+        .context("Model output is smaller than the expected mask")?;
 
     // Resize mask back to original size
-    let mask_resized = image::DynamicImage::ImageLuma8(mask_img)
-        .resize_exact(width, height, FilterType::Lanczos3)
-        .to_luma8();
+    let mask_resized = imageops::resize(&mask_img, width, height, FilterType::Lanczos3);
+    scratch.mask = mask_img.into_raw();
 
     Ok(mask_resized)
 }
 
-fn prepare_model(lang: &LanguageManager, logger: &LogOutput, status: &Arc<Mutex<ModelState>>, config: &ModelConfig) -> Result<PathBuf> {
+/// A model loaded by this process.
+pub struct LoadedModel {
+    pub name: String,
+    pub path: PathBuf,
+    pub license: &'static str,
+}
+
+/// Every model loaded by this process.
+pub fn loaded_model_files() -> Vec<LoadedModel> {
+    MODEL_FILES.lock()
+        .map(|files| files.iter().map(|(name, (path, license))| LoadedModel { name: name.clone(), path: path.clone(), license }).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn prepare_model(lang: &LanguageManager, logger: &LogOutput, config: &ModelConfig) -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not find home directory")?;
     let model_dir = home.join(".transparente_models");
     fs::create_dir_all(&model_dir)?;
@@ -133,26 +234,24 @@ fn prepare_model(lang: &LanguageManager, logger: &LogOutput, status: &Arc<Mutex<
     };
 
     if needs_download {
-        {
-            let mut s = status.lock().unwrap();
-            *s = ModelState::Loading;
-        }
-        let msg = format!("{} {} (~{}MB)...", lang.t("log_downloading_model_generic"), config.name, config.size_mb);
+        logger.model_state(ModelState::Loading);
+        let msg = lang.t_args("log_downloading_model_generic", &[config.name.to_string(), lang.format_size(config.size_mb as u64 * 1024 * 1024)]);
         logger.send(msg);
         
-        let mut response = reqwest::blocking::get(&config.url)?;
+        let download_error = |reason: String| AlphasvgError::ModelDownload { model: config.name.clone(), reason };
+        let mut response = reqwest::blocking::get(&config.url).map_err(|e| download_error(e.to_string()))?;
         if !response.status().is_success() {
-            return Err(anyhow!("Failed to download model {}: HTTP {}", config.name, response.status()));
+            return Err(download_error(format!("HTTP {}", response.status())).into());
         }
 
-        let mut file = fs::File::create(&model_path)?;
-        response.copy_to(&mut file)?;
+        let mut file = fs::File::create(&model_path).map_err(AlphasvgError::io(&model_path))?;
+        response.copy_to(&mut file).map_err(|e| download_error(e.to_string()))?;
         
         // Final check after download
         let len = fs::metadata(&model_path)?.len();
         if len < 1024 * 1024 {
             let _ = fs::remove_file(&model_path); // Clean up
-            return Err(anyhow!("Downloaded model {} is too small (corrupt or invalid URL)", config.name));
+            return Err(download_error("file is too small (corrupt or invalid URL)".to_string()).into());
         }
         
         logger.send(lang.t("log_model_downloaded"));
@@ -160,3 +259,18 @@ fn prepare_model(lang: &LanguageManager, logger: &LogOutput, status: &Arc<Mutex<
 
     Ok(model_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocation_failures_are_recognized() {
+        let error = |message: &str| anyhow!(message.to_string()).context("Inference failed");
+        assert!(is_allocation_failure(&error("Failed to allocate memory for requested buffer of size 4294967296")));
+        assert!(is_allocation_failure(&error("std::bad_alloc")));
+        assert!(is_allocation_failure(&error("CUDA error: Out of memory")));
+        assert!(!is_allocation_failure(&error("Got invalid dimensions for input: input.1")));
+        assert!(!is_allocation_failure(&error("Failed to load model")));
+    }
+}
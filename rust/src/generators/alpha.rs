@@ -1,44 +1,495 @@
-use image::{DynamicImage, RgbaImage};
-use std::path::Path;
-use anyhow::Result;
-use crate::config;
-use std::sync::{Mutex, Arc};
+use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, Rgb, Rgb32FImage, RgbaImage, imageops::{self, FilterType}};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::{config, temp};
+use crate::error::AlphasvgError;
 use crate::lang::LanguageManager;
-use crate::generators::{LogOutput, ModelState, ModelType, ai};
+use crate::generators::{LogOutput, ModelType, PreprocessOptions, ai, auto, preprocess};
+use crate::generators::ai::ModelMask;
+use crate::generators::mask_adjust::{HoleFill, MaskAdjust};
+use crate::generators::watermark::{self, WatermarkOptions};
 
-pub fn generate_alpha_png(input_path: &Path, output_path: Option<&Path>, lang: &LanguageManager, logger: &LogOutput, status: &Arc<Mutex<ModelState>>, model_type: ModelType) -> Result<DynamicImage> {
-    // If output path is provided and exists, return loaded image (Cache)
-    if let Some(path) = output_path {
-        if path.exists() {
-            return Ok(image::open(path)?);
+/// Options controlling alpha generation.
+pub struct AlphaOptions {
+    /// Inputs larger than this are downscaled before processing (`None` disables the guard).
+    pub max_megapixels: Option<f32>,
+    /// Upscale the mask and apply it to the full-resolution input instead of the downscaled copy.
+    pub upscale_mask: bool,
+    /// Run two models and fuse their masks instead of the single selected model.
+    pub ensemble: Option<Ensemble>,
+    /// Corrections applied to the input before inference.
+    pub preprocess: PreprocessOptions,
+    /// Only this region is cut out; the rest of the image stays transparent.
+    pub roi: Option<Roi>,
+    /// Retry at a lower model resolution when inference runs out of memory.
+    pub allow_downscale_retry: bool,
+    /// Threshold curve and strength applied to the mask.
+    pub mask: MaskAdjust,
+    /// Mark stamped on the saved PNG; the returned cut-out stays clean for the
+    /// other outputs.
+    pub watermark: Option<WatermarkOptions>,
+}
+
+impl Default for AlphaOptions {
+    fn default() -> Self {
+        Self {
+            max_megapixels: Some(config::MAX_MEGAPIXELS),
+            upscale_mask: false,
+            ensemble: None,
+            preprocess: PreprocessOptions::default(),
+            roi: None,
+            allow_downscale_retry: false,
+            mask: MaskAdjust::default(),
+            watermark: None,
+        }
+    }
+}
+
+/// Region of interest in input pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Roi {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Roi {
+    /// The part of the region inside a `width`×`height` image, or an error when they do not overlap.
+    pub fn clamp(self, width: u32, height: u32) -> Result<Roi> {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        let clamped = Roi {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        };
+        if clamped.width == 0 || clamped.height == 0 {
+            return Err(anyhow!("Region {} is outside the {}x{} image", self, width, height));
+        }
+        Ok(clamped)
+    }
+}
+
+impl FromStr for Roi {
+    type Err = String;
+
+    /// Parses `x,y,w,h`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<u32> = value.split(',')
+            .map(|p| p.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("expected x,y,w,h in pixels: {}", value))?;
+        match parts[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Roi { x, y, width, height }),
+            _ => Err(format!("expected x,y,w,h with a non-empty size: {}", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for Roi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// How the two masks of an ensemble are combined.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum MaskFusion {
+    #[default]
+    Average,
+    Max,
+    /// Per pixel, trust the model that is further from 50%.
+    Confidence,
+}
+
+impl MaskFusion {
+    /// Locale key of the fusion name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            MaskFusion::Average => "fusion_average",
+            MaskFusion::Max => "fusion_max",
+            MaskFusion::Confidence => "fusion_confidence",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ensemble {
+    pub models: [ModelType; 2],
+    pub fusion: MaskFusion,
+}
+
+/// Parses an ensemble specification such as `u2net+isnet-general-use`.
+pub fn parse_ensemble_models(value: &str) -> Result<[ModelType; 2], String> {
+    match value.split_once('+') {
+        Some((a, b)) => Ok([a.trim().parse()?, b.trim().parse()?]),
+        None => Err(format!("expected two models joined by '+': {}", value)),
+    }
+}
+
+/// Grayscale transparency mask (0 = background, 255 = subject).
+pub type Mask = GrayImage;
+
+/// Cut-out produced by [`generate_alpha_png`].
+pub struct AlphaOutput {
+    pub image: DynamicImage,
+    /// Model(s) that produced the mask; `None` when the cached mask was reused.
+    pub model: Option<String>,
+    /// Time spent computing the mask, including model loading.
+    pub inference: Duration,
+    /// Lower model resolution used after running out of memory, if any.
+    pub retried_at: Option<u32>,
+    /// Model mask before the [`MaskAdjust`], for previewing other adjustments;
+    /// `None` when only a region was cut out.
+    pub mask: Option<Mask>,
+}
+
+/// Generates the transparent PNG for `input_path`.
+///
+/// With an `output_path`, the model mask is cached beside it (see
+/// [`mask_cache_path`]): a re-run with the same input, model, corrections and
+/// region skips inference and only applies the mask adjustments again.
+#[tracing::instrument(skip_all)]
+pub fn generate_alpha_png(input_path: &Path, output_path: Option<&Path>, opts: &AlphaOptions, lang: &LanguageManager, logger: &LogOutput, model_type: ModelType) -> Result<AlphaOutput> {
+    let full = preprocess::preprocess(image::open(input_path).map_err(|e| AlphasvgError::decode(input_path, e))?, &opts.preprocess, lang, logger);
+    let (full_width, full_height) = full.dimensions();
+    let (original, roi) = match opts.roi {
+        Some(roi) => {
+            let roi = roi.clamp(full_width, full_height)?;
+            logger.verbose(lang.t_args("log_roi", &[roi]));
+            (full.crop_imm(roi.x, roi.y, roi.width, roi.height), Some(roi))
         }
+        None => (full, None),
+    };
+    let working = downscale_to_limit(&original, opts.max_megapixels, lang, logger);
+
+    // 1. Get Mask from AI module or the cache (the mask is resized to the target when compositing)
+    let start = Instant::now();
+    let cache = output_path.map(|path| (mask_cache_path(path), mask_cache_key(input_path, opts, model_type)));
+    let mut cached = cache.as_ref().and_then(|(path, key)| read_cached_mask(path, key));
+    let from_cache = cached.is_some();
+    if from_cache {
+        logger.verbose(lang.t("log_mask_cached"));
     }
+    let mut mask_for = |img: &DynamicImage| match cached.take() {
+        Some(mask) => Ok((ModelMask { mask, retried_at: None }, None)),
+        None => infer(img, opts, model_type, lang, logger).map(|(mask, model)| (mask, Some(model))),
+    };
+    let (img, (ModelMask { mask, retried_at }, model)) = match working {
+        Some(small) if opts.upscale_mask => {
+            let mask = mask_for(&small)?;
+            (original, mask)
+        }
+        Some(small) => {
+            drop(original);
+            let mask = mask_for(&small)?;
+            (small, mask)
+        }
+        None => {
+            let mask = mask_for(&original)?;
+            (original, mask)
+        }
+    };
 
-    let img = image::open(input_path)?;
-    let rgba = img.to_rgba8();
-    
-    // 1. Get Mask from AI module
-    let mask_resized = ai::get_model_mask(&img, lang, logger, status, model_type)?;
+    let inference = start.elapsed();
+    if let Some((path, key)) = &cache
+        && !from_cache
+        && let Err(e) = write_cached_mask(&mask, key, path)
+    {
+        // The cache only saves time on re-runs
+        logger.verbose(lang.t_args("log_mask_cache_failed", &[format!("{:#}", e)]));
+    }
+
+    // 2. Apply mask and refine, then place the region back on a transparent canvas
+    let mut final_img = apply_mask(img, &mask, opts);
+    if let Some(roi) = roi {
+        final_img = place_region(&final_img, roi, full_width, full_height);
+    }
+
+    // 3. Save or keep in memory
+    match (output_path, &opts.watermark) {
+        (Some(path), Some(mark)) => {
+            let mut stamped = final_img.clone();
+            watermark::stamp(&mut stamped, mark)?;
+            save_alpha(&stamped, path, lang, logger)?;
+        }
+        (Some(path), None) => save_alpha(&final_img, path, lang, logger)?,
+        (None, _) => logger.send(lang.t("log_alpha_mem")),
+    }
 
-    // 2. Apply mask to original image
-    let mut final_img = rgba.clone();
-    for (x, y, pixel) in final_img.enumerate_pixels_mut() {
-        let mask_val = mask_resized.get_pixel(x, y)[0];
+    let mask = roi.is_none().then_some(mask);
+    Ok(AlphaOutput { image: DynamicImage::ImageRgba8(final_img), model, inference, retried_at, mask })
+}
+
+/// tEXt keyword holding the [`mask_cache_key`] of a cached mask.
+const MASK_CACHE_KEYWORD: &str = "alphasvg-mask-key";
+
+/// Hidden file beside the alpha PNG holding the model mask it was made from.
+fn mask_cache_path(output: &Path) -> PathBuf {
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!(".{}.mask.png", name))
+}
+
+/// Hash of everything the model mask depends on: the input file, the model or
+/// ensemble, the corrections, the region and the working size limit. The mask
+/// adjustments are not part of it, as they are applied to the cached mask.
+fn mask_cache_key(input: &Path, opts: &AlphaOptions, model_type: ModelType) -> String {
+    let meta = std::fs::metadata(input).ok();
+    let modified = meta.as_ref()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos().to_string());
+    let key = serde_json::json!({
+        "input": std::path::absolute(input).unwrap_or_else(|_| input.to_path_buf()),
+        "size": meta.map(|m| m.len()),
+        "modified": modified,
+        "model": model_type.name(),
+        "ensemble": opts.ensemble.map(|e| format!("{:?}", e)),
+        "preprocess": opts.preprocess,
+        "roi": opts.roi,
+        "max_megapixels": opts.max_megapixels,
+    });
+    Sha256::digest(key.to_string().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The mask cached at `path`, if it was made with `key`.
+fn read_cached_mask(path: &Path, key: &str) -> Option<Mask> {
+    let file = std::fs::File::open(path).ok()?;
+    let reader = png::Decoder::new(std::io::BufReader::new(file)).read_info().ok()?;
+    let matches = reader.info().uncompressed_latin1_text.iter().any(|t| t.keyword == MASK_CACHE_KEYWORD && t.text == key);
+    drop(reader);
+    matches.then(|| image::open(path).ok().map(DynamicImage::into_luma8)).flatten()
+}
+
+/// Caches `mask` at `path` under `key`, through a temporary file so an
+/// interrupted run never leaves a truncated mask behind.
+fn write_cached_mask(mask: &Mask, key: &str, path: &Path) -> Result<()> {
+    let temp = temp::file_beside(path, "tmp")?;
+    {
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(temp.as_file()), mask.width(), mask.height());
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk(MASK_CACHE_KEYWORD.to_string(), key.to_string())?;
+        encoder.write_header()?.write_image_data(mask.as_raw())?;
+    }
+    temp.persist(path).map_err(|e| e.error).map_err(AlphasvgError::io(path))?;
+    Ok(())
+}
+
+/// Runs the selected model, or the ensemble when configured, returning the mask
+/// and the name of the model(s) used.
+fn infer(img: &DynamicImage, opts: &AlphaOptions, model_type: ModelType, lang: &LanguageManager, logger: &LogOutput) -> Result<(ModelMask, String)> {
+    match &opts.ensemble {
+        Some(ensemble) => {
+            let name = ensemble.models.map(ModelType::name).join("+");
+            Ok((ensemble_mask(img, ensemble, opts.allow_downscale_retry, lang, logger)?, name))
+        }
+        None => {
+            let model = resolve_model(img, model_type, lang, logger);
+            Ok((ai::get_model_mask(img, lang, logger, model, opts.allow_downscale_retry)?, model.name()))
+        }
+    }
+}
+
+/// Runs both ensemble models on `img` and fuses their masks.
+///
+/// `retried_at` is the lowest resolution either model fell back to.
+#[tracing::instrument(skip_all)]
+pub fn ensemble_mask(img: &DynamicImage, ensemble: &Ensemble, allow_downscale_retry: bool, lang: &LanguageManager, logger: &LogOutput) -> Result<ModelMask> {
+    let [first, second] = ensemble.models;
+    logger.verbose(lang.t_args("log_ensemble", &[first.name(), second.name(), lang.t(ensemble.fusion.label_key())]));
+    let a = infer_mask(img, first, allow_downscale_retry, lang, logger)?;
+    let b = infer_mask(img, second, allow_downscale_retry, lang, logger)?;
+    let retried_at = a.retried_at.into_iter().chain(b.retried_at).min();
+    Ok(ModelMask { mask: fuse_masks(&a.mask, &b.mask, ensemble.fusion), retried_at })
+}
+
+/// Combines two masks pixel by pixel; `b` is resized to `a` if needed.
+pub fn fuse_masks(a: &Mask, b: &Mask, fusion: MaskFusion) -> Mask {
+    let resized;
+    let b = if b.dimensions() != a.dimensions() {
+        resized = imageops::resize(b, a.width(), a.height(), FilterType::Triangle);
+        &resized
+    } else {
+        b
+    };
+
+    let mut fused = a.clone();
+    for (out, pb) in fused.pixels_mut().zip(b.pixels()) {
+        let (va, vb) = (out[0] as f32, pb[0] as f32);
+        out[0] = match fusion {
+            MaskFusion::Average => ((va + vb) / 2.0).round() as u8,
+            MaskFusion::Max => va.max(vb) as u8,
+            MaskFusion::Confidence => {
+                let (wa, wb) = ((va - 127.5).abs() + 1.0, (vb - 127.5).abs() + 1.0);
+                ((va * wa + vb * wb) / (wa + wb)).round() as u8
+            }
+        };
+    }
+    fused
+}
+
+/// Runs the AI model on `img` and returns its mask at the image resolution.
+pub fn infer_mask(img: &DynamicImage, model_type: ModelType, allow_downscale_retry: bool, lang: &LanguageManager, logger: &LogOutput) -> Result<ModelMask> {
+    let model_type = resolve_model(img, model_type, lang, logger);
+    ai::get_model_mask(img, lang, logger, model_type, allow_downscale_retry)
+}
+
+/// Replaces `Auto` with the model chosen for `img`'s content.
+fn resolve_model(img: &DynamicImage, model_type: ModelType, lang: &LanguageManager, logger: &LogOutput) -> ModelType {
+    match model_type {
+        ModelType::Auto => {
+            let (kind, model) = auto::choose_model(img);
+            logger.send(lang.t_args("log_auto_model", &[kind.name().to_string(), model.name()]));
+            model
+        }
+        model => model,
+    }
+}
+
+/// Composites `mask`, adjusted per [`AlphaOptions::mask`], into the alpha channel
+/// of `img` and applies the halo and alpha refinements.
+///
+/// The mask is resized when its dimensions differ from the image.
+pub fn apply_mask(img: DynamicImage, mask: &Mask, opts: &AlphaOptions) -> RgbaImage {
+    let mut final_img = img.into_rgba8();
+    let (width, height) = final_img.dimensions();
+
+    let resized;
+    let mask = if mask.dimensions() != (width, height) {
+        resized = imageops::resize(mask, width, height, FilterType::Lanczos3);
+        &resized
+    } else {
+        mask
+    };
+
+    let lut = opts.mask.lut();
+    for (pixel, mask_px) in final_img.pixels_mut().zip(mask.pixels()) {
+        let mask_val = lut[mask_px[0] as usize];
         pixel[3] = (pixel[3] as u16 * mask_val as u16 / 255) as u8;
     }
 
-    // 3. Post-processing Refinements
-    clean_white_halo(&mut final_img);
+    // Post-processing Refinements (the halo cleanup would punch holes in a light backdrop)
+    if !opts.mask.invert {
+        clean_white_halo(&mut final_img);
+    }
     refine_alpha(&mut final_img);
+    if opts.mask.invert {
+        fill_hole(&mut final_img, opts.mask.fill);
+    }
+    final_img
+}
+
+/// Fills the transparent parts of an inverted cut-out, leaving it opaque.
+///
+/// The blur is normalized by alpha, so only the background spreads into the
+/// hole; pixels the blur does not reach take the average background color.
+fn fill_hole(img: &mut RgbaImage, fill: HoleFill) {
+    let color = match fill {
+        HoleFill::None => return,
+        HoleFill::Solid(color) => color.map(|c| c as f32 / 255.0),
+        HoleFill::Blur => {
+            let (width, height) = img.dimensions();
+            let alpha = |x: u32, y: u32| img.get_pixel(x, y)[3] as f32 / 255.0;
+            let weighted = Rgb32FImage::from_fn(width, height, |x, y| {
+                let a = alpha(x, y);
+                Rgb(std::array::from_fn(|c| img.get_pixel(x, y)[c] as f32 / 255.0 * a))
+            });
+            let weights: ImageBuffer<Luma<f32>, Vec<f32>> = ImageBuffer::from_fn(width, height, |x, y| Luma([alpha(x, y)]));
+            let total: f32 = weights.pixels().map(|w| w[0]).sum();
+            let average = if total > 0.0 {
+                let sum = weighted.pixels().fold([0.0; 3], |acc, p| [acc[0] + p[0], acc[1] + p[1], acc[2] + p[2]]);
+                sum.map(|v| v / total)
+            } else {
+                [0.0; 3]
+            };
+            let sigma = (width.max(height) as f32 / config::HOLE_FILL_BLUR_DIVISOR).max(1.0);
+            let blurred = imageops::fast_blur(&weighted, sigma);
+            let weights = imageops::fast_blur(&weights, sigma);
+            for (x, y, pixel) in img.enumerate_pixels_mut() {
+                let w = weights.get_pixel(x, y)[0];
+                let background = if w > 1e-3 { blurred.get_pixel(x, y).0.map(|v| v / w) } else { average };
+                composite_opaque(pixel, background);
+            }
+            return;
+        }
+    };
+    for pixel in img.pixels_mut() {
+        composite_opaque(pixel, color);
+    }
+}
 
-    if let Some(path) = output_path {
-         final_img.save(path)?;
-         logger.send(format!("{}{:?}", lang.t("log_alpha_ok"), path.file_name().unwrap()));
+/// Puts `pixel` over `background` (RGB from 0 to 1) and makes it opaque.
+fn composite_opaque(pixel: &mut image::Rgba<u8>, background: [f32; 3]) {
+    let a = pixel[3] as f32 / 255.0;
+    for c in 0..3 {
+        let value = pixel[c] as f32 / 255.0 * a + background[c] * (1.0 - a);
+        pixel[c] = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    pixel[3] = 255;
+}
+
+/// Composites the cut-out of `roi` onto a transparent canvas the size of the
+/// full image, scaled like the cut-out when it was downscaled.
+fn place_region(cutout: &RgbaImage, roi: Roi, full_width: u32, full_height: u32) -> RgbaImage {
+    let scale = cutout.width() as f64 / roi.width as f64;
+    let scaled = |v: u32| (v as f64 * scale).round() as u32;
+    let mut canvas = RgbaImage::new(scaled(full_width).max(1), scaled(full_height).max(1));
+    imageops::replace(&mut canvas, cutout, scaled(roi.x) as i64, scaled(roi.y) as i64);
+    canvas
+}
+
+/// Extracts the alpha channel of a cut-out as a mask of `width`×`height`,
+/// resizing it when the cut-out was downscaled.
+pub fn mask_from_cutout(cutout: &DynamicImage, width: u32, height: u32) -> Mask {
+    let rgba = cutout.to_rgba8();
+    let mask = Mask::from_fn(rgba.width(), rgba.height(), |x, y| image::Luma([rgba.get_pixel(x, y)[3]]));
+    if mask.dimensions() == (width, height) {
+        mask
     } else {
-         logger.send(lang.t("log_alpha_mem"));
+        imageops::resize(&mask, width, height, FilterType::Triangle)
+    }
+}
+
+/// Saves the composited RGBA image as PNG, through a temporary file so a failed
+/// or interrupted write never replaces `path` with a truncated image.
+pub fn save_alpha(img: &RgbaImage, path: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    let temp = temp::file_beside(path, "tmp")?;
+    {
+        let mut writer = std::io::BufWriter::new(temp.as_file());
+        img.write_to(&mut writer, image::ImageFormat::Png).map_err(|e| AlphasvgError::encode(path, e))?;
+        writer.flush().map_err(AlphasvgError::io(path))?;
+    }
+    temp.persist(path).map_err(|e| e.error).map_err(AlphasvgError::io(path))?;
+    logger.send(lang.t_args("log_alpha_ok", &[lang.format_file_name(path)]));
+    Ok(())
+}
+
+/// Returns a downscaled copy when `img` exceeds `max_megapixels`, or `None` if it fits.
+fn downscale_to_limit(img: &DynamicImage, max_megapixels: Option<f32>, lang: &LanguageManager, logger: &LogOutput) -> Option<DynamicImage> {
+    let max_mp = max_megapixels?;
+    let (width, height) = img.dimensions();
+    let megapixels = (width as f64 * height as f64) / 1_000_000.0;
+    if megapixels <= max_mp as f64 {
+        return None;
     }
-    
-    Ok(DynamicImage::ImageRgba8(final_img))
+
+    let scale = (max_mp as f64 / megapixels).sqrt();
+    let new_width = ((width as f64 * scale) as u32).max(1);
+    let new_height = ((height as f64 * scale) as u32).max(1);
+    logger.send(lang.t_args("log_downscaled", &[
+        lang.format_number(megapixels, 1),
+        new_width.to_string(),
+        new_height.to_string(),
+    ]));
+    Some(img.resize_exact(new_width, new_height, FilterType::Lanczos3))
 }
 
 fn clean_white_halo(img: &mut RgbaImage) {
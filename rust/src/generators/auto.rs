@@ -0,0 +1,105 @@
+//! Automatic model selection.
+//!
+//! A fast heuristic on a small thumbnail classifies the image as a portrait,
+//! flat-colored artwork, a product shot on a clean background, or general
+//! content, and picks the model best suited to it.
+
+use image::{DynamicImage, GenericImageView, Rgb, imageops::FilterType};
+use crate::generators::ModelType;
+
+/// Side of the thumbnail the heuristics run on.
+const SAMPLE_SIZE: u32 = 128;
+
+/// Content category detected by [`choose_model`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContentKind {
+    Portrait,
+    Artwork,
+    Product,
+    General,
+}
+
+impl ContentKind {
+    pub fn model(self) -> ModelType {
+        match self {
+            ContentKind::Portrait => ModelType::BiRefNetPortrait,
+            ContentKind::Artwork => ModelType::IsNetAnime,
+            ContentKind::Product => ModelType::IsNetGeneralUse,
+            ContentKind::General => ModelType::BiRefNetGeneralLite,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentKind::Portrait => "portrait",
+            ContentKind::Artwork => "artwork",
+            ContentKind::Product => "product",
+            ContentKind::General => "general",
+        }
+    }
+}
+
+/// Classifies `img` and returns the detected content with its model.
+pub fn choose_model(img: &DynamicImage) -> (ContentKind, ModelType) {
+    let kind = classify(img);
+    (kind, kind.model())
+}
+
+fn classify(img: &DynamicImage) -> ContentKind {
+    let (w, h) = img.dimensions();
+    let sample = img.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle).to_rgb8();
+    let (sw, sh) = sample.dimensions();
+    if sw < 3 || sh < 3 {
+        return ContentKind::General;
+    }
+
+    // Flat artwork: most neighbouring pixels share (almost) the same color
+    let mut flat = 0usize;
+    let mut pairs = 0usize;
+    for y in 0..sh {
+        for x in 1..sw {
+            pairs += 1;
+            if color_distance(sample.get_pixel(x, y), sample.get_pixel(x - 1, y)) < 6 {
+                flat += 1;
+            }
+        }
+    }
+    let flatness = flat as f32 / pairs.max(1) as f32;
+
+    // Portrait: skin tones concentrated in the central area of a non-landscape frame
+    let (x0, x1, y0, y1) = (sw / 4, sw * 3 / 4, sh / 8, sh * 3 / 4);
+    let central = ((x1 - x0) * (y1 - y0)) as f32;
+    let skin = (y0..y1)
+        .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_skin(sample.get_pixel(x, y)))
+        .count() as f32 / central;
+
+    // Product: a uniform border around the subject
+    let border: Vec<&Rgb<u8>> = (0..sw).flat_map(|x| [sample.get_pixel(x, 0), sample.get_pixel(x, sh - 1)])
+        .chain((0..sh).flat_map(|y| [sample.get_pixel(0, y), sample.get_pixel(sw - 1, y)]))
+        .collect();
+    let first = border[0];
+    let uniform_border = border.iter().filter(|p| color_distance(p, first) < 24).count() as f32 / border.len() as f32;
+
+    if skin > 0.15 && h >= w * 3 / 4 {
+        ContentKind::Portrait
+    } else if flatness > 0.7 {
+        ContentKind::Artwork
+    } else if uniform_border > 0.9 {
+        ContentKind::Product
+    } else {
+        ContentKind::General
+    }
+}
+
+fn color_distance(a: &Rgb<u8>, b: &Rgb<u8>) -> u32 {
+    a.0.iter().zip(b.0.iter()).map(|(&x, &y)| (x as i32 - y as i32).unsigned_abs()).sum()
+}
+
+/// Classic YCbCr skin-tone rule.
+fn is_skin(p: &Rgb<u8>) -> bool {
+    let [r, g, b] = p.0.map(|v| v as f32);
+    let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+    let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+    (77.0..=127.0).contains(&cb) && (133.0..=173.0).contains(&cr)
+}
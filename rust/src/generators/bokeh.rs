@@ -0,0 +1,62 @@
+//! Background blur ("portrait mode") output.
+//!
+//! Keeps the original background but blurs it outside the subject mask. The
+//! blur is normalized by the background weight so subject colors do not bleed
+//! into the halo around it, and the mask edge is feathered before compositing.
+
+use std::path::Path;
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgb, Rgb32FImage, RgbImage, imageops};
+use crate::lang::LanguageManager;
+use crate::generators::{LogOutput, alpha, encode};
+
+#[derive(Clone, Debug)]
+pub struct BokehOptions {
+    /// Background blur radius in pixels.
+    pub radius: f32,
+    /// Width of the soft transition at the subject edge, in pixels.
+    pub feather: f32,
+    /// JPEG quality from 1 to 100.
+    pub quality: u8,
+}
+
+impl Default for BokehOptions {
+    fn default() -> Self {
+        Self { radius: 12.0, feather: 2.0, quality: encode::DEFAULT_QUALITY }
+    }
+}
+
+/// Writes `original` with its background blurred, using the alpha of `cutout` as the mask.
+/// The cut-out may be smaller than the original (megapixel guard); its mask is resized.
+#[tracing::instrument(skip_all)]
+pub fn generate_bokeh(original: &DynamicImage, cutout: &DynamicImage, output_path: &Path, opts: &BokehOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    let (width, height) = original.dimensions();
+    let mask = alpha::mask_from_cutout(cutout, width, height);
+
+    let rgb = original.to_rgb32f();
+    let background = |x: u32, y: u32| 1.0 - mask.get_pixel(x, y)[0] as f32 / 255.0;
+
+    // Blur only the background, then divide by the blurred background weight
+    let sigma = (opts.radius / 2.0).max(0.1);
+    let weighted = Rgb32FImage::from_fn(width, height, |x, y| {
+        let w = background(x, y);
+        Rgb(rgb.get_pixel(x, y).0.map(|v| v * w))
+    });
+    let weights: ImageBuffer<Luma<f32>, Vec<f32>> = ImageBuffer::from_fn(width, height, |x, y| Luma([background(x, y)]));
+    let blurred = imageops::fast_blur(&weighted, sigma);
+    let weights = imageops::fast_blur(&weights, sigma);
+
+    let feathered = if opts.feather > 0.0 { imageops::blur(&mask, opts.feather / 2.0) } else { mask };
+
+    let out = RgbImage::from_fn(width, height, |x, y| {
+        let src = rgb.get_pixel(x, y).0;
+        let w = weights.get_pixel(x, y)[0];
+        let bg = if w > 1e-3 { blurred.get_pixel(x, y).0.map(|v| v / w) } else { src };
+        let m = feathered.get_pixel(x, y)[0] as f32 / 255.0;
+        Rgb([0, 1, 2].map(|c| ((src[c] * m + bg[c] * (1.0 - m)) * 255.0).round().clamp(0.0, 255.0) as u8))
+    });
+
+    encode::save(&DynamicImage::ImageRgb8(out), output_path, opts.quality)?;
+    logger.send(lang.t_args("log_bokeh_ok", &[lang.format_file_name(output_path)]));
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! CMYK halftone separation for print output.
+//!
+//! Uses a naive RGB to CMYK conversion (no ICC profile) with full black
+//! generation, then screens each channel at the traditional angles.
+
+use image::{DynamicImage, GrayImage, Luma};
+use std::path::Path;
+use anyhow::Result;
+use crate::lang::LanguageManager;
+use crate::generators::LogOutput;
+use crate::generators::mono::halftone_dots;
+use crate::generators::svg::{SvgLayer, SvgOptions, WorkingImage};
+
+/// Channel name, screen angle in degrees and preview ink color.
+const CHANNELS: [(&str, f32, &str); 4] = [
+    ("Cyan", 15.0, "#00ffff"),
+    ("Magenta", 75.0, "#ff00ff"),
+    ("Yellow", 0.0, "#ffff00"),
+    ("Black", 45.0, "#000000"),
+];
+
+/// Writes a composite CMYK preview to `output_path` and one black film per
+/// channel as `{stem}_sep_{channel}.svg`.
+#[tracing::instrument(skip_all)]
+pub fn generate_cmyk_halftone(img: &DynamicImage, output_path: &Path, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    let work = WorkingImage::new(img, opts);
+    let tones = separate(&work.image);
+    let (width, height) = tones[0].dimensions();
+
+    let mut preview = vec![SvgLayer::background(
+        "Background",
+        format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>", width, height),
+    )];
+    let mut films = Vec::new();
+    for ((name, angle, color), tone) in CHANNELS.iter().zip(&tones) {
+        let dots = halftone_dots(tone, *angle, color);
+        preview.push(SvgLayer::new(*name, format!("<g style=\"mix-blend-mode:multiply\">\n{}\n</g>", dots)));
+        films.push(SvgLayer::new(*name, halftone_dots(tone, *angle, "#000")));
+    }
+
+    work.write(output_path, &preview)?;
+    work.write_separations(output_path, &films)?;
+    logger.send(lang.t_args("log_svg_mono_ok", &[lang.format_file_name(output_path)]));
+    Ok(())
+}
+
+/// Splits an image into C, M, Y, K tone images (0 = full ink, 255 = none).
+/// Transparent areas are treated as paper white.
+fn separate(img: &DynamicImage) -> [GrayImage; 4] {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mut channels: [GrayImage; 4] = std::array::from_fn(|_| GrayImage::new(width, height));
+
+    for (x, y, p) in rgba.enumerate_pixels() {
+        let alpha = p.0[3] as f32 / 255.0;
+        // Composite over white
+        let [r, g, b] = [0, 1, 2].map(|i| 1.0 - alpha * (1.0 - p.0[i] as f32 / 255.0));
+        let k = 1.0 - r.max(g).max(b);
+        let inks = if k >= 1.0 {
+            [0.0, 0.0, 0.0, 1.0]
+        } else {
+            [
+                (1.0 - r - k) / (1.0 - k),
+                (1.0 - g - k) / (1.0 - k),
+                (1.0 - b - k) / (1.0 - k),
+                k,
+            ]
+        };
+        for (channel, ink) in channels.iter_mut().zip(inks) {
+            channel.put_pixel(x, y, Luma([((1.0 - ink) * 255.0).round() as u8]));
+        }
+    }
+    channels
+}
@@ -1,29 +1,92 @@
-use image::{DynamicImage, Luma};
+use image::{DynamicImage, GrayImage, Luma, RgbaImage};
 use std::path::Path;
 use std::process::Command;
-use std::fs;
 use anyhow::{Result, anyhow};
-use tempfile::NamedTempFile;
 use kmeans_colors::get_kmeans;
 use palette::{Srgb, Lab, FromColor, IntoColor};
+use tempfile::TempPath;
 
+use crate::error::AlphasvgError;
 use crate::lang::LanguageManager;
-use crate::generators::LogOutput;
+use crate::temp;
+use crate::generators::{LogOutput, denoise, geometry, gradient, ocr, regions, symmetry, text_regions};
+use crate::generators::colorblind::SafePalette;
+use crate::generators::embroidery::{self, StitchBlock};
+use crate::generators::svg::{self, PotracePaint, SvgOptions, WorkingImage};
 
-pub fn generate_logo(img: &DynamicImage, output_path: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
-    generate_color_svg(img, output_path, 16, lang, logger)
+/// Default palette size of the logo output.
+pub const LOGO_COLORS: u32 = 16;
+/// Default palette size of the illustration output.
+pub const ILLUSTRATION_COLORS: u32 = 48;
+
+/// Quantization settings of the color SVG generators.
+#[derive(Clone, Copy, Debug)]
+pub struct Quantize {
+    pub num_colors: u32,
+    /// Snap the quantized colors to a colorblind-safe set; clusters that land
+    /// on the same color share a layer.
+    pub palette: Option<SafePalette>,
+    /// Edge-preserving smoothing before quantization, from 0 (off) to 1, so
+    /// flat areas come out as single regions instead of speckles.
+    pub smoothing: f32,
+    /// Levels per channel the smoothed image is posterized to (below 2 disables it).
+    pub posterize: u32,
 }
 
-pub fn generate_illustration(img: &DynamicImage, output_path: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
-    generate_color_svg(img, output_path, 48, lang, logger)
+/// Drawing options of the logo output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogoStyle {
+    /// Draw each color region as a stroked outline of this width, in document
+    /// pixels, with no fill (for laser engraving or coloring pages) instead of a filled shape.
+    pub outline: Option<f32>,
+    /// Make a near-symmetric logo exactly symmetric, tolerating this share
+    /// (0 to 1) of its pixels out of symmetry, see [`symmetry`].
+    pub symmetry: Option<f32>,
+    /// Strength, from 0 (off) to 1, of the straightening and smoothing of the
+    /// traced paths, see [`geometry`].
+    pub cleanup: f32,
 }
 
-fn generate_color_svg(img: &DynamicImage, output_path: &Path, num_colors: u32, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+/// Passes of the bilateral filter at full smoothing strength.
+const SMOOTHING_PASSES: f32 = 3.0;
+
+/// Smooths and posterizes `rgba` per `quantize`, keeping alpha.
+fn presmooth(rgba: RgbaImage, quantize: &Quantize) -> RgbaImage {
+    let mut rgba = rgba;
+    if quantize.smoothing > 0.0 {
+        let passes = (quantize.smoothing * SMOOTHING_PASSES).ceil() as u32;
+        for _ in 0..passes {
+            rgba = denoise::bilateral(&rgba, quantize.smoothing);
+        }
+    }
+    if quantize.posterize >= 2 {
+        let step = 255.0 / (quantize.posterize - 1) as f32;
+        for pixel in rgba.pixels_mut() {
+            for c in 0..3 {
+                pixel[c] = ((pixel[c] as f32 / step).round() * step).round() as u8;
+            }
+        }
+    }
+    rgba
+}
+
+/// Writes the logo SVG, drawn per `style`.
+pub fn generate_logo(img: &DynamicImage, output_path: &Path, quantize: Quantize, style: LogoStyle, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    generate_color_svg(img, output_path, quantize, style, opts, lang, logger)
+}
+
+pub fn generate_illustration(img: &DynamicImage, output_path: &Path, quantize: Quantize, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    generate_color_svg(img, output_path, quantize, LogoStyle::default(), opts, lang, logger)
+}
+
+#[tracing::instrument(skip_all)]
+fn generate_color_svg(img: &DynamicImage, output_path: &Path, quantize: Quantize, style: LogoStyle, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
     if output_path.exists() {
         return Ok(());
     }
 
-    let rgba = img.to_rgba8();
+    let work = WorkingImage::new(img, opts);
+    let rgba = tracing::info_span!("presmooth").in_scope(|| presmooth(work.image.to_rgba8(), &quantize));
     let (width, height) = rgba.dimensions();
     
     // Collect visible pixels for K-means
@@ -44,113 +107,204 @@ fn generate_color_svg(img: &DynamicImage, output_path: &Path, num_colors: u32, l
         return Err(anyhow!("No visible pixels found for color quantization"));
     }
 
-    let k = num_colors.min(pixels.len() as u32) as usize;
-    let result = get_kmeans(k, 10, 0.005, false, &pixels, 12345);
+    let k = quantize.num_colors.min(pixels.len() as u32) as usize;
+    let result = tracing::info_span!("kmeans").in_scope(|| get_kmeans(k, 10, 0.005, false, &pixels, 12345));
     let colors = result.centroids;
-    
-    let mut svg_layers = Vec::new();
 
-    for (i, Lab { l, a: ca, b: cb, .. }) in colors.iter().enumerate() {
-        let srgb: Srgb = Srgb::from_color(Lab::new(*l, *ca, *cb));
-        let r_u8 = (srgb.red * 255.0) as u8;
-        let g_u8 = (srgb.green * 255.0) as u8;
-        let b_u8 = (srgb.blue * 255.0) as u8;
+    // Output color of each cluster, snapped to the safe palette if one is selected
+    let cluster_colors: Vec<[u8; 3]> = colors.iter().map(|&lab| match quantize.palette {
+        Some(palette) => palette.nearest(lab),
+        None => {
+            let srgb: Srgb = Srgb::from_color(lab);
+            [(srgb.red * 255.0) as u8, (srgb.green * 255.0) as u8, (srgb.blue * 255.0) as u8]
+        }
+    }).collect();
+    let mut layer_colors: Vec<[u8; 3]> = Vec::new();
+    for color in &cluster_colors {
+        if !layer_colors.contains(color) {
+            layer_colors.push(*color);
+        }
+    }
+
+    // Nearest centroid of every visible pixel
+    let mut labels: Vec<Option<usize>> = rgba.pixels().map(|pixel| {
+        (pixel.0[3] > 20).then(|| {
+            let px_lab: Lab = Srgb::new(
+                pixel.0[0] as f32 / 255.0,
+                pixel.0[1] as f32 / 255.0,
+                pixel.0[2] as f32 / 255.0,
+            ).into_color();
+            let dist = |c: &Lab| (px_lab.l - c.l).powi(2) + (px_lab.a - c.a).powi(2) + (px_lab.b - c.b).powi(2);
+            (0..colors.len()).min_by(|&a, &b| dist(&colors[a]).total_cmp(&dist(&colors[b]))).unwrap_or(0)
+        })
+    }).collect();
+
+    if let Some(min_area) = opts.min_region_area {
+        let merged = regions::merge_small(&mut labels, width, height, min_area.pixels(width, height, work.scale()));
+        logger.verbose(lang.t_args("log_regions_merged", &[merged]));
+    }
+
+    // Text is drawn over the color layers on its own, so it is kept out of them;
+    // regions already recognized as text are not traced again
+    let text_regions: Vec<text_regions::TextRegion> = opts.text_regions.iter()
+        .filter(|r| !opts.ocr_lines.iter().any(|line| line.overlaps(r)))
+        .copied()
+        .collect();
+    let text_mode = opts.text.filter(|_| !text_regions.is_empty());
+    if text_mode.is_some() || !opts.ocr_lines.is_empty() {
+        let scale = work.scale();
+        for (i, label) in labels.iter_mut().enumerate() {
+            let (x, y) = (((i as u32 % width) as f32 * scale) as u32, ((i as u32 / width) as f32 * scale) as u32);
+            if text_regions.iter().any(|r| r.contains(x, y)) || opts.ocr_lines.iter().any(|line| line.contains(x, y)) {
+                *label = None;
+            }
+        }
+    }
+
+    // Each color layer is streamed to the file once traced
+    let mut document = work.sink(output_path)?;
+    let mut stitch_blocks = Vec::new();
+    let mm_per_px = work.scale() * 25.4 / opts.dpi;
+    // Potrace units are a tenth of a working pixel, and working pixels are scaled back to the document
+    let stroke_width = style.outline.map(|width| width * 10.0 / work.scale());
+
+    // Smooth shadings go below the flat colors as single gradient shapes, leaving the flat layers without them
+    if opts.gradients {
+        let bands: Vec<Option<[u8; 3]>> = labels.iter().map(|label| label.map(|i| cluster_colors[i])).collect();
+        let regions = tracing::info_span!("gradients").in_scope(|| gradient::find_gradients(&rgba, &bands));
+        for (index, region) in regions.iter().enumerate() {
+            let mut mask = GrayImage::from_pixel(width, height, Luma([255u8]));
+            for &i in &region.pixels {
+                mask.as_mut()[i] = 0;
+                labels[i] = None;
+            }
+            if let Some(traced) = trace(&mask, output_path, style.cleanup)? {
+                let id = format!("gradient{}", index + 1);
+                let fill = format!("url(#{})", id);
+                document.add_with(&format!("Gradient {}", index + 1), |out| {
+                    out.write_all(region.fill.svg_defs(&id, height).as_bytes())?;
+                    svg::copy_potrace_body(&traced, paint(&fill, stroke_width), out)
+                })?;
+            }
+        }
+        logger.verbose(lang.t_args("log_gradients_found", &[regions.len()]));
+    }
 
+    // A near-symmetric logo is made exactly symmetric: one region is traced and repeated.
+    // Outlines are traced whole instead, as the cut edges of the region would be stroked.
+    let symmetry = style.symmetry.and_then(|tolerance| {
+        tracing::info_span!("symmetry").in_scope(|| symmetry::detect(&labels, width, height, tolerance))
+    });
+    if let Some(found) = symmetry {
+        symmetry::enforce(&mut labels, width, height, found);
+        logger.verbose(lang.t_args("log_symmetry_found", &[found]));
+    }
+    let repeated = symmetry.filter(|_| style.outline.is_none());
+
+    for [r_u8, g_u8, b_u8] in layer_colors {
         if r_u8 > 245 && g_u8 > 245 && b_u8 > 245 { continue; } // Skip background
 
-        let mut mask = image::ImageBuffer::new(width, height);
         let mut found = false;
-        
-        for (x, y, pixel) in rgba.enumerate_pixels() {
-            if pixel.0[3] > 20 {
-                let px_srgb = Srgb::new(
-                    pixel.0[0] as f32 / 255.0,
-                    pixel.0[1] as f32 / 255.0,
-                    pixel.0[2] as f32 / 255.0,
-                );
-                let px_lab: Lab = px_srgb.into_color();
-                
-                // Find nearest centroid
-                let mut min_dist = f32::MAX;
-                let mut best_idx = 0;
-                for (idx, centroid) in colors.iter().enumerate() {
-                    let d = (px_lab.l - centroid.l).powi(2) + (px_lab.a - centroid.a).powi(2) + (px_lab.b - centroid.b).powi(2);
-                    if d < min_dist {
-                        min_dist = d;
-                        best_idx = idx;
-                    }
-                }
-
-                if best_idx == i {
-                    mask.put_pixel(x, y, Luma([0u8]));
-                    found = true;
-                } else {
-                    mask.put_pixel(x, y, Luma([255u8]));
-                }
+        let mask = image::ImageBuffer::from_fn(width, height, |x, y| {
+            let label = labels[(y * width + x) as usize];
+            if label.is_some_and(|i| cluster_colors[i] == [r_u8, g_u8, b_u8]) {
+                found = true;
+                Luma([0u8])
             } else {
-                mask.put_pixel(x, y, Luma([255u8]));
+                Luma([255u8])
             }
-        }
+        });
 
         if !found { continue; }
 
-        let temp_bmp = NamedTempFile::new_in(".")?;
-        let bmp_path = temp_bmp.path().with_extension("bmp");
-        mask.save(&bmp_path)?;
-
-        let temp_svg = NamedTempFile::new_in(".")?;
-        let svg_tmp_path = temp_svg.path().with_extension("svg");
-
-        let status = Command::new("potrace")
-            .args(&[
-                bmp_path.to_str().unwrap(),
-                "-s",
-                "-o",
-                svg_tmp_path.to_str().unwrap(),
-                "--flat",
-                "--turdsize", "2",
-                "--alphamax", "0.8",
-            ])
-            .status()?;
-
-        if status.success() {
-            let content = fs::read_to_string(&svg_tmp_path)?;
+        if let Some(embroidery_opts) = &opts.embroidery {
+            stitch_blocks.push(StitchBlock::fill(&mask, [r_u8, g_u8, b_u8], mm_per_px, embroidery_opts));
+        }
+
+        let mask = match repeated {
+            Some(found) => GrayImage::from_fn(width, height, |x, y| if found.traces(x, y) { *mask.get_pixel(x, y) } else { Luma([255u8]) }),
+            None => mask,
+        };
+        if let Some(traced) = trace(&mask, output_path, style.cleanup)? {
             let hex_color = format!("#{:02x}{:02x}{:02x}", r_u8, g_u8, b_u8);
-            
-            // Robustly extract the content between <svg ...> and </svg>
-            if let Some(start_idx) = content.find("<svg") {
-                if let Some(content_start) = content[start_idx..].find('>') {
-                    let inner_content_start = start_idx + content_start + 1;
-                    if let Some(end_idx) = content.rfind("</svg>") {
-                        let inner_content = &content[inner_content_start..end_idx];
-                        // Replace common black fill values
-                        let colored_content = inner_content
-                            .replace("fill=\"black\"", &format!("fill=\"{}\"", hex_color))
-                            .replace("fill=\"#000000\"", &format!("fill=\"{}\"", hex_color));
-                        svg_layers.push(colored_content);
+            let paint = paint(&hex_color, stroke_width);
+            document.add_with(&format!("Color {}", hex_color), |out| match repeated {
+                Some(found) => {
+                    for transform in found.transforms() {
+                        if transform.is_empty() {
+                            svg::copy_potrace_body(&traced, paint, out)?;
+                        } else {
+                            writeln!(out, "<g transform=\"{}\">", transform)?;
+                            svg::copy_potrace_body(&traced, paint, out)?;
+                            writeln!(out, "</g>")?;
+                        }
                     }
+                    Ok(())
                 }
-            }
+                None => svg::copy_potrace_body(&traced, paint, out),
+            })?;
         }
-        
-        let _ = fs::remove_file(bmp_path);
-        let _ = fs::remove_file(svg_tmp_path);
     }
 
-    let mut final_svg = format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
-        <svg version=\"1.1\" xmlns=\"http://www.w3.org/2000/svg\" \
-        width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
-        width, height, width, height
-    );
-
-    for layer in svg_layers {
-        final_svg.push_str(&layer);
-        final_svg.push('\n');
+    if let Some(mode) = text_mode {
+        for layer in text_regions::layers(img, &text_regions, mode, output_path)? {
+            document.add(layer)?;
+        }
+    }
+    if !opts.ocr_lines.is_empty() {
+        document.add(ocr::layer(img, &opts.ocr_lines))?;
+        logger.verbose(lang.t_args("log_ocr_lines", &[opts.ocr_lines.len()]));
     }
-    final_svg.push_str("</svg>");
 
-    fs::write(output_path, final_svg)?;
-    logger.send(format!("{}{:?}", lang.t("log_svg_color_ok"), output_path.file_name().unwrap()));
+    document.finish()?;
+
+    if let Some(embroidery_opts) = &opts.embroidery {
+        let stitch_path = output_path.with_extension(embroidery_opts.format.extension());
+        let label = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("alphasvg");
+        embroidery::write(&stitch_blocks, label, embroidery_opts.format, &stitch_path)?;
+        let stitches: usize = stitch_blocks.iter().map(StitchBlock::stitch_count).sum();
+        let threads: Vec<String> = stitch_blocks.iter()
+            .map(|b| format!("#{:02x}{:02x}{:02x}", b.color[0], b.color[1], b.color[2]))
+            .collect();
+        logger.verbose(lang.t_args("log_embroidery", &[lang.format_file_name(&stitch_path), lang.format_number(stitches as f64, 0), threads.join(" ")]));
+    }
+    logger.send(lang.t_args("log_svg_color_ok", &[lang.format_file_name(output_path)]));
     Ok(())
 }
+
+/// Fill with `color`, or an outline of `stroke_width` potrace units in it.
+fn paint(color: &str, stroke_width: Option<f32>) -> PotracePaint<'_> {
+    match stroke_width {
+        Some(width) => PotracePaint::Stroke { color, width },
+        None => PotracePaint::Fill(color),
+    }
+}
+
+/// Traces the black pixels of `mask` with potrace into a temporary SVG next to
+/// `output_path`, with the paths cleaned at `cleanup`; `None` when potrace fails.
+fn trace(mask: &GrayImage, output_path: &Path, cleanup: f32) -> Result<Option<TempPath>> {
+    let bmp_path = temp::path_beside(output_path, "bmp")?;
+    mask.save(&bmp_path).map_err(|e| AlphasvgError::encode(&bmp_path, e))?;
+
+    let svg_tmp_path = temp::path_beside(output_path, "svg")?;
+
+    let status = tracing::info_span!("potrace").in_scope(|| Command::new("potrace")
+        .args(&[
+            bmp_path.to_str().unwrap(),
+            "-s",
+            "-o",
+            svg_tmp_path.to_str().unwrap(),
+            "--flat",
+            "--turdsize", "2",
+            "--alphamax", "0.8",
+        ])
+        .status())?;
+
+    if !status.success() {
+        return Ok(None);
+    }
+    if cleanup > 0.0 {
+        geometry::clean_file(&svg_tmp_path, cleanup)?;
+    }
+    Ok(Some(svg_tmp_path))
+}
@@ -0,0 +1,42 @@
+//! Selective desaturation ("color pop") output.
+//!
+//! The subject keeps its colors while the background of the original photo is
+//! turned to grayscale, optionally tinted. The format follows the output
+//! extension, see [`encode`].
+
+use std::path::Path;
+use anyhow::Result;
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use crate::lang::LanguageManager;
+use crate::generators::{LogOutput, alpha, encode};
+
+/// Parses a `#RRGGBB` (or `RRGGBB`) tint color.
+pub fn parse_tint(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.trim_start_matches('#');
+    let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("expected a color as #RRGGBB: {}", value)),
+    }
+}
+
+/// Writes `original` with everything outside the `cutout` alpha desaturated.
+/// With a `tint`, the background grayscale is multiplied by that color.
+#[tracing::instrument(skip_all)]
+pub fn generate_color_pop(original: &DynamicImage, cutout: &DynamicImage, output_path: &Path, tint: Option<[u8; 3]>, quality: u8, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    let (width, height) = original.dimensions();
+    let mask = alpha::mask_from_cutout(cutout, width, height);
+    let rgb = original.to_rgb8();
+    let tint = tint.unwrap_or([255, 255, 255]).map(|c| c as f32 / 255.0);
+
+    let out = RgbImage::from_fn(width, height, |x, y| {
+        let px = rgb.get_pixel(x, y).0.map(f32::from);
+        let gray = px[0] * 0.299 + px[1] * 0.587 + px[2] * 0.114;
+        let m = mask.get_pixel(x, y)[0] as f32 / 255.0;
+        Rgb([0, 1, 2].map(|c| (px[c] * m + gray * tint[c] * (1.0 - m)).round() as u8))
+    });
+
+    encode::save(&DynamicImage::ImageRgb8(out), output_path, quality)?;
+    logger.send(lang.t_args("log_color_pop_ok", &[lang.format_file_name(output_path)]));
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Colorblind-safe palettes and color vision deficiency simulation.
+//!
+//! The color SVG generators can snap their quantized colors to a safe set, and
+//! the GUI previews outputs as seen with deuteranopia or protanopia using the
+//! Machado, Oliveira & Fernandes (2009) matrices at full severity.
+
+use image::RgbaImage;
+use palette::{FromColor, Lab, LinSrgb, Srgb};
+
+/// Fixed palette the quantized colors are snapped to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SafePalette {
+    /// Okabe & Ito's eight colors, distinguishable with every common deficiency.
+    OkabeIto,
+    /// Eight samples of viridis, monotonic in lightness.
+    Viridis,
+}
+
+/// Names accepted by the `palette` parameter; `free` keeps the k-means colors.
+pub const PALETTE_OPTIONS: &[&str] = &["free", "okabe-ito", "viridis"];
+
+impl SafePalette {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "okabe-ito" => Some(SafePalette::OkabeIto),
+            "viridis" => Some(SafePalette::Viridis),
+            _ => None,
+        }
+    }
+
+    pub fn colors(self) -> &'static [[u8; 3]] {
+        match self {
+            SafePalette::OkabeIto => &[
+                [0x00, 0x00, 0x00], [0xe6, 0x9f, 0x00], [0x56, 0xb4, 0xe9], [0x00, 0x9e, 0x73],
+                [0xf0, 0xe4, 0x42], [0x00, 0x72, 0xb2], [0xd5, 0x5e, 0x00], [0xcc, 0x79, 0xa7],
+            ],
+            SafePalette::Viridis => &[
+                [0x44, 0x01, 0x54], [0x46, 0x32, 0x7e], [0x36, 0x5c, 0x8d], [0x27, 0x7f, 0x8e],
+                [0x1f, 0xa1, 0x87], [0x4a, 0xc1, 0x6d], [0xa0, 0xda, 0x39], [0xfd, 0xe7, 0x25],
+            ],
+        }
+    }
+
+    /// Palette color closest to `color` in CIELAB.
+    pub fn nearest(self, color: Lab) -> [u8; 3] {
+        *self.colors().iter()
+            .min_by(|a, b| lab_distance(color, to_lab(**a)).total_cmp(&lab_distance(color, to_lab(**b))))
+            .expect("palettes are not empty")
+    }
+}
+
+/// Color vision deficiency simulated by the GUI preview.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Deficiency {
+    Deuteranopia,
+    Protanopia,
+}
+
+impl Deficiency {
+    pub const ALL: [Deficiency; 2] = [Deficiency::Deuteranopia, Deficiency::Protanopia];
+
+    /// Locale key of the deficiency name.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            Deficiency::Deuteranopia => "cvd_deuteranopia",
+            Deficiency::Protanopia => "cvd_protanopia",
+        }
+    }
+
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            Deficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Deficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+        }
+    }
+
+    /// How `rgb` appears with this deficiency.
+    pub fn simulate_color(self, [r, g, b]: [u8; 3]) -> [u8; 3] {
+        let linear: LinSrgb = Srgb::new(r, g, b).into_format::<f32>().into_linear();
+        let c = [linear.red, linear.green, linear.blue];
+        let m = self.matrix();
+        let row = |i: usize| (m[i][0] * c[0] + m[i][1] * c[1] + m[i][2] * c[2]).clamp(0.0, 1.0);
+        let out: Srgb<u8> = Srgb::from_linear(LinSrgb::new(row(0), row(1), row(2)));
+        [out.red, out.green, out.blue]
+    }
+
+    /// Copy of `img` as seen with this deficiency; alpha is kept.
+    pub fn simulate(self, img: &RgbaImage) -> RgbaImage {
+        let mut out = img.clone();
+        for pixel in out.pixels_mut() {
+            let [r, g, b] = self.simulate_color([pixel[0], pixel[1], pixel[2]]);
+            pixel.0 = [r, g, b, pixel[3]];
+        }
+        out
+    }
+}
+
+fn to_lab([r, g, b]: [u8; 3]) -> Lab {
+    Lab::from_color(Srgb::new(r, g, b).into_format::<f32>())
+}
+
+fn lab_distance(a: Lab, b: Lab) -> f32 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
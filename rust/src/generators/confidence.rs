@@ -0,0 +1,97 @@
+//! Mask confidence diagnostics.
+//!
+//! The alpha channel of a cut-out is read as the model's foreground probability:
+//! values near 0 or 255 are confident, values near the middle are ambiguous.
+//! The heatmap highlights the ambiguous areas so users can tell at a glance
+//! whether an image needs manual touch-up.
+
+use std::path::Path;
+use anyhow::Result;
+use image::{DynamicImage, GrayImage, Rgb, RgbImage};
+use serde::Serialize;
+
+use crate::error::AlphasvgError;
+
+/// Pixels below this confidence (0..1) count as ambiguous.
+const AMBIGUOUS_BELOW: f32 = 0.5;
+/// Radius, in pixels, around the foreground boundary considered "edge".
+const EDGE_RADIUS: i32 = 2;
+/// Weight of the heatmap over the dimmed image.
+const OVERLAY_OPACITY: f32 = 0.6;
+
+/// Summary stats added to the batch report.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub struct ConfidenceStats {
+    /// Mean confidence (0..1) of the pixels along the subject boundary.
+    pub mean_edge_confidence: f32,
+    /// Share of all pixels whose confidence is below 50%, in percent.
+    pub ambiguous_percent: f32,
+}
+
+/// Confidence of a single alpha value: 0 at 50% opacity, 1 when fully opaque or transparent.
+fn confidence(alpha: u8) -> f32 {
+    (alpha as f32 - 127.5).abs() / 127.5
+}
+
+/// Computes the confidence stats of an alpha channel.
+fn alpha_stats(alpha: &GrayImage) -> ConfidenceStats {
+    let total = alpha.pixels().len().max(1);
+    let ambiguous = alpha.pixels().filter(|p| confidence(p[0]) < AMBIGUOUS_BELOW).count();
+
+    let edge = edge_mask(alpha);
+    let (sum, count) = alpha.pixels().zip(edge.pixels())
+        .filter(|(_, e)| e[0] > 0)
+        .fold((0.0f32, 0usize), |(sum, count), (p, _)| (sum + confidence(p[0]), count + 1));
+
+    ConfidenceStats {
+        mean_edge_confidence: if count > 0 { sum / count as f32 } else { 1.0 },
+        ambiguous_percent: ambiguous as f32 * 100.0 / total as f32,
+    }
+}
+
+/// Writes the confidence heatmap of `img` over a dimmed copy of it and returns its stats.
+#[tracing::instrument(skip_all)]
+pub fn write_confidence_overlay(img: &DynamicImage, output_path: &Path) -> Result<ConfidenceStats> {
+    let alpha = alpha_channel(img);
+    let rgb = img.to_rgb8();
+    let mut overlay = RgbImage::new(rgb.width(), rgb.height());
+
+    for ((out, src), a) in overlay.pixels_mut().zip(rgb.pixels()).zip(alpha.pixels()) {
+        let heat = heat_color(confidence(a[0]));
+        let gray = (src[0] as f32 * 0.299 + src[1] as f32 * 0.587 + src[2] as f32 * 0.114) * 0.5;
+        *out = Rgb(heat.map(|h| (gray * (1.0 - OVERLAY_OPACITY) + h as f32 * OVERLAY_OPACITY).round() as u8));
+    }
+
+    overlay.save(output_path).map_err(|e| AlphasvgError::encode(output_path, e))?;
+    Ok(alpha_stats(&alpha))
+}
+
+/// Red for ambiguous, through yellow, to green for confident.
+fn heat_color(confidence: f32) -> [u8; 3] {
+    let c = confidence.clamp(0.0, 1.0);
+    if c < 0.5 {
+        [255, (c * 2.0 * 255.0) as u8, 0]
+    } else {
+        [((1.0 - c) * 2.0 * 255.0) as u8, 255, 0]
+    }
+}
+
+fn alpha_channel(img: &DynamicImage) -> GrayImage {
+    let rgba = img.to_rgba8();
+    GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| image::Luma([rgba.get_pixel(x, y)[3]]))
+}
+
+/// Marks pixels within [`EDGE_RADIUS`] of a foreground/background transition.
+fn edge_mask(alpha: &GrayImage) -> GrayImage {
+    let (w, h) = alpha.dimensions();
+    let inside = |x: i32, y: i32| alpha.get_pixel(x as u32, y as u32)[0] >= 128;
+    GrayImage::from_fn(w, h, |x, y| {
+        let (x, y) = (x as i32, y as i32);
+        let here = inside(x, y);
+        let crosses = (-EDGE_RADIUS..=EDGE_RADIUS).any(|dy| (-EDGE_RADIUS..=EDGE_RADIUS).any(|dx| {
+            let (nx, ny) = (x + dx, y + dy);
+            nx >= 0 && ny >= 0 && nx < w as i32 && ny < h as i32 && inside(nx, ny) != here
+        }));
+        image::Luma([if crosses { 255 } else { 0 }])
+    })
+}
@@ -0,0 +1,157 @@
+//! Freedesktop thumbnails of the outputs for Linux file managers.
+//!
+//! Follows the XDG thumbnail specification: a PNG per size in
+//! `$XDG_CACHE_HOME/thumbnails/{normal,large}`, named by the MD5 of the file
+//! URI and tagged with `Thumb::URI` and `Thumb::MTime` so file managers show
+//! the previews right away instead of queueing their own thumbnailers.
+
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use anyhow::{Result, Context, anyhow};
+use image::{DynamicImage, RgbaImage, imageops::FilterType};
+use md5::{Digest, Md5};
+use crate::generators::rasterize;
+use crate::temp;
+
+/// Thumbnail directories and the longest edge of their images.
+const SIZES: [(&str, u32); 2] = [("normal", 128), ("large", 256)];
+
+/// Writes the normal and large thumbnails of `path` (PNG, JPEG, WebP, SVG or SVGZ).
+pub fn write_thumbnails(path: &Path) -> Result<()> {
+    let Some(root) = thumbnail_root() else {
+        return Err(anyhow!("No cache directory for thumbnails"));
+    };
+    let path = fs::canonicalize(path)?;
+    let meta = fs::metadata(&path)?;
+    let mtime = meta.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+    let uri = file_uri(&path);
+    let mime = mime_type(&path);
+
+    let largest = SIZES.iter().map(|&(_, size)| size).max().unwrap_or(256);
+    let source = load(&path, mime, largest)?;
+    let name = format!("{}.png", md5_hex(uri.as_bytes()));
+
+    for (dir, size) in SIZES {
+        let dir = root.join(dir);
+        create_private_dir(&dir)?;
+        let thumb = fit(&source, size);
+        let texts = [
+            ("Thumb::URI", uri.clone()),
+            ("Thumb::MTime", mtime.to_string()),
+            ("Thumb::Size", meta.len().to_string()),
+            ("Thumb::Mimetype", mime.to_string()),
+            ("Software", format!("Transparente {}", env!("CARGO_PKG_VERSION"))),
+        ];
+        write_png(&thumb, &texts, &dir.join(&name))?;
+    }
+    Ok(())
+}
+
+/// MIME type the thumbnail records for `path`; compressed SVG is still `image/svg+xml`.
+fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("svg" | "svgz") => "image/svg+xml",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// `$XDG_CACHE_HOME/thumbnails`, or `~/.cache/thumbnails`.
+fn thumbnail_root() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("thumbnails"))
+}
+
+/// Loads `path` at no more than `max` pixels on its longest edge.
+fn load(path: &Path, mime: &str, max: u32) -> Result<RgbaImage> {
+    if mime == "image/svg+xml" {
+        let pixmap = rasterize::render_svg(path, |size| max as f32 / size.width().max(size.height()))?;
+        let png = pixmap.encode_png()?;
+        return Ok(image::load_from_memory(&png)?.into_rgba8());
+    }
+    Ok(fit(&image::open(path)?.into_rgba8(), max))
+}
+
+/// Downscales `img` to fit in `size`×`size`; smaller images are kept as is.
+fn fit(img: &RgbaImage, size: u32) -> RgbaImage {
+    if img.width().max(img.height()) <= size {
+        return img.clone();
+    }
+    DynamicImage::ImageRgba8(img.clone()).resize(size, size, FilterType::Triangle).into_rgba8()
+}
+
+/// Writes `img` with tEXt chunks, through a temporary file so readers never see a partial PNG.
+fn write_png(img: &RgbaImage, texts: &[(&str, String)], path: &Path) -> Result<()> {
+    let temp = temp::file_beside(path, "tmp")?;
+    {
+        let file = BufWriter::new(temp.as_file());
+        let mut encoder = png::Encoder::new(file, img.width(), img.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        for (keyword, text) in texts {
+            encoder.add_text_chunk(keyword.to_string(), text.clone())?;
+        }
+        encoder.write_header()?.write_image_data(img.as_raw())?;
+    }
+    set_private(temp.path(), 0o600)?;
+    temp.persist(path).map_err(|e| e.error).with_context(|| format!("Failed to write thumbnail {:?}", path))?;
+    Ok(())
+}
+
+fn create_private_dir(dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        fs::create_dir_all(dir)?;
+        set_private(dir, 0o700)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_private(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_private(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// `file://` URI of an absolute path, escaped like GLib's `g_filename_to_uri`.
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// MD5 digest of `data` as lowercase hex, as the spec names thumbnails by it.
+fn md5_hex(data: &[u8]) -> String {
+    Md5::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_thumbnails_like_the_spec() {
+        // The example of the freedesktop.org thumbnail specification
+        assert_eq!(md5_hex(b"file:///home/jens/photos/me.png"), "c6ee772d9e49320e97ec29a7eb5b1697");
+    }
+
+    #[test]
+    fn tags_every_previewable_format() {
+        for (name, mime) in [("a.png", "image/png"), ("a.jpg", "image/jpeg"), ("a.webp", "image/webp"), ("a.svg", "image/svg+xml"), ("a.svgz", "image/svg+xml")] {
+            assert_eq!(mime_type(Path::new(name)), mime);
+        }
+    }
+}
@@ -0,0 +1,593 @@
+//! Experimental embroidery export (Tajima DST or Brother PES).
+//!
+//! Each quantized color region becomes a block of one thread color, separated
+//! from the next by a color stop. Regions whose every line across is a single
+//! run no wider than the satin width are sewn as satin columns, zigzagging from
+//! edge to edge; the others are filled with tatami-style rows of running
+//! stitches. PES files carry only the PEC section, with the stitches, the
+//! nearest Brother thread of each color and the preview icons, which is what
+//! machines read; editors that need the PES design section may not open them.
+
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use image::GrayImage;
+
+/// DST and PEC coordinates are in 0.1 mm.
+const UNITS_PER_MM: f32 = 10.0;
+/// Largest move a single DST record can encode, in DST units.
+const DST_MAX_DELTA: i32 = 121;
+/// Largest move a long-form PEC record can encode.
+const PEC_MAX_DELTA: i32 = 2047;
+/// Bytes before the PEC section of a PES file: signature and PEC offset.
+const PES_HEADER_LEN: usize = 22;
+/// Length of the PEC header, up to the stitch block.
+const PEC_HEADER_LEN: usize = 512;
+/// Size of the monochrome PEC preview icons.
+const ICON_WIDTH: usize = 48;
+const ICON_HEIGHT: usize = 38;
+
+/// RGB of the PEC thread palette, indices 1 to 64.
+const PEC_THREADS: [[u8; 3]; 64] = [
+    [14, 31, 124], [10, 85, 163], [0, 135, 119], [75, 107, 175], [237, 23, 31], [209, 92, 0], [145, 54, 151], [228, 154, 203],
+    [145, 95, 172], [158, 214, 125], [232, 169, 0], [254, 186, 53], [255, 255, 0], [112, 188, 31], [186, 152, 0], [168, 168, 168],
+    [125, 111, 0], [255, 255, 179], [79, 85, 86], [0, 0, 0], [11, 61, 145], [119, 1, 118], [41, 49, 51], [42, 19, 1],
+    [246, 74, 138], [178, 118, 36], [252, 187, 197], [254, 55, 15], [240, 240, 240], [106, 28, 138], [168, 221, 196], [37, 132, 187],
+    [254, 179, 67], [255, 243, 107], [208, 166, 96], [209, 84, 0], [102, 186, 73], [19, 74, 70], [135, 135, 135], [216, 204, 198],
+    [67, 86, 7], [253, 217, 222], [249, 147, 188], [0, 56, 34], [178, 175, 212], [104, 106, 176], [239, 227, 185], [247, 56, 102],
+    [181, 75, 100], [19, 43, 26], [199, 1, 86], [254, 158, 50], [168, 222, 235], [0, 103, 62], [78, 41, 144], [47, 126, 32],
+    [255, 204, 204], [255, 217, 17], [9, 91, 166], [240, 249, 112], [227, 243, 91], [255, 153, 0], [255, 240, 141], [255, 200, 200],
+];
+
+/// File format of the embroidery export.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum EmbroideryFormat {
+    /// Tajima DST, accepted by nearly every machine and editor.
+    #[default]
+    Dst,
+    /// Brother PES (PEC section only).
+    Pes,
+}
+
+impl EmbroideryFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            EmbroideryFormat::Dst => "dst",
+            EmbroideryFormat::Pes => "pes",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmbroideryOptions {
+    /// Distance between fill rows in millimetres (lower is denser); satin
+    /// needle points are half as far apart on each edge.
+    pub row_spacing_mm: f32,
+    /// Longest stitch in millimetres; longer runs are split.
+    pub max_stitch_mm: f32,
+    /// Widest region, in millimetres, sewn as a satin column; 0 fills every region.
+    pub satin_max_width_mm: f32,
+    pub format: EmbroideryFormat,
+}
+
+impl Default for EmbroideryOptions {
+    fn default() -> Self {
+        Self { row_spacing_mm: 0.4, max_stitch_mm: 3.5, satin_max_width_mm: 4.0, format: EmbroideryFormat::Dst }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Move {
+    Stitch(f32, f32),
+    Jump(f32, f32),
+}
+
+impl Move {
+    /// Position in 0.1 mm units.
+    fn units(self) -> (i32, i32) {
+        let (Move::Stitch(x, y) | Move::Jump(x, y)) = self;
+        ((x * UNITS_PER_MM).round() as i32, (y * UNITS_PER_MM).round() as i32)
+    }
+}
+
+/// Direction the scan lines across a region run in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Axis {
+    Rows,
+    Columns,
+}
+
+impl Axis {
+    /// Point in millimetres at `along` on scan line `line`, both in pixels.
+    fn point(self, line: f32, along: f32, mm_per_px: f32) -> (f32, f32) {
+        match self {
+            Axis::Rows => (along * mm_per_px, line * mm_per_px),
+            Axis::Columns => (line * mm_per_px, along * mm_per_px),
+        }
+    }
+}
+
+/// A 4-connected region of a mask, as the pixels of its bounding box.
+struct Region {
+    x0: u32,
+    y0: u32,
+    width: u32,
+    height: u32,
+    inside: Vec<bool>,
+}
+
+impl Region {
+    /// The 4-connected regions of the black (0) pixels of `mask`.
+    fn find(mask: &GrayImage) -> Vec<Region> {
+        let (width, height) = (mask.width() as usize, mask.height() as usize);
+        let pixels = mask.as_raw();
+        let mut seen = vec![false; pixels.len()];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+        for seed in 0..pixels.len() {
+            if seen[seed] || pixels[seed] != 0 {
+                continue;
+            }
+            seen[seed] = true;
+            stack.push(seed);
+            let mut members = Vec::new();
+            while let Some(i) = stack.pop() {
+                members.push(i);
+                let (x, y) = (i % width, i / width);
+                let neighbors = [
+                    (x > 0).then(|| i - 1),
+                    (x + 1 < width).then(|| i + 1),
+                    (y > 0).then(|| i - width),
+                    (y + 1 < height).then(|| i + width),
+                ];
+                for n in neighbors.into_iter().flatten() {
+                    if !seen[n] && pixels[n] == 0 {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+            let x0 = members.iter().map(|i| i % width).min().unwrap_or(0);
+            let x1 = members.iter().map(|i| i % width).max().unwrap_or(0);
+            let y0 = members.iter().map(|i| i / width).min().unwrap_or(0);
+            let y1 = members.iter().map(|i| i / width).max().unwrap_or(0);
+            let region_width = x1 - x0 + 1;
+            let mut inside = vec![false; region_width * (y1 - y0 + 1)];
+            for i in members {
+                inside[(i / width - y0) * region_width + i % width - x0] = true;
+            }
+            regions.push(Region { x0: x0 as u32, y0: y0 as u32, width: region_width as u32, height: (y1 - y0 + 1) as u32, inside });
+        }
+        regions
+    }
+
+    fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x0 && y >= self.y0 && x < self.x0 + self.width && y < self.y0 + self.height
+            && self.inside[((y - self.y0) * self.width + x - self.x0) as usize]
+    }
+
+    /// First scan line along `axis` and how many cross the region.
+    fn lines(&self, axis: Axis) -> (u32, u32) {
+        match axis {
+            Axis::Rows => (self.y0, self.height),
+            Axis::Columns => (self.x0, self.width),
+        }
+    }
+
+    /// Runs of the region on scan line `line`, as the first and last pixel along it.
+    fn runs(&self, axis: Axis, line: u32) -> Vec<(u32, u32)> {
+        let (start, length) = self.lines(match axis {
+            Axis::Rows => Axis::Columns,
+            Axis::Columns => Axis::Rows,
+        });
+        let at = |p: u32| match axis {
+            Axis::Rows => self.contains(p, line),
+            Axis::Columns => self.contains(line, p),
+        };
+        let mut runs = Vec::new();
+        let mut p = start;
+        while p < start + length {
+            if at(p) {
+                let first = p;
+                while p < start + length && at(p) {
+                    p += 1;
+                }
+                runs.push((first, p - 1));
+            }
+            p += 1;
+        }
+        runs
+    }
+
+    /// Axis whose scan lines cross the region as a satin column: every line a
+    /// single run of at most `max_width` pixels. The narrower one when both do.
+    fn satin_axis(&self, max_width: f32) -> Option<Axis> {
+        [Axis::Rows, Axis::Columns].into_iter()
+            .filter_map(|axis| {
+                let (first, count) = self.lines(axis);
+                let mut widest = 0;
+                for line in first..first + count {
+                    match self.runs(axis, line)[..] {
+                        [(from, to)] => widest = widest.max(to - from + 1),
+                        _ => return None,
+                    }
+                }
+                (widest as f32 <= max_width).then_some((axis, widest))
+            })
+            .min_by_key(|&(_, widest)| widest)
+            .map(|(axis, _)| axis)
+    }
+}
+
+/// Stitches of one thread color, in millimetres with y pointing down.
+#[derive(Clone, Debug)]
+pub struct StitchBlock {
+    pub color: [u8; 3],
+    moves: Vec<Move>,
+}
+
+impl StitchBlock {
+    /// Sews the black (0) pixels of `mask`, region by region, as satin columns
+    /// or back-and-forth rows of fill stitches.
+    pub fn fill(mask: &GrayImage, color: [u8; 3], mm_per_px: f32, opts: &EmbroideryOptions) -> Self {
+        let row_step = (opts.row_spacing_mm / mm_per_px).max(1.0);
+        let max_stitch = opts.max_stitch_mm.max(0.1);
+        let satin_width = opts.satin_max_width_mm / mm_per_px;
+        let mut block = Self { color, moves: Vec::new() };
+        for region in Region::find(mask) {
+            match region.satin_axis(satin_width) {
+                Some(axis) => block.satin(&region, axis, mm_per_px, row_step / 2.0, max_stitch),
+                None => block.tatami(&region, mm_per_px, row_step, max_stitch),
+            }
+        }
+        block
+    }
+
+    pub fn stitch_count(&self) -> usize {
+        self.moves.iter().filter(|m| matches!(m, Move::Stitch(..))).count()
+    }
+
+    /// Goes to `point` with a stitch when it is within `max_stitch` of the needle, or else with a jump.
+    fn travel(&mut self, point: (f32, f32), max_stitch: f32) {
+        match self.moves.last() {
+            Some(&(Move::Stitch(x, y) | Move::Jump(x, y))) if distance((x, y), point) <= max_stitch => self.moves.push(Move::Stitch(point.0, point.1)),
+            _ => self.moves.push(Move::Jump(point.0, point.1)),
+        }
+    }
+
+    /// Fills `region` with rows `row_step` pixels apart.
+    fn tatami(&mut self, region: &Region, mm_per_px: f32, row_step: f32, max_stitch: f32) {
+        let (first, count) = region.lines(Axis::Rows);
+        let mut row = 0;
+        let mut y = first as f32;
+        while (y as u32) < first + count {
+            let mut runs = region.runs(Axis::Rows, y as u32);
+            // Alternate direction every row to avoid long returns
+            if row % 2 == 1 {
+                runs.reverse();
+                runs.iter_mut().for_each(|r| *r = (r.1, r.0));
+            }
+
+            let ymm = y * mm_per_px;
+            for (from, to) in runs {
+                let (x0, x1) = (from as f32 * mm_per_px, to as f32 * mm_per_px);
+                self.travel((x0, ymm), max_stitch);
+                let length = (x1 - x0).abs();
+                let steps = (length / max_stitch).ceil().max(1.0) as usize;
+                for i in 1..=steps {
+                    let sx = x0 + (x1 - x0) * i as f32 / steps as f32;
+                    self.moves.push(Move::Stitch(sx, ymm));
+                }
+            }
+
+            row += 1;
+            y += row_step;
+        }
+    }
+
+    /// Zigzags across `region` along `axis`, a needle point every `step` pixels
+    /// on alternate edges. Satin stitches are never split, as the column is narrow.
+    fn satin(&mut self, region: &Region, axis: Axis, mm_per_px: f32, step: f32, max_stitch: f32) {
+        let (first, count) = region.lines(axis);
+        let mut edge = 0;
+        let mut line = first as f32;
+        while (line as u32) < first + count {
+            if let [(from, to)] = region.runs(axis, line as u32)[..] {
+                // Pixel edges, so that one pixel wide columns are still crossed
+                let along = if edge % 2 == 0 { from as f32 } else { to as f32 + 1.0 };
+                let point = axis.point(line, along, mm_per_px);
+                if edge == 0 {
+                    self.travel(point, max_stitch);
+                } else {
+                    self.moves.push(Move::Stitch(point.0, point.1));
+                }
+                edge += 1;
+            }
+            line += step.max(0.5);
+        }
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Writes `blocks` at `output_path` in `format`.
+pub fn write(blocks: &[StitchBlock], label: &str, format: EmbroideryFormat, output_path: &Path) -> Result<()> {
+    match format {
+        EmbroideryFormat::Dst => write_dst(blocks, label, output_path),
+        EmbroideryFormat::Pes => write_pes(blocks, label, output_path),
+    }
+}
+
+/// Writes `blocks` as a Tajima DST file with a color stop between blocks.
+pub fn write_dst(blocks: &[StitchBlock], label: &str, output_path: &Path) -> Result<()> {
+    let mut records = Vec::new();
+    let (mut cx, mut cy) = (0i32, 0i32);
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (0i32, 0i32, 0i32, 0i32);
+    let mut stitches = 0usize;
+    let blocks: Vec<&StitchBlock> = blocks.iter().filter(|b| !b.moves.is_empty()).collect();
+
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            records.extend(encode_record(0, 0, RecordKind::ColorChange));
+        }
+        for mv in &block.moves {
+            let kind = match mv {
+                Move::Stitch(..) => RecordKind::Stitch,
+                Move::Jump(..) => RecordKind::Jump,
+            };
+            let (tx, ty) = mv.units();
+            // Split moves that do not fit in one record into jumps
+            loop {
+                let (dx, dy) = (tx - cx, ty - cy);
+                let fits = dx.abs() <= DST_MAX_DELTA && dy.abs() <= DST_MAX_DELTA;
+                let (sx, sy) = (dx.clamp(-DST_MAX_DELTA, DST_MAX_DELTA), dy.clamp(-DST_MAX_DELTA, DST_MAX_DELTA));
+                records.extend(encode_record(sx, sy, if fits { kind } else { RecordKind::Jump }));
+                cx += sx;
+                cy += sy;
+                stitches += 1;
+                if fits {
+                    break;
+                }
+            }
+            min_x = min_x.min(cx);
+            max_x = max_x.max(cx);
+            min_y = min_y.min(cy);
+            max_y = max_y.max(cy);
+        }
+    }
+    records.extend(encode_record(0, 0, RecordKind::End));
+
+    let signed = |v: i32| format!("{}{:>5}", if v < 0 { '-' } else { '+' }, v.abs());
+    let mut header = format!(
+        "LA:{:<16}\rST:{:>7}\rCO:{:>3}\r+X:{:>5}\r-X:{:>5}\r+Y:{:>5}\r-Y:{:>5}\r\
+        AX:{}\rAY:{}\rMX:+{:>5}\rMY:+{:>5}\rPD:{:<6}\r",
+        label.chars().take(16).collect::<String>(), stitches, blocks.len().saturating_sub(1),
+        max_x, -min_x, -min_y, max_y, signed(cx), signed(-cy), 0, 0, "******"
+    ).into_bytes();
+    header.push(0x1A);
+    header.resize(512, b' ');
+
+    header.extend(records);
+    fs::write(output_path, header)?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum RecordKind {
+    Stitch,
+    Jump,
+    ColorChange,
+    End,
+}
+
+/// Encodes a relative move in DST's balanced-ternary record layout.
+/// DST's y axis points up, so `dy` (down in image space) is negated.
+fn encode_record(dx: i32, dy: i32, kind: RecordKind) -> [u8; 3] {
+    let mut b = [0u8, 0u8, 0u8];
+    b[2] = match kind {
+        RecordKind::Stitch => 0x03,
+        RecordKind::Jump => 0x83,
+        RecordKind::ColorChange => 0xC3,
+        RecordKind::End => return [0x00, 0x00, 0xF3],
+    };
+
+    // (weight, byte, positive bit, negative bit) for each axis
+    const X_BITS: [(i32, usize, u8, u8); 5] = [(81, 2, 0x04, 0x08), (27, 1, 0x04, 0x08), (9, 0, 0x04, 0x08), (3, 1, 0x01, 0x02), (1, 0, 0x01, 0x02)];
+    const Y_BITS: [(i32, usize, u8, u8); 5] = [(81, 2, 0x20, 0x10), (27, 1, 0x20, 0x10), (9, 0, 0x20, 0x10), (3, 1, 0x80, 0x40), (1, 0, 0x80, 0x40)];
+
+    for (mut value, bits) in [(dx, X_BITS), (-dy, Y_BITS)] {
+        for (weight, byte, pos, neg) in bits {
+            let threshold = weight / 2;
+            if value > threshold {
+                b[byte] |= pos;
+                value -= weight;
+            } else if value < -threshold {
+                b[byte] |= neg;
+                value += weight;
+            }
+        }
+    }
+    b
+}
+
+/// Writes `blocks` as a version 1 PES file holding only the PEC section.
+pub fn write_pes(blocks: &[StitchBlock], label: &str, output_path: &Path) -> Result<()> {
+    let blocks: Vec<&StitchBlock> = blocks.iter().filter(|b| !b.moves.is_empty()).collect();
+    let mut out = b"#PES0001".to_vec();
+    out.extend((PES_HEADER_LEN as u32).to_le_bytes());
+    out.resize(PES_HEADER_LEN, 0);
+    out.extend(pec_section(&blocks, label));
+    fs::write(output_path, out)?;
+    Ok(())
+}
+
+/// PEC header with the thread colors, stitch block and preview icons.
+fn pec_section(blocks: &[&StitchBlock], label: &str) -> Vec<u8> {
+    let label: String = label.chars().filter(|c| c.is_ascii_graphic() || *c == ' ').take(16).collect();
+    let mut out = format!("LA:{:<16}\r", label).into_bytes();
+    out.extend([b' '; 12]);
+    out.extend([0xFF, 0x00, (ICON_WIDTH / 8) as u8, ICON_HEIGHT as u8]);
+    out.extend([b' '; 12]);
+    out.push(blocks.len().saturating_sub(1) as u8);
+    out.extend(blocks.iter().map(|b| nearest_thread(b.color)));
+    out.resize(PEC_HEADER_LEN, b' ');
+
+    let points = || blocks.iter().flat_map(|b| &b.moves).map(|m| m.units());
+    let bounds = points().fold((0, 0, 0, 0), |(x0, y0, x1, y1), (x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)));
+    let (min_x, min_y, max_x, max_y) = bounds;
+    let stitches = pec_stitches(blocks);
+    // The stitch block length counts its own 20-byte header
+    out.extend([0x00, 0x00]);
+    out.extend(&((20 + stitches.len()) as u32).to_le_bytes()[..3]);
+    out.extend([0x31, 0xFF, 0xF0]);
+    for value in [max_x - min_x, max_y - min_y, 0x1E0, 0x1B0] {
+        out.extend((value as u16).to_le_bytes());
+    }
+    for value in [min_x, min_y] {
+        out.extend((0x9000 | (-value & 0x0FFF) as u16).to_be_bytes());
+    }
+    out.extend(stitches);
+    out.extend(pec_icons(blocks, bounds));
+    out
+}
+
+/// Encodes the moves as PEC records, relative to the previous needle position:
+/// two 7-bit bytes for short stitches, 12-bit big-endian pairs flagged 0x8000
+/// for longer ones and jumps, `FE B0 nn` between colors and `FF` at the end.
+fn pec_stitches(blocks: &[&StitchBlock]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let (mut cx, mut cy) = (0i32, 0i32);
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.extend([0xFE, 0xB0, if i % 2 == 1 { 2 } else { 1 }]);
+        }
+        for mv in &block.moves {
+            let (tx, ty) = mv.units();
+            // Split moves that do not fit in one record into jumps
+            loop {
+                let (dx, dy) = (tx - cx, ty - cy);
+                let fits = dx.abs() <= PEC_MAX_DELTA && dy.abs() <= PEC_MAX_DELTA;
+                let (sx, sy) = (dx.clamp(-PEC_MAX_DELTA, PEC_MAX_DELTA), dy.clamp(-PEC_MAX_DELTA, PEC_MAX_DELTA));
+                let jump = !fits || matches!(mv, Move::Jump(..));
+                let short = |v: i32| (-64..=63).contains(&v);
+                if !jump && short(sx) && short(sy) {
+                    out.extend([(sx & 0x7F) as u8, (sy & 0x7F) as u8]);
+                } else {
+                    for v in [sx, sy] {
+                        let flag = if jump { 0x1000 } else { 0 };
+                        out.extend((0x8000 | flag | (v & 0x0FFF) as u16).to_be_bytes());
+                    }
+                }
+                cx += sx;
+                cy += sy;
+                if fits {
+                    break;
+                }
+            }
+        }
+    }
+    out.push(0xFF);
+    out
+}
+
+/// Monochrome preview icons, one bit per pixel with the leftmost in the lowest
+/// bit: the whole design, then each color.
+fn pec_icons(blocks: &[&StitchBlock], (min_x, min_y, max_x, max_y): (i32, i32, i32, i32)) -> Vec<u8> {
+    const BORDER: usize = 2;
+    let scale = ((ICON_WIDTH - 2 * BORDER) as f32 / (max_x - min_x).max(1) as f32)
+        .min((ICON_HEIGHT - 2 * BORDER) as f32 / (max_y - min_y).max(1) as f32);
+    let draw = |icon: &mut [u8], block: &StitchBlock| {
+        for mv in block.moves.iter().filter(|m| matches!(m, Move::Stitch(..))) {
+            let (x, y) = mv.units();
+            let ix = BORDER + ((x - min_x) as f32 * scale) as usize;
+            let iy = BORDER + ((y - min_y) as f32 * scale) as usize;
+            icon[iy * ICON_WIDTH / 8 + ix / 8] |= 1 << (ix % 8);
+        }
+    };
+    let mut design = vec![0u8; ICON_WIDTH / 8 * ICON_HEIGHT];
+    let mut colors = Vec::new();
+    for block in blocks {
+        let mut icon = vec![0u8; ICON_WIDTH / 8 * ICON_HEIGHT];
+        draw(&mut icon, block);
+        draw(&mut design, block);
+        colors.extend(icon);
+    }
+    design.extend(colors);
+    design
+}
+
+/// Index of the PEC palette thread closest to `color`.
+fn nearest_thread(color: [u8; 3]) -> u8 {
+    let distance = |thread: &[u8; 3]| thread.iter().zip(color).map(|(&a, b)| (a as i32 - b as i32).pow(2)).sum::<i32>();
+    let index = PEC_THREADS.iter().enumerate().min_by_key(|(_, thread)| distance(thread)).map_or(0, |(i, _)| i);
+    index as u8 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(rows: &[&str]) -> GrayImage {
+        GrayImage::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            image::Luma([if rows[y as usize].as_bytes()[x as usize] == b'#' { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn sews_narrow_regions_as_satin_columns() {
+        let regions = Region::find(&mask(&[
+            "#...####",
+            "#...####",
+            "#...####",
+            "#...####",
+        ]));
+        assert_eq!(regions.len(), 2);
+        // The bar is crossed along its rows; the square is too wide at 3 pixels
+        assert_eq!(regions[0].satin_axis(3.0), Some(Axis::Rows));
+        assert_eq!(regions[1].satin_axis(3.0), None);
+        assert_eq!(regions[1].satin_axis(4.0), Some(Axis::Rows));
+
+        // A fork has two runs on some rows but one down each column; a ring has two either way
+        let fork = Region::find(&mask(&["#.#", "#.#", "###"]));
+        assert_eq!(fork[0].satin_axis(10.0), Some(Axis::Columns));
+        let ring = Region::find(&mask(&["###", "#.#", "###"]));
+        assert_eq!(ring[0].satin_axis(10.0), None);
+    }
+
+    #[test]
+    fn satin_zigzags_between_the_edges() {
+        let opts = EmbroideryOptions { row_spacing_mm: 2.0, ..EmbroideryOptions::default() };
+        let block = StitchBlock::fill(&mask(&["##", "##", "##", "##"]), [0, 0, 0], 1.0, &opts);
+        let xs: Vec<f32> = block.moves.iter().map(|&(Move::Stitch(x, _) | Move::Jump(x, _))| x).collect();
+        assert_eq!(xs, [0.0, 2.0, 0.0, 2.0]);
+        assert!(matches!(block.moves[0], Move::Jump(..)));
+    }
+
+    #[test]
+    fn encodes_pec_records() {
+        let block = StitchBlock { color: [255, 0, 0], moves: vec![Move::Jump(0.5, 0.0), Move::Stitch(0.5, -0.2), Move::Stitch(20.5, -0.2)] };
+        let other = StitchBlock { color: [0, 0, 0], moves: vec![Move::Stitch(20.5, 0.0)] };
+        assert_eq!(pec_stitches(&[&block, &other]), [
+            0x90, 0x05, 0x90, 0x00, // jump of 5, 0
+            0x00, 0x7E, // short stitch of 0, -2
+            0x80, 0xC8, 0x80, 0x00, // long stitch of 200, 0
+            0xFE, 0xB0, 0x02, // color change
+            0x00, 0x02, // short stitch of 0, 2
+            0xFF,
+        ]);
+        assert_eq!(nearest_thread([250, 20, 30]), 5);
+    }
+
+    #[test]
+    fn pes_points_to_its_pec_section() {
+        let block = StitchBlock { color: [0, 0, 0], moves: vec![Move::Jump(0.0, 0.0), Move::Stitch(1.0, 1.0)] };
+        let pec = pec_section(&[&block], "logo");
+        assert!(pec.starts_with(b"LA:logo            \r"));
+        assert_eq!(pec[48], 0);
+        assert_eq!(pec[49], 20);
+        let stitches_len = u32::from_le_bytes([pec[514], pec[515], pec[516], 0]) as usize;
+        assert_eq!(pec.len(), PEC_HEADER_LEN + stitches_len + 2 * ICON_WIDTH / 8 * ICON_HEIGHT);
+    }
+}
@@ -0,0 +1,57 @@
+//! Encoding of the raster outputs that need no transparency: thumbnail, color
+//! pop and background blur.
+//!
+//! The format follows the output extension: PNG, JPEG at the given quality, or
+//! WebP. WebP is written lossless, as the `image` crate has no lossy encoder,
+//! so the quality only applies to JPEG.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use anyhow::Result;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+
+use crate::error::AlphasvgError;
+
+/// Formats offered for these outputs, as output extensions.
+pub const FORMATS: &[&str] = &["png", "jpg", "webp"];
+pub const DEFAULT_QUALITY: u8 = 90;
+
+/// Parses a JPEG quality from 1 to 100.
+pub fn parse_quality(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(q) if (1..=100).contains(&q) => Ok(q),
+        _ => Err(format!("quality must be between 1 and 100: {}", value)),
+    }
+}
+
+/// Writes `img` in the format of the extension of `path`; JPEG gets the
+/// transparent areas flattened onto white.
+pub fn save(img: &DynamicImage, path: &Path, quality: u8) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    let mut writer = BufWriter::new(File::create(path).map_err(AlphasvgError::io(path))?);
+    match extension.as_str() {
+        "jpg" | "jpeg" => DynamicImage::ImageRgb8(flatten(img)).write_with_encoder(JpegEncoder::new_with_quality(&mut writer, quality)),
+        // The encoder takes 8-bit RGB(A) only
+        "webp" if img.color().has_alpha() => DynamicImage::ImageRgba8(img.to_rgba8()).write_with_encoder(WebPEncoder::new_lossless(&mut writer)),
+        "webp" => DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(WebPEncoder::new_lossless(&mut writer)),
+        _ => img.write_to(&mut writer, ImageFormat::Png),
+    }.map_err(|e| AlphasvgError::encode(path, e))?;
+    writer.flush().map_err(AlphasvgError::io(path))?;
+    Ok(())
+}
+
+/// Composites `img` onto white.
+fn flatten(img: &DynamicImage) -> RgbImage {
+    if !img.color().has_alpha() {
+        return img.to_rgb8();
+    }
+    let rgba = img.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let a = a as u32;
+        Rgb([r, g, b].map(|c| ((c as u32 * a + 255 * (255 - a)) / 255) as u8))
+    })
+}
@@ -0,0 +1,95 @@
+//! Fidelity of the vector outputs (`--fidelity`).
+//!
+//! Each SVG is rendered back to a raster and compared with the image it was
+//! traced from, both at up to [`MAX_PX`] on the long edge: SSIM over the luma
+//! composited onto mid gray, so missing or extra coverage counts, and the mean
+//! CIE76 color difference (ΔE) over the source's opaque pixels. The monochrome
+//! generators are only held to the lightness of the source, and the stylized
+//! ones (halftone dots, line art and its plotter files) are not scored at all,
+//! since they are not meant to look like it. Outputs below
+//! [`config::FIDELITY_MIN_SSIM`] or above [`config::FIDELITY_MAX_DELTA_E`] are
+//! flagged as low fidelity in the report, to re-run with more colors or tones
+//! or a higher `--max-vector-px`.
+
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use image::{DynamicImage, RgbaImage, imageops::{self, FilterType}};
+use palette::{IntoColor, Lab, Srgb};
+use serde::Serialize;
+
+use crate::{config, diff};
+use crate::generators::rasterize;
+use crate::naming::OutputKind;
+
+/// Long edge, in pixels, the output and the source are compared at.
+pub const MAX_PX: u32 = 512;
+/// Source pixels with less alpha are left out of the color difference.
+const MIN_ALPHA: u8 = 128;
+
+/// How closely a vector output matches its source.
+#[derive(Serialize, Clone, Debug)]
+pub struct Fidelity {
+    pub output: PathBuf,
+    /// Structural similarity, from 0 to 1 (identical).
+    pub ssim: f64,
+    /// Mean color difference (CIE76 ΔE) over the source's opaque pixels; lightness only for monochrome outputs.
+    pub delta_e: f64,
+    /// Below the SSIM or above the ΔE limit.
+    pub low: bool,
+}
+
+/// Whether outputs of `kind` are scored; the stylized ones are left out.
+pub fn is_measured(kind: OutputKind) -> bool {
+    !matches!(kind, OutputKind::Halftone | OutputKind::Lineart)
+}
+
+/// Renders `svg_path` and compares it with `source`; `color` is false for
+/// monochrome outputs, which are compared by lightness alone.
+pub fn measure(svg_path: &Path, source: &DynamicImage, color: bool) -> Result<Fidelity> {
+    let source = source.resize(MAX_PX, MAX_PX, FilterType::Triangle).to_rgba8();
+    let (width, height) = source.dimensions();
+    let pixmap = rasterize::render_svg(svg_path, |size| width as f32 / size.width())?;
+    let rendered = RgbaImage::from_fn(pixmap.width(), pixmap.height(), |x, y| {
+        image::Rgba(pixmap.pixel(x, y).map_or([0; 4], |p| {
+            let p = p.demultiply();
+            [p.red(), p.green(), p.blue(), p.alpha()]
+        }))
+    });
+    // Rounding the render size up can add a row or column
+    let rendered = if rendered.dimensions() == (width, height) {
+        rendered
+    } else {
+        imageops::resize(&rendered, width, height, FilterType::Triangle)
+    };
+
+    let (mut total, mut count) = (0.0f64, 0usize);
+    for (s, r) in source.pixels().zip(rendered.pixels()) {
+        if s[3] < MIN_ALPHA {
+            continue;
+        }
+        let (a, b) = (lab(s.0, 255), lab(r.0, r[3]));
+        let delta = if color {
+            ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt()
+        } else {
+            (a.l - b.l).abs()
+        };
+        total += delta as f64;
+        count += 1;
+    }
+
+    let ssim = diff::ssim(&diff::composite_luma(&source), &diff::composite_luma(&rendered));
+    let delta_e = total / count.max(1) as f64;
+    Ok(Fidelity {
+        output: svg_path.to_path_buf(),
+        ssim,
+        delta_e,
+        low: ssim < config::FIDELITY_MIN_SSIM || delta_e > config::FIDELITY_MAX_DELTA_E,
+    })
+}
+
+/// Lab color of an RGBA pixel composited onto white with `alpha`.
+fn lab([r, g, b, _]: [u8; 4], alpha: u8) -> Lab {
+    let a = alpha as f32 / 255.0;
+    let channel = |v: u8| (v as f32 / 255.0) * a + (1.0 - a);
+    Srgb::new(channel(r), channel(g), channel(b)).into_color()
+}
@@ -0,0 +1,388 @@
+//! Geometric cleanup of traced paths.
+//!
+//! Potrace fits curves to pixel staircases, so edges meant to be straight come
+//! out slightly slanted, split into several segments or gently bowed, and joins
+//! meant to be smooth get small kinks. With a cleanup strength above 0 the
+//! potrace SVG is rewritten before it is copied into the document: nearly
+//! straight curves become lines, consecutive lines that are almost collinear
+//! are merged, lines close to horizontal or vertical are snapped onto the axis,
+//! and joins that bend only slightly are made smooth while sharp corners are
+//! kept. The tolerances grow with the strength, from 0 to 1.
+
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+
+use crate::error::AlphasvgError;
+
+/// Lines this close to an axis, and consecutive lines turning less, are
+/// snapped or merged at full strength.
+const MAX_SNAP_DEGREES: f32 = 8.0;
+/// Joins bending less than this at full strength are made smooth.
+const MAX_SMOOTH_DEGREES: f32 = 20.0;
+/// Distance, in potrace units (a tenth of a traced pixel), a curve may bow away
+/// from its chord at full strength and still be drawn as a line; also how far
+/// a merged line may pass from the points it drops.
+const MAX_FLATNESS: f32 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+impl Point {
+    fn sub(self, other: Point) -> Point {
+        Point { x: self.x - other.x, y: self.y - other.y }
+    }
+
+    fn add(self, other: Point) -> Point {
+        Point { x: self.x + other.x, y: self.y + other.y }
+    }
+
+    fn scale(self, factor: f32) -> Point {
+        Point { x: self.x * factor, y: self.y * factor }
+    }
+
+    fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    /// Angle, from 0 to π, between the directions of two vectors.
+    fn angle_to(self, other: Point) -> f32 {
+        let cross = self.x * other.y - self.y * other.x;
+        let dot = self.x * other.x + self.y * other.y;
+        cross.atan2(dot).abs()
+    }
+
+    /// Distance from the line through `a` and `b`.
+    fn distance_to_line(self, a: Point, b: Point) -> f32 {
+        let chord = b.sub(a);
+        let length = chord.length();
+        if length < f32::EPSILON {
+            return self.sub(a).length();
+        }
+        (chord.x * (self.y - a.y) - chord.y * (self.x - a.x)).abs() / length
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Segment {
+    Line { end: Point },
+    Cubic { c1: Point, c2: Point, end: Point },
+}
+
+impl Segment {
+    fn end(self) -> Point {
+        match self {
+            Segment::Line { end } | Segment::Cubic { end, .. } => end,
+        }
+    }
+}
+
+/// A closed subpath: each segment starts where the previous one ends, the
+/// first where the last one ends.
+type Ring = Vec<Segment>;
+
+/// Rewrites the paths of the potrace SVG at `path` cleaned at `strength`, from 0 to 1.
+pub fn clean_file(path: &Path, strength: f32) -> Result<()> {
+    let svg = fs::read_to_string(path).map_err(AlphasvgError::io(path))?;
+    fs::write(path, clean_svg(&svg, strength)).map_err(AlphasvgError::io(path))?;
+    Ok(())
+}
+
+/// Cleans the data of every ` d="..."` attribute of `svg`; data that does not
+/// parse is left as it is.
+fn clean_svg(svg: &str, strength: f32) -> String {
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+    while let Some(start) = rest.find(" d=\"") {
+        let (head, tail) = rest.split_at(start + 4);
+        out.push_str(head);
+        let Some(end) = tail.find('"') else {
+            rest = tail;
+            break;
+        };
+        let data = &tail[..end];
+        match parse(data) {
+            Some(rings) => out.push_str(&format_rings(&rings.into_iter().map(|ring| clean(ring, strength)).collect::<Vec<_>>())),
+            None => out.push_str(data),
+        }
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses path data made of moveto, lineto, cubic and closepath commands,
+/// absolute or relative, the ones potrace writes.
+fn parse(data: &str) -> Option<Vec<Ring>> {
+    let mut rings = Vec::new();
+    let mut ring: Ring = Vec::new();
+    let (mut position, mut start) = (Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 0.0 });
+    let mut command = None;
+    let mut numbers: Vec<f32> = Vec::new();
+
+    // Closes the current subpath, with a line back to its start if it does not end there
+    let close = |ring: &mut Ring, rings: &mut Vec<Ring>, start: Point| {
+        if ring.is_empty() {
+            return;
+        }
+        if ring.last().is_some_and(|s| s.end() != start) {
+            ring.push(Segment::Line { end: start });
+        }
+        rings.push(std::mem::take(ring));
+    };
+
+    for token in tokenize(data) {
+        match token {
+            Token::Command('z' | 'Z') => {
+                close(&mut ring, &mut rings, start);
+                position = start;
+                command = None;
+                continue;
+            }
+            Token::Command(c) => {
+                command = Some(c);
+                continue;
+            }
+            Token::Number(value) => numbers.push(value),
+        }
+        let c = command?;
+        let arity = match c.to_ascii_uppercase() {
+            'M' | 'L' => 2,
+            'C' => 6,
+            _ => return None,
+        };
+        if numbers.len() < arity {
+            continue;
+        }
+        let origin = if c.is_ascii_lowercase() { position } else { Point { x: 0.0, y: 0.0 } };
+        let point = |i: usize| Point { x: origin.x + numbers[i], y: origin.y + numbers[i + 1] };
+        match c.to_ascii_uppercase() {
+            'M' => {
+                close(&mut ring, &mut rings, start);
+                start = point(0);
+                position = start;
+                // Further pairs after a moveto are linetos
+                command = Some(if c.is_ascii_lowercase() { 'l' } else { 'L' });
+            }
+            'L' => {
+                position = point(0);
+                ring.push(Segment::Line { end: position });
+            }
+            _ => {
+                let segment = Segment::Cubic { c1: point(0), c2: point(2), end: point(4) };
+                position = segment.end();
+                ring.push(segment);
+            }
+        }
+        numbers.clear();
+    }
+    close(&mut ring, &mut rings, start);
+    numbers.is_empty().then_some(rings)
+}
+
+enum Token {
+    Command(char),
+    Number(f32),
+}
+
+fn tokenize(data: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+    let flush = |number: &mut String, tokens: &mut Vec<Token>| {
+        if let Ok(value) = number.parse() {
+            tokens.push(Token::Number(value));
+        }
+        number.clear();
+    };
+    for c in data.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'e' | 'E' if !number.is_empty() => number.push(c),
+            '-' | '+' => {
+                if !number.ends_with(['e', 'E']) {
+                    flush(&mut number, &mut tokens);
+                }
+                number.push(c);
+            }
+            c if c.is_ascii_alphabetic() => {
+                flush(&mut number, &mut tokens);
+                tokens.push(Token::Command(c));
+            }
+            _ => flush(&mut number, &mut tokens),
+        }
+    }
+    flush(&mut number, &mut tokens);
+    tokens
+}
+
+/// Cleans one closed subpath at `strength`.
+fn clean(mut ring: Ring, strength: f32) -> Ring {
+    let strength = strength.clamp(0.0, 1.0);
+    if strength <= 0.0 || ring.len() < 3 {
+        return ring;
+    }
+    let snap = (MAX_SNAP_DEGREES * strength).to_radians();
+    let smooth = (MAX_SMOOTH_DEGREES * strength).to_radians();
+    let flatness = MAX_FLATNESS * strength;
+    let start_of = |ring: &Ring, i: usize| ring[(i + ring.len() - 1) % ring.len()].end();
+
+    // Nearly straight curves become lines
+    for i in 0..ring.len() {
+        let start = start_of(&ring, i);
+        if let Segment::Cubic { c1, c2, end } = ring[i]
+            && c1.distance_to_line(start, end) <= flatness && c2.distance_to_line(start, end) <= flatness {
+            ring[i] = Segment::Line { end };
+        }
+    }
+
+    // Lines that continue each other become one
+    let mut i = 0;
+    while ring.len() > 3 && i < ring.len() {
+        let next = (i + 1) % ring.len();
+        if let (Segment::Line { end: middle }, Segment::Line { end }) = (ring[i], ring[next]) {
+            let start = start_of(&ring, i);
+            if middle.sub(start).angle_to(end.sub(middle)) < snap && middle.distance_to_line(start, end) <= flatness {
+                ring.remove(i);
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    // Lines close to an axis are put on it, moving both ends and the handles attached to them
+    let tolerance = snap.tan();
+    for i in 0..ring.len() {
+        let Segment::Line { end } = ring[i] else { continue };
+        let previous = (i + ring.len() - 1) % ring.len();
+        let start = ring[previous].end();
+        let delta = end.sub(start);
+        let (shift_start, shift_end) = if delta.y.abs() <= tolerance * delta.x.abs() {
+            let middle = (start.y + end.y) / 2.0;
+            (Point { x: 0.0, y: middle - start.y }, Point { x: 0.0, y: middle - end.y })
+        } else if delta.x.abs() <= tolerance * delta.y.abs() {
+            let middle = (start.x + end.x) / 2.0;
+            (Point { x: middle - start.x, y: 0.0 }, Point { x: middle - end.x, y: 0.0 })
+        } else {
+            continue;
+        };
+        move_vertex(&mut ring, previous, shift_start);
+        move_vertex(&mut ring, i, shift_end);
+    }
+
+    // Joins that bend slightly are made smooth; sharper ones stay corners
+    for i in 0..ring.len() {
+        let next = (i + 1) % ring.len();
+        let vertex = ring[i].end();
+        let incoming = match ring[i] {
+            Segment::Cubic { c2, .. } if c2 != vertex => vertex.sub(c2),
+            _ => vertex.sub(start_of(&ring, i)),
+        };
+        let outgoing = match ring[next] {
+            Segment::Cubic { c1, .. } if c1 != vertex => c1.sub(vertex),
+            segment => segment.end().sub(vertex),
+        };
+        let (in_length, out_length) = (incoming.length(), outgoing.length());
+        if in_length < f32::EPSILON || out_length < f32::EPSILON || incoming.angle_to(outgoing) >= smooth {
+            continue;
+        }
+        let (in_unit, out_unit) = (incoming.scale(1.0 / in_length), outgoing.scale(1.0 / out_length));
+        // A line keeps its direction and the curve turns to it; two curves meet halfway
+        let direction = match (ring[i], ring[next]) {
+            (Segment::Line { .. }, _) => in_unit,
+            (_, Segment::Line { .. }) => out_unit,
+            _ => {
+                let sum = in_unit.add(out_unit);
+                sum.scale(1.0 / sum.length().max(f32::EPSILON))
+            }
+        };
+        if let Segment::Cubic { c1, c2, end } = ring[i] {
+            ring[i] = Segment::Cubic { c1, c2: vertex.sub(direction.scale(vertex.sub(c2).length())), end };
+        }
+        if let Segment::Cubic { c1, c2, end } = ring[next] {
+            ring[next] = Segment::Cubic { c1: vertex.add(direction.scale(c1.sub(vertex).length())), c2, end };
+        }
+    }
+    ring
+}
+
+/// Moves the end of segment `i`, and the start of the next, by `shift`,
+/// carrying the curve handles attached to that point along.
+fn move_vertex(ring: &mut Ring, i: usize, shift: Point) {
+    let next = (i + 1) % ring.len();
+    ring[i] = match ring[i] {
+        Segment::Line { end } => Segment::Line { end: end.add(shift) },
+        Segment::Cubic { c1, c2, end } => Segment::Cubic { c1, c2: c2.add(shift), end: end.add(shift) },
+    };
+    if let Segment::Cubic { c1, c2, end } = ring[next] {
+        ring[next] = Segment::Cubic { c1: c1.add(shift), c2, end };
+    }
+}
+
+/// Path data of `rings` in absolute commands.
+fn format_rings(rings: &[Ring]) -> String {
+    let coordinate = |v: f32| {
+        let rounded = (v * 10.0).round() / 10.0;
+        if rounded.fract() == 0.0 { format!("{}", rounded as i64) } else { format!("{:.1}", rounded) }
+    };
+    let point = |p: Point| format!("{} {}", coordinate(p.x), coordinate(p.y));
+    let mut data = String::new();
+    for ring in rings {
+        let Some(last) = ring.last() else { continue };
+        data.push_str(&format!("M{}", point(last.end())));
+        for segment in ring {
+            match *segment {
+                Segment::Line { end } => data.push_str(&format!(" L{}", point(end))),
+                Segment::Cubic { c1, c2, end } => data.push_str(&format!(" C{} {} {}", point(c1), point(c2), point(end))),
+            }
+        }
+        data.push_str(" z\n");
+    }
+    data.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(ring: &Ring) -> Vec<(Point, Point)> {
+        (0..ring.len()).filter_map(|i| match ring[i] {
+            Segment::Line { end } => Some((ring[(i + ring.len() - 1) % ring.len()].end(), end)),
+            Segment::Cubic { .. } => None,
+        }).collect()
+    }
+
+    #[test]
+    fn parses_relative_commands_and_implicit_linetos() {
+        // Pairs after a moveto are linetos, relative ones after "m"; "z" draws the line back
+        let rings = parse("m10 20 30 0 0 40 -30 0z M0 0 c1 2 3 4 5 6 L5 0 z").unwrap();
+        assert_eq!(format_rings(&rings), "M10 20 L40 20 L40 60 L10 60 L10 20 z\nM0 0 C1 2 3 4 5 6 L5 0 L0 0 z");
+    }
+
+    #[test]
+    fn formatted_data_parses_back_to_the_same_rings() {
+        let data = format_rings(&parse("M1.25 -3e1 l2.5.5 c-1-1 -2 0 -3 4.05Z").unwrap());
+        assert_eq!(format_rings(&parse(&data).unwrap()), data);
+    }
+
+    #[test]
+    fn leaves_data_it_cannot_parse() {
+        let svg = r#"<path d="M0 0 A5 5 0 0 1 10 10 z"/>"#;
+        assert_eq!(clean_svg(svg, 1.0), svg);
+        assert!(parse("M0 0 L5").is_none());
+    }
+
+    #[test]
+    fn snaps_nearly_axis_aligned_lines() {
+        let ring = parse("M0 0 L100 3 L100 100 L0 100 z").unwrap().remove(0);
+        for (start, end) in lines(&clean(ring.clone(), 1.0)) {
+            assert!(start.x == end.x || start.y == end.y, "{:?} -> {:?} is not on an axis", start, end);
+        }
+        // A slant beyond the tolerance, or no strength, keeps the line as it is
+        assert_eq!(format_rings(&[clean(ring.clone(), 0.0)]), format_rings(&[ring]));
+        let steep = parse("M0 0 L100 30 L100 100 L0 100 z").unwrap().remove(0);
+        assert!(lines(&clean(steep, 1.0)).iter().any(|(start, end)| start.x != end.x && start.y != end.y));
+    }
+}
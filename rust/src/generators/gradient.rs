@@ -0,0 +1,278 @@
+//! Gradient detection for the color SVG generators (`--gradients`).
+//!
+//! Quantization bands a smooth shading into many flat regions. Here the image
+//! is split into smooth regions, areas where neighbouring pixels never step by
+//! more than [`EDGE_STEP`], and every large region spanning several quantized
+//! colors is fitted with a color ramp: along one direction (linear) or out from
+//! a center (radial). When the ramp explains the region's colors closely, the
+//! region is traced as a single shape filled with an SVG gradient instead of one
+//! path per band.
+
+use image::RgbaImage;
+
+/// Largest per-channel step between neighbours inside a smooth region.
+const EDGE_STEP: u8 = 6;
+/// Smallest region, as a share of the visible pixels, worth a gradient.
+const MIN_AREA_FRACTION: f64 = 0.01;
+/// Smallest region in pixels, whatever the image size.
+const MIN_AREA_PX: usize = 400;
+/// Quantized colors a region must span; fewer bands trace fine as flat regions.
+const MIN_BANDS: usize = 3;
+/// Largest RMS difference, on a 0–255 scale, between the region and its ramp.
+const MAX_RMS: f64 = 8.0;
+/// Smallest difference between the ends of the ramp on any channel.
+const MIN_RANGE: f64 = 24.0;
+/// Share of the linear fit's error a radial fit must stay under to be preferred;
+/// a linear shading is fitted about as well by a circle with a distant center.
+const RADIAL_GAIN: f64 = 0.8;
+/// Farthest a radial center may lie from the region, in region half-extents.
+const MAX_CENTER_DISTANCE: f64 = 2.0;
+
+/// Color ramp of a region, in working pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientFill {
+    Linear { from: (f32, f32), to: (f32, f32), stops: [[u8; 3]; 2] },
+    /// `stops[0]` at the center, `stops[1]` at `radius`.
+    Radial { center: (f32, f32), radius: f32, stops: [[u8; 3]; 2] },
+}
+
+/// A smooth region to trace as one shape filled with `fill`.
+pub struct GradientRegion {
+    /// Indices (`y * width + x`) of the pixels in the region.
+    pub pixels: Vec<usize>,
+    pub fill: GradientFill,
+}
+
+impl GradientFill {
+    /// `<defs>` declaring the gradient as `id`, in the coordinates of a potrace
+    /// trace of an image `height` pixels high (a tenth of a pixel, y flipped).
+    pub fn svg_defs(&self, id: &str, height: u32) -> String {
+        let point = |(x, y): (f32, f32)| (x * 10.0, (height as f32 - y) * 10.0);
+        let stops = |stops: &[[u8; 3]; 2]| {
+            stops.iter().enumerate()
+                .map(|(i, [r, g, b])| format!("<stop offset=\"{}\" stop-color=\"#{:02x}{:02x}{:02x}\"/>", i, r, g, b))
+                .collect::<String>()
+        };
+        match self {
+            GradientFill::Linear { from, to, stops: colors } => {
+                let ((x1, y1), (x2, y2)) = (point(*from), point(*to));
+                format!(
+                    "<defs><linearGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\">{}</linearGradient></defs>\n",
+                    id, x1, y1, x2, y2, stops(colors)
+                )
+            }
+            GradientFill::Radial { center, radius, stops: colors } => {
+                let (cx, cy) = point(*center);
+                format!(
+                    "<defs><radialGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\">{}</radialGradient></defs>\n",
+                    id, cx, cy, radius * 10.0, stops(colors)
+                )
+            }
+        }
+    }
+}
+
+/// Finds the smooth regions of `rgba` that are better drawn as gradients.
+/// `bands` holds the quantized color of every pixel, `None` for transparent ones.
+pub fn find_gradients(rgba: &RgbaImage, bands: &[Option<[u8; 3]>]) -> Vec<GradientRegion> {
+    let (width, height) = rgba.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let visible = bands.iter().filter(|b| b.is_some()).count();
+    let min_area = MIN_AREA_PX.max((visible as f64 * MIN_AREA_FRACTION) as usize);
+
+    let smooth = |a: usize, b: usize| {
+        let (pa, pb) = (rgba.as_raw()[a * 4..a * 4 + 3].iter(), rgba.as_raw()[b * 4..b * 4 + 3].iter());
+        pa.zip(pb).all(|(x, y)| x.abs_diff(*y) <= EDGE_STEP)
+    };
+
+    let mut seen = vec![false; w * h];
+    let mut regions = Vec::new();
+    for start in 0..w * h {
+        if seen[start] || bands[start].is_none() {
+            continue;
+        }
+        // Flood fill over visible neighbours with no edge between them
+        seen[start] = true;
+        let mut pixels = vec![start];
+        let mut next = 0;
+        while next < pixels.len() {
+            let i = pixels[next];
+            next += 1;
+            let (x, y) = (i % w, i / w);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < w).then(|| i + 1),
+                (y > 0).then(|| i - w),
+                (y + 1 < h).then(|| i + w),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !seen[n] && bands[n].is_some() && smooth(i, n) {
+                    seen[n] = true;
+                    pixels.push(n);
+                }
+            }
+        }
+        if pixels.len() < min_area {
+            continue;
+        }
+        let mut colors: Vec<[u8; 3]> = pixels.iter().filter_map(|&i| bands[i]).collect();
+        colors.sort_unstable();
+        colors.dedup();
+        if colors.len() < MIN_BANDS {
+            continue;
+        }
+        if let Some(fill) = fit(rgba, w, &pixels) {
+            regions.push(GradientRegion { pixels, fill });
+        }
+    }
+    regions
+}
+
+/// Fits a linear and a radial ramp to the region and keeps the closer one, if
+/// it is close enough and actually changes color.
+fn fit(rgba: &RgbaImage, width: usize, pixels: &[usize]) -> Option<GradientFill> {
+    let samples: Vec<((f64, f64), [f64; 3])> = pixels.iter().map(|&i| {
+        let p = &rgba.as_raw()[i * 4..i * 4 + 3];
+        (((i % width) as f64 + 0.5, (i / width) as f64 + 0.5), [p[0] as f64, p[1] as f64, p[2] as f64])
+    }).collect();
+
+    // Center and scale the coordinates so the normal equations stay well conditioned
+    let n = samples.len() as f64;
+    let (mx, my) = samples.iter().fold((0.0, 0.0), |(sx, sy), ((x, y), _)| (sx + x / n, sy + y / n));
+    let scale = samples.iter().map(|((x, y), _)| (x - mx).abs().max((y - my).abs())).fold(1.0, f64::max);
+    let norm = |(x, y): (f64, f64)| ((x - mx) / scale, (y - my) / scale);
+
+    // Linear: the direction of steepest change over all channels
+    let planes = least_squares(&samples, |p| { let (u, v) = norm(p); vec![1.0, u, v] })?;
+    let direction = principal_direction(planes.iter().map(|c| (c[1], c[2])));
+    let linear = ramp(&samples, |p| { let (u, v) = norm(p); u * direction.0 + v * direction.1 });
+
+    // Radial: the center from an isotropic quadratic fit on the channel that curves most
+    let quadratic = least_squares(&samples, |p| { let (u, v) = norm(p); vec![1.0, u, v, u * u + v * v] })?;
+    let curved = quadratic.iter().max_by(|a, b| a[3].abs().total_cmp(&b[3].abs()))?;
+    let offset = (-curved[1] / (2.0 * curved[3]), -curved[2] / (2.0 * curved[3]));
+    let radial = (offset.0.hypot(offset.1) <= MAX_CENTER_DISTANCE).then(|| {
+        let center = (mx + offset.0 * scale, my + offset.1 * scale);
+        let fitted = ramp(&samples, |(x, y)| (x - center.0).hypot(y - center.1));
+        (center, fitted)
+    });
+
+    let (rms, fill) = match radial {
+        Some((center, radial)) if radial.rms < linear.rms * RADIAL_GAIN => {
+            let stops = [radial.color_at(0.0), radial.color_at(radial.max)];
+            (radial.rms, GradientFill::Radial { center: (center.0 as f32, center.1 as f32), radius: radial.max as f32, stops })
+        }
+        _ => {
+            let point = |t: f64| ((mx + direction.0 * t * scale) as f32, (my + direction.1 * t * scale) as f32);
+            let stops = [linear.color_at(linear.min), linear.color_at(linear.max)];
+            (linear.rms, GradientFill::Linear { from: point(linear.min), to: point(linear.max), stops })
+        }
+    };
+    let stops = match fill {
+        GradientFill::Linear { stops, .. } | GradientFill::Radial { stops, .. } => stops,
+    };
+    let range = (0..3).map(|c| (stops[0][c] as f64 - stops[1][c] as f64).abs()).fold(0.0, f64::max);
+    (rms <= MAX_RMS && range >= MIN_RANGE).then_some(fill)
+}
+
+/// Per-channel color as a linear function of a scalar `t` over a region.
+struct Ramp {
+    intercept: [f64; 3],
+    slope: [f64; 3],
+    min: f64,
+    max: f64,
+    rms: f64,
+}
+
+impl Ramp {
+    fn color_at(&self, t: f64) -> [u8; 3] {
+        std::array::from_fn(|c| (self.intercept[c] + self.slope[c] * t).round().clamp(0.0, 255.0) as u8)
+    }
+}
+
+/// Fits every channel as a linear function of `t(pixel)`.
+fn ramp(samples: &[((f64, f64), [f64; 3])], t: impl Fn((f64, f64)) -> f64) -> Ramp {
+    let ts: Vec<f64> = samples.iter().map(|(p, _)| t(*p)).collect();
+    let n = ts.len() as f64;
+    let mean_t = ts.iter().sum::<f64>() / n;
+    let var_t = ts.iter().map(|t| (t - mean_t).powi(2)).sum::<f64>().max(f64::EPSILON);
+    let mut intercept = [0.0; 3];
+    let mut slope = [0.0; 3];
+    for c in 0..3 {
+        let mean_c = samples.iter().map(|(_, color)| color[c]).sum::<f64>() / n;
+        let cov: f64 = samples.iter().zip(&ts).map(|((_, color), t)| (t - mean_t) * (color[c] - mean_c)).sum();
+        slope[c] = cov / var_t;
+        intercept[c] = mean_c - slope[c] * mean_t;
+    }
+    let squared: f64 = samples.iter().zip(&ts)
+        .map(|((_, color), t)| (0..3).map(|c| (color[c] - intercept[c] - slope[c] * t).powi(2)).sum::<f64>())
+        .sum();
+    Ramp {
+        intercept,
+        slope,
+        min: ts.iter().copied().fold(f64::INFINITY, f64::min),
+        max: ts.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        rms: (squared / (3.0 * n)).sqrt(),
+    }
+}
+
+/// Least-squares coefficients of every channel over the `basis` functions of
+/// the pixel position, or `None` when the basis is degenerate over the region.
+fn least_squares(samples: &[((f64, f64), [f64; 3])], basis: impl Fn((f64, f64)) -> Vec<f64>) -> Option<Vec<Vec<f64>>> {
+    let terms = basis((0.0, 0.0)).len();
+    let mut normal = vec![vec![0.0; terms]; terms];
+    let mut rhs = vec![[0.0; 3]; terms];
+    for (p, color) in samples {
+        let b = basis(*p);
+        for i in 0..terms {
+            for j in 0..terms {
+                normal[i][j] += b[i] * b[j];
+            }
+            for c in 0..3 {
+                rhs[i][c] += b[i] * color[c];
+            }
+        }
+    }
+
+    // Gaussian elimination with partial pivoting, all channels at once
+    for col in 0..terms {
+        let pivot = (col..terms).max_by(|&a, &b| normal[a][col].abs().total_cmp(&normal[b][col].abs()))?;
+        if normal[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        normal.swap(col, pivot);
+        rhs.swap(col, pivot);
+        let (pivot_row, pivot_rhs) = (normal[col].clone(), rhs[col]);
+        for row in col + 1..terms {
+            let factor = normal[row][col] / pivot_row[col];
+            for (value, p) in normal[row].iter_mut().zip(&pivot_row).skip(col) {
+                *value -= factor * p;
+            }
+            for (value, p) in rhs[row].iter_mut().zip(pivot_rhs) {
+                *value -= factor * p;
+            }
+        }
+    }
+    let mut solution = vec![[0.0; 3]; terms];
+    for row in (0..terms).rev() {
+        for c in 0..3 {
+            let known: f64 = (row + 1..terms).map(|k| normal[row][k] * solution[k][c]).sum();
+            solution[row][c] = (rhs[row][c] - known) / normal[row][row];
+        }
+    }
+    // One coefficient vector per channel
+    Some((0..3).map(|c| solution.iter().map(|s| s[c]).collect()).collect())
+}
+
+/// Unit direction along which the per-channel gradients `(du, dv)` agree most.
+fn principal_direction(gradients: impl Iterator<Item = (f64, f64)>) -> (f64, f64) {
+    let (mut a, mut b, mut d) = (0.0, 0.0, 0.0);
+    for (gu, gv) in gradients {
+        a += gu * gu;
+        b += gu * gv;
+        d += gv * gv;
+    }
+    // Eigenvector of the largest eigenvalue of [[a, b], [b, d]]
+    let angle = 0.5 * (2.0 * b).atan2(a - d);
+    (angle.cos(), angle.sin())
+}
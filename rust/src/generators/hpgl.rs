@@ -0,0 +1,47 @@
+//! HPGL writer for vinyl cutters and pen plotters.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use crate::generators::plotter::Polyline;
+
+/// HPGL plotter units per millimetre (1 unit = 0.025 mm).
+pub const HPGL_UNITS_PER_MM: f32 = 40.0;
+
+#[derive(Clone, Debug)]
+pub struct HpglOptions {
+    /// Plotter units per millimetre; most devices use 40.
+    pub units_per_mm: f32,
+    /// Pen (or cutter tool) selected with `SP`.
+    pub pen: u8,
+}
+
+impl Default for HpglOptions {
+    fn default() -> Self {
+        Self { units_per_mm: HPGL_UNITS_PER_MM, pen: 1 }
+    }
+}
+
+/// Writes `paths` as HPGL. `mm_per_unit` converts path coordinates to millimetres.
+pub fn write_hpgl(paths: &[Polyline], mm_per_unit: f32, opts: &HpglOptions, output_path: &Path) -> Result<()> {
+    let scale = mm_per_unit * opts.units_per_mm;
+    let to_plu = |(x, y): (f32, f32)| ((x * scale).round() as i64, (y * scale).round() as i64);
+
+    let mut hpgl = format!("IN;SP{};\n", opts.pen);
+    for path in paths {
+        let Some((&first, rest)) = path.split_first() else { continue };
+        let (x, y) = to_plu(first);
+        let _ = write!(hpgl, "PU{},{};PD", x, y);
+        let coords: Vec<String> = rest.iter().map(|&p| {
+            let (x, y) = to_plu(p);
+            format!("{},{}", x, y)
+        }).collect();
+        hpgl.push_str(&coords.join(","));
+        hpgl.push_str(";\n");
+    }
+    hpgl.push_str("PU;SP0;\n");
+
+    fs::write(output_path, hpgl)?;
+    Ok(())
+}
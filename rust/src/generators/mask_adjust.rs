@@ -0,0 +1,136 @@
+//! Adjustments applied to the model mask before compositing.
+//!
+//! The mask is first remapped by levels (black point, white point, gamma),
+//! then goes through a threshold curve, from a hard cut at `threshold` to the
+//! plain mask when `softness` is 1, and is finally blended with the input's
+//! own alpha by `strength`. Partly translucent subjects such as glass or veils
+//! keep some of their see-through areas at a lower strength instead of being
+//! cut out or kept entirely.
+//!
+//! Inverting the mask keeps the background and removes the subject instead,
+//! for backdrops and clean plates; the hole it leaves can be filled with the
+//! surrounding background blurred into it or with a solid color.
+
+use serde::{Deserialize, Serialize};
+use crate::generators::color_pop;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskAdjust {
+    /// Mask value, from 0 to 1, at and below which the mask becomes 0.
+    pub black: f32,
+    /// Mask value, from 0 to 1, at and above which the mask becomes 1.
+    pub white: f32,
+    /// Above 1 keeps more of the partly masked pixels, below 1 fewer.
+    pub gamma: f32,
+    /// How much of the mask is applied, from 0 (input alpha kept) to 1.
+    pub strength: f32,
+    /// Mask value, from 0 to 1, at the middle of the curve.
+    pub threshold: f32,
+    /// Width of the ramp around `threshold`, from 0 (hard cut) to 1.
+    pub softness: f32,
+    /// Keep the background and remove the subject.
+    pub invert: bool,
+    /// What replaces the removed subject when the mask is inverted.
+    pub fill: HoleFill,
+}
+
+/// Fill for the hole left by the subject in an inverted cut-out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HoleFill {
+    /// The hole stays transparent.
+    #[default]
+    None,
+    /// The surrounding background is blurred into the hole.
+    Blur,
+    /// The hole is filled with a color.
+    Solid([u8; 3]),
+}
+
+impl Default for MaskAdjust {
+    fn default() -> Self {
+        Self { black: 0.0, white: 1.0, gamma: 1.0, strength: 1.0, threshold: 0.5, softness: 1.0, invert: false, fill: HoleFill::None }
+    }
+}
+
+impl MaskAdjust {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Levels for a mask value from 0 to 1.
+    pub fn levels(&self, value: f32) -> f32 {
+        let range = (self.white - self.black).max(f32::EPSILON);
+        ((value - self.black) / range).clamp(0.0, 1.0).powf(1.0 / self.gamma.max(0.01))
+    }
+
+    /// Threshold curve for a mask value from 0 to 1.
+    pub fn curve(&self, value: f32) -> f32 {
+        let half = self.softness.clamp(0.0, 1.0) / 2.0;
+        let (low, high) = (self.threshold - half, self.threshold + half);
+        if high - low <= f32::EPSILON {
+            return if value >= self.threshold { 1.0 } else { 0.0 };
+        }
+        ((value - low) / (high - low)).clamp(0.0, 1.0)
+    }
+
+    /// Factor, from 0 to 1, the input alpha is multiplied by for a mask value
+    /// from 0 to 1: levels, then the curve, then the inversion and the strength.
+    pub fn transfer(&self, value: f32) -> f32 {
+        let strength = self.strength.clamp(0.0, 1.0);
+        let curved = self.curve(self.levels(value));
+        let curved = if self.invert { 1.0 - curved } else { curved };
+        1.0 - strength + strength * curved
+    }
+
+    /// [`Self::transfer`] for every 8-bit mask value, scaled to 0–255.
+    pub fn lut(&self) -> [u8; 256] {
+        std::array::from_fn(|v| (self.transfer(v as f32 / 255.0) * 255.0).round() as u8)
+    }
+}
+
+/// Parses a percentage from 0 to 100 into a fraction.
+pub fn parse_percent(value: &str) -> Result<f32, String> {
+    let percent: f32 = value.trim_end_matches('%').parse().map_err(|_| format!("invalid percentage: {}", value))?;
+    if (0.0..=100.0).contains(&percent) {
+        Ok(percent / 100.0)
+    } else {
+        Err(format!("percentage must be between 0 and 100: {}", value))
+    }
+}
+
+/// Parses mask levels given as `black,white,gamma`.
+pub fn parse_levels(value: &str) -> Result<[f32; 3], String> {
+    let parts: Vec<f32> = value.split(',')
+        .map(|p| p.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("expected black,white,gamma: {}", value))?;
+    match parts[..] {
+        [black, white, gamma] if (0.0..=1.0).contains(&black) && (0.0..=1.0).contains(&white) && black < white && gamma > 0.0 => {
+            Ok([black, white, gamma])
+        }
+        _ => Err(format!("expected 0 <= black < white <= 1 and a positive gamma: {}", value)),
+    }
+}
+
+/// Parses a hole fill: `none`, `blur` or a color as `#RRGGBB`.
+pub fn parse_hole_fill(value: &str) -> Result<HoleFill, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Ok(HoleFill::None),
+        "blur" => Ok(HoleFill::Blur),
+        _ => color_pop::parse_tint(value)
+            .map(HoleFill::Solid)
+            .map_err(|_| format!("expected none, blur or a color as #RRGGBB: {}", value)),
+    }
+}
+
+/// Parses a fraction from 0 to 1.
+pub fn parse_fraction(value: &str) -> Result<f32, String> {
+    let fraction: f32 = value.parse().map_err(|_| format!("invalid value: {}", value))?;
+    if (0.0..=1.0).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        Err(format!("value must be between 0 and 1: {}", value))
+    }
+}
@@ -0,0 +1,314 @@
+//! Descriptive metadata of the input (XMP, IPTC-IIM and PNG text chunks),
+//! carried over to the SVG `<metadata>` and the batch report.
+//!
+//! Sources are merged field by field in order of preference: XMP, then IPTC,
+//! then PNG text. Which source fields end up in which Dublin Core element is
+//! set by a [`MetadataMapping`], loaded from JSON with `--metadata-map`, e.g.
+//!
+//! ```json
+//! { "title": ["headline", "title"], "description": ["caption"], "subject": [] }
+//! ```
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+/// Photoshop image resource holding the IPTC-IIM records.
+const IPTC_RESOURCE: u16 = 0x0404;
+const PNG_XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_PHOTOSHOP: &str = "http://ns.adobe.com/photoshop/1.0/";
+const NS_RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// Descriptive fields found in the input file.
+#[derive(Clone, Debug, Default)]
+pub struct ImageMetadata {
+    pub title: Option<String>,
+    pub headline: Option<String>,
+    pub caption: Option<String>,
+    pub creator: Vec<String>,
+    pub copyright: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+/// Field of [`ImageMetadata`] named in a mapping.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceField {
+    Title,
+    Headline,
+    Caption,
+    Creator,
+    Copyright,
+    Keywords,
+}
+
+/// Source fields tried, in order, for each Dublin Core element of the output.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataMapping {
+    pub title: Vec<SourceField>,
+    pub description: Vec<SourceField>,
+    pub creator: Vec<SourceField>,
+    pub rights: Vec<SourceField>,
+    pub subject: Vec<SourceField>,
+}
+
+impl Default for MetadataMapping {
+    fn default() -> Self {
+        Self {
+            title: vec![SourceField::Title, SourceField::Headline],
+            description: vec![SourceField::Caption],
+            creator: vec![SourceField::Creator],
+            rights: vec![SourceField::Copyright],
+            subject: vec![SourceField::Keywords],
+        }
+    }
+}
+
+/// Metadata written to the SVG outputs and the report.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DocumentMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rights: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subject: Vec<String>,
+}
+
+impl MetadataMapping {
+    /// Loads the mapping from a JSON job file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read metadata mapping {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid metadata mapping {:?}", path))
+    }
+
+    /// Maps `meta` to the output elements, or `None` when none of them is set.
+    pub fn apply(&self, meta: &ImageMetadata) -> Option<DocumentMetadata> {
+        let first = |fields: &[SourceField]| fields.iter().map(|&f| meta.values(f)).find(|v| !v.is_empty());
+        let text = |fields: &[SourceField]| first(fields).map(|values| values.join("; "));
+        let doc = DocumentMetadata {
+            title: text(&self.title),
+            description: text(&self.description),
+            creator: text(&self.creator),
+            rights: text(&self.rights),
+            subject: first(&self.subject).unwrap_or_default(),
+        };
+        (doc != DocumentMetadata::default()).then_some(doc)
+    }
+}
+
+impl ImageMetadata {
+    /// Reads the metadata of a JPEG or PNG file; other formats have none.
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut meta = Self::default();
+        if bytes.starts_with(&[0xff, 0xd8]) {
+            read_jpeg(&bytes, &mut meta);
+        } else if bytes.starts_with(b"\x89PNG") {
+            read_png(&bytes, &mut meta)?;
+        }
+        Ok(meta)
+    }
+
+    fn values(&self, field: SourceField) -> Vec<String> {
+        match field {
+            SourceField::Title => self.title.iter().cloned().collect(),
+            SourceField::Headline => self.headline.iter().cloned().collect(),
+            SourceField::Caption => self.caption.iter().cloned().collect(),
+            SourceField::Creator => self.creator.clone(),
+            SourceField::Copyright => self.copyright.iter().cloned().collect(),
+            SourceField::Keywords => self.keywords.clone(),
+        }
+    }
+
+    /// Fills the fields still missing from `other`.
+    fn merge(&mut self, other: ImageMetadata) {
+        fn fill(field: &mut Option<String>, value: Option<String>) {
+            if field.is_none() {
+                *field = value.filter(|v| !v.trim().is_empty());
+            }
+        }
+        fill(&mut self.title, other.title);
+        fill(&mut self.headline, other.headline);
+        fill(&mut self.caption, other.caption);
+        fill(&mut self.copyright, other.copyright);
+        if self.creator.is_empty() {
+            self.creator = other.creator;
+        }
+        if self.keywords.is_empty() {
+            self.keywords = other.keywords;
+        }
+    }
+}
+
+/// Walks the JPEG segments before the image data for XMP (APP1) and IPTC (APP13).
+fn read_jpeg(bytes: &[u8], meta: &mut ImageMetadata) {
+    let mut xmp = None;
+    let mut iptc = None;
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xff {
+        let marker = bytes[pos + 1];
+        // Start of scan or end of image: no more metadata segments
+        if marker == 0xda || marker == 0xd9 {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let Some(data) = bytes.get(pos + 4..pos + 2 + len) else { break };
+        match marker {
+            0xe1 if data.starts_with(XMP_HEADER) => xmp = Some(&data[XMP_HEADER.len()..]),
+            0xed if data.starts_with(PHOTOSHOP_HEADER) => iptc = photoshop_iptc(&data[PHOTOSHOP_HEADER.len()..]),
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+    if let Some(xmp) = xmp {
+        meta.merge(parse_xmp(&String::from_utf8_lossy(xmp)));
+    }
+    if let Some(iptc) = iptc {
+        meta.merge(parse_iim(iptc));
+    }
+}
+
+/// IPTC-IIM block among the Photoshop image resources.
+fn photoshop_iptc(mut data: &[u8]) -> Option<&[u8]> {
+    while data.len() >= 12 && data.starts_with(b"8BIM") {
+        let id = u16::from_be_bytes([data[4], data[5]]);
+        // Pascal name padded to an even length, including its length byte
+        let name_len = data[6] as usize;
+        let name_end = 6 + (name_len + 2) / 2 * 2;
+        let size_bytes = data.get(name_end..name_end + 4)?;
+        let size = u32::from_be_bytes(size_bytes.try_into().ok()?) as usize;
+        let start = name_end + 4;
+        let block = data.get(start..start + size)?;
+        if id == IPTC_RESOURCE {
+            return Some(block);
+        }
+        data = data.get(start + size + size % 2..)?;
+    }
+    None
+}
+
+/// Reads the application record (2) of IPTC-IIM datasets.
+fn parse_iim(mut data: &[u8]) -> ImageMetadata {
+    let mut datasets = Vec::new();
+    let mut utf8 = false;
+    while data.len() >= 5 && data[0] == 0x1c {
+        let (record, dataset) = (data[1], data[2]);
+        let size = u16::from_be_bytes([data[3], data[4]]) as usize;
+        // Extended sizes are only used for large binary previews
+        if size & 0x8000 != 0 {
+            break;
+        }
+        let Some(value) = data.get(5..5 + size) else { break };
+        match (record, dataset) {
+            (1, 90) => utf8 = value == b"\x1b%G",
+            (2, _) => datasets.push((dataset, value)),
+            _ => {}
+        }
+        data = &data[5 + size..];
+    }
+
+    let decode = |value: &[u8]| if utf8 {
+        String::from_utf8_lossy(value).trim().to_string()
+    } else {
+        value.iter().map(|&b| b as char).collect::<String>().trim().to_string()
+    };
+    let mut meta = ImageMetadata::default();
+    for (dataset, value) in datasets {
+        let value = decode(value);
+        match dataset {
+            5 => meta.title = Some(value),
+            105 => meta.headline = Some(value),
+            120 => meta.caption = Some(value),
+            80 => meta.creator.push(value),
+            116 => meta.copyright = Some(value),
+            25 => meta.keywords.push(value),
+            _ => {}
+        }
+    }
+    meta
+}
+
+/// Reads XMP from an iTXt chunk and the conventional `Title`, `Author`,
+/// `Description` and `Copyright` text chunks.
+fn read_png(bytes: &[u8], meta: &mut ImageMetadata) -> Result<()> {
+    let reader = png::Decoder::new(Cursor::new(bytes)).read_info()?;
+    let info = reader.info();
+    let mut texts: Vec<(String, String)> = info.uncompressed_latin1_text.iter()
+        .map(|t| (t.keyword.clone(), t.text.clone()))
+        .collect();
+    for chunk in &info.compressed_latin1_text {
+        if let Ok(text) = chunk.get_text() {
+            texts.push((chunk.keyword.clone(), text));
+        }
+    }
+    for chunk in &info.utf8_text {
+        if let Ok(text) = chunk.get_text() {
+            texts.push((chunk.keyword.clone(), text));
+        }
+    }
+
+    if let Some((_, xmp)) = texts.iter().find(|(keyword, _)| keyword == PNG_XMP_KEYWORD) {
+        meta.merge(parse_xmp(xmp));
+    }
+    let text = |keyword: &str| texts.iter().find(|(k, _)| k == keyword).map(|(_, t)| t.trim().to_string());
+    meta.merge(ImageMetadata {
+        title: text("Title"),
+        caption: text("Description"),
+        creator: text("Author").into_iter().collect(),
+        copyright: text("Copyright"),
+        ..Default::default()
+    });
+    Ok(())
+}
+
+/// Extracts the Dublin Core and Photoshop fields of an XMP packet.
+fn parse_xmp(xmp: &str) -> ImageMetadata {
+    let xml = xmp.trim_end_matches(['\0', ' ', '\n']);
+    let Ok(doc) = roxmltree::Document::parse(xml) else { return ImageMetadata::default() };
+
+    // Values are either `rdf:li` items of an Alt/Seq/Bag, element text, or an attribute of rdf:Description
+    let values = |ns: &str, name: &str| -> Vec<String> {
+        for node in doc.descendants() {
+            if node.tag_name().namespace() == Some(ns) && node.tag_name().name() == name {
+                let items: Vec<String> = node.descendants()
+                    .filter(|n| n.tag_name().namespace() == Some(NS_RDF) && n.tag_name().name() == "li")
+                    .filter_map(|n| n.text())
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                if !items.is_empty() {
+                    return items;
+                }
+                if let Some(text) = node.text().map(str::trim).filter(|t| !t.is_empty()) {
+                    return vec![text.to_string()];
+                }
+            }
+            if let Some(value) = node.attribute((ns, name)) {
+                return vec![value.trim().to_string()];
+            }
+        }
+        Vec::new()
+    };
+    let single = |ns: &str, name: &str| values(ns, name).into_iter().next();
+
+    ImageMetadata {
+        title: single(NS_DC, "title"),
+        headline: single(NS_PHOTOSHOP, "Headline"),
+        caption: single(NS_DC, "description"),
+        creator: values(NS_DC, "creator"),
+        copyright: single(NS_DC, "rights"),
+        keywords: values(NS_DC, "subject"),
+    }
+}
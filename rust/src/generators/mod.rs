@@ -1,16 +1,54 @@
-use std::sync::mpsc::Sender;
-
-pub mod alpha;
-pub mod mono;
-pub mod color;
-pub mod thumbnail;
+// The model configs and tensor conversions are all the browser build uses
+pub mod auto;
 pub mod models;
-pub mod ai;
+pub mod tensor;
+
+native! {
+    use crate::events::{AppEvent, EventBus};
 
-pub use alpha::generate_alpha_png;
-pub use mono::{generate_grayscale_svg, generate_halftone_svg, generate_lineart_svg};
-pub use color::{generate_logo, generate_illustration};
-pub use thumbnail::generate_thumbnail;
+    pub mod alpha;
+    pub mod mono;
+    pub mod color;
+    pub mod cmyk;
+    pub mod thumbnail;
+    pub mod desktop_thumbnail;
+    pub mod svg;
+    pub mod rasterize;
+    pub mod path_model;
+    pub mod plotter;
+    pub mod hpgl;
+    pub mod embroidery;
+    pub mod encode;
+    pub mod ai;
+    pub mod confidence;
+    pub mod preprocess;
+    pub mod gradient;
+    pub mod fidelity;
+    pub mod regions;
+    pub mod geometry;
+    pub mod symmetry;
+    pub mod text_regions;
+    pub mod ocr;
+    pub mod mask_adjust;
+    pub mod transform;
+    pub mod denoise;
+    pub mod upscale;
+    pub mod bokeh;
+    pub mod color_pop;
+    pub mod colorblind;
+    pub mod watermark;
+    pub mod metadata;
+    pub mod registry;
+    pub mod params;
+    pub mod presets;
+
+    pub use alpha::{generate_alpha_png, AlphaOptions};
+    pub use mono::{generate_grayscale_svg, generate_halftone_svg, generate_lineart_svg};
+    pub use color::{generate_logo, generate_illustration, LogoStyle};
+    pub use thumbnail::generate_thumbnail;
+    pub use svg::SvgOptions;
+    pub use preprocess::PreprocessOptions;
+}
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum ModelState {
@@ -21,6 +59,8 @@ pub enum ModelState {
 
 #[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
 pub enum ModelType {
+    /// Picks a model per image from its content, see [`auto`].
+    Auto,
     U2Net,
     U2NetP,
     U2NetHumanSeg,
@@ -41,20 +81,116 @@ pub enum ModelType {
 
 impl Default for ModelType {
     fn default() -> Self {
-        ModelType::U2Net
+        ModelType::Auto
+    }
+}
+
+impl ModelType {
+    /// Every selectable entry, `Auto` first.
+    pub const ALL: [ModelType; 17] = [
+        ModelType::Auto,
+        ModelType::U2Net, ModelType::U2NetP, ModelType::U2NetHumanSeg, ModelType::U2NetClothSeg,
+        ModelType::Silueta, ModelType::IsNetGeneralUse, ModelType::IsNetAnime, ModelType::Sam,
+        ModelType::BiRefNetGeneral, ModelType::BiRefNetGeneralLite, ModelType::BiRefNetPortrait,
+        ModelType::BiRefNetDis, ModelType::BiRefNetHrsod, ModelType::BiRefNetCod,
+        ModelType::BiRefNetMassive, ModelType::BriaRmbg,
+    ];
+
+    /// Registry name used on the command line (`auto`, `u2net`, `birefnet-portrait`...).
+    pub fn name(self) -> String {
+        match self {
+            ModelType::Auto => "auto".to_string(),
+            model => models::get_model_config(model).name,
+        }
+    }
+}
+
+impl std::str::FromStr for ModelType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ModelType::ALL.into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(value))
+            .ok_or_else(|| {
+                let names: Vec<String> = ModelType::ALL.iter().map(|m| m.name()).collect();
+                format!("unknown model '{}', expected one of: {}", value, names.join(", "))
+            })
     }
 }
 
+/// How much the console logger prints.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    Debug,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 pub enum LogOutput {
-    StdOut,
-    Channel(Sender<String>),
+    StdOut(Verbosity),
+    /// Publishes log lines, progress and model state to the GUI event bus.
+    Events(EventBus),
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl LogOutput {
     pub fn send(&self, msg: String) {
+        self.log(Verbosity::Normal, msg);
+    }
+
+    /// Extra detail shown with `-v`.
+    pub fn verbose(&self, msg: String) {
+        self.log(Verbosity::Verbose, msg);
+    }
+
+    /// Diagnostic detail shown with `-vv`.
+    pub fn debug(&self, msg: String) {
+        self.log(Verbosity::Debug, msg);
+    }
+
+    /// Errors are always shown, on stderr for the console logger.
+    pub fn error(&self, msg: String) {
+        match self {
+            LogOutput::StdOut(_) => eprintln!("{}", msg),
+            LogOutput::Events(bus) => bus.publish(AppEvent::LogLine(msg)),
+        }
+    }
+
+    /// Reports a model loading state change; only the GUI shows it.
+    pub fn model_state(&self, state: ModelState) {
+        if let LogOutput::Events(bus) = self {
+            bus.publish(AppEvent::ModelStateChanged(state));
+        }
+    }
+
+    /// Reports that `done` of `total` pipeline steps are complete; only the GUI shows it.
+    pub fn progress(&self, done: usize, total: usize) {
+        if let LogOutput::Events(bus) = self {
+            bus.publish(AppEvent::Progress { done, total });
+        }
+    }
+
+    /// Hands the unadjusted model mask of `input` to the preview; only the GUI shows it.
+    pub fn mask_ready(&self, input: &std::path::Path, mask: alpha::Mask) {
+        if let LogOutput::Events(bus) = self {
+            bus.publish(AppEvent::MaskReady { input: input.to_path_buf(), mask: std::sync::Arc::new(mask) });
+        }
+    }
+
+    fn log(&self, level: Verbosity, msg: String) {
         match self {
-            LogOutput::StdOut => println!("{}", msg),
-            LogOutput::Channel(tx) => { let _ = tx.send(msg); }
+            LogOutput::StdOut(verbosity) => {
+                if level <= *verbosity {
+                    println!("{}", msg);
+                }
+            }
+            LogOutput::Events(bus) => {
+                if level <= Verbosity::Normal {
+                    bus.publish(AppEvent::LogLine(msg));
+                }
+            }
         }
     }
 }
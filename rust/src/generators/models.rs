@@ -11,17 +11,155 @@ pub struct ModelConfig {
     pub filename: String,
     pub resolution: u32,
     pub size_mb: u32,
+    pub normalization: Normalization,
+    pub output: OutputTransform,
+    /// License of the model weights, as an SPDX identifier.
+    pub license: &'static str,
+    /// Paper or project to credit when publishing results.
+    pub citation: &'static str,
+}
+
+impl ModelConfig {
+    /// Whether the weights may not be used commercially (e.g. CC BY-NC).
+    pub fn non_commercial(&self) -> bool {
+        self.license.contains("-NC")
+    }
+}
+
+const U2NET_CITATION: &str = "Qin et al., \"U²-Net: Going Deeper with Nested U-Structure for Salient Object Detection\", Pattern Recognition, 2020";
+const ISNET_CITATION: &str = "Qin et al., \"Highly Accurate Dichotomous Image Segmentation\", ECCV 2022";
+const BIREFNET_CITATION: &str = "Zheng et al., \"Bilateral Reference for High-Resolution Dichotomous Image Segmentation\", CAAI AIR, 2024";
+
+/// Order of the color planes in the input tensor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChannelOrder {
+    Rgb,
+    #[allow(dead_code)] // None of the bundled models expects BGR input
+    Bgr,
+}
+
+/// Input preprocessing expected by a model: `(value * scale - mean) / std`,
+/// with `mean` and `std` given in tensor channel order.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Normalization {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+    pub scale: f32,
+    pub channel_order: ChannelOrder,
+}
+
+impl Normalization {
+    /// ImageNet statistics, used by the U2-Net family and BiRefNet.
+    pub const IMAGENET: Normalization = Normalization {
+        mean: [0.485, 0.456, 0.406],
+        std: [0.229, 0.224, 0.225],
+        scale: 1.0 / 255.0,
+        channel_order: ChannelOrder::Rgb,
+    };
+
+    /// Centered to [-0.5, 0.5] without scaling, used by IS-Net and BRIA RMBG.
+    pub const CENTERED: Normalization = Normalization {
+        mean: [0.5, 0.5, 0.5],
+        std: [1.0, 1.0, 1.0],
+        scale: 1.0 / 255.0,
+        channel_order: ChannelOrder::Rgb,
+    };
+
+    /// Plain 0..1 scaling, used by the Real-ESRGAN upscaler.
+    pub const UNIT: Normalization = Normalization {
+        mean: [0.0, 0.0, 0.0],
+        std: [1.0, 1.0, 1.0],
+        scale: 1.0 / 255.0,
+        channel_order: ChannelOrder::Rgb,
+    };
+
+    /// Index into an RGB pixel for each tensor plane.
+    pub fn source_channels(&self) -> [usize; 3] {
+        match self.channel_order {
+            ChannelOrder::Rgb => [0, 1, 2],
+            ChannelOrder::Bgr => [2, 1, 0],
+        }
+    }
+
+    /// Per-plane lookup tables mapping a `u8` value to its normalized `f32`.
+    pub fn luts(&self) -> [[f32; 256]; 3] {
+        let mut luts = [[0.0f32; 256]; 3];
+        for (c, lut) in luts.iter_mut().enumerate() {
+            for (v, out) in lut.iter_mut().enumerate() {
+                *out = (v as f32 * self.scale - self.mean[c]) / self.std[c];
+            }
+        }
+        luts
+    }
+}
+
+/// Activation applied to the raw model output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Activation {
+    /// Output is already a probability-like map.
+    Identity,
+    /// Output is logits for a single foreground class.
+    Sigmoid,
+    /// Output is per-class logits across channels.
+    Softmax,
+}
+
+/// Which output channel becomes the mask.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MaskChannel {
+    /// Use this channel directly.
+    Index(usize),
+    /// Use the complement of this background channel (everything else is subject).
+    NotBackground(usize),
+}
+
+/// Turns the model output into a 0..1 mask.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OutputTransform {
+    pub activation: Activation,
+    pub channel: MaskChannel,
+    /// Stretch the mask to the full 0..1 range.
+    pub minmax: bool,
+}
+
+impl OutputTransform {
+    /// Saliency map rescaled to its min/max, as for U2-Net and IS-Net.
+    pub const MINMAX: OutputTransform = OutputTransform {
+        activation: Activation::Identity,
+        channel: MaskChannel::Index(0),
+        minmax: true,
+    };
+
+    /// Logits squashed with a sigmoid, then rescaled, as for BiRefNet.
+    pub const SIGMOID_MINMAX: OutputTransform = OutputTransform {
+        activation: Activation::Sigmoid,
+        channel: MaskChannel::Index(0),
+        minmax: true,
+    };
+
+    /// Multi-class segmentation where channel 0 is the background.
+    pub const SOFTMAX_FOREGROUND: OutputTransform = OutputTransform {
+        activation: Activation::Softmax,
+        channel: MaskChannel::NotBackground(0),
+        minmax: false,
+    };
 }
 
 /// Returns the configuration for a given model type.
 pub fn get_model_config(model: ModelType) -> ModelConfig {
     match model {
+        // Resolved per image before inference; fall back to the general-purpose choice
+        ModelType::Auto => get_model_config(super::auto::ContentKind::General.model()),
         ModelType::U2Net => ModelConfig {
             name: "u2net".to_string(),
             url: "https://github.com/danielgatis/rembg/releases/download/v0.0.0/u2net.onnx".to_string(),
             filename: "u2net.onnx".to_string(),
             resolution: 320,
             size_mb: 170,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: U2NET_CITATION,
         },
         ModelType::U2NetP => ModelConfig {
             name: "u2netp".to_string(),
@@ -29,6 +167,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "u2netp.onnx".to_string(),
             resolution: 320,
             size_mb: 4,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: U2NET_CITATION,
         },
         ModelType::U2NetHumanSeg => ModelConfig {
             name: "u2net_human_seg".to_string(),
@@ -36,6 +178,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "u2net_human_seg.onnx".to_string(),
             resolution: 320,
             size_mb: 170,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: U2NET_CITATION,
         },
         ModelType::U2NetClothSeg => ModelConfig {
             name: "u2net_cloth_seg".to_string(),
@@ -43,6 +189,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "u2net_cloth_seg.onnx".to_string(),
             resolution: 320,
             size_mb: 170,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SOFTMAX_FOREGROUND,
+            license: "MIT",
+            citation: "levindabhi, \"cloth-segmentation\", github.com/levindabhi/cloth-segmentation, 2021",
         },
         ModelType::Silueta => ModelConfig {
             name: "silueta".to_string(),
@@ -50,6 +200,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "silueta.onnx".to_string(),
             resolution: 320,
             size_mb: 43,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: U2NET_CITATION,
         },
         ModelType::IsNetGeneralUse => ModelConfig {
             name: "isnet-general-use".to_string(),
@@ -57,6 +211,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "isnet-general-use.onnx".to_string(),
             resolution: 1024,
             size_mb: 176,
+            normalization: Normalization::CENTERED,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: ISNET_CITATION,
         },
         ModelType::IsNetAnime => ModelConfig {
             name: "isnet-anime".to_string(),
@@ -64,6 +222,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "isnet-anime.onnx".to_string(),
             resolution: 1024,
             size_mb: 176,
+            normalization: Normalization::CENTERED,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: "SkyTNT, \"anime-segmentation\", github.com/SkyTNT/anime-segmentation, 2022",
         },
         ModelType::Sam => ModelConfig {
             name: "sam".to_string(),
@@ -71,6 +233,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "sam-encoder.onnx".to_string(),
             resolution: 1024,
             size_mb: 358,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::MINMAX,
+            license: "Apache-2.0",
+            citation: "Kirillov et al., \"Segment Anything\", ICCV 2023",
         },
         ModelType::BiRefNetGeneral => ModelConfig {
             name: "birefnet-general".to_string(),
@@ -78,6 +244,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-general.onnx".to_string(),
             resolution: 1024,
             size_mb: 290,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BiRefNetGeneralLite => ModelConfig {
             name: "birefnet-general-lite".to_string(),
@@ -85,6 +255,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-general-lite.onnx".to_string(),
             resolution: 1024,
             size_mb: 145,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BiRefNetPortrait => ModelConfig {
             name: "birefnet-portrait".to_string(),
@@ -92,6 +266,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-portrait.onnx".to_string(),
             resolution: 1024,
             size_mb: 290,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BiRefNetDis => ModelConfig {
             name: "birefnet-dis".to_string(),
@@ -99,6 +277,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-dis.onnx".to_string(),
             resolution: 1024,
             size_mb: 290,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BiRefNetHrsod => ModelConfig {
             name: "birefnet-hrsod".to_string(),
@@ -106,6 +288,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-hrsod.onnx".to_string(),
             resolution: 1024,
             size_mb: 290,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BiRefNetCod => ModelConfig {
             name: "birefnet-cod".to_string(),
@@ -113,6 +299,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-cod.onnx".to_string(),
             resolution: 1024,
             size_mb: 290,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BiRefNetMassive => ModelConfig {
             name: "birefnet-massive".to_string(),
@@ -120,6 +310,10 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "birefnet-massive.onnx".to_string(),
             resolution: 1024,
             size_mb: 290,
+            normalization: Normalization::IMAGENET,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "MIT",
+            citation: BIREFNET_CITATION,
         },
         ModelType::BriaRmbg => ModelConfig {
             name: "bria-rmbg".to_string(),
@@ -127,6 +321,27 @@ pub fn get_model_config(model: ModelType) -> ModelConfig {
             filename: "bria-rmbg.onnx".to_string(),
             resolution: 1024,
             size_mb: 72,
+            normalization: Normalization::CENTERED,
+            output: OutputTransform::SIGMOID_MINMAX,
+            license: "CC-BY-NC-4.0",
+            citation: "BRIA AI, \"RMBG-2.0\", huggingface.co/briaai/RMBG-2.0, 2024",
         },
     }
 }
+
+/// Real-ESRGAN ×4 super-resolution model used before vectorization.
+/// `resolution` is the fixed tile size the model accepts.
+pub fn get_upscaler_config() -> ModelConfig {
+    ModelConfig {
+        name: "real-esrgan-x4plus".to_string(),
+        url: "https://huggingface.co/qualcomm/Real-ESRGAN-x4plus/resolve/main/Real-ESRGAN-x4plus.onnx".to_string(),
+        filename: "real-esrgan-x4plus.onnx".to_string(),
+        resolution: 128,
+        size_mb: 67,
+        normalization: Normalization::UNIT,
+        // Unused: the output is an RGB image rather than a mask
+        output: OutputTransform::MINMAX,
+        license: "BSD-3-Clause",
+        citation: "Wang et al., \"Real-ESRGAN: Training Real-World Blind Super-Resolution with Pure Synthetic Data\", ICCVW 2021",
+    }
+}
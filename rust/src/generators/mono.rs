@@ -1,34 +1,50 @@
-use image::{DynamicImage, Luma};
+use image::{DynamicImage, GrayImage, Luma};
+use std::io::{self, Write};
 use std::path::Path;
 use std::process::Command;
-use std::fs;
 use anyhow::{Result, anyhow};
+use crate::error::AlphasvgError;
 use crate::lang::LanguageManager;
-use crate::generators::LogOutput;
-use tempfile::NamedTempFile;
+use crate::temp;
+use crate::generators::{LogOutput, cmyk, geometry, hpgl, plotter, regions};
+use crate::generators::svg::{self, PotracePaint, SvgLayer, SvgOptions, WorkingImage};
 
-pub fn generate_grayscale_svg(img: &DynamicImage, output_path: &Path, num_tones: u32, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+#[tracing::instrument(skip_all)]
+pub fn generate_grayscale_svg(img: &DynamicImage, output_path: &Path, num_tones: u32, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
     if output_path.exists() {
         return Ok(());
     }
 
-    let gray = img.to_luma8();
+    let work = WorkingImage::new(img, opts);
+    let gray = work.image.to_luma8();
     let (width, height) = gray.dimensions();
     
-    let mut svg_layers = Vec::new();
     let tone_levels: Vec<u8> = (0..=num_tones).map(|i| (i * 255 / num_tones) as u8).collect();
 
-    for i in 0..num_tones as usize {
+    // Tone of every pixel, so small specks can be merged into the tones around them
+    let mut tones: Vec<Option<usize>> = gray.pixels()
+        .map(|p| (0..num_tones as usize).find(|&i| p.0[0] >= tone_levels[i] && p.0[0] < tone_levels[i + 1]))
+        .collect();
+    if let Some(min_area) = opts.min_region_area {
+        let merged = regions::merge_small(&mut tones, width, height, min_area.pixels(width, height, work.scale()));
+        logger.verbose(lang.t_args("log_regions_merged", &[merged]));
+    }
+
+    // Each level is streamed to the file once traced; lightest tones first so darker ones stack on top
+    let mut document = work.sink(output_path)?;
+    let mut level = 0;
+    let mut mask = GrayImage::new(width, height);
+    for i in (0..num_tones as usize).rev() {
         let min_val = tone_levels[i];
         let max_val = tone_levels[i + 1];
         let tone_value = ((min_val as u16 + max_val as u16) / 2) as u8;
 
         if tone_value > 245 { continue; }
 
-        let mut mask = image::ImageBuffer::new(width, height);
         let mut pixel_count = 0;
-        for (x, y, p) in gray.enumerate_pixels() {
-            if p.0[0] >= min_val && p.0[0] < max_val {
+        for (j, &tone) in tones.iter().enumerate() {
+            let (x, y) = (j as u32 % width, j as u32 / width);
+            if tone == Some(i) {
                 mask.put_pixel(x, y, Luma([0u8])); // Black
                 pixel_count += 1;
             } else {
@@ -38,14 +54,12 @@ pub fn generate_grayscale_svg(img: &DynamicImage, output_path: &Path, num_tones:
 
         if pixel_count < 50 { continue; }
 
-        let temp_bmp = NamedTempFile::new_in(".")?;
-        mask.save(temp_bmp.path().with_extension("bmp"))?;
-        let bmp_path = temp_bmp.path().with_extension("bmp");
+        let bmp_path = temp::path_beside(output_path, "bmp")?;
+        mask.save(&bmp_path).map_err(|e| AlphasvgError::encode(&bmp_path, e))?;
 
-        let temp_svg = NamedTempFile::new_in(".")?;
-        let svg_tmp_path = temp_svg.path().with_extension("svg");
+        let svg_tmp_path = temp::path_beside(output_path, "svg")?;
 
-        let status = Command::new("potrace")
+        let status = tracing::info_span!("potrace").in_scope(|| Command::new("potrace")
             .args(&[
                 bmp_path.to_str().unwrap(),
                 "-s",
@@ -55,66 +69,59 @@ pub fn generate_grayscale_svg(img: &DynamicImage, output_path: &Path, num_tones:
                 "--turdsize", "8",
                 "--alphamax", "1.0",
             ])
-            .status()?;
+            .status())?;
 
         if status.success() {
-            let content = fs::read_to_string(&svg_tmp_path)?;
             let hex_color = format!("#{:02x}{:02x}{:02x}", tone_value, tone_value, tone_value);
-            
-            // Robustly extract the content between <svg ...> and </svg>
-            if let Some(start_idx) = content.find("<svg") {
-                if let Some(content_start) = content[start_idx..].find('>') {
-                    let inner_content_start = start_idx + content_start + 1;
-                    if let Some(end_idx) = content.rfind("</svg>") {
-                        let inner_content = &content[inner_content_start..end_idx];
-                        let colored_content = inner_content
-                            .replace("fill=\"black\"", &format!("fill=\"{}\"", hex_color))
-                            .replace("fill=\"#000000\"", &format!("fill=\"{}\"", hex_color));
-                        svg_layers.push((tone_value, colored_content));
-                    }
-                }
-            }
+            level += 1;
+            document.add_with(&format!("Level {}", level), |out| svg::copy_potrace_body(&svg_tmp_path, PotracePaint::Fill(&hex_color), out))?;
         }
-        
-        // Clean up manual bmp
-        let _ = fs::remove_file(bmp_path);
-        let _ = fs::remove_file(svg_tmp_path);
-    }
-
-    svg_layers.sort_by(|a, b| b.0.cmp(&a.0));
-
-    let mut final_svg = format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
-        <svg version=\"1.1\" xmlns=\"http://www.w3.org/2000/svg\" \
-        width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
-        width, height, width, height
-    );
-
-    for (_, layer) in svg_layers {
-        final_svg.push_str(&layer);
-        final_svg.push('\n');
     }
-    final_svg.push_str("</svg>");
 
-    fs::write(output_path, final_svg)?;
-    logger.send(format!("{}{:?}", lang.t("log_svg_mono_ok"), output_path.file_name().unwrap()));
+    document.finish()?;
+    logger.send(lang.t_args("log_svg_mono_ok", &[lang.format_file_name(output_path)]));
     Ok(())
 }
 
-pub fn generate_halftone_svg(img: &DynamicImage, output_path: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+#[tracing::instrument(skip_all)]
+pub fn generate_halftone_svg(img: &DynamicImage, output_path: &Path, angle_deg: f32, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
     if output_path.exists() {
         return Ok(());
     }
 
-    let gray = img.to_luma8();
+    if opts.cmyk {
+        return cmyk::generate_cmyk_halftone(img, output_path, opts, lang, logger);
+    }
+
+    let work = WorkingImage::new(img, opts);
+    let gray = work.image.to_luma8();
     let (width, height) = gray.dimensions();
+    let background = format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>", width, height);
+    let mut document = work.sink(output_path)?;
+    document.add(SvgLayer::background("Background", background))?;
+    document.add_with("Halftone dots", |out| write_halftone_dots(&gray, angle_deg, "#000", out))?;
+    document.finish()?;
+    logger.send(lang.t_args("log_svg_mono_ok", &[lang.format_file_name(output_path)]));
+    Ok(())
+}
+
+/// Builds an amplitude-modulated dot screen at `angle_deg` from a tone image
+/// (0 = full coverage, 255 = no ink).
+pub(crate) fn halftone_dots(tone: &GrayImage, angle_deg: f32, fill: &str) -> String {
+    let mut dots = Vec::new();
+    write_halftone_dots(tone, angle_deg, fill, &mut dots).expect("writing to memory cannot fail");
+    String::from_utf8(dots).expect("dots are ASCII")
+}
+
+/// Writes the dot screen of [`halftone_dots`] to `out` one screen row at a time.
+fn write_halftone_dots(tone: &GrayImage, angle_deg: f32, fill: &str, out: &mut dyn Write) -> io::Result<()> {
+    let (width, height) = tone.dimensions();
     let spacing = 5.0;
     let dot_size = 3.0;
-    let angle = 45.0f32.to_radians();
+    let angle = angle_deg.to_radians();
     let cos_a = angle.cos();
     let sin_a = angle.sin();
 
-    let mut circles = Vec::new();
     let diagonal = ((width as f32).powi(2) + (height as f32).powi(2)).sqrt() as i32;
 
     for y in (-diagonal..diagonal).step_by(spacing as usize) {
@@ -125,58 +132,41 @@ pub fn generate_halftone_svg(img: &DynamicImage, output_path: &Path, lang: &Lang
             let orig_y = (xf * sin_a + yf * cos_a + height as f32 / 2.0) as i32;
 
             if orig_x >= 0 && orig_x < width as i32 && orig_y >= 0 && orig_y < height as i32 {
-                let gray_val = gray.get_pixel(orig_x as u32, orig_y as u32).0[0];
+                let gray_val = tone.get_pixel(orig_x as u32, orig_y as u32).0[0];
                 let darkness = 1.0 - (gray_val as f32 / 255.0);
                 let radius = (dot_size * darkness) * 0.8;
 
                 if radius > 0.5 {
-                    circles.push(format!(
-                        "<circle cx=\"{}\" cy=\"{}\" r=\"{:.2}\" fill=\"#000\" />",
-                        orig_x, orig_y, radius
-                    ));
+                    writeln!(out, "<circle cx=\"{}\" cy=\"{}\" r=\"{:.2}\" fill=\"{}\" />", orig_x, orig_y, radius, fill)?;
                 }
             }
         }
     }
-
-    let mut svg = format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-        <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n\
-        <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n",
-        width, height, width, height
-    );
-    for c in circles {
-        svg.push_str("  ");
-        svg.push_str(&c);
-        svg.push('\n');
-    }
-    svg.push_str("</svg>");
-
-    fs::write(output_path, svg)?;
-    logger.send(format!("{}{:?}", lang.t("log_svg_mono_ok"), output_path.file_name().unwrap()));
     Ok(())
 }
 
-pub fn generate_lineart_svg(img: &DynamicImage, output_path: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+/// Writes the line art SVG, with the traced paths straightened and smoothed at
+/// `cleanup`, from 0 (off) to 1, see [`geometry`].
+#[tracing::instrument(skip_all)]
+pub fn generate_lineart_svg(img: &DynamicImage, output_path: &Path, threshold: u8, cleanup: f32, opts: &SvgOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
     if output_path.exists() {
         return Ok(());
     }
 
-    let gray = img.to_luma8();
+    let work = WorkingImage::new(img, opts);
+    let gray = work.image.to_luma8();
     let mut mask = image::ImageBuffer::new(gray.width(), gray.height());
     for (x, y, p) in gray.enumerate_pixels() {
-        let val = if p.0[0] < 140 { 0u8 } else { 255u8 };
+        let val = if p.0[0] < threshold { 0u8 } else { 255u8 };
         mask.put_pixel(x, y, Luma([val]));
     }
 
-    let temp_bmp = NamedTempFile::new_in(".")?;
-    let bmp_path = temp_bmp.path().with_extension("bmp");
-    mask.save(&bmp_path)?;
+    let bmp_path = temp::path_beside(output_path, "bmp")?;
+    mask.save(&bmp_path).map_err(|e| AlphasvgError::encode(&bmp_path, e))?;
 
-    let temp_svg = NamedTempFile::new_in(".")?;
-    let svg_tmp_path = temp_svg.path().with_extension("svg");
+    let svg_tmp_path = temp::path_beside(output_path, "svg")?;
 
-    let status = Command::new("potrace")
+    let status = tracing::info_span!("potrace").in_scope(|| Command::new("potrace")
         .args(&[
             bmp_path.to_str().unwrap(),
             "-s",
@@ -185,36 +175,36 @@ pub fn generate_lineart_svg(img: &DynamicImage, output_path: &Path, lang: &Langu
             "--flat",
             "--turdsize", "10",
         ])
-        .status()?;
+        .status())?;
 
     if status.success() {
-        let content = fs::read_to_string(&svg_tmp_path)?;
-        let mut final_svg = format!(
-            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
-            <svg version=\"1.1\" xmlns=\"http://www.w3.org/2000/svg\" \
-            width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
-            gray.width(), gray.height(), gray.width(), gray.height()
-        );
-
-        if let Some(start_idx) = content.find("<svg") {
-            if let Some(content_start) = content[start_idx..].find('>') {
-                let inner_content_start = start_idx + content_start + 1;
-                if let Some(end_idx) = content.rfind("</svg>") {
-                    final_svg.push_str(&content[inner_content_start..end_idx]);
-                }
-            }
+        if cleanup > 0.0 {
+            geometry::clean_file(&svg_tmp_path, cleanup)?;
         }
-        final_svg.push_str("</svg>");
-        fs::write(output_path, final_svg)?;
+        let mut document = work.sink(output_path)?;
+        document.add_with("Line art", |out| svg::copy_potrace_body(&svg_tmp_path, PotracePaint::Black, out))?;
+        document.finish()?;
     }
 
-    let _ = fs::remove_file(bmp_path);
-    let _ = fs::remove_file(svg_tmp_path);
+    let hpgl_result = match &opts.hpgl {
+        Some(hpgl_opts) if status.success() => plotter::trace_polylines(&bmp_path, 10).and_then(|paths| {
+            let mm_per_px = work.scale() * 25.4 / opts.dpi;
+            let (paths, stats) = plotter::optimize(paths);
+            logger.verbose(format!(
+                "🧭 Plot travel {:.0} -> {:.0} mm, {} -> {} points (~{:.0} s saved)",
+                stats.travel_before * mm_per_px, stats.travel_after * mm_per_px,
+                stats.points_before, stats.points_after, stats.seconds_saved(mm_per_px)
+            ));
+            hpgl::write_hpgl(&paths, mm_per_px, hpgl_opts, &output_path.with_extension("hpgl"))
+        }),
+        _ => Ok(()),
+    };
 
     if !status.success() {
         return Err(anyhow!("Potrace failed for lineart"));
     }
+    hpgl_result?;
 
-    logger.send(format!("{}{:?}", lang.t("log_svg_mono_ok"), output_path.file_name().unwrap()));
+    logger.send(lang.t_args("log_svg_mono_ok", &[lang.format_file_name(output_path)]));
     Ok(())
 }
@@ -0,0 +1,132 @@
+//! Optical character recognition for the color SVG generators (`--ocr`).
+//!
+//! Built with the `ocr` cargo feature. The image is flattened onto white and
+//! handed to the `tesseract` command, whose word boxes are grouped back into
+//! lines; lines recognized with less than [`MIN_CONFIDENCE`] are dropped. Each
+//! line becomes an editable `<text>` element in its own layer, over a rectangle
+//! of the line's background color, sized to the line's box: the font size is
+//! the box height and `textLength` stretches the fallback font to its width.
+//! The color layers leave the recognized lines out, and `--text-regions` does
+//! not trace them again.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::generators::svg::{self, SvgLayer};
+use crate::generators::text_regions::{self, Glyphs, TextRegion};
+
+/// Mean word confidence, from 0 to 100, a line needs to be kept.
+pub const MIN_CONFIDENCE: f32 = 60.0;
+/// Baseline of a line, as a share of its height from the top; the rest is left for descenders.
+const BASELINE: f32 = 0.8;
+
+/// A line of recognized text, in pixels of the vectorized image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Mean confidence of the words, from 0 to 100.
+    pub confidence: f32,
+}
+
+impl OcrLine {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Whether the line and a text region share any pixel.
+    pub fn overlaps(&self, region: &TextRegion) -> bool {
+        self.x < region.x + region.width && region.x < self.x + self.width
+            && self.y < region.y + region.height && region.y < self.y + self.height
+    }
+}
+
+/// Recognizes the lines of text of `img` in `language`, a tesseract language
+/// code such as `eng` or `spa+eus`. Temporary files go next to `output_path`.
+#[cfg(feature = "ocr")]
+pub fn recognize(img: &DynamicImage, language: &str, output_path: &Path) -> Result<Vec<OcrLine>> {
+    use std::process::Command;
+    use anyhow::{Context, bail};
+    use crate::error::AlphasvgError;
+    use crate::temp;
+
+    let png_path = temp::path_beside(output_path, "png")?;
+    text_regions::flatten_gray(img).save(&png_path).map_err(|e| AlphasvgError::encode(&png_path, e))?;
+    let output = tracing::info_span!("tesseract").in_scope(|| Command::new("tesseract")
+        .args([png_path.to_str().unwrap(), "stdout", "-l", language, "tsv"])
+        .output())
+        .context("Could not run tesseract")?;
+    if !output.status.success() {
+        bail!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Without the `ocr` feature there is no recognizer.
+#[cfg(not(feature = "ocr"))]
+pub fn recognize(_img: &DynamicImage, _language: &str, _output_path: &Path) -> Result<Vec<OcrLine>> {
+    anyhow::bail!("alphasvg was built without the ocr feature")
+}
+
+/// A recognized word: its text, box and confidence.
+struct Word<'a> {
+    text: &'a str,
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    confidence: f32,
+}
+
+/// Groups the words of tesseract's TSV output into lines.
+#[cfg_attr(not(feature = "ocr"), allow(dead_code))]
+fn parse_tsv(tsv: &str) -> Vec<OcrLine> {
+    // Words by block, paragraph and line number
+    let mut lines: BTreeMap<(u32, u32, u32), Vec<Word>> = BTreeMap::new();
+    for row in tsv.lines().skip(1) {
+        let fields: Vec<&str> = row.split('\t').collect();
+        let [level, _page, block, par, line, _word, left, top, width, height, conf, text] = fields[..] else { continue };
+        let text = text.trim();
+        let Ok(confidence) = conf.parse::<f32>() else { continue };
+        if level != "5" || confidence < 0.0 || text.is_empty() {
+            continue;
+        }
+        let numbers: Option<Vec<u32>> = [block, par, line, left, top, width, height].iter().map(|v| v.parse().ok()).collect();
+        let Some(&[block, par, line, left, top, width, height]) = numbers.as_deref() else { continue };
+        lines.entry((block, par, line)).or_default().push(Word { text, left, top, right: left + width, bottom: top + height, confidence });
+    }
+
+    lines.into_values().filter_map(|words| {
+        let x = words.iter().map(|w| w.left).min()?;
+        let y = words.iter().map(|w| w.top).min()?;
+        let right = words.iter().map(|w| w.right).max()?;
+        let bottom = words.iter().map(|w| w.bottom).max()?;
+        let confidence = words.iter().map(|w| w.confidence).sum::<f32>() / words.len() as f32;
+        let text = words.iter().map(|w| w.text).collect::<Vec<_>>().join(" ");
+        (confidence >= MIN_CONFIDENCE).then_some(OcrLine { text, x, y, width: right - x, height: bottom - y, confidence })
+    }).collect()
+}
+
+/// Layer with a `<text>` element per line, in the colors of its glyphs and background.
+pub fn layer(img: &DynamicImage, lines: &[OcrLine]) -> SvgLayer {
+    let mut content = String::new();
+    for line in lines {
+        let Glyphs { background, color, .. } = text_regions::split_glyphs(&img.crop_imm(line.x, line.y, line.width, line.height));
+        content.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+            line.x, line.y, line.width, line.height, background
+        ));
+        content.push_str(&format!(
+            "<text x=\"{}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"{}\" textLength=\"{}\" \
+            lengthAdjust=\"spacingAndGlyphs\" fill=\"{}\">{}</text>\n",
+            line.x, line.y as f32 + line.height as f32 * BASELINE, line.height, line.width, color, svg::escape_attr(&line.text)
+        ));
+    }
+    SvgLayer::new("Text (OCR)", content).at_document_scale()
+}
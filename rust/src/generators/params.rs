@@ -0,0 +1,166 @@
+//! Parameter schema of the registered generators.
+//!
+//! Each [`Generator`] declares its tunable parameters as [`ParamSpec`]s. The GUI
+//! renders them as controls in an "Advanced" section under the generator's
+//! checkbox, and the CLI sets them with `--set generator.param=value`. Values
+//! are kept in [`Params`], keyed by `generator.param`; unset or invalid values
+//! fall back to the spec default.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::generators::color_pop;
+use crate::generators::registry::{self, Generator};
+
+/// Type, range and default of a parameter.
+#[derive(Clone, Copy, Debug)]
+pub enum ParamKind {
+    Int { min: i64, max: i64, default: i64 },
+    Float { min: f64, max: f64, step: f64, default: f64 },
+    /// One of a fixed set of options; `default` indexes `options`.
+    Choice { options: &'static [&'static str], default: usize },
+    /// `#RRGGBB` color.
+    Color { default: [u8; 3] },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    /// Locale key of the GUI label.
+    pub label_key: &'static str,
+    pub kind: ParamKind,
+}
+
+/// A parameter value: numbers for `Int`/`Float`, text for `Choice`/`Color`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamValue::Number(n) => write!(f, "{}", n),
+            ParamValue::Text(t) => f.write_str(t),
+        }
+    }
+}
+
+impl ParamSpec {
+    pub fn default_value(&self) -> ParamValue {
+        match self.kind {
+            ParamKind::Int { default, .. } => ParamValue::Number(default as f64),
+            ParamKind::Float { default, .. } => ParamValue::Number(default),
+            ParamKind::Choice { options, default } => ParamValue::Text(options[default].to_string()),
+            ParamKind::Color { default: [r, g, b] } => ParamValue::Text(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        }
+    }
+
+    /// Parses and validates a value given on the command line.
+    pub fn parse(&self, raw: &str) -> Result<ParamValue, String> {
+        let value = match self.kind {
+            ParamKind::Int { .. } | ParamKind::Float { .. } => ParamValue::Number(
+                raw.trim().parse().map_err(|_| format!("expected a number for {}: {}", self.name, raw))?,
+            ),
+            ParamKind::Choice { .. } | ParamKind::Color { .. } => ParamValue::Text(raw.trim().to_lowercase()),
+        };
+        self.validate(&value).map(|()| value)
+    }
+
+    /// Checks that `value` has the right type and lies in range.
+    pub fn validate(&self, value: &ParamValue) -> Result<(), String> {
+        match (self.kind, value) {
+            (ParamKind::Int { min, max, .. }, ParamValue::Number(n)) if n.fract() == 0.0 && (min as f64..=max as f64).contains(n) => Ok(()),
+            (ParamKind::Int { min, max, .. }, _) => Err(format!("{} must be an integer in {}..={}", self.name, min, max)),
+            (ParamKind::Float { min, max, .. }, ParamValue::Number(n)) if (min..=max).contains(n) => Ok(()),
+            (ParamKind::Float { min, max, .. }, _) => Err(format!("{} must be a number in {}..={}", self.name, min, max)),
+            (ParamKind::Choice { options, .. }, ParamValue::Text(t)) if options.contains(&t.as_str()) => Ok(()),
+            (ParamKind::Choice { options, .. }, _) => Err(format!("{} must be one of: {}", self.name, options.join(", "))),
+            (ParamKind::Color { .. }, ParamValue::Text(t)) => color_pop::parse_tint(t).map(|_| ()),
+            (ParamKind::Color { .. }, _) => Err(format!("{} must be a color as #RRGGBB", self.name)),
+        }
+    }
+}
+
+/// Parameter values of a job, keyed by `generator.param`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Params(BTreeMap<String, ParamValue>);
+
+impl Params {
+    pub fn set(&mut self, key: String, value: ParamValue) {
+        self.0.insert(key, value);
+    }
+
+    /// Values that were set, keyed by `generator.param`.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ParamValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Mutable value of `spec` for `generator`, reset to the default when unset or invalid.
+    pub fn entry(&mut self, generator: &dyn Generator, spec: &ParamSpec) -> &mut ParamValue {
+        let value = self.0.entry(key(generator, spec.name)).or_insert_with(|| spec.default_value());
+        if spec.validate(value).is_err() {
+            *value = spec.default_value();
+        }
+        value
+    }
+
+    /// Value of `name` for `generator`, or the spec default when unset or invalid.
+    pub fn get(&self, generator: &dyn Generator, name: &str) -> ParamValue {
+        let spec = spec(generator, name);
+        self.0.get(&key(generator, name))
+            .filter(|v| spec.validate(v).is_ok())
+            .cloned()
+            .unwrap_or_else(|| spec.default_value())
+    }
+
+    pub fn int(&self, generator: &dyn Generator, name: &str) -> i64 {
+        match self.get(generator, name) {
+            ParamValue::Number(n) => n as i64,
+            ParamValue::Text(_) => unreachable!("validated as a number"),
+        }
+    }
+
+    pub fn float(&self, generator: &dyn Generator, name: &str) -> f64 {
+        match self.get(generator, name) {
+            ParamValue::Number(n) => n,
+            ParamValue::Text(_) => unreachable!("validated as a number"),
+        }
+    }
+
+    pub fn text(&self, generator: &dyn Generator, name: &str) -> String {
+        match self.get(generator, name) {
+            ParamValue::Text(t) => t,
+            ParamValue::Number(_) => unreachable!("validated as text"),
+        }
+    }
+
+    pub fn color(&self, generator: &dyn Generator, name: &str) -> [u8; 3] {
+        color_pop::parse_tint(&self.text(generator, name)).expect("validated as a color")
+    }
+}
+
+fn key(generator: &dyn Generator, name: &str) -> String {
+    format!("{}.{}", generator.name(), name)
+}
+
+fn spec(generator: &dyn Generator, name: &str) -> ParamSpec {
+    *generator.params().iter()
+        .find(|s| s.name == name)
+        .unwrap_or_else(|| panic!("{} has no parameter {}", generator.name(), name))
+}
+
+/// Parses a `--set generator.param=value` assignment.
+pub fn parse_assignment(value: &str) -> Result<(String, ParamValue), String> {
+    let (key, raw) = value.split_once('=').ok_or_else(|| format!("expected generator.param=value: {}", value))?;
+    let (name, param) = key.split_once('.').ok_or_else(|| format!("expected generator.param=value: {}", value))?;
+    let generator = registry::get(registry::parse_kind(name)?).expect("parsed from the registry");
+    let spec = generator.params().iter().find(|s| s.name == param).ok_or_else(|| {
+        let names: Vec<&str> = generator.params().iter().map(|s| s.name).collect();
+        format!("unknown parameter '{}' for {}, expected one of: {}", param, generator.name(), names.join(", "))
+    })?;
+    Ok((format!("{}.{}", generator.name(), spec.name), spec.parse(raw)?))
+}
@@ -0,0 +1,169 @@
+//! JSON path model of the vector outputs for design-tool plugins.
+//!
+//! Figma or Sketch plugins can import `{stem}.paths.json` without an SVG
+//! parser. The file is built by parsing the written SVG with usvg, so
+//! transforms, circles and other shapes are already resolved to absolute path
+//! commands in document units. The schema (version [`PATH_MODEL_VERSION`]):
+//!
+//! ```json
+//! {
+//!   "format": "alphasvg-paths",
+//!   "version": 1,
+//!   "width": 800.0, "height": 600.0,
+//!   "palette": ["#1a2b3c", "#000000"],
+//!   "paths": [{
+//!     "layer": "layer1",
+//!     "fill": 0, "fill_opacity": 1.0, "fill_rule": "nonzero",
+//!     "stroke": null, "stroke_width": null,
+//!     "bbox": [10.0, 20.0, 100.0, 50.0],
+//!     "subpaths": [{ "closed": true, "commands": [["M", 10.0, 20.0], ["L", 110.0, 20.0], ["C", 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]] }]
+//!   }]
+//! }
+//! ```
+//!
+//! `fill` and `stroke` index `palette` (`null` when unpainted or not a flat
+//! color); `bbox` is `[x, y, width, height]`. Commands are `M`/`L` with one
+//! point, `Q` with two and `C` with three. Paths are listed in paint order.
+//! Any incompatible change to this layout bumps the version.
+
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use resvg::usvg::{self, Node, Paint, tiny_skia_path::PathSegment};
+use serde::Serialize;
+
+/// Version of the JSON layout, bumped on incompatible changes.
+pub const PATH_MODEL_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+pub struct PathModel {
+    pub format: &'static str,
+    pub version: u32,
+    pub width: f32,
+    pub height: f32,
+    /// Distinct fill and stroke colors as `#rrggbb`, in order of first use.
+    pub palette: Vec<String>,
+    pub paths: Vec<ModelPath>,
+}
+
+#[derive(Serialize)]
+pub struct ModelPath {
+    /// Id of the enclosing layer group, empty at the document root.
+    pub layer: String,
+    pub fill: Option<usize>,
+    pub fill_opacity: Option<f32>,
+    pub fill_rule: Option<&'static str>,
+    pub stroke: Option<usize>,
+    pub stroke_width: Option<f32>,
+    pub bbox: [f32; 4],
+    pub subpaths: Vec<Subpath>,
+}
+
+#[derive(Serialize)]
+pub struct Subpath {
+    pub closed: bool,
+    /// Each command is its letter followed by its absolute coordinates.
+    pub commands: Vec<Vec<serde_json::Value>>,
+}
+
+/// Builds the path model of an SVG document.
+pub fn from_svg(data: &[u8]) -> Result<PathModel> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+    let mut model = PathModel {
+        format: "alphasvg-paths",
+        version: PATH_MODEL_VERSION,
+        width: tree.size().width(),
+        height: tree.size().height(),
+        palette: Vec::new(),
+        paths: Vec::new(),
+    };
+    collect(tree.root(), "", &mut model);
+    Ok(model)
+}
+
+/// Writes the path model of `svg_path` to `{stem}.paths.json` next to it and returns its path.
+#[tracing::instrument(skip_all)]
+pub fn export_svg(svg_path: &Path) -> Result<PathBuf> {
+    let data = std::fs::read(svg_path)?;
+    let model = from_svg(&data).with_context(|| format!("Failed to parse {:?}", svg_path))?;
+    let json_path = svg_path.with_extension("paths.json");
+    std::fs::write(&json_path, serde_json::to_string(&model)?)?;
+    Ok(json_path)
+}
+
+fn collect(group: &usvg::Group, layer: &str, model: &mut PathModel) {
+    for node in group.children() {
+        match node {
+            Node::Group(child) => {
+                let layer = if child.id().is_empty() { layer } else { child.id() };
+                collect(child, layer, model);
+            }
+            Node::Path(path) if path.is_visible() => {
+                let entry = model_path(path, layer, &mut model.palette);
+                if !entry.subpaths.is_empty() {
+                    model.paths.push(entry);
+                }
+            }
+            Node::Text(text) => collect(text.flattened(), layer, model),
+            _ => {}
+        }
+    }
+}
+
+fn model_path(path: &usvg::Path, layer: &str, palette: &mut Vec<String>) -> ModelPath {
+    let fill = path.fill();
+    let stroke = path.stroke();
+    let bbox = path.abs_bounding_box();
+    let data = path.data().clone().transform(path.abs_transform());
+    ModelPath {
+        layer: layer.to_string(),
+        fill: fill.and_then(|f| palette_index(f.paint(), palette)),
+        fill_opacity: fill.map(|f| f.opacity().get()),
+        fill_rule: fill.map(|f| match f.rule() {
+            usvg::FillRule::NonZero => "nonzero",
+            usvg::FillRule::EvenOdd => "evenodd",
+        }),
+        stroke: stroke.and_then(|s| palette_index(s.paint(), palette)),
+        stroke_width: stroke.map(|s| s.width().get()),
+        bbox: [bbox.x(), bbox.y(), bbox.width(), bbox.height()],
+        subpaths: data.map(|d| subpaths(&d)).unwrap_or_default(),
+    }
+}
+
+/// Splits path data into subpaths at every move-to.
+fn subpaths(data: &usvg::tiny_skia_path::Path) -> Vec<Subpath> {
+    let num = |v: f32| serde_json::Value::from((v as f64 * 100.0).round() / 100.0);
+    let mut out: Vec<Subpath> = Vec::new();
+    for segment in data.segments() {
+        let (letter, points) = match segment {
+            PathSegment::MoveTo(p) => {
+                out.push(Subpath { closed: false, commands: Vec::new() });
+                ("M", vec![p])
+            }
+            PathSegment::LineTo(p) => ("L", vec![p]),
+            PathSegment::QuadTo(p1, p) => ("Q", vec![p1, p]),
+            PathSegment::CubicTo(p1, p2, p) => ("C", vec![p1, p2, p]),
+            PathSegment::Close => {
+                if let Some(last) = out.last_mut() {
+                    last.closed = true;
+                }
+                continue;
+            }
+        };
+        let Some(current) = out.last_mut() else { continue };
+        let mut command = vec![serde_json::Value::from(letter)];
+        command.extend(points.iter().flat_map(|p| [num(p.x), num(p.y)]));
+        current.commands.push(command);
+    }
+    out.retain(|s| s.commands.len() > 1);
+    out
+}
+
+/// Index of a flat color in `palette`, added on first use.
+fn palette_index(paint: &Paint, palette: &mut Vec<String>) -> Option<usize> {
+    let Paint::Color(c) = paint else { return None };
+    let hex = format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue);
+    Some(palette.iter().position(|p| *p == hex).unwrap_or_else(|| {
+        palette.push(hex);
+        palette.len() - 1
+    }))
+}
@@ -0,0 +1,214 @@
+//! Polyline tracing for plotter and cutter outputs.
+//!
+//! Potrace's GeoJSON backend flattens the traced curves into polygons, which is
+//! what pen plotters and vinyl cutters consume. Coordinates are in working
+//! pixels with the origin at the bottom-left, as in HPGL.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use crate::temp;
+
+/// A sequence of points; closed outlines repeat their first point at the end.
+pub type Polyline = Vec<(f32, f32)>;
+
+/// Traces the black regions of a 1-bit mask BMP into outline polylines.
+pub fn trace_polylines(bmp_path: &Path, turdsize: u32) -> Result<Vec<Polyline>> {
+    let json_path = temp::path_beside(bmp_path, "json")?;
+
+    let status = tracing::info_span!("potrace").in_scope(|| Command::new("potrace")
+        .args([
+            bmp_path.to_str().unwrap(),
+            "-b", "geojson",
+            "-o", json_path.to_str().unwrap(),
+            "--turdsize", &turdsize.to_string(),
+        ])
+        .status())?;
+
+    let content = fs::read_to_string(&json_path);
+    if !status.success() {
+        return Err(anyhow!("Potrace failed while tracing polylines"));
+    }
+
+    let json: Value = serde_json::from_str(&content?)?;
+    let mut polylines = Vec::new();
+    if let Some(features) = json["features"].as_array() {
+        for feature in features {
+            collect_rings(&feature["geometry"]["coordinates"], &mut polylines);
+        }
+    }
+    Ok(polylines)
+}
+
+/// Collects every ring (array of `[x, y]` pairs) nested in a GeoJSON coordinates value.
+fn collect_rings(value: &Value, out: &mut Vec<Polyline>) {
+    let Some(items) = value.as_array() else { return };
+    let is_ring = items.first()
+        .and_then(Value::as_array)
+        .is_some_and(|point| point.first().is_some_and(Value::is_number));
+
+    if is_ring {
+        let ring: Polyline = items.iter()
+            .filter_map(|p| Some((p.get(0)?.as_f64()? as f32, p.get(1)?.as_f64()? as f32)))
+            .collect();
+        if ring.len() > 1 {
+            out.push(ring);
+        }
+    } else {
+        for item in items {
+            collect_rings(item, out);
+        }
+    }
+}
+
+/// Assumed pen-up travel speed used for plot-time estimates, in path units per second.
+const PEN_UP_SPEED: f32 = 100.0;
+/// Maximum distance from the chord for a point to count as collinear.
+const COLLINEAR_TOLERANCE: f32 = 0.1;
+
+/// Effect of [`optimize`] on a set of paths.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlotStats {
+    pub travel_before: f32,
+    pub travel_after: f32,
+    pub points_before: usize,
+    pub points_after: usize,
+}
+
+impl PlotStats {
+    /// Estimated pen-up time saved, in seconds, for paths in units of `mm_per_unit`.
+    pub fn seconds_saved(&self, mm_per_unit: f32) -> f32 {
+        (self.travel_before - self.travel_after).max(0.0) * mm_per_unit / PEN_UP_SPEED
+    }
+}
+
+/// Merges collinear segments and reorders paths (greedy nearest neighbour
+/// followed by 2-opt) to minimise pen-up travel from the origin.
+pub fn optimize(paths: Vec<Polyline>) -> (Vec<Polyline>, PlotStats) {
+    let travel_before = travel_distance(&paths);
+    let points_before = paths.iter().map(Vec::len).sum();
+
+    let merged: Vec<Polyline> = paths.iter().map(merge_collinear).collect();
+    let ordered = two_opt(greedy_order(merged));
+
+    let stats = PlotStats {
+        travel_before,
+        travel_after: travel_distance(&ordered),
+        points_before,
+        points_after: ordered.iter().map(Vec::len).sum(),
+    };
+    (ordered, stats)
+}
+
+/// Total pen-up distance starting at the origin.
+pub fn travel_distance(paths: &[Polyline]) -> f32 {
+    let mut pos = (0.0, 0.0);
+    let mut total = 0.0;
+    for path in paths {
+        if let (Some(&first), Some(&last)) = (path.first(), path.last()) {
+            total += dist(pos, first);
+            pos = last;
+        }
+    }
+    total
+}
+
+/// Drops interior points lying on the straight line between their neighbours.
+pub fn merge_collinear(path: &Polyline) -> Polyline {
+    if path.len() < 3 {
+        return path.clone();
+    }
+    let mut out = vec![path[0]];
+    for i in 1..path.len() - 1 {
+        let (a, b, c) = (*out.last().unwrap(), path[i], path[i + 1]);
+        let chord = dist(a, c);
+        let cross = ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).abs();
+        let within_chord = dist(a, b) <= chord && dist(b, c) <= chord;
+        if chord == 0.0 || cross / chord > COLLINEAR_TOLERANCE || !within_chord {
+            out.push(b);
+        }
+    }
+    out.push(path[path.len() - 1]);
+    out
+}
+
+fn dist(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn is_closed(path: &Polyline) -> bool {
+    path.len() > 2 && path.first() == path.last()
+}
+
+/// Orients `path` so it starts as close to `pos` as possible: closed paths are
+/// rotated to their nearest vertex, open paths may be reversed.
+fn orient_from(mut path: Polyline, pos: (f32, f32)) -> Polyline {
+    if is_closed(&path) {
+        path.pop();
+        let nearest = (0..path.len())
+            .min_by(|&a, &b| dist(pos, path[a]).total_cmp(&dist(pos, path[b])))
+            .unwrap_or(0);
+        path.rotate_left(nearest);
+        path.push(path[0]);
+    } else if let (Some(&first), Some(&last)) = (path.first(), path.last())
+        && dist(pos, last) < dist(pos, first)
+    {
+        path.reverse();
+    }
+    path
+}
+
+fn greedy_order(mut remaining: Vec<Polyline>) -> Vec<Polyline> {
+    remaining.retain(|p| !p.is_empty());
+    let mut ordered = Vec::with_capacity(remaining.len());
+    let mut pos = (0.0, 0.0);
+    while !remaining.is_empty() {
+        let nearest = (0..remaining.len())
+            .min_by(|&a, &b| entry_distance(&remaining[a], pos).total_cmp(&entry_distance(&remaining[b], pos)))
+            .unwrap();
+        let path = orient_from(remaining.swap_remove(nearest), pos);
+        pos = *path.last().unwrap();
+        ordered.push(path);
+    }
+    ordered
+}
+
+/// Distance from `pos` to the closest point a path can be started from.
+fn entry_distance(path: &Polyline, pos: (f32, f32)) -> f32 {
+    if is_closed(path) {
+        path.iter().map(|&p| dist(pos, p)).fold(f32::MAX, f32::min)
+    } else {
+        dist(pos, path[0]).min(dist(pos, path[path.len() - 1]))
+    }
+}
+
+/// Improves the order by reversing runs of paths while that shortens travel.
+fn two_opt(mut paths: Vec<Polyline>) -> Vec<Polyline> {
+    const MAX_PASSES: usize = 8;
+    let n = paths.len();
+    let start = |p: &Polyline| p[0];
+    let end = |p: &Polyline| p[p.len() - 1];
+
+    for _ in 0..MAX_PASSES {
+        let mut improved = false;
+        for i in 0..n {
+            let before = if i == 0 { (0.0, 0.0) } else { end(&paths[i - 1]) };
+            for j in i + 1..n {
+                let next = paths.get(j + 1).map(start);
+                let old = dist(before, start(&paths[i])) + next.map_or(0.0, |s| dist(end(&paths[j]), s));
+                let new = dist(before, end(&paths[j])) + next.map_or(0.0, |s| dist(start(&paths[i]), s));
+                if new + 1e-3 < old {
+                    paths[i..=j].reverse();
+                    paths[i..=j].iter_mut().for_each(|p| p.reverse());
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    paths
+}
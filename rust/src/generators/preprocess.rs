@@ -0,0 +1,170 @@
+//! Optional input corrections applied before inference and vectorization.
+//!
+//! Underexposed or color-cast photos produce noticeably worse masks, so these
+//! run on the source image before anything else. Alpha is left untouched,
+//! except for the transparent corners added by straightening, see [`Transform`].
+
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use anyhow::Result;
+use crate::error::AlphasvgError;
+use crate::generators::{LogOutput, denoise};
+use crate::generators::transform::Transform;
+use crate::lang::LanguageManager;
+
+/// Share of the darkest and brightest pixels ignored by auto-levels.
+const LEVELS_CLIP: f32 = 0.005;
+/// Gamma used to apply exposure in (approximately) linear light.
+const GAMMA: f32 = 2.2;
+/// Exposure compensation range offered by the GUI and accepted by the CLI, in EV.
+pub const EXPOSURE_RANGE: std::ops::RangeInclusive<f32> = -3.0..=3.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreprocessOptions {
+    /// Stretch the luminance histogram to the full range.
+    pub auto_levels: bool,
+    /// Gray-world white balance.
+    pub white_balance: bool,
+    /// Exposure compensation in EV stops (0 = unchanged).
+    pub exposure_ev: f32,
+    /// Soften JPEG 8×8 block seams.
+    pub deblock: bool,
+    /// Bilateral denoise strength from 0 (off) to 1.
+    pub denoise: f32,
+    /// Rotation, flips and straightening.
+    pub transform: Transform,
+}
+
+impl PreprocessOptions {
+    pub fn is_identity(&self) -> bool {
+        !self.auto_levels && !self.white_balance && self.exposure_ev == 0.0 && !self.deblock && self.denoise == 0.0
+            && self.transform.is_identity()
+    }
+}
+
+/// Parses an exposure compensation in EV within [`EXPOSURE_RANGE`].
+pub fn parse_exposure(value: &str) -> Result<f32, String> {
+    let ev: f32 = value.parse().map_err(|_| format!("invalid exposure: {}", value))?;
+    if EXPOSURE_RANGE.contains(&ev) {
+        Ok(ev)
+    } else {
+        Err(format!("exposure must be between {} and {} EV", EXPOSURE_RANGE.start(), EXPOSURE_RANGE.end()))
+    }
+}
+
+/// Decodes the image at `path` and applies the enabled corrections.
+pub fn open(path: &Path, opts: &PreprocessOptions, lang: &LanguageManager, logger: &LogOutput) -> Result<DynamicImage> {
+    let img = image::open(path).map_err(|e| AlphasvgError::decode(path, e))?;
+    Ok(preprocess(img, opts, lang, logger))
+}
+
+/// Applies the enabled corrections in order: deblock, geometric transform,
+/// denoise, white balance, exposure, levels. Blocks are smoothed on the original
+/// JPEG grid and artifacts are removed before they can be amplified.
+#[tracing::instrument(skip_all)]
+pub fn preprocess(img: DynamicImage, opts: &PreprocessOptions, lang: &LanguageManager, logger: &LogOutput) -> DynamicImage {
+    if opts.is_identity() {
+        return img;
+    }
+
+    let mut rgba = img.into_rgba8();
+    if opts.deblock {
+        denoise::deblock(&mut rgba);
+    }
+    if !opts.transform.is_identity() {
+        rgba = opts.transform.apply(DynamicImage::ImageRgba8(rgba)).into_rgba8();
+        logger.verbose(lang.t_args("log_transform", &[opts.transform.describe(lang)]));
+    }
+    if opts.denoise > 0.0 {
+        rgba = denoise::bilateral(&rgba, opts.denoise);
+    }
+    if opts.white_balance {
+        white_balance(&mut rgba);
+    }
+    if opts.exposure_ev != 0.0 {
+        exposure(&mut rgba, opts.exposure_ev);
+    }
+    if opts.auto_levels {
+        auto_levels(&mut rgba);
+    }
+    logger.verbose(format!(
+        "🎚️ Pre-processing: deblock {}, denoise {:.2}, levels {}, white balance {}, exposure {:+.1} EV",
+        opts.deblock, opts.denoise, opts.auto_levels, opts.white_balance, opts.exposure_ev
+    ));
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Scales each channel so the average of the visible pixels is neutral gray.
+fn white_balance(img: &mut RgbaImage) {
+    let mut sums = [0f64; 3];
+    let mut count = 0usize;
+    for p in img.pixels().filter(|p| p[3] > 0) {
+        for c in 0..3 {
+            sums[c] += p[c] as f64;
+        }
+        count += 1;
+    }
+    if count == 0 || sums.contains(&0.0) {
+        return;
+    }
+
+    let gray = (sums[0] + sums[1] + sums[2]) / 3.0;
+    let gains = sums.map(|s| (gray / s) as f32);
+    for p in img.pixels_mut() {
+        for c in 0..3 {
+            p[c] = (p[c] as f32 * gains[c]).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+fn exposure(img: &mut RgbaImage, ev: f32) {
+    let gain = 2f32.powf(ev);
+    let lut: Vec<u8> = (0..=255u16)
+        .map(|v| {
+            let linear = (v as f32 / 255.0).powf(GAMMA) * gain;
+            (linear.min(1.0).powf(1.0 / GAMMA) * 255.0).round() as u8
+        })
+        .collect();
+    for p in img.pixels_mut() {
+        for c in 0..3 {
+            p[c] = lut[p[c] as usize];
+        }
+    }
+}
+
+/// Stretches luminance so the clipped darkest/brightest pixels map to 0 and 255.
+fn auto_levels(img: &mut RgbaImage) {
+    let mut histogram = [0usize; 256];
+    for p in img.pixels().filter(|p| p[3] > 0) {
+        histogram[luma(p[0], p[1], p[2]) as usize] += 1;
+    }
+    let total: usize = histogram.iter().sum();
+    let clip = (total as f32 * LEVELS_CLIP) as usize;
+
+    let (Some(low), Some(high)) = (first_past(&histogram, 0..256, clip), first_past(&histogram, (0..256).rev(), clip)) else { return };
+    if high <= low {
+        return;
+    }
+
+    let scale = 255.0 / (high - low) as f32;
+    for p in img.pixels_mut() {
+        for c in 0..3 {
+            p[c] = ((p[c] as f32 - low as f32) * scale).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// First bin, in `bins` order, where the cumulative count exceeds `clip`.
+fn first_past(histogram: &[usize; 256], mut bins: impl Iterator<Item = usize>, clip: usize) -> Option<usize> {
+    let mut seen = 0;
+    bins.find(|&i| {
+        seen += histogram[i];
+        seen > clip
+    })
+}
+
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    (r as f32 * 0.299 + g as f32 * 0.587 + b as f32 * 0.114).round() as u8
+}
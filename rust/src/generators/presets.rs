@@ -0,0 +1,62 @@
+//! Output presets: named sets of generators with the parameter values that
+//! suit a destination.
+//!
+//! The GUI shows them as profile chips that can be combined; the job then runs
+//! the union of their generators. Parameters are written as `--set`
+//! assignments, and when two selected presets set the same parameter the one
+//! selected last wins.
+
+use crate::generators::params::{Params, parse_assignment};
+use crate::generators::registry;
+use crate::naming::OutputKind;
+
+pub struct Preset {
+    /// Name stored in job files.
+    pub name: &'static str,
+    /// Locale key of the GUI chip.
+    pub label_key: &'static str,
+    pub generators: &'static [OutputKind],
+    /// `generator.param=value` assignments.
+    pub params: &'static [&'static str],
+}
+
+pub static PRESETS: &[Preset] = &[
+    Preset {
+        name: "web",
+        label_key: "profile_web",
+        generators: &[OutputKind::Alpha, OutputKind::Thumbnail, OutputKind::Logo],
+        params: &["thumb.width=256", "logo.colors=12"],
+    },
+    Preset {
+        name: "print",
+        label_key: "profile_print",
+        generators: &[OutputKind::Alpha, OutputKind::Gray, OutputKind::Halftone, OutputKind::Illustration],
+        params: &["gray.tones=12", "halftone.angle=45", "illustration.colors=64"],
+    },
+    Preset {
+        name: "plotter",
+        label_key: "profile_plotter",
+        generators: &[OutputKind::Lineart, OutputKind::Logo],
+        params: &["lineart.threshold=128", "logo.colors=4"],
+    },
+];
+
+/// Returns the preset called `name`.
+pub fn get(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+/// Generators, in registry order, and parameters of the presets in `names`;
+/// unknown names are ignored.
+pub fn combine(names: &[String]) -> (Vec<OutputKind>, Params) {
+    let selected: Vec<&Preset> = names.iter().filter_map(|name| get(name)).collect();
+    let generators = registry::REGISTRY.iter()
+        .map(|g| g.kind())
+        .filter(|kind| selected.iter().any(|p| p.generators.contains(kind)))
+        .collect();
+    let mut params = Params::default();
+    for (key, value) in selected.iter().flat_map(|p| p.params).map(|a| parse_assignment(a).expect("valid preset assignment")) {
+        params.set(key, value);
+    }
+    (generators, params)
+}
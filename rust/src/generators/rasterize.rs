@@ -0,0 +1,83 @@
+//! Raster renders of generated SVGs.
+//!
+//! Each SVG is rendered with resvg to a PNG next to it, so the vector outputs
+//! can be checked or used as rasters right away.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{Result, Context, anyhow};
+use resvg::{tiny_skia, usvg};
+
+/// CSS reference resolution: SVG user units are 1/96 in.
+const CSS_DPI: f32 = 96.0;
+
+/// Target size of a render: a resolution (`300dpi`) or a plain factor (`2x`).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RasterScale {
+    Dpi(f32),
+    Factor(f32),
+}
+
+impl RasterScale {
+    fn factor(self) -> f32 {
+        match self {
+            RasterScale::Dpi(dpi) => dpi / CSS_DPI,
+            RasterScale::Factor(f) => f,
+        }
+    }
+}
+
+impl FromStr for RasterScale {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_ascii_lowercase();
+        let (number, scale): (&str, fn(f32) -> RasterScale) = if let Some(n) = value.strip_suffix("dpi") {
+            (n, RasterScale::Dpi)
+        } else if let Some(n) = value.strip_suffix('x') {
+            (n, RasterScale::Factor)
+        } else {
+            (value.as_str(), RasterScale::Dpi)
+        };
+        match number.trim().parse::<f32>() {
+            Ok(n) if n > 0.0 && n.is_finite() => Ok(scale(n)),
+            _ => Err(format!("expected a resolution like 300dpi or a scale like 2x: {}", value)),
+        }
+    }
+}
+
+impl fmt::Display for RasterScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RasterScale::Dpi(dpi) => write!(f, "{}dpi", dpi),
+            RasterScale::Factor(x) => write!(f, "{}x", x),
+        }
+    }
+}
+
+/// Renders `svg_path` to a PNG with the same stem and returns its path.
+#[tracing::instrument(skip_all)]
+pub fn rasterize_svg(svg_path: &Path, scale: RasterScale) -> Result<PathBuf> {
+    let pixmap = render_svg(svg_path, |_| scale.factor())?;
+    let png_path = svg_path.with_extension("png");
+    pixmap.save_png(&png_path)?;
+    Ok(png_path)
+}
+
+/// Renders `svg_path` at the factor `scale_for` returns for its document size.
+pub fn render_svg(svg_path: &Path, scale_for: impl FnOnce(usvg::Size) -> f32) -> Result<tiny_skia::Pixmap> {
+    let data = std::fs::read(svg_path)?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default())
+        .with_context(|| format!("Failed to parse {:?}", svg_path))?;
+
+    let size = tree.size();
+    let factor = scale_for(size);
+    let width = (size.width() * factor).ceil() as u32;
+    let height = (size.height() * factor).ceil() as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("Invalid render size {}x{} for {:?}", width, height, svg_path))?;
+
+    resvg::render(&tree, tiny_skia::Transform::from_scale(factor, factor), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
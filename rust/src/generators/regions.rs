@@ -0,0 +1,206 @@
+//! Small region filter of the color and grayscale SVG outputs (`--min-region-area`).
+//!
+//! Quantization leaves specks of a few pixels wherever colors meet, and each
+//! one becomes its own traced shape. Before tracing, the connected regions of
+//! the label map are found, and those smaller than the minimum area are merged,
+//! smallest first, into their largest neighbor. Empty (transparent) areas count
+//! as regions too, so specks floating in the background go and pinholes in the
+//! subject are filled.
+
+use serde::{Deserialize, Serialize};
+
+/// Smallest area a region is kept at.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MinArea {
+    /// Pixels of the input image.
+    Pixels(u32),
+    /// Percentage of the image area.
+    Percent(f32),
+}
+
+impl MinArea {
+    /// Area in pixels of a `width` × `height` image traced at `scale` input pixels per pixel.
+    pub fn pixels(self, width: u32, height: u32, scale: f32) -> usize {
+        match self {
+            MinArea::Pixels(pixels) => (pixels as f32 / (scale * scale)).round() as usize,
+            MinArea::Percent(percent) => (width as f32 * height as f32 * percent / 100.0).round() as usize,
+        }
+    }
+}
+
+/// Parses a minimum area: pixels (`50`) or a percentage of the image (`0.1%`).
+pub fn parse_min_area(value: &str) -> Result<MinArea, String> {
+    match value.strip_suffix('%') {
+        Some(percent) => match percent.trim().parse::<f32>() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(MinArea::Percent(percent)),
+            _ => Err(format!("percentage must be between 0 and 100: {}", value)),
+        },
+        None => value.trim().parse().map(MinArea::Pixels).map_err(|_| format!("expected pixels or a percentage: {}", value)),
+    }
+}
+
+/// Merges the 4-connected regions of `labels` (row-major, `None` where empty)
+/// smaller than `min_area` pixels into their largest neighbor. Returns how many
+/// regions were merged.
+pub fn merge_small(labels: &mut [Option<usize>], width: u32, height: u32, min_area: usize) -> usize {
+    let (width, height) = (width as usize, height as usize);
+    if min_area <= 1 || labels.len() != width * height {
+        return 0;
+    }
+
+    // Connected regions by flood fill
+    const UNSET: usize = usize::MAX;
+    let mut region = vec![UNSET; labels.len()];
+    let mut region_labels: Vec<Option<usize>> = Vec::new();
+    let mut areas: Vec<usize> = Vec::new();
+    let mut stack = Vec::new();
+    for seed in 0..labels.len() {
+        if region[seed] != UNSET {
+            continue;
+        }
+        let id = areas.len();
+        let label = labels[seed];
+        region[seed] = id;
+        stack.push(seed);
+        let mut area = 0;
+        while let Some(i) = stack.pop() {
+            area += 1;
+            let (x, y) = (i % width, i / width);
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbors.into_iter().flatten() {
+                if region[n] == UNSET && labels[n] == label {
+                    region[n] = id;
+                    stack.push(n);
+                }
+            }
+        }
+        region_labels.push(label);
+        areas.push(area);
+    }
+    if !areas.iter().any(|&a| a < min_area) {
+        return 0;
+    }
+
+    // Regions that touch each other
+    let mut adjacent: Vec<Vec<usize>> = vec![Vec::new(); areas.len()];
+    for i in 0..labels.len() {
+        let (x, y) = (i % width, i / width);
+        for n in [(x + 1 < width).then(|| i + 1), (y + 1 < height).then(|| i + width)].into_iter().flatten() {
+            let (a, b) = (region[i], region[n]);
+            if a != b {
+                adjacent[a].push(b);
+                adjacent[b].push(a);
+            }
+        }
+    }
+    for list in &mut adjacent {
+        list.sort_unstable();
+        list.dedup();
+    }
+
+    // Smallest first, each into the largest region it touches; merged regions point to where they went
+    let mut parent: Vec<usize> = (0..areas.len()).collect();
+    let find = |parent: &mut Vec<usize>, mut r: usize| {
+        while parent[r] != r {
+            parent[r] = parent[parent[r]];
+            r = parent[r];
+        }
+        r
+    };
+    let mut order: Vec<usize> = (0..areas.len()).filter(|&r| areas[r] < min_area).collect();
+    order.sort_by_key(|&r| areas[r]);
+    let mut merged = 0;
+    for r in order {
+        if find(&mut parent, r) != r || areas[r] >= min_area {
+            continue;
+        }
+        let neighbors = std::mem::take(&mut adjacent[r]);
+        let target = neighbors.iter()
+            .map(|&n| find(&mut parent, n))
+            .filter(|&n| n != r)
+            .max_by_key(|&n| areas[n]);
+        let Some(target) = target else {
+            adjacent[r] = neighbors;
+            continue;
+        };
+        parent[r] = target;
+        areas[target] += areas[r];
+        adjacent[target].extend(neighbors);
+        merged += 1;
+    }
+
+    for (label, r) in labels.iter_mut().zip(&region) {
+        *label = region_labels[find(&mut parent, *r)];
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Label map of `rows`, one character per pixel: a letter is a label, `.` is empty.
+    fn grid(rows: &[&str]) -> (Vec<Option<usize>>, u32, u32) {
+        let labels = rows.iter().flat_map(|row| row.chars()).map(|c| (c != '.').then(|| c as usize - 'a' as usize)).collect();
+        (labels, rows[0].len() as u32, rows.len() as u32)
+    }
+
+    #[test]
+    fn merges_smallest_first_into_the_largest_neighbor() {
+        // b goes first, into c, the larger of its neighbors; a then only touches c
+        let (mut labels, width, height) = grid(&["aabccc"]);
+        assert_eq!(merge_small(&mut labels, width, height, 3), 2);
+        assert_eq!(labels, grid(&["cccccc"]).0);
+
+        // Regions at the minimum area stay
+        let (mut labels, width, height) = grid(&["aaabbb"]);
+        assert_eq!(merge_small(&mut labels, width, height, 3), 0);
+        assert_eq!(labels, grid(&["aaabbb"]).0);
+    }
+
+    #[test]
+    fn empty_areas_are_regions_too() {
+        let (mut labels, width, height) = grid(&[
+            ".....",
+            ".b...",
+            "...aa",
+            "...a.",
+            "...aa",
+        ]);
+        assert_eq!(merge_small(&mut labels, width, height, 2), 2);
+        assert_eq!(labels, grid(&[
+            ".....",
+            ".....",
+            "...aa",
+            "...aa",
+            "...aa",
+        ]).0);
+    }
+
+    #[test]
+    fn keeps_a_small_region_without_neighbors() {
+        let (mut labels, width, height) = grid(&["aa", "aa"]);
+        assert_eq!(merge_small(&mut labels, width, height, 10), 0);
+        assert_eq!(labels, grid(&["aa", "aa"]).0);
+    }
+
+    #[test]
+    fn scales_the_minimum_area() {
+        assert_eq!(MinArea::Pixels(48).pixels(100, 100, 1.0), 48);
+        // Downscaled tracing has fewer pixels per input pixel, upscaled more
+        assert_eq!(MinArea::Pixels(48).pixels(100, 100, 2.0), 12);
+        assert_eq!(MinArea::Pixels(48).pixels(100, 100, 0.5), 192);
+        assert_eq!(MinArea::Percent(1.0).pixels(200, 100, 2.0), 200);
+
+        assert_eq!(parse_min_area("50"), Ok(MinArea::Pixels(50)));
+        assert_eq!(parse_min_area("0.5%"), Ok(MinArea::Percent(0.5)));
+        assert!(parse_min_area("150%").is_err());
+        assert!(parse_min_area("-3").is_err());
+    }
+}
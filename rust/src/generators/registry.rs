@@ -0,0 +1,353 @@
+//! Registry of the selectable output generators.
+//!
+//! The GUI options column, the job configuration, the CLI `--only` flag and
+//! both pipelines iterate over [`REGISTRY`], so adding a generator means
+//! implementing [`Generator`] and listing it here.
+
+use std::path::Path;
+use anyhow::Result;
+use image::DynamicImage;
+
+use crate::config;
+use crate::error::AlphasvgError;
+use crate::generators::{self, LogOutput, SvgOptions, color, color_pop, colorblind, encode};
+use crate::generators::color::{LogoStyle, Quantize};
+use crate::generators::colorblind::SafePalette;
+use crate::generators::transform::Transform;
+use crate::generators::params::{ParamKind, ParamSpec, Params};
+use crate::lang::LanguageManager;
+use crate::naming::OutputKind;
+
+/// Group a generator's checkbox is shown under in the GUI.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Section {
+    Png,
+    /// Black and white SVG.
+    SvgMono,
+    /// Color SVG.
+    SvgColor,
+}
+
+/// Shared inputs of one pipeline run.
+pub struct GenerateContext<'a> {
+    /// Original input photo.
+    pub input: &'a Path,
+    /// Geometric transform the cut-out was made with.
+    pub transform: Transform,
+    /// AI cut-out of the input.
+    pub cutout: &'a DynamicImage,
+    pub svg: &'a SvgOptions,
+    pub params: &'a Params,
+    pub lang: &'a LanguageManager,
+    pub logger: &'a LogOutput,
+}
+
+impl GenerateContext<'_> {
+    /// The input photo, rotated and flipped like the cut-out.
+    pub fn original(&self) -> Result<DynamicImage> {
+        let img = image::open(self.input).map_err(|e| AlphasvgError::decode(self.input, e))?;
+        Ok(self.transform.apply(img))
+    }
+}
+
+pub trait Generator: Sync {
+    fn kind(&self) -> OutputKind;
+
+    /// Name used by `--only` and in job files.
+    fn name(&self) -> &'static str {
+        self.kind().suffix()
+    }
+
+    /// Locale key of the GUI checkbox label.
+    fn label_key(&self) -> &'static str;
+
+    /// Locale key of the GUI status message shown while it runs.
+    fn status_key(&self) -> &'static str;
+
+    fn section(&self) -> Section;
+
+    /// Whether the generator is selected in a new job.
+    fn default_enabled(&self) -> bool {
+        true
+    }
+
+    /// Vector generators read the (optionally upscaled) SVG source instead of the cut-out.
+    fn is_vector(&self) -> bool {
+        self.kind().extension() == "svg"
+    }
+
+    /// Whether the AI cut-out must be computed for this generator.
+    fn needs_cutout(&self) -> bool {
+        true
+    }
+
+    /// Tunable parameters, see [`crate::generators::params`].
+    fn params(&self) -> &'static [ParamSpec] {
+        &[]
+    }
+
+    /// Extension of the written file, which may depend on the parameters.
+    fn extension(&self, _params: &Params) -> &'static str {
+        self.kind().extension()
+    }
+
+    /// Writes the output for `source` to `path`.
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()>;
+}
+
+/// JPEG quality of the raster outputs that offer a format choice.
+const QUALITY: ParamKind = ParamKind::Int { min: 1, max: 100, default: encode::DEFAULT_QUALITY as i64 };
+
+/// Rectangle behind a vector output: `none`, `white` or `color` (`background_color`).
+const BACKGROUND: ParamSpec = ParamSpec { name: "background", label_key: "param_background", kind: ParamKind::Choice { options: &["none", "white", "color"], default: 0 } };
+const BACKGROUND_COLOR: ParamSpec = ParamSpec { name: "background_color", label_key: "param_background_color", kind: ParamKind::Color { default: [255, 255, 255] } };
+/// Strength, from 0 (off) to 1, of the geometric cleanup of the traced paths.
+const CLEANUP: ParamSpec = ParamSpec { name: "cleanup", label_key: "param_cleanup", kind: ParamKind::Float { min: 0.0, max: 1.0, step: 0.05, default: 0.0 } };
+
+/// SVG options of a vector generator, with the background from its parameters.
+fn svg_options(generator: &dyn Generator, ctx: &GenerateContext) -> SvgOptions {
+    let background = match ctx.params.text(generator, "background").as_str() {
+        "white" => Some([255, 255, 255]),
+        "color" => Some(ctx.params.color(generator, "background_color")),
+        _ => None,
+    };
+    SvgOptions { background, ..ctx.svg.clone() }
+}
+
+/// Output extension picked with the `format` parameter.
+fn raster_extension(generator: &dyn Generator, params: &Params) -> &'static str {
+    let format = params.text(generator, "format");
+    encode::FORMATS.iter().copied().find(|&f| f == format).unwrap_or(encode::FORMATS[0])
+}
+
+struct Alpha;
+struct Thumbnail;
+struct ColorPop;
+struct Gray;
+struct Halftone;
+struct Lineart;
+struct Logo;
+struct Illustration;
+
+impl Generator for Alpha {
+    fn kind(&self) -> OutputKind { OutputKind::Alpha }
+    fn label_key(&self) -> &'static str { "chk_transparent" }
+    fn status_key(&self) -> &'static str { "status_gen_alpha" }
+    fn section(&self) -> Section { Section::Png }
+
+    /// The alpha stage normally writes this file already.
+    fn generate(&self, source: &DynamicImage, path: &Path, _ctx: &GenerateContext) -> Result<()> {
+        if !path.exists() {
+            source.save(path).map_err(|e| AlphasvgError::encode(path, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Generator for Thumbnail {
+    fn kind(&self) -> OutputKind { OutputKind::Thumbnail }
+    fn label_key(&self) -> &'static str { "chk_thumbnail" }
+    fn status_key(&self) -> &'static str { "status_gen_thumb" }
+    fn section(&self) -> Section { Section::Png }
+    fn needs_cutout(&self) -> bool { false }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            ParamSpec { name: "width", label_key: "param_width", kind: ParamKind::Int { min: 16, max: 1024, default: config::THUMB_WIDTH as i64 } },
+            ParamSpec { name: "format", label_key: "param_format", kind: ParamKind::Choice { options: encode::FORMATS, default: 0 } },
+            ParamSpec { name: "quality", label_key: "param_quality", kind: QUALITY },
+        ]
+    }
+
+    fn extension(&self, params: &Params) -> &'static str {
+        raster_extension(self, params)
+    }
+
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        let quality = ctx.params.int(self, "quality") as u8;
+        generators::generate_thumbnail(source, path, ctx.params.int(self, "width") as u32, quality, ctx.lang, ctx.logger)
+    }
+}
+
+impl Generator for ColorPop {
+    fn kind(&self) -> OutputKind { OutputKind::ColorPop }
+    fn label_key(&self) -> &'static str { "chk_color_pop" }
+    fn status_key(&self) -> &'static str { "status_gen_color_pop" }
+    fn section(&self) -> Section { Section::Png }
+    fn default_enabled(&self) -> bool { false }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            // White leaves the grayscale background untinted
+            ParamSpec { name: "tint", label_key: "param_tint", kind: ParamKind::Color { default: [255, 255, 255] } },
+            ParamSpec { name: "format", label_key: "param_format", kind: ParamKind::Choice { options: encode::FORMATS, default: 0 } },
+            ParamSpec { name: "quality", label_key: "param_quality", kind: QUALITY },
+        ]
+    }
+
+    fn extension(&self, params: &Params) -> &'static str {
+        raster_extension(self, params)
+    }
+
+    fn generate(&self, _source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        let original = ctx.original()?;
+        let tint = Some(ctx.params.color(self, "tint")).filter(|&t| t != [255, 255, 255]);
+        let quality = ctx.params.int(self, "quality") as u8;
+        color_pop::generate_color_pop(&original, ctx.cutout, path, tint, quality, ctx.lang, ctx.logger)
+    }
+}
+
+impl Generator for Gray {
+    fn kind(&self) -> OutputKind { OutputKind::Gray }
+    fn label_key(&self) -> &'static str { "chk_grayscale" }
+    fn status_key(&self) -> &'static str { "status_gen_gray" }
+    fn section(&self) -> Section { Section::SvgMono }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            ParamSpec { name: "tones", label_key: "param_tones", kind: ParamKind::Int { min: 2, max: 16, default: 8 } },
+            BACKGROUND,
+            BACKGROUND_COLOR,
+        ]
+    }
+
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        generators::generate_grayscale_svg(source, path, ctx.params.int(self, "tones") as u32, &svg_options(self, ctx), ctx.lang, ctx.logger)
+    }
+}
+
+impl Generator for Halftone {
+    fn kind(&self) -> OutputKind { OutputKind::Halftone }
+    fn label_key(&self) -> &'static str { "chk_halftone" }
+    fn status_key(&self) -> &'static str { "status_gen_halftone" }
+    fn section(&self) -> Section { Section::SvgMono }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            ParamSpec { name: "angle", label_key: "param_angle", kind: ParamKind::Float { min: 0.0, max: 90.0, step: 1.0, default: 45.0 } },
+            BACKGROUND,
+            BACKGROUND_COLOR,
+        ]
+    }
+
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        generators::generate_halftone_svg(source, path, ctx.params.float(self, "angle") as f32, &svg_options(self, ctx), ctx.lang, ctx.logger)
+    }
+}
+
+impl Generator for Lineart {
+    fn kind(&self) -> OutputKind { OutputKind::Lineart }
+    fn label_key(&self) -> &'static str { "chk_lineart" }
+    fn status_key(&self) -> &'static str { "status_gen_lineart" }
+    fn section(&self) -> Section { Section::SvgMono }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            ParamSpec { name: "threshold", label_key: "param_threshold", kind: ParamKind::Int { min: 1, max: 255, default: 140 } },
+            CLEANUP,
+            BACKGROUND,
+            BACKGROUND_COLOR,
+        ]
+    }
+
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        generators::generate_lineart_svg(source, path, ctx.params.int(self, "threshold") as u8, ctx.params.float(self, "cleanup") as f32, &svg_options(self, ctx), ctx.lang, ctx.logger)
+    }
+}
+
+impl Generator for Logo {
+    fn kind(&self) -> OutputKind { OutputKind::Logo }
+    fn label_key(&self) -> &'static str { "chk_logo" }
+    fn status_key(&self) -> &'static str { "status_gen_logo" }
+    fn section(&self) -> Section { Section::SvgColor }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            ParamSpec { name: "colors", label_key: "param_colors", kind: ParamKind::Int { min: 2, max: 64, default: color::LOGO_COLORS as i64 } },
+            ParamSpec { name: "palette", label_key: "param_palette", kind: ParamKind::Choice { options: colorblind::PALETTE_OPTIONS, default: 0 } },
+            ParamSpec { name: "style", label_key: "param_style", kind: ParamKind::Choice { options: &["filled", "outline"], default: 0 } },
+            ParamSpec { name: "stroke_width", label_key: "param_stroke_width", kind: ParamKind::Float { min: 0.1, max: 20.0, step: 0.1, default: 1.0 } },
+            ParamSpec { name: "symmetry", label_key: "param_symmetry", kind: ParamKind::Choice { options: &["off", "auto"], default: 0 } },
+            // Share of the logo's pixels that may break the symmetry
+            ParamSpec { name: "symmetry_tolerance", label_key: "param_symmetry_tolerance", kind: ParamKind::Float { min: 0.0, max: 0.3, step: 0.01, default: 0.08 } },
+            CLEANUP,
+            BACKGROUND,
+            BACKGROUND_COLOR,
+        ]
+    }
+
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        let style = LogoStyle {
+            outline: (ctx.params.text(self, "style") == "outline").then(|| ctx.params.float(self, "stroke_width") as f32),
+            symmetry: (ctx.params.text(self, "symmetry") == "auto").then(|| ctx.params.float(self, "symmetry_tolerance") as f32),
+            cleanup: ctx.params.float(self, "cleanup") as f32,
+        };
+        generators::generate_logo(source, path, quantize(self, ctx.params), style, &svg_options(self, ctx), ctx.lang, ctx.logger)
+    }
+}
+
+impl Generator for Illustration {
+    fn kind(&self) -> OutputKind { OutputKind::Illustration }
+    fn label_key(&self) -> &'static str { "chk_illus" }
+    fn status_key(&self) -> &'static str { "status_gen_illus" }
+    fn section(&self) -> Section { Section::SvgColor }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[
+            ParamSpec { name: "colors", label_key: "param_colors", kind: ParamKind::Int { min: 2, max: 96, default: color::ILLUSTRATION_COLORS as i64 } },
+            ParamSpec { name: "palette", label_key: "param_palette", kind: ParamKind::Choice { options: colorblind::PALETTE_OPTIONS, default: 0 } },
+            ParamSpec { name: "smoothing", label_key: "param_smoothing", kind: ParamKind::Float { min: 0.0, max: 1.0, step: 0.05, default: 0.0 } },
+            // 0 leaves the colors to the quantization alone
+            ParamSpec { name: "posterize", label_key: "param_posterize", kind: ParamKind::Int { min: 0, max: 16, default: 0 } },
+            BACKGROUND,
+            BACKGROUND_COLOR,
+        ]
+    }
+
+    fn generate(&self, source: &DynamicImage, path: &Path, ctx: &GenerateContext) -> Result<()> {
+        let quantize = Quantize {
+            smoothing: ctx.params.float(self, "smoothing") as f32,
+            posterize: ctx.params.int(self, "posterize") as u32,
+            ..quantize(self, ctx.params)
+        };
+        generators::generate_illustration(source, path, quantize, &svg_options(self, ctx), ctx.lang, ctx.logger)
+    }
+}
+
+/// Quantization of a color SVG generator from its `colors` and `palette` parameters.
+fn quantize(generator: &dyn Generator, params: &Params) -> Quantize {
+    Quantize {
+        num_colors: params.int(generator, "colors") as u32,
+        palette: SafePalette::from_name(&params.text(generator, "palette")),
+        smoothing: 0.0,
+        posterize: 0,
+    }
+}
+
+/// Every selectable generator, in GUI order.
+pub static REGISTRY: &[&dyn Generator] = &[
+    &Alpha, &Thumbnail, &ColorPop,
+    &Gray, &Halftone, &Lineart,
+    &Logo, &Illustration,
+];
+
+/// Returns the registered generator for `kind`.
+pub fn get(kind: OutputKind) -> Option<&'static dyn Generator> {
+    REGISTRY.iter().copied().find(|g| g.kind() == kind)
+}
+
+/// Output kinds selected in a new job.
+pub fn default_kinds() -> Vec<OutputKind> {
+    REGISTRY.iter().filter(|g| g.default_enabled()).map(|g| g.kind()).collect()
+}
+
+/// Parses a generator name for `--only`.
+pub fn parse_kind(value: &str) -> Result<OutputKind, String> {
+    REGISTRY.iter()
+        .find(|g| g.name().eq_ignore_ascii_case(value))
+        .map(|g| g.kind())
+        .ok_or_else(|| {
+            let names: Vec<&str> = REGISTRY.iter().map(|g| g.name()).collect();
+            format!("unknown generator '{}', expected one of: {}", value, names.join(", "))
+        })
+}
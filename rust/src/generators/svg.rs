@@ -0,0 +1,612 @@
+//! Shared SVG document framing for the vector generators.
+//!
+//! Generators trace a downscaled "working" copy of the image when it exceeds the
+//! configured long-edge cap; the document keeps the original dimensions in its
+//! `viewBox` and scales the traced content back up.
+//!
+//! Documents are either assembled in memory ([`WorkingImage::write`]) or, for
+//! generators that emit many elements, streamed to the file layer by layer
+//! through an [`SvgSink`] so the whole document never exists as one string.
+//! Files go through a buffered writer, gzip-compressed when `.svgz` output is
+//! requested.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use base64::Engine;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use tempfile::NamedTempFile;
+use image::{DynamicImage, GenericImageView, ImageFormat, imageops::FilterType};
+use crate::{config, temp};
+use crate::error::AlphasvgError;
+use crate::generators::embroidery::EmbroideryOptions;
+use crate::generators::hpgl::HpglOptions;
+use crate::generators::metadata::DocumentMetadata;
+use crate::generators::ocr::OcrLine;
+use crate::generators::regions::MinArea;
+use crate::generators::text_regions::{TextMode, TextRegion};
+use crate::provenance::Provenance;
+
+/// Unit for the document `width`/`height` attributes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum SvgUnit {
+    #[default]
+    Px,
+    Mm,
+    Cm,
+    In,
+}
+
+impl SvgUnit {
+    fn suffix(self) -> &'static str {
+        match self {
+            SvgUnit::Px => "",
+            SvgUnit::Mm => "mm",
+            SvgUnit::Cm => "cm",
+            SvgUnit::In => "in",
+        }
+    }
+
+    /// Converts a length in pixels at `dpi` to this unit.
+    pub fn convert_px(self, px: u32, dpi: f32) -> f32 {
+        let inches = px as f32 / dpi;
+        match self {
+            SvgUnit::Px => px as f32,
+            SvgUnit::Mm => inches * 25.4,
+            SvgUnit::Cm => inches * 2.54,
+            SvgUnit::In => inches,
+        }
+    }
+}
+
+/// Options shared by the SVG generators.
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    /// Longest edge, in pixels, the generators vectorize at (`None` disables the cap).
+    pub max_working_px: Option<u32>,
+    /// Unit of the document size; the `viewBox` stays in pixels.
+    pub unit: SvgUnit,
+    /// Resolution assumed when converting pixels to physical units.
+    pub dpi: f32,
+    /// Value of the `preserveAspectRatio` attribute, omitted when `None`.
+    pub preserve_aspect_ratio: Option<String>,
+    /// Also write each layer as its own `{stem}_sep_{layer}.svg` plus a registration marks overlay.
+    pub separate_layers: bool,
+    /// Print mode: the halftone output becomes a CMYK screen with per-channel films.
+    pub cmyk: bool,
+    /// Embed a base64 PNG of the traced image behind the vector layers.
+    pub embed_raster: bool,
+    /// Also write the line art outlines as HPGL (`{stem}_lineart.hpgl`).
+    pub hpgl: Option<HpglOptions>,
+    /// Also write the color regions as an embroidery file (`{stem}_logo.dst` or `.pes`).
+    pub embroidery: Option<EmbroideryOptions>,
+    /// Title, description and other fields carried over from the input, see [`crate::generators::metadata`].
+    pub metadata: Option<DocumentMetadata>,
+    /// Tool version, models and parameters of the run, see [`crate::provenance`].
+    pub provenance: Option<Provenance>,
+    /// Write gzip-compressed `.svgz` documents.
+    pub compress: bool,
+    /// Color of a rectangle behind the layers, for viewers that show transparency as black.
+    pub background: Option<[u8; 3]>,
+    /// Color and gray regions smaller than this are merged into their largest neighbor, see [`crate::generators::regions`].
+    pub min_region_area: Option<MinArea>,
+    /// Draw smooth shadings of the color outputs as gradient fills, see [`crate::generators::gradient`].
+    pub gradients: bool,
+    /// How lines of text are drawn in the color outputs, see [`crate::generators::text_regions`].
+    pub text: Option<TextMode>,
+    /// Lines of text found in the image being vectorized.
+    pub text_regions: Vec<TextRegion>,
+    /// Language to recognize text in for the color outputs, see [`crate::generators::ocr`].
+    pub ocr: Option<String>,
+    /// Lines of text recognized in the image being vectorized.
+    pub ocr_lines: Vec<OcrLine>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            max_working_px: Some(config::MAX_VECTOR_PX),
+            unit: SvgUnit::Px,
+            dpi: config::SVG_DPI,
+            preserve_aspect_ratio: None,
+            separate_layers: false,
+            cmyk: false,
+            embed_raster: false,
+            hpgl: None,
+            embroidery: None,
+            metadata: None,
+            provenance: None,
+            compress: false,
+            background: None,
+            min_region_area: None,
+            gradients: false,
+            text: None,
+            text_regions: Vec::new(),
+            ocr: None,
+            ocr_lines: Vec::new(),
+        }
+    }
+}
+
+/// Parses a strictly positive DPI value.
+pub fn parse_dpi(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(dpi) if dpi > 0.0 && dpi.is_finite() => Ok(dpi),
+        _ => Err(format!("DPI must be a positive number: {}", value)),
+    }
+}
+
+/// Validates a `preserveAspectRatio` value such as `xMidYMid meet` or `none`.
+pub fn parse_preserve_aspect_ratio(value: &str) -> Result<String, String> {
+    const ALIGNS: [&str; 10] = [
+        "none", "xMinYMin", "xMidYMin", "xMaxYMin", "xMinYMid",
+        "xMidYMid", "xMaxYMid", "xMinYMax", "xMidYMax", "xMaxYMax",
+    ];
+    let mut parts = value.split_whitespace();
+    let align = parts.next().unwrap_or("");
+    let meet_or_slice = parts.next();
+    if !ALIGNS.contains(&align)
+        || meet_or_slice.is_some_and(|m| m != "meet" && m != "slice")
+        || parts.next().is_some()
+    {
+        return Err(format!("invalid preserveAspectRatio: {}", value));
+    }
+    Ok(value.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Image a generator traces, together with the original document size.
+pub struct WorkingImage<'a> {
+    pub image: Cow<'a, DynamicImage>,
+    /// Original width and height, used for the document size and `viewBox`.
+    pub width: u32,
+    pub height: u32,
+    opts: &'a SvgOptions,
+}
+
+impl<'a> WorkingImage<'a> {
+    /// Downscales `img` to the working resolution cap, borrowing it when no resize is needed.
+    pub fn new(img: &'a DynamicImage, opts: &'a SvgOptions) -> Self {
+        let (width, height) = img.dimensions();
+        let image = match opts.max_working_px {
+            Some(max) if max > 0 && width.max(height) > max => {
+                let scale = max as f32 / width.max(height) as f32;
+                let w = ((width as f32 * scale).round() as u32).max(1);
+                let h = ((height as f32 * scale).round() as u32).max(1);
+                Cow::Owned(img.resize_exact(w, h, FilterType::Triangle))
+            }
+            _ => Cow::Borrowed(img),
+        };
+        Self { image, width, height, opts }
+    }
+
+    /// Factor from working pixels to document units.
+    pub fn scale(&self) -> f32 {
+        self.width as f32 / self.image.width() as f32
+    }
+
+    fn is_scaled(&self) -> bool {
+        matches!(self.image, Cow::Owned(_))
+    }
+
+    /// Assembles the document: XML declaration, `<svg>` root with working
+    /// resolution and input metadata, and one Inkscape layer per entry of `layers`.
+    /// Layers traced from a downscaled image are scaled back to full size.
+    pub fn document(&self, layers: &[SvgLayer]) -> String {
+        let mut svg = self.header();
+        for (i, layer) in layers.iter().enumerate() {
+            svg.push_str(&self.layer_start(i, &layer.label, layer.full_size));
+            svg.push_str(&layer.content);
+            svg.push_str(LAYER_END);
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// XML declaration, `<svg>` root and `<metadata>`.
+    fn header(&self) -> String {
+        let (work_w, work_h) = self.image.dimensions();
+        let aspect = self.opts.preserve_aspect_ratio.as_ref()
+            .map(|value| format!(" preserveAspectRatio=\"{}\"", value))
+            .unwrap_or_default();
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+            <svg version=\"1.1\" xmlns=\"http://www.w3.org/2000/svg\" \
+            xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" \
+            xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+            width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\"{}>\n{}\
+            <metadata><alphasvg:working-resolution xmlns:alphasvg=\"https://github.com/danloi2/alphasvg\" \
+            width=\"{}\" height=\"{}\" scale=\"{:.4}\" dpi=\"{}\"/>{}{}{}</metadata>\n",
+            self.length(self.width), self.length(self.height), self.width, self.height, aspect,
+            self.opts.metadata.as_ref().and_then(|m| m.title.as_ref()).map(|t| format!("<title>{}</title>\n", escape_attr(t))).unwrap_or_default(),
+            work_w, work_h, self.scale(), self.opts.dpi,
+            self.opts.background.map(|color| format!(
+                "<alphasvg:background xmlns:alphasvg=\"https://github.com/danloi2/alphasvg\" color=\"{}\"/>", hex_color(color)
+            )).unwrap_or_default(),
+            self.opts.provenance.as_ref().map(provenance_metadata).unwrap_or_default(),
+            self.opts.metadata.as_ref().map(rdf_metadata).unwrap_or_default()
+        )
+    }
+
+    /// Opening tag of the `index`-th layer, scaling working pixels back to full size.
+    fn layer_start(&self, index: usize, label: &str, full_size: bool) -> String {
+        let transform = if self.is_scaled() && !full_size {
+            format!(" transform=\"scale({:.6})\"", self.scale())
+        } else {
+            String::new()
+        };
+        format!(
+            "<g inkscape:groupmode=\"layer\" inkscape:label=\"{}\" id=\"layer{}\"{}>\n",
+            escape_attr(label), index + 1, transform
+        )
+    }
+
+    /// Starts a document whose layers are added one at a time. It is streamed
+    /// to `output_path`, unless separations are enabled and need the layers again.
+    pub fn sink(&'a self, output_path: &Path) -> Result<SvgSink<'a>> {
+        let mut sink = if self.opts.separate_layers {
+            SvgSink::Collect { work: self, path: output_path.to_path_buf(), layers: Vec::new() }
+        } else {
+            let extension = output_path.extension().and_then(|e| e.to_str()).unwrap_or("svg");
+            let partial = temp::file_beside(output_path, extension)?;
+            let mut out = SvgFile::new(partial.reopen()?, self.opts.compress);
+            out.write_all(self.header().as_bytes())?;
+            SvgSink::Stream { work: self, out, partial, path: output_path.to_path_buf(), layers: 0 }
+        };
+        for layer in self.base_layers()? {
+            sink.add(layer)?;
+        }
+        Ok(sink)
+    }
+
+    /// Writes the combined document to `output_path` and, when separations are
+    /// enabled, the per-layer files (see [`WorkingImage::write_separations`]).
+    pub fn write(&self, output_path: &Path, layers: &[SvgLayer]) -> Result<()> {
+        let mut all = self.base_layers()?;
+        if all.is_empty() {
+            self.save(output_path, &self.document(layers))?;
+        } else {
+            all.extend_from_slice(layers);
+            self.save(output_path, &self.document(&all))?;
+        }
+        if self.opts.separate_layers {
+            self.write_separations(output_path, layers)?;
+        }
+        Ok(())
+    }
+
+    /// Writes each separable layer of `layers` next to `output_path` as
+    /// `{stem}_sep_{layer}.svg` with registration marks, plus the marks alone.
+    pub fn write_separations(&self, output_path: &Path, layers: &[SvgLayer]) -> Result<()> {
+        let stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+        let extension = if self.opts.compress { "svgz" } else { "svg" };
+        let sep_path = |name: &str| output_path.with_file_name(format!("{}_sep_{}.{}", stem, name, extension));
+        let marks = || SvgLayer::registration_marks(self.width, self.height);
+
+        for layer in layers.iter().filter(|l| l.separable) {
+            let separation = [layer.clone(), marks()];
+            self.save(&sep_path(&layer.slug()), &self.document(&separation))?;
+        }
+        self.save(&sep_path("registration"), &self.document(&[marks()]))?;
+        Ok(())
+    }
+
+    fn save(&self, path: &Path, document: &str) -> Result<()> {
+        let mut out = SvgFile::create(path, self.opts.compress).map_err(AlphasvgError::io(path))?;
+        out.write_all(document.as_bytes()).map_err(AlphasvgError::io(path))?;
+        out.finish().map_err(AlphasvgError::io(path))?;
+        Ok(())
+    }
+
+    /// Layers below the traced ones: the background rectangle and the raster
+    /// fallback, when enabled. Neither is separable.
+    fn base_layers(&self) -> Result<Vec<SvgLayer>> {
+        let mut layers = Vec::new();
+        if let Some(color) = self.opts.background {
+            let content = format!(
+                "<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                self.width, self.height, hex_color(color)
+            );
+            layers.push(SvgLayer { full_size: true, ..SvgLayer::background("Background", content) });
+        }
+        if self.opts.embed_raster {
+            layers.push(self.raster_layer()?);
+        }
+        Ok(layers)
+    }
+
+    /// PNG of the working image as an `<image>` covering the whole document.
+    fn raster_layer(&self) -> Result<SvgLayer> {
+        let mut png = Vec::new();
+        self.image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        let content = format!(
+            "<image x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" \
+            xlink:href=\"data:image/png;base64,{}\"/>",
+            self.width, self.height, base64::engine::general_purpose::STANDARD.encode(&png)
+        );
+        Ok(SvgLayer { full_size: true, ..SvgLayer::background("Raster fallback", content) })
+    }
+
+    /// Formats a document length in the configured unit.
+    fn length(&self, px: u32) -> String {
+        let unit = self.opts.unit;
+        match unit {
+            SvgUnit::Px => px.to_string(),
+            _ => format!("{:.3}{}", unit.convert_px(px, self.opts.dpi), unit.suffix()),
+        }
+    }
+}
+
+const LAYER_END: &str = "\n</g>\n";
+
+/// Document being built layer by layer, see [`WorkingImage::sink`].
+pub enum SvgSink<'a> {
+    /// Each layer is written as soon as it is added, to a temporary file moved
+    /// to `path` when finished, so a failed run leaves no truncated output.
+    Stream { work: &'a WorkingImage<'a>, out: SvgFile, partial: NamedTempFile, path: PathBuf, layers: usize },
+    /// Layers are kept until [`SvgSink::finish`] writes the document and its separations.
+    Collect { work: &'a WorkingImage<'a>, path: PathBuf, layers: Vec<SvgLayer> },
+}
+
+impl SvgSink<'_> {
+    pub fn add(&mut self, layer: SvgLayer) -> Result<()> {
+        match self {
+            SvgSink::Stream { work, out, layers, .. } => {
+                out.write_all(work.layer_start(*layers, &layer.label, layer.full_size).as_bytes())?;
+                out.write_all(layer.content.as_bytes())?;
+                out.write_all(LAYER_END.as_bytes())?;
+                *layers += 1;
+            }
+            SvgSink::Collect { layers, .. } => layers.push(layer),
+        }
+        Ok(())
+    }
+
+    /// Adds a separable layer whose content is written by `content` as it is generated.
+    pub fn add_with(&mut self, label: &str, content: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> Result<()> {
+        match self {
+            SvgSink::Stream { work, out, layers, .. } => {
+                out.write_all(work.layer_start(*layers, label, false).as_bytes())?;
+                content(out)?;
+                out.write_all(LAYER_END.as_bytes())?;
+                *layers += 1;
+            }
+            SvgSink::Collect { layers, .. } => {
+                let mut buffer = Vec::new();
+                content(&mut buffer)?;
+                layers.push(SvgLayer::new(label, String::from_utf8_lossy(&buffer).into_owned()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes the document.
+    pub fn finish(self) -> Result<()> {
+        match self {
+            SvgSink::Stream { mut out, partial, path, .. } => {
+                out.write_all(b"</svg>").map_err(AlphasvgError::io(&path))?;
+                out.finish().map_err(AlphasvgError::io(&path))?;
+                partial.persist(&path).map_err(|e| AlphasvgError::io(&path)(e.error))?;
+            }
+            SvgSink::Collect { work, path, layers } => {
+                // The raster fallback is among the layers but is not separable
+                work.save(&path, &work.document(&layers))?;
+                work.write_separations(&path, &layers)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Buffered output file, gzip-compressed for `.svgz`.
+pub enum SvgFile {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl SvgFile {
+    fn create(path: &Path, compress: bool) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?, compress))
+    }
+
+    fn new(file: File, compress: bool) -> Self {
+        let file = BufWriter::new(file);
+        if compress { SvgFile::Gzip(GzEncoder::new(file, Compression::default())) } else { SvgFile::Plain(file) }
+    }
+
+    /// Writes the gzip trailer, if any, and flushes the buffer.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            SvgFile::Plain(mut file) => file.flush(),
+            SvgFile::Gzip(gzip) => gzip.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for SvgFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            SvgFile::Plain(file) => file.write(buf),
+            SvgFile::Gzip(gzip) => gzip.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            SvgFile::Plain(file) => file.flush(),
+            SvgFile::Gzip(gzip) => gzip.flush(),
+        }
+    }
+}
+
+/// How the shapes of a potrace SVG are painted when copied.
+#[derive(Clone, Copy, Debug)]
+pub enum PotracePaint<'a> {
+    /// Potrace's own black fill.
+    Black,
+    Fill(&'a str),
+    /// Outlines only, with `width` in potrace units (a tenth of a traced pixel).
+    Stroke { color: &'a str, width: f32 },
+}
+
+impl PotracePaint<'_> {
+    fn apply(self, body: &str) -> Cow<'_, str> {
+        match self {
+            PotracePaint::Black => Cow::Borrowed(body),
+            PotracePaint::Fill(fill) => Cow::Owned(body.replace("fill=\"black\"", &format!("fill=\"{}\"", fill))
+                .replace("fill=\"#000000\"", &format!("fill=\"{}\"", fill))),
+            PotracePaint::Stroke { color, width } => {
+                let stroke = format!("fill=\"none\" stroke=\"{}\" stroke-width=\"{:.2}\" stroke-linejoin=\"round\"", color, width);
+                Cow::Owned(body.replace(" stroke=\"none\"", "").replace("fill=\"black\"", &stroke).replace("fill=\"#000000\"", &stroke))
+            }
+        }
+    }
+}
+
+/// Copies the drawing of a potrace SVG, everything inside its `<svg>` root, to
+/// `out` line by line, painted as `paint` says.
+pub fn copy_potrace_body(path: &Path, paint: PotracePaint, out: &mut dyn Write) -> io::Result<()> {
+    let mut in_root_tag = false;
+    let mut inside = false;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let mut rest = line.as_str();
+        if !inside {
+            if !in_root_tag {
+                let Some(start) = rest.find("<svg") else { continue };
+                rest = &rest[start..];
+                in_root_tag = true;
+            }
+            let Some(end) = rest.find('>') else { continue };
+            rest = &rest[end + 1..];
+            inside = true;
+        }
+        let (body, done) = match rest.find("</svg>") {
+            Some(end) => (&rest[..end], true),
+            None => (rest, false),
+        };
+        out.write_all(paint.apply(body).as_bytes())?;
+        if done {
+            break;
+        }
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// A named group of SVG content, emitted as an Inkscape layer.
+#[derive(Clone)]
+pub struct SvgLayer {
+    pub label: String,
+    pub content: String,
+    /// Written as its own file when separations are enabled (false for backgrounds).
+    pub separable: bool,
+    /// Content is in document units rather than working pixels, so it is never rescaled.
+    full_size: bool,
+}
+
+impl SvgLayer {
+    pub fn new(label: impl Into<String>, content: impl Into<String>) -> Self {
+        Self { label: label.into(), content: content.into(), separable: true, full_size: false }
+    }
+
+    /// The layer's content is in document pixels, so it is not scaled with the working resolution.
+    pub fn at_document_scale(self) -> Self {
+        Self { full_size: true, ..self }
+    }
+
+    /// A layer that is kept out of the per-layer separations.
+    pub fn background(label: impl Into<String>, content: impl Into<String>) -> Self {
+        Self { separable: false, ..Self::new(label, content) }
+    }
+
+    /// File name fragment derived from the label (`Gray 3` -> `gray_3`).
+    pub fn slug(&self) -> String {
+        self.label
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    /// Crosshair registration marks in the four corners of a `width` x `height` document.
+    fn registration_marks(width: u32, height: u32) -> Self {
+        let size = (width.min(height) as f32 * 0.03).max(6.0);
+        let inset = size * 1.5;
+        let corners = [
+            (inset, inset),
+            (width as f32 - inset, inset),
+            (inset, height as f32 - inset),
+            (width as f32 - inset, height as f32 - inset),
+        ];
+        let marks: Vec<String> = corners.iter().map(|&(x, y)| format!(
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{r:.1}\"/>\
+            <path d=\"M {x0:.1} {y:.1} H {x1:.1} M {x:.1} {y0:.1} V {y1:.1}\"/>",
+            r = size / 2.0, x0 = x - size, x1 = x + size, y0 = y - size, y1 = y + size,
+        )).collect();
+        let content = format!(
+            "<g fill=\"none\" stroke=\"#000\" stroke-width=\"{:.2}\">\n{}\n</g>",
+            (size / 12.0).max(0.5), marks.join("\n")
+        );
+        Self { separable: false, full_size: true, ..Self::new("Registration", content) }
+    }
+}
+
+/// Dublin Core description of the document, in the RDF form Inkscape reads.
+fn rdf_metadata(meta: &DocumentMetadata) -> String {
+    let mut work = String::new();
+    if let Some(title) = &meta.title {
+        work.push_str(&format!("<dc:title>{}</dc:title>", escape_attr(title)));
+    }
+    if let Some(description) = &meta.description {
+        work.push_str(&format!("<dc:description>{}</dc:description>", escape_attr(description)));
+    }
+    for (element, value) in [("creator", &meta.creator), ("rights", &meta.rights)] {
+        if let Some(value) = value {
+            work.push_str(&format!("<dc:{0}><cc:Agent><dc:title>{1}</dc:title></cc:Agent></dc:{0}>", element, escape_attr(value)));
+        }
+    }
+    if !meta.subject.is_empty() {
+        let items: String = meta.subject.iter().map(|k| format!("<rdf:li>{}</rdf:li>", escape_attr(k))).collect();
+        work.push_str(&format!("<dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>", items));
+    }
+    format!(
+        "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\" \
+        xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:cc=\"http://creativecommons.org/ns#\">\
+        <cc:Work rdf:about=\"\">{}</cc:Work></rdf:RDF>",
+        work
+    )
+}
+
+/// Provenance element in the alphasvg namespace; parameters are kept as JSON text.
+fn provenance_metadata(provenance: &Provenance) -> String {
+    let models: String = provenance.models.iter()
+        .map(|m| format!(
+            "<alphasvg:model name=\"{}\" file=\"{}\" sha256=\"{}\" license=\"{}\"/>",
+            escape_attr(&m.name), escape_attr(&m.file), m.sha256.as_deref().unwrap_or_default(), escape_attr(&m.license)
+        ))
+        .collect();
+    let arguments = if provenance.arguments.is_empty() {
+        String::new()
+    } else {
+        let arguments = serde_json::to_string(&provenance.arguments).unwrap_or_default();
+        format!("<alphasvg:arguments>{}</alphasvg:arguments>", escape_attr(&arguments))
+    };
+    format!(
+        "<alphasvg:provenance xmlns:alphasvg=\"https://github.com/danloi2/alphasvg\" \
+        version=\"{}\" os=\"{}\" execution-provider=\"{}\">{}{}<alphasvg:parameters>{}</alphasvg:parameters></alphasvg:provenance>",
+        escape_attr(&provenance.version), escape_attr(&provenance.os), escape_attr(&provenance.execution_provider),
+        models, arguments, escape_attr(&provenance.parameters.to_string())
+    )
+}
+
+fn hex_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub(crate) fn escape_attr(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+}
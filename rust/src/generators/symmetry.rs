@@ -0,0 +1,187 @@
+//! Symmetry detection and enforcement for the logo output.
+//!
+//! Traced logos come out slightly lopsided, and evening them out by hand is
+//! slow. Before tracing, the quantized color labels are compared with their
+//! mirror images about the vertical and horizontal lines through the subject's
+//! centroid, and with their rotations about it by a whole fraction of a turn.
+//! When no more than the tolerated share of the subject's pixels disagrees,
+//! the largest symmetry found is enforced: every pixel takes the color of its
+//! counterpart in a fundamental region (a half, a quarter or a wedge), only
+//! that region is traced, and the layer repeats the traced paths mirrored or
+//! rotated, so the drawing is exactly symmetric.
+
+use std::f32::consts::TAU;
+use std::fmt;
+
+/// Highest rotational order looked for.
+const MAX_ORDER: u32 = 8;
+/// Subject pixels compared per candidate; larger images are sampled on a grid.
+const MAX_SAMPLES: usize = 200_000;
+/// Pixels the traced region reaches past its edges, so the repeated copies
+/// overlap instead of leaving hairline seams.
+const SEAM_OVERLAP: f32 = 1.0;
+
+/// A symmetry of the subject, in working pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symmetry {
+    /// Left and right mirror each other about the vertical line `x = axis`.
+    Horizontal { axis: f32 },
+    /// Top and bottom mirror each other about the horizontal line `y = axis`.
+    Vertical { axis: f32 },
+    /// Mirrored about both lines through `(x, y)`: a quarter repeated four times.
+    Both { x: f32, y: f32 },
+    /// Repeats `order` times around `(x, y)`.
+    Radial { x: f32, y: f32, order: u32 },
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Symmetry::Horizontal { .. } => write!(f, "horizontal"),
+            Symmetry::Vertical { .. } => write!(f, "vertical"),
+            Symmetry::Both { .. } => write!(f, "horizontal+vertical"),
+            Symmetry::Radial { order, .. } => write!(f, "radial ×{}", order),
+        }
+    }
+}
+
+impl Symmetry {
+    /// Copies of the fundamental region the symmetry makes.
+    fn order(self) -> u32 {
+        match self {
+            Symmetry::Horizontal { .. } | Symmetry::Vertical { .. } => 2,
+            Symmetry::Both { .. } => 4,
+            Symmetry::Radial { order, .. } => order,
+        }
+    }
+
+    /// Where the point `(px, py)` lands in the fundamental region.
+    fn fold(self, px: f32, py: f32) -> (f32, f32) {
+        let mirror = |p: f32, axis: f32| if p > axis { 2.0 * axis - p } else { p };
+        match self {
+            Symmetry::Horizontal { axis } => (mirror(px, axis), py),
+            Symmetry::Vertical { axis } => (px, mirror(py, axis)),
+            Symmetry::Both { x, y } => (mirror(px, x), mirror(py, y)),
+            Symmetry::Radial { x, y, order } => {
+                let wedge = TAU / order as f32;
+                let (dx, dy) = (px - x, py - y);
+                let back = -(dy.atan2(dx).rem_euclid(TAU) / wedge).floor() * wedge;
+                let (sin, cos) = back.sin_cos();
+                (x + dx * cos - dy * sin, y + dx * sin + dy * cos)
+            }
+        }
+    }
+
+    /// Whether the pixel at `(x, y)` is traced: it lies in the fundamental
+    /// region or within [`SEAM_OVERLAP`] of it.
+    pub fn traces(self, x: u32, y: u32) -> bool {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        match self {
+            Symmetry::Horizontal { axis } => px <= axis + SEAM_OVERLAP,
+            Symmetry::Vertical { axis } => py <= axis + SEAM_OVERLAP,
+            Symmetry::Both { x, y } => px <= x + SEAM_OVERLAP && py <= y + SEAM_OVERLAP,
+            Symmetry::Radial { x, y, order } => {
+                let (dx, dy) = (px - x, py - y);
+                let radius = dx.hypot(dy);
+                if radius <= SEAM_OVERLAP {
+                    return true;
+                }
+                let slack = (SEAM_OVERLAP / radius).min(TAU / order as f32 / 2.0);
+                let angle = dy.atan2(dx).rem_euclid(TAU);
+                angle <= TAU / order as f32 + slack || angle >= TAU - slack
+            }
+        }
+    }
+
+    /// SVG transforms, in working pixels, placing the copies of the traced
+    /// region; the first, empty, is the region itself.
+    pub fn transforms(self) -> Vec<String> {
+        match self {
+            Symmetry::Horizontal { axis } => vec![String::new(), format!("matrix(-1 0 0 1 {} 0)", 2.0 * axis)],
+            Symmetry::Vertical { axis } => vec![String::new(), format!("matrix(1 0 0 -1 0 {})", 2.0 * axis)],
+            Symmetry::Both { x, y } => vec![
+                String::new(),
+                format!("matrix(-1 0 0 1 {} 0)", 2.0 * x),
+                format!("matrix(1 0 0 -1 0 {})", 2.0 * y),
+                format!("matrix(-1 0 0 -1 {} {})", 2.0 * x, 2.0 * y),
+            ],
+            Symmetry::Radial { x, y, order } => (0..order)
+                .map(|k| if k == 0 { String::new() } else { format!("rotate({} {} {})", k as f32 * 360.0 / order as f32, x, y) })
+                .collect(),
+        }
+    }
+}
+
+/// Finds the largest symmetry of the labelled pixels, if any holds for all but
+/// `tolerance` (0 to 1) of them. `labels` is row-major, `None` where empty.
+pub fn detect(labels: &[Option<usize>], width: u32, height: u32, tolerance: f32) -> Option<Symmetry> {
+    let (mut count, mut sum_x, mut sum_y) = (0usize, 0.0f64, 0.0f64);
+    for (i, label) in labels.iter().enumerate() {
+        if label.is_some() {
+            count += 1;
+            sum_x += (i as u32 % width) as f64 + 0.5;
+            sum_y += (i as u32 / width) as f64 + 0.5;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+    let (cx, cy) = ((sum_x / count as f64) as f32, (sum_y / count as f64) as f32);
+    let step = ((count as f32 / MAX_SAMPLES as f32).sqrt().ceil() as usize).max(1);
+
+    // Share of the sampled subject pixels whose image under `map` has the same label
+    let agreement = |map: &dyn Fn(f32, f32) -> (f32, f32)| {
+        let (mut total, mut matched) = (0usize, 0usize);
+        for y in (0..height).step_by(step) {
+            for x in (0..width).step_by(step) {
+                let Some(label) = labels[(y * width + x) as usize] else { continue };
+                total += 1;
+                let (mx, my) = map(x as f32 + 0.5, y as f32 + 0.5);
+                if mx >= 0.0 && my >= 0.0 && (mx as u32) < width && (my as u32) < height
+                    && labels[(my as u32 * width + mx as u32) as usize] == Some(label) {
+                    matched += 1;
+                }
+            }
+        }
+        matched as f32 / total.max(1) as f32
+    };
+    let holds = |score: f32| score >= 1.0 - tolerance;
+
+    let horizontal = holds(agreement(&|px, py| (2.0 * cx - px, py)));
+    let vertical = holds(agreement(&|px, py| (px, 2.0 * cy - py)));
+    let mut best = match (horizontal, vertical) {
+        (true, true) => Some(Symmetry::Both { x: cx, y: cy }),
+        (true, false) => Some(Symmetry::Horizontal { axis: cx }),
+        (false, true) => Some(Symmetry::Vertical { axis: cy }),
+        (false, false) => None,
+    };
+    for order in 2..=MAX_ORDER {
+        if best.is_some_and(|b| b.order() >= order) {
+            continue;
+        }
+        let (sin, cos) = (TAU / order as f32).sin_cos();
+        let rotated = |px: f32, py: f32| {
+            let (dx, dy) = (px - cx, py - cy);
+            (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+        };
+        if holds(agreement(&rotated)) {
+            best = Some(Symmetry::Radial { x: cx, y: cy, order });
+        }
+    }
+    best
+}
+
+/// Gives every pixel the label of its counterpart in the fundamental region of
+/// `symmetry`, making the labels exactly symmetric up to resampling.
+pub fn enforce(labels: &mut [Option<usize>], width: u32, height: u32, symmetry: Symmetry) {
+    let source = labels.to_vec();
+    for (i, label) in labels.iter_mut().enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let (fx, fy) = symmetry.fold(x as f32 + 0.5, y as f32 + 0.5);
+        *label = if fx >= 0.0 && fy >= 0.0 && (fx as u32) < width && (fy as u32) < height {
+            source[(fy as u32 * width + fx as u32) as usize]
+        } else {
+            None
+        };
+    }
+}
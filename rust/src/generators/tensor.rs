@@ -0,0 +1,68 @@
+//! Conversions between images and model tensors, shared by the ONNX Runtime
+//! inference in [`super::ai`] and the tract inference of the browser build.
+
+use image::DynamicImage;
+
+use super::models::{Activation, MaskChannel, Normalization, OutputTransform};
+
+/// Converts the raw `[C, H*W]` model output into an 8-bit mask per the model's transform.
+pub fn transform_output(raw: &[f32], channels: usize, plane: usize, transform: &OutputTransform, out: &mut Vec<u8>) {
+    let (index, complement) = match transform.channel {
+        MaskChannel::Index(i) => (i.min(channels - 1), false),
+        MaskChannel::NotBackground(i) => (i.min(channels - 1), true),
+    };
+    let selected = &raw[index * plane..(index + 1) * plane];
+
+    let mut values: Vec<f32> = match transform.activation {
+        Activation::Identity => selected.to_vec(),
+        Activation::Sigmoid => selected.iter().map(|&v| 1.0 / (1.0 + (-v).exp())).collect(),
+        Activation::Softmax => (0..plane).map(|p| {
+            let max = (0..channels).map(|c| raw[c * plane + p]).fold(f32::MIN, f32::max);
+            let sum: f32 = (0..channels).map(|c| (raw[c * plane + p] - max).exp()).sum();
+            (raw[index * plane + p] - max).exp() / sum
+        }).collect(),
+    };
+    if complement {
+        values.iter_mut().for_each(|v| *v = 1.0 - *v);
+    }
+
+    let (min, max) = if transform.minmax {
+        values.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)))
+    } else {
+        (0.0, 1.0)
+    };
+    let range = if max - min > f32::EPSILON { max - min } else { 1.0 };
+
+    out.clear();
+    out.extend(values.iter().map(|&v| ((v - min) / range * 255.0).clamp(0.0, 255.0) as u8));
+}
+
+/// Fills `data` with a channel-planar (NCHW) tensor of the image normalized for the model,
+/// reusing its allocation.
+///
+/// RGB and RGBA buffers are read in place; other formats are converted to RGB first.
+pub fn fill_planar_tensor(img: &DynamicImage, normalization: &Normalization, data: &mut Vec<f32>) {
+    let luts = normalization.luts();
+    let [c0, c1, c2] = normalization.source_channels();
+    let converted;
+    let (bytes, channels): (&[u8], usize) = match img {
+        DynamicImage::ImageRgb8(buf) => (buf.as_raw(), 3),
+        DynamicImage::ImageRgba8(buf) => (buf.as_raw(), 4),
+        other => {
+            converted = other.to_rgb8();
+            (converted.as_raw(), 3)
+        }
+    };
+
+    let plane = bytes.len() / channels;
+    data.clear();
+    data.resize(plane * 3, 0.0);
+    let (plane0, rest) = data.split_at_mut(plane);
+    let (plane1, plane2) = rest.split_at_mut(plane);
+
+    for (((px, v0), v1), v2) in bytes.chunks_exact(channels).zip(plane0).zip(plane1).zip(plane2) {
+        *v0 = luts[0][px[c0] as usize];
+        *v1 = luts[1][px[c1] as usize];
+        *v2 = luts[2][px[c2] as usize];
+    }
+}
@@ -0,0 +1,296 @@
+//! Text region detection for the color SVG generators (`--text-regions`).
+//!
+//! Lettering quantizes into blobs, so lines of text are looked for before
+//! tracing: the image is binarized at its Otsu threshold, in both polarities,
+//! and the connected components sized and filled like glyphs are chained into
+//! lines of similar height that sit side by side. A line of at least
+//! [`MIN_GLYPHS`] glyphs is a text region. The regions are left out of the
+//! color layers and drawn on top of them, either traced again on their own at
+//! full resolution with a finer potrace setup or embedded as a raster, and are
+//! listed in the report since the text may need OCR or retypesetting.
+
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+use anyhow::Result;
+use base64::Engine;
+use image::{DynamicImage, GrayImage, ImageFormat, Luma};
+use imageproc::contrast::otsu_level;
+use imageproc::region_labelling::{Connectivity, connected_components};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AlphasvgError;
+use crate::generators::svg::{self, PotracePaint, SvgLayer};
+use crate::temp;
+
+/// Glyphs a line needs to count as text.
+const MIN_GLYPHS: usize = 3;
+/// Tallest glyph, as a share of the image height.
+const MAX_GLYPH_HEIGHT: f32 = 0.2;
+/// Shortest glyph in pixels.
+const MIN_GLYPH_HEIGHT: u32 = 6;
+/// Range of the share of its bounding box a glyph fills.
+const GLYPH_DENSITY: std::ops::RangeInclusive<f32> = 0.1..=0.9;
+/// Most glyph candidates considered; noisy photos beyond this are not text.
+const MAX_CANDIDATES: usize = 4000;
+/// Margin around a line, as a share of its glyph height.
+const PADDING: f32 = 0.25;
+/// Pixels with less alpha count as background.
+const MIN_ALPHA: u8 = 20;
+
+/// What happens to detected text in the color SVG outputs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TextMode {
+    /// Trace the text again on its own, at full resolution and finer settings.
+    #[default]
+    Preserve,
+    /// Embed the text as a PNG layer over the vector layers.
+    Raster,
+}
+
+/// Bounding box of a line of text, in pixels of the vectorized image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TextRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Glyph-like shapes found on the line.
+    pub glyphs: usize,
+}
+
+impl TextRegion {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    fn overlaps(&self, other: &TextRegion) -> bool {
+        self.x < other.x + other.width && other.x < self.x + self.width
+            && self.y < other.y + other.height && other.y < self.y + self.height
+    }
+
+    fn union(&self, other: &TextRegion) -> TextRegion {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        TextRegion { x, y, width: right - x, height: bottom - y, glyphs: self.glyphs + other.glyphs }
+    }
+}
+
+/// Bounding box and size of a connected component.
+#[derive(Clone, Copy, Debug)]
+struct Component {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+    pixels: u32,
+}
+
+impl Component {
+    fn width(&self) -> u32 {
+        self.x1 - self.x0 + 1
+    }
+
+    fn height(&self) -> u32 {
+        self.y1 - self.y0 + 1
+    }
+}
+
+/// Finds the lines of text in `img`.
+pub fn detect(img: &DynamicImage) -> Vec<TextRegion> {
+    let gray = flatten_gray(img);
+    let threshold = otsu_level(&gray);
+    let (width, height) = gray.dimensions();
+    let max_height = ((height as f32 * MAX_GLYPH_HEIGHT) as u32).max(MIN_GLYPH_HEIGHT);
+
+    let mut glyphs: Vec<Component> = [true, false].into_iter()
+        .flat_map(|dark| components(&gray, |v| if dark { v <= threshold } else { v > threshold }))
+        .filter(|c| {
+            let density = c.pixels as f32 / (c.width() * c.height()) as f32;
+            (MIN_GLYPH_HEIGHT..=max_height).contains(&c.height()) && c.width() <= 2 * c.height() && GLYPH_DENSITY.contains(&density)
+        })
+        .collect();
+    if glyphs.len() > MAX_CANDIDATES {
+        return Vec::new();
+    }
+    glyphs.sort_by_key(|c| c.x0);
+
+    // Chain neighbours of similar height on the same baseline band
+    let mut parent: Vec<usize> = (0..glyphs.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (i, &a) in glyphs.iter().enumerate() {
+        for (j, &b) in glyphs.iter().enumerate().skip(i + 1) {
+            let tallest = a.height().max(b.height());
+            if b.x0 > a.x1 + tallest * 3 / 2 {
+                break;
+            }
+            let overlap = a.y1.min(b.y1) as i64 - a.y0.max(b.y0) as i64 + 1;
+            let shortest = a.height().min(b.height());
+            if overlap * 2 >= shortest as i64 && tallest <= shortest * 2 {
+                let (ra, rb) = (root(&mut parent, i), root(&mut parent, j));
+                parent[ra] = rb;
+            }
+        }
+    }
+
+    let mut lines: Vec<(Component, usize, u32)> = Vec::new();
+    let mut line_of = std::collections::HashMap::new();
+    for (i, &g) in glyphs.iter().enumerate() {
+        let r = root(&mut parent, i);
+        let index = *line_of.entry(r).or_insert_with(|| {
+            lines.push((g, 0, 0));
+            lines.len() - 1
+        });
+        let (bbox, count, heights) = &mut lines[index];
+        (bbox.x0, bbox.y0, bbox.x1, bbox.y1) = (bbox.x0.min(g.x0), bbox.y0.min(g.y0), bbox.x1.max(g.x1), bbox.y1.max(g.y1));
+        *count += 1;
+        *heights += g.height();
+    }
+
+    let mut regions: Vec<TextRegion> = Vec::new();
+    for (bbox, count, heights) in lines.into_iter().filter(|(_, count, _)| *count >= MIN_GLYPHS) {
+        let pad = (heights as f32 / count as f32 * PADDING).ceil() as u32;
+        let (x, y) = (bbox.x0.saturating_sub(pad), bbox.y0.saturating_sub(pad));
+        let region = TextRegion {
+            x,
+            y,
+            width: (bbox.x1 + pad + 1).min(width) - x,
+            height: (bbox.y1 + pad + 1).min(height) - y,
+            glyphs: count,
+        };
+        // Glyphs found in both polarities (outlined letters, counters) give overlapping lines
+        match regions.iter().position(|r| r.overlaps(&region)) {
+            Some(i) => regions[i] = regions[i].union(&region),
+            None => regions.push(region),
+        }
+    }
+    regions.sort_by_key(|r| (r.y, r.x));
+    regions
+}
+
+/// Layers drawing `regions` of `img` per `mode`, in document pixels.
+pub fn layers(img: &DynamicImage, regions: &[TextRegion], mode: TextMode, output_path: &Path) -> Result<Vec<SvgLayer>> {
+    let mut layers = Vec::new();
+    for (index, region) in regions.iter().enumerate() {
+        let crop = img.crop_imm(region.x, region.y, region.width, region.height);
+        let label = format!("Text {}", index + 1);
+        let content = match mode {
+            TextMode::Raster => {
+                let mut png = Vec::new();
+                crop.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+                format!(
+                    "<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" \
+                    xlink:href=\"data:image/png;base64,{}\"/>",
+                    region.x, region.y, region.width, region.height, base64::engine::general_purpose::STANDARD.encode(&png)
+                )
+            }
+            TextMode::Preserve => match trace_text(&crop, output_path)? {
+                Some(content) => format!("<g transform=\"translate({},{})\">\n{}\n</g>", region.x, region.y, content),
+                None => continue,
+            },
+        };
+        layers.push(SvgLayer::new(label, content).at_document_scale());
+    }
+    Ok(layers)
+}
+
+/// Text of a crop split from its background.
+pub(crate) struct Glyphs {
+    /// Black on the glyphs, white elsewhere.
+    pub mask: GrayImage,
+    /// Average colors of the background and of the glyphs, as `#rrggbb`.
+    pub background: String,
+    pub color: String,
+}
+
+/// Splits the glyphs of `crop`, the minority side of its Otsu threshold, from the background.
+pub(crate) fn split_glyphs(crop: &DynamicImage) -> Glyphs {
+    let gray = flatten_gray(crop);
+    let threshold = otsu_level(&gray);
+    let dark = gray.pixels().filter(|p| p[0] <= threshold).count();
+    let glyphs_dark = dark * 2 <= gray.len();
+    let is_glyph = |v: u8| if glyphs_dark { v <= threshold } else { v > threshold };
+
+    let rgba = crop.to_rgba8();
+    let mut sums = [[0u64; 3]; 2];
+    let mut counts = [0u64; 2];
+    let mut mask = GrayImage::new(gray.width(), gray.height());
+    for ((x, y, g), p) in gray.enumerate_pixels().zip(rgba.pixels()) {
+        let glyph = p[3] >= MIN_ALPHA && is_glyph(g[0]);
+        mask.put_pixel(x, y, Luma([if glyph { 0 } else { 255 }]));
+        let side = usize::from(glyph);
+        for c in 0..3 {
+            sums[side][c] += p[c] as u64;
+        }
+        counts[side] += 1;
+    }
+    let average = |side: usize| {
+        let [r, g, b] = sums[side].map(|s| (s / counts[side].max(1)) as u8);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    };
+    Glyphs { mask, background: average(0), color: average(1) }
+}
+
+/// Traces the text of `crop`: a rectangle of its background color with the
+/// glyphs filled in their average color.
+fn trace_text(crop: &DynamicImage, output_path: &Path) -> Result<Option<String>> {
+    let Glyphs { mask, background, color } = split_glyphs(crop);
+    let bmp_path = temp::path_beside(output_path, "bmp")?;
+    mask.save(&bmp_path).map_err(|e| AlphasvgError::encode(&bmp_path, e))?;
+    let svg_path = temp::path_beside(output_path, "svg")?;
+    let status = tracing::info_span!("potrace").in_scope(|| Command::new("potrace")
+        .args([
+            bmp_path.to_str().unwrap(),
+            "-s",
+            "-o",
+            svg_path.to_str().unwrap(),
+            "--flat",
+            "--turdsize", "0",
+            "--alphamax", "0.6",
+            "--opttolerance", "0.1",
+        ])
+        .status())?;
+    if !status.success() {
+        return Ok(None);
+    }
+
+    let mut content = format!("<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", crop.width(), crop.height(), background).into_bytes();
+    svg::copy_potrace_body(&svg_path, PotracePaint::Fill(&color), &mut content)?;
+    Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+}
+
+/// Luminance with transparent pixels composited onto white.
+pub(crate) fn flatten_gray(img: &DynamicImage) -> GrayImage {
+    let rgba = img.to_rgba8();
+    GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let p = rgba.get_pixel(x, y);
+        let a = p[3] as f32 / 255.0;
+        let luma = 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32;
+        Luma([(luma * a + 255.0 * (1.0 - a)).round() as u8])
+    })
+}
+
+/// Connected components of the pixels where `inside` holds.
+fn components(gray: &GrayImage, inside: impl Fn(u8) -> bool) -> Vec<Component> {
+    let binary = GrayImage::from_fn(gray.width(), gray.height(), |x, y| Luma([u8::from(inside(gray.get_pixel(x, y)[0]))]));
+    let labels = connected_components(&binary, Connectivity::Eight, Luma([0u8]));
+    let mut found: Vec<Option<Component>> = Vec::new();
+    for (x, y, label) in labels.enumerate_pixels() {
+        let Some(index) = (label[0] as usize).checked_sub(1) else { continue };
+        if index >= found.len() {
+            found.resize(index + 1, None);
+        }
+        let c = found[index].get_or_insert(Component { x0: x, y0: y, x1: x, y1: y, pixels: 0 });
+        (c.x0, c.y0, c.x1, c.y1) = (c.x0.min(x), c.y0.min(y), c.x1.max(x), c.y1.max(y));
+        c.pixels += 1;
+    }
+    found.into_iter().flatten().collect()
+}
@@ -1,17 +1,18 @@
 use image::{DynamicImage, imageops::FilterType, GenericImageView};
 use std::path::Path;
 use anyhow::Result;
-use crate::config;
 use crate::lang::LanguageManager;
-use crate::generators::LogOutput;
+use crate::generators::{LogOutput, encode};
 
-pub fn generate_thumbnail(img: &DynamicImage, output_path: &Path, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+/// Writes `img` scaled to `thumb_width`, in the format of the output extension.
+#[tracing::instrument(skip_all)]
+pub fn generate_thumbnail(img: &DynamicImage, output_path: &Path, thumb_width: u32, quality: u8, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
     let (width, height) = img.dimensions();
     let aspect_ratio = height as f32 / width as f32;
-    let new_height = (config::THUMB_WIDTH as f32 * aspect_ratio) as u32;
+    let new_height = (thumb_width as f32 * aspect_ratio) as u32;
     
-    let thumb = img.resize(config::THUMB_WIDTH, new_height, FilterType::Lanczos3);
-    thumb.save(output_path)?;
-    logger.send(format!("{}{:?}", lang.t("log_thumb_ok"), output_path.file_name().unwrap()));
+    let thumb = img.resize(thumb_width, new_height, FilterType::Lanczos3);
+    encode::save(&thumb, output_path, quality)?;
+    logger.send(lang.t_args("log_thumb_ok", &[lang.format_file_name(output_path)]));
     Ok(())
 }
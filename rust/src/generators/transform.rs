@@ -0,0 +1,188 @@
+//! Geometric corrections of the input: quarter-turn rotation, flips and
+//! arbitrary-angle straightening.
+//!
+//! They run as part of [`crate::generators::preprocess`], so every output and
+//! the region of interest refer to the transformed image. Straightening
+//! expands the canvas to fit the rotated image; the new corners are transparent
+//! and stay so in the cut-out.
+
+use image::{DynamicImage, Rgba};
+use imageproc::geometric_transformations::{Interpolation, Projection, warp_into};
+use serde::{Deserialize, Serialize};
+
+use crate::lang::LanguageManager;
+
+/// Straightening range offered by the GUI and accepted by the CLI, in degrees.
+pub const STRAIGHTEN_RANGE: std::ops::RangeInclusive<f32> = -45.0..=45.0;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    /// Clockwise rotation in quarter turns (0 to 3).
+    pub quarter_turns: u8,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Clockwise straightening in degrees, applied after the rotation and flips.
+    pub straighten: f32,
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        self.quarter_turns.is_multiple_of(4) && !self.flip_horizontal && !self.flip_vertical && self.straighten == 0.0
+    }
+
+    /// Rotates a further quarter turn clockwise, or counter-clockwise when `clockwise` is false.
+    pub fn turn(&mut self, clockwise: bool) {
+        self.quarter_turns = (self.quarter_turns + if clockwise { 1 } else { 3 }) % 4;
+    }
+
+    /// Applies the rotation, the flips and then the straightening.
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        if self.is_identity() {
+            return img;
+        }
+        let img = match self.quarter_turns % 4 {
+            1 => img.rotate90(),
+            2 => img.rotate180(),
+            3 => img.rotate270(),
+            _ => img,
+        };
+        let img = if self.flip_horizontal { img.fliph() } else { img };
+        let img = if self.flip_vertical { img.flipv() } else { img };
+        if self.straighten == 0.0 {
+            img
+        } else {
+            straighten(img, self.straighten)
+        }
+    }
+
+    /// Size of a `width`×`height` image after the transform.
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = if self.quarter_turns % 2 == 1 { (height, width) } else { (width, height) };
+        if self.straighten == 0.0 {
+            return (width, height);
+        }
+        straightened_size(width, height, self.straighten)
+    }
+
+    /// Point of a `width`×`height` input shown at `(x, y)` in the transformed
+    /// image, or `None` in the corners straightening adds.
+    pub fn source_point(&self, x: f32, y: f32, width: u32, height: u32) -> Option<(f32, f32)> {
+        let (w, h) = (width as f32, height as f32);
+        let (turned_width, turned_height) = if self.quarter_turns % 2 == 1 { (height, width) } else { (width, height) };
+        let (tw, th) = (turned_width as f32, turned_height as f32);
+        let (mut x, mut y) = (x, y);
+        if self.straighten != 0.0 {
+            // Rotate back around the center of the expanded canvas
+            let (out_width, out_height) = straightened_size(turned_width, turned_height, self.straighten);
+            let (sin, cos) = self.straighten.to_radians().sin_cos();
+            let (dx, dy) = (x - out_width as f32 / 2.0, y - out_height as f32 / 2.0);
+            (x, y) = (cos * dx + sin * dy + tw / 2.0, cos * dy - sin * dx + th / 2.0);
+        }
+        if !(0.0..tw).contains(&x) || !(0.0..th).contains(&y) {
+            return None;
+        }
+        if self.flip_vertical {
+            y = th - y;
+        }
+        if self.flip_horizontal {
+            x = tw - x;
+        }
+        Some(match self.quarter_turns % 4 {
+            1 => (y, h - x),
+            2 => (w - x, h - y),
+            3 => (w - y, x),
+            _ => (x, y),
+        })
+    }
+
+    /// Short description for the logs, e.g. `rotated 90°, flipped horizontally, straightened +2.5°`.
+    pub fn describe(&self, lang: &LanguageManager) -> String {
+        let mut parts = Vec::new();
+        if !self.quarter_turns.is_multiple_of(4) {
+            parts.push(lang.t_args("transform_rotate", &[u32::from(self.quarter_turns % 4) * 90]));
+        }
+        if self.flip_horizontal {
+            parts.push(lang.t("transform_flip_h"));
+        }
+        if self.flip_vertical {
+            parts.push(lang.t("transform_flip_v"));
+        }
+        if self.straighten != 0.0 {
+            let sign = if self.straighten > 0.0 { "+" } else { "" };
+            parts.push(lang.t_args("transform_straighten", &[format!("{}{}", sign, lang.format_number(self.straighten as f64, 1))]));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Parses a `--rotate` angle (0, 90, 180 or 270) into quarter turns.
+pub fn parse_rotation(value: &str) -> Result<u8, String> {
+    match value.trim().trim_end_matches('°') {
+        "0" => Ok(0),
+        "90" => Ok(1),
+        "180" => Ok(2),
+        "270" | "-90" => Ok(3),
+        _ => Err(format!("rotation must be 0, 90, 180 or 270 degrees: {}", value)),
+    }
+}
+
+/// Parses a straightening angle in degrees within [`STRAIGHTEN_RANGE`].
+pub fn parse_straighten(value: &str) -> Result<f32, String> {
+    let degrees: f32 = value.parse().map_err(|_| format!("invalid angle: {}", value))?;
+    if STRAIGHTEN_RANGE.contains(&degrees) {
+        Ok(degrees)
+    } else {
+        Err(format!("straightening must be between {} and {} degrees", STRAIGHTEN_RANGE.start(), STRAIGHTEN_RANGE.end()))
+    }
+}
+
+/// Rotates `img` clockwise by `degrees` onto a canvas large enough to hold it.
+fn straighten(img: DynamicImage, degrees: f32) -> DynamicImage {
+    let rgba = img.into_rgba8();
+    let (out_width, out_height) = straightened_size(rgba.width(), rgba.height(), degrees);
+    let (width, height) = (rgba.width() as f32, rgba.height() as f32);
+    let projection = Projection::translate(-width / 2.0, -height / 2.0)
+        .and_then(Projection::rotate(degrees.to_radians()))
+        .and_then(Projection::translate(out_width as f32 / 2.0, out_height as f32 / 2.0));
+    let mut out = image::RgbaImage::new(out_width, out_height);
+    warp_into(&rgba, &projection, Interpolation::Bilinear, Rgba([0, 0, 0, 0]), &mut out);
+    DynamicImage::ImageRgba8(out)
+}
+
+/// Bounding box of a `width`×`height` image rotated by `degrees`.
+fn straightened_size(width: u32, height: u32, degrees: f32) -> (u32, u32) {
+    let theta = degrees.to_radians();
+    let (sin, cos) = (theta.sin().abs(), theta.cos().abs());
+    let (width, height) = (width as f32, height as f32);
+    (((width * cos + height * sin).ceil() as u32).max(1), ((width * sin + height * cos).ceil() as u32).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_points_follow_turns_and_flips() {
+        let input = image::RgbaImage::from_fn(3, 2, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        for quarter_turns in 0..4 {
+            for (flip_horizontal, flip_vertical) in [(false, false), (true, false), (false, true), (true, true)] {
+                let transform = Transform { quarter_turns, flip_horizontal, flip_vertical, straighten: 0.0 };
+                let output = transform.apply(DynamicImage::ImageRgba8(input.clone())).into_rgba8();
+                for (x, y, pixel) in output.enumerate_pixels() {
+                    let (sx, sy) = transform.source_point(x as f32 + 0.5, y as f32 + 0.5, 3, 2).unwrap();
+                    assert_eq!(input.get_pixel(sx as u32, sy as u32), pixel, "{:?} at {},{}", transform, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn straightened_corners_have_no_source() {
+        let transform = Transform { straighten: 30.0, ..Default::default() };
+        let (width, height) = transform.output_size(100, 50);
+        assert!(transform.source_point(0.5, 0.5, 100, 50).is_none());
+        let (sx, sy) = transform.source_point(width as f32 / 2.0, height as f32 / 2.0, 100, 50).unwrap();
+        assert!((sx - 50.0).abs() < 0.01 && (sy - 25.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,125 @@
+//! Super-resolution before vectorization.
+//!
+//! Small source logos trace into lumpy paths. The Real-ESRGAN model from the
+//! registry upscales the color channels ×4 in fixed-size tiles; ×2 is obtained
+//! by downsampling that result. Alpha is resized with Lanczos and re-applied.
+//! The result is kept within the working resolution of the tracers, which would
+//! only downscale anything larger again.
+
+use anyhow::{Result, anyhow};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage, imageops::{self, FilterType}};
+use ort::{inputs, session::Session, value::TensorRef};
+
+use crate::generators::{LogOutput, ai, tensor};
+use crate::generators::models::{ModelConfig, get_upscaler_config};
+use crate::lang::LanguageManager;
+
+/// Native scale of the model.
+const MODEL_SCALE: u32 = 4;
+/// Context pixels around each tile, discarded from the output to hide seams.
+const TILE_OVERLAP: u32 = 8;
+
+/// Parses the `--upscale` factor (2 or 4, optionally written `2x`/`4x`).
+pub fn parse_factor(value: &str) -> Result<u32, String> {
+    match value.trim_end_matches(['x', 'X']) {
+        "2" => Ok(2),
+        "4" => Ok(4),
+        _ => Err(format!("upscale factor must be 2 or 4: {}", value)),
+    }
+}
+
+/// Upscales `img` by `factor` (2 or 4) with the super-resolution model.
+///
+/// ×2 is used instead of ×4 when ×4 would take the long edge over `max_px`,
+/// and `None` is returned, without running the model, when ×2 would as well.
+#[tracing::instrument(skip_all)]
+pub fn upscale(img: &DynamicImage, factor: u32, max_px: Option<u32>, lang: &LanguageManager, logger: &LogOutput) -> Result<Option<DynamicImage>> {
+    let long_edge = img.width().max(img.height());
+    let fits = |f: u32| max_px.is_none_or(|max| long_edge * f <= max);
+    let requested = factor;
+    let Some(factor) = [requested, 2].into_iter().filter(|f| *f <= requested).find(|f| fits(*f)) else {
+        logger.send(lang.t_args("log_upscale_skipped", &[requested, max_px.unwrap_or_default()]));
+        return Ok(None);
+    };
+    if factor != requested {
+        logger.send(lang.t_args("log_upscale_lowered", &[factor, requested, max_px.unwrap_or_default()]));
+    }
+
+    let config = get_upscaler_config();
+    let model_path = ai::prepare_model(lang, logger, &config)?;
+    logger.send(lang.t_args("log_upscaling", &[factor.to_string(), config.name.clone()]));
+
+    let rgba = img.to_rgba8();
+    let upscaled = ai::with_session(lang, logger, &config, &model_path, |session| {
+        upscale_tiles(&rgba, &config, session)
+    })?;
+
+    let (width, height) = (rgba.width() * factor, rgba.height() * factor);
+    let mut out = if factor == MODEL_SCALE {
+        upscaled
+    } else {
+        imageops::resize(&upscaled, width, height, FilterType::Lanczos3)
+    };
+
+    let alpha = imageops::resize(&rgba, width, height, FilterType::Lanczos3);
+    for (px, a) in out.pixels_mut().zip(alpha.pixels()) {
+        px[3] = a[3];
+    }
+    Ok(Some(DynamicImage::ImageRgba8(out)))
+}
+
+/// Runs the model over overlapping tiles and stitches the ×4 result.
+fn upscale_tiles(img: &RgbaImage, config: &ModelConfig, session: &mut Session) -> Result<RgbaImage> {
+    let tile = config.resolution;
+    let (width, height) = img.dimensions();
+    let padded = pad_to(img, tile);
+    let (pw, ph) = padded.dimensions();
+    let step = tile - 2 * TILE_OVERLAP;
+
+    let input_name = session.inputs()[0].name().to_string();
+    let output_name = session.outputs()[0].name().to_string();
+    let mut out = RgbaImage::new(width * MODEL_SCALE, height * MODEL_SCALE);
+    let mut input = Vec::new();
+
+    for ty in (0..height).step_by(step as usize) {
+        for tx in (0..width).step_by(step as usize) {
+            // Window around the tile, shifted inwards at the borders
+            let sx = tx.saturating_sub(TILE_OVERLAP).min(pw - tile);
+            let sy = ty.saturating_sub(TILE_OVERLAP).min(ph - tile);
+            let window = DynamicImage::ImageRgba8(padded.view(sx, sy, tile, tile).to_image());
+            tensor::fill_planar_tensor(&window, &config.normalization, &mut input);
+
+            let shape = vec![1, 3, tile as usize, tile as usize];
+            let tensor = TensorRef::from_array_view((shape, input.as_slice()))?;
+            let outputs = session.run(inputs![input_name.as_str() => tensor])?;
+            let (_, data) = outputs[output_name.as_str()].try_extract_tensor::<f32>()?;
+
+            let size = tile * MODEL_SCALE;
+            let plane = (size * size) as usize;
+            if data.len() < plane * 3 {
+                return Err(anyhow!("Upscaler output is smaller than expected"));
+            }
+
+            let (cw, ch) = (step.min(width - tx), step.min(height - ty));
+            for y in 0..ch * MODEL_SCALE {
+                for x in 0..cw * MODEL_SCALE {
+                    let src = ((ty - sy) * MODEL_SCALE + y) * size + (tx - sx) * MODEL_SCALE + x;
+                    let [r, g, b] = [0, 1, 2].map(|c| (data[c * plane + src as usize] * 255.0).round().clamp(0.0, 255.0) as u8);
+                    out.put_pixel(tx * MODEL_SCALE + x, ty * MODEL_SCALE + y, Rgba([r, g, b, 255]));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Extends `img` by repeating its edges so both sides are at least `min_size`.
+fn pad_to(img: &RgbaImage, min_size: u32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    if width >= min_size && height >= min_size {
+        return img.clone();
+    }
+    RgbaImage::from_fn(width.max(min_size), height.max(min_size), |x, y| {
+        *img.get_pixel(x.min(width - 1), y.min(height - 1))
+    })
+}
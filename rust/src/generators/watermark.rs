@@ -0,0 +1,167 @@
+//! Watermark stamping for raster outputs.
+//!
+//! Agencies deliver protected previews of batch results by stamping a text or
+//! logo mark on the PNG/JPEG outputs. The mark is configured in the settings
+//! file or in a job JSON file (see [`WatermarkOptions`]). The alpha PNG is
+//! stamped in memory as it is written, so the cut-out the other outputs are
+//! made from stays clean; the other raster outputs are stamped once written.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use ab_glyph::{FontVec, PxScale};
+use anyhow::{Result, Context, anyhow};
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage, imageops::{self, FilterType}};
+use imageproc::drawing::{draw_text_mut, text_size};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AlphasvgError;
+
+/// Font size used to measure text before scaling it to the requested width.
+const MEASURE_SIZE: f32 = 64.0;
+
+/// Where the mark is placed when not tiled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkOptions {
+    /// Text to stamp; ignored when `image` is set.
+    pub text: Option<String>,
+    /// Logo image to stamp (PNG with transparency works best).
+    pub image: Option<PathBuf>,
+    /// Text color.
+    pub color: [u8; 3],
+    pub anchor: Anchor,
+    /// Opacity of the mark, 0..1.
+    pub opacity: f32,
+    /// Distance from the edges (and between tiles), in pixels.
+    pub margin: u32,
+    /// Width of the mark as a fraction of the output width.
+    pub scale: f32,
+    /// Repeat the mark over the whole image instead of anchoring it.
+    pub tiled: bool,
+}
+
+impl Default for WatermarkOptions {
+    fn default() -> Self {
+        Self {
+            text: None,
+            image: None,
+            color: [255, 255, 255],
+            anchor: Anchor::default(),
+            opacity: 0.5,
+            margin: 16,
+            scale: 0.25,
+            tiled: false,
+        }
+    }
+}
+
+impl WatermarkOptions {
+    /// Loads the options from a JSON job file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read watermark file {:?}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid watermark file {:?}", path))
+    }
+}
+
+/// Whether `path` is a raster output the watermark applies to.
+pub fn is_stampable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ["png", "jpg", "jpeg", "webp"].contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Stamps the watermark on the image file at `path`, overwriting it.
+#[tracing::instrument(skip_all)]
+pub fn stamp_file(path: &Path, opts: &WatermarkOptions) -> Result<()> {
+    let img = image::open(path).map_err(|e| AlphasvgError::decode(path, e))?;
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.into_rgba8();
+    stamp(&mut rgba, opts)?;
+    if has_alpha {
+        rgba.save(path).map_err(|e| AlphasvgError::encode(path, e))?;
+    } else {
+        DynamicImage::ImageRgba8(rgba).to_rgb8().save(path).map_err(|e| AlphasvgError::encode(path, e))?;
+    }
+    Ok(())
+}
+
+/// Stamps the watermark on `img`.
+pub fn stamp(img: &mut RgbaImage, opts: &WatermarkOptions) -> Result<()> {
+    let target_width = ((img.width() as f32 * opts.scale.clamp(0.01, 1.0)) as u32).max(1);
+    let mut mark = render_mark(opts, target_width)?;
+    let opacity = opts.opacity.clamp(0.0, 1.0);
+    mark.pixels_mut().for_each(|p| p[3] = (p[3] as f32 * opacity).round() as u8);
+    let (mw, mh) = (mark.width() as i64, mark.height() as i64);
+    let (w, h) = (img.width() as i64, img.height() as i64);
+    let margin = opts.margin as i64;
+
+    if opts.tiled {
+        let (step_x, step_y) = (mw + margin.max(1), mh + margin.max(1));
+        let mut y = margin;
+        while y < h {
+            // Offset every other row for a brick pattern
+            let mut x = margin - if (y / step_y) % 2 == 1 { step_x / 2 } else { 0 };
+            while x < w {
+                imageops::overlay(img, &mark, x, y);
+                x += step_x;
+            }
+            y += step_y;
+        }
+    } else {
+        let x = match opts.anchor {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => margin,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => (w - mw) / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => w - mw - margin,
+        };
+        let y = match opts.anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => margin,
+            Anchor::Left | Anchor::Center | Anchor::Right => (h - mh) / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => h - mh - margin,
+        };
+        imageops::overlay(img, &mark, x, y);
+    }
+    Ok(())
+}
+
+/// Renders the logo or text mark at `width` pixels wide.
+fn render_mark(opts: &WatermarkOptions, width: u32) -> Result<RgbaImage> {
+    if let Some(path) = &opts.image {
+        let logo = image::open(path).with_context(|| format!("Failed to open watermark image {:?}", path))?;
+        let height = ((logo.height() as f32 * width as f32 / logo.width().max(1) as f32) as u32).max(1);
+        return Ok(logo.resize_exact(width, height, FilterType::Lanczos3).into_rgba8());
+    }
+
+    let text = opts.text.as_deref().filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| anyhow!("Watermark needs either a text or an image"))?;
+    let font = default_font()?;
+    let (measured, _) = text_size(PxScale::from(MEASURE_SIZE), &font, text);
+    let size = MEASURE_SIZE * width as f32 / measured.max(1) as f32;
+    let (tw, th) = text_size(PxScale::from(size), &font, text);
+
+    let mut coverage = GrayImage::new(tw.max(1), th.max(1));
+    draw_text_mut(&mut coverage, Luma([255]), 0, 0, PxScale::from(size), &font, text);
+    let [r, g, b] = opts.color;
+    Ok(RgbaImage::from_fn(coverage.width(), coverage.height(), |x, y| Rgba([r, g, b, coverage.get_pixel(x, y)[0]])))
+}
+
+/// The proportional font bundled with the GUI.
+fn default_font() -> Result<FontVec> {
+    let fonts = eframe::egui::FontDefinitions::default();
+    let data = fonts.font_data.get("Ubuntu-Light").context("Bundled font not found")?;
+    FontVec::try_from_vec(data.font.to_vec()).map_err(|e| anyhow!("Invalid bundled font: {}", e))
+}
@@ -0,0 +1,135 @@
+//! Backdrop drawn behind the previews, so transparency defects can be checked
+//! against the background they will end up on.
+
+use eframe::egui;
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::lang::LanguageManager;
+
+/// Side of a checkerboard square, in points.
+const CHECKER_POINTS: f32 = 8.0;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backdrop {
+    #[default]
+    Checkerboard,
+    Black,
+    White,
+    Color([u8; 3]),
+    /// Picture scaled to cover the preview.
+    Image(PathBuf),
+}
+
+impl Backdrop {
+    fn label_key(&self) -> &'static str {
+        match self {
+            Self::Checkerboard => "backdrop_checkerboard",
+            Self::Black => "backdrop_black",
+            Self::White => "backdrop_white",
+            Self::Color(_) => "backdrop_color",
+            Self::Image(_) => "backdrop_image",
+        }
+    }
+}
+
+/// Backdrop choice: a combo box, plus a color button or file picker for the
+/// custom kinds.
+pub fn selector(ui: &mut egui::Ui, lang: &LanguageManager, backdrop: &mut Backdrop) {
+    egui::ComboBox::from_id_salt("preview_backdrop")
+        .selected_text(lang.t(backdrop.label_key()))
+        .show_ui(ui, |ui| {
+            for choice in [Backdrop::Checkerboard, Backdrop::Black, Backdrop::White] {
+                let label = lang.t(choice.label_key());
+                ui.selectable_value(backdrop, choice, label);
+            }
+            if ui.selectable_label(matches!(backdrop, Backdrop::Color(_)), lang.t("backdrop_color")).clicked()
+                && !matches!(backdrop, Backdrop::Color(_))
+            {
+                *backdrop = Backdrop::Color([128, 128, 128]);
+            }
+            if ui.selectable_label(matches!(backdrop, Backdrop::Image(_)), lang.t("backdrop_image")).clicked()
+                && let Some(path) = FileDialog::new().add_filter("Images", &["png", "jpg", "jpeg", "webp"]).pick_file()
+            {
+                *backdrop = Backdrop::Image(path);
+            }
+        })
+        .response
+        .on_hover_text(lang.t("backdrop_label"));
+    if let Backdrop::Color(color) = backdrop {
+        ui.color_edit_button_srgb(color);
+    }
+}
+
+/// Draws backdrops, keeping the textures they need.
+#[derive(Default)]
+pub struct BackdropPainter {
+    checker: Option<egui::TextureHandle>,
+    /// Loaded backdrop picture; None inside when it could not be read.
+    image: Option<(PathBuf, Option<egui::TextureHandle>)>,
+}
+
+impl BackdropPainter {
+    /// Fills `rect` with `backdrop`, clipped by `painter`.
+    pub fn paint(&mut self, painter: &egui::Painter, rect: egui::Rect, backdrop: &Backdrop) {
+        match backdrop {
+            Backdrop::Checkerboard => self.paint_checker(painter, rect),
+            Backdrop::Black => {
+                painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+            }
+            Backdrop::White => {
+                painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+            }
+            Backdrop::Color([r, g, b]) => {
+                painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(*r, *g, *b));
+            }
+            Backdrop::Image(path) => {
+                if self.image.as_ref().is_none_or(|(loaded, _)| loaded != path) {
+                    self.image = Some((path.clone(), load_texture(painter.ctx(), path)));
+                }
+                match self.image.as_ref().and_then(|(_, texture)| texture.clone()) {
+                    Some(texture) => {
+                        painter.image(texture.id(), rect, cover_uv(texture.size_vec2(), rect.size()), egui::Color32::WHITE);
+                    }
+                    // Unreadable picture: fall back to the checkerboard
+                    None => self.paint_checker(painter, rect),
+                }
+            }
+        }
+    }
+
+    fn paint_checker(&mut self, painter: &egui::Painter, rect: egui::Rect) {
+        let checker = self.checker.get_or_insert_with(|| checker_texture(painter.ctx()));
+        // The 2×2 texture repeats, one texel per square
+        let uv = egui::Rect::from_min_size(egui::Pos2::ZERO, rect.size() / (2.0 * CHECKER_POINTS));
+        painter.image(checker.id(), rect, uv, egui::Color32::WHITE);
+    }
+}
+
+fn checker_texture(ctx: &egui::Context) -> egui::TextureHandle {
+    let (light, dark) = (egui::Color32::from_gray(204), egui::Color32::from_gray(153));
+    let image = egui::ColorImage::new([2, 2], vec![light, dark, dark, light]);
+    let options = egui::TextureOptions { wrap_mode: egui::TextureWrapMode::Repeat, ..egui::TextureOptions::NEAREST };
+    ctx.load_texture("backdrop_checker", image, options)
+}
+
+/// Loads the picture at `path`, scaled down to fit the largest texture the GPU takes.
+fn load_texture(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    let mut img = image::open(path).ok()?;
+    let max_side = ctx.input(|i| i.max_texture_side) as u32;
+    if img.width().max(img.height()) > max_side {
+        img = img.resize(max_side, max_side, image::imageops::FilterType::Triangle);
+    }
+    let img = img.into_rgba8();
+    let size = [img.width() as usize, img.height() as usize];
+    Some(ctx.load_texture("backdrop_image", egui::ColorImage::from_rgba_unmultiplied(size, img.as_raw()), egui::TextureOptions::LINEAR))
+}
+
+/// Texture coordinates cropping a picture of `image` size to the aspect ratio of `target`.
+fn cover_uv(image: egui::Vec2, target: egui::Vec2) -> egui::Rect {
+    let scale = (target.x / image.x).max(target.y / image.y);
+    let visible = target / scale / image;
+    egui::Rect::from_center_size(egui::pos2(0.5, 0.5), visible)
+}
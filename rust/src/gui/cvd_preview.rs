@@ -0,0 +1,141 @@
+//! Color vision deficiency preview window.
+
+use eframe::egui;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+use crate::generators::colorblind::Deficiency;
+use crate::generators::rasterize;
+use crate::lang::LanguageManager;
+use super::a11y;
+use super::backdrop::{Backdrop, BackdropPainter};
+use super::panels;
+use super::pyramid::{LevelTextures, Pyramid};
+
+/// Longest edge of the previewed images, in points.
+const PREVIEW_POINTS: f32 = 480.0;
+/// Longest edge SVG outputs are rendered at.
+const SVG_PX: f32 = 2048.0;
+
+/// Loaded file with the textures drawn so far.
+struct Rendered {
+    path: PathBuf,
+    pyramid: Pyramid,
+    original: LevelTextures,
+    /// Deficiency the simulated textures were rendered with.
+    deficiency: Deficiency,
+    simulated: LevelTextures,
+}
+
+/// State of the preview window.
+pub struct CvdPreview {
+    selected: Option<PathBuf>,
+    deficiency: Deficiency,
+    rendered: Option<Rendered>,
+    error: Option<String>,
+    backdrop_painter: BackdropPainter,
+}
+
+impl Default for CvdPreview {
+    fn default() -> Self {
+        Self { selected: None, deficiency: Deficiency::Deuteranopia, rendered: None, error: None, backdrop_painter: BackdropPainter::default() }
+    }
+}
+
+impl CvdPreview {
+    /// Shows the window while `open` is true, previewing one of `files` over `backdrop`.
+    pub fn show(&mut self, ctx: &egui::Context, lang: &LanguageManager, open: &mut bool, files: &[PathBuf], backdrop: &Backdrop) {
+        if self.selected.as_ref().is_none_or(|p| !files.contains(p)) {
+            self.selected = files.first().cloned();
+        }
+
+        egui::Window::new(lang.t("cvd_title"))
+            .open(open)
+            .default_size([1000.0, 560.0])
+            .show(ctx, |ui| {
+                ui.with_layout(panels::column_layout(lang), |ui| {
+                    if files.is_empty() {
+                        ui.label(lang.t("cvd_no_files"));
+                        return;
+                    }
+
+                    panels::row(ui, lang, |ui| {
+                        let name = |p: &Path| p.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                        let combo = egui::ComboBox::from_id_salt("cvd_file")
+                            .selected_text(self.selected.as_deref().map(name).unwrap_or_default())
+                            .show_ui(ui, |ui| {
+                                for file in files {
+                                    ui.selectable_value(&mut self.selected, Some(file.clone()), name(file));
+                                }
+                            });
+                        a11y::describe(&combo.response, lang.t("cvd_file"), egui::WidgetInfo::new(egui::WidgetType::ComboBox));
+                        for deficiency in Deficiency::ALL {
+                            ui.radio_value(&mut self.deficiency, deficiency, lang.t(deficiency.label_key()));
+                        }
+                    });
+                    ui.separator();
+
+                    let Some(path) = self.selected.clone() else { return };
+                    if let Some(rendered) = &mut self.rendered
+                        && rendered.deficiency != self.deficiency
+                    {
+                        rendered.deficiency = self.deficiency;
+                        rendered.simulated.clear();
+                    }
+                    if self.rendered.as_ref().is_none_or(|r| r.path != path) {
+                        match load(&path, self.deficiency) {
+                            Ok(rendered) => {
+                                self.rendered = Some(rendered);
+                                self.error = None;
+                            }
+                            Err(e) => {
+                                self.rendered = None;
+                                self.error = Some(format!("Error: {}", e));
+                            }
+                        }
+                    }
+
+                    if let Some(error) = &self.error {
+                        ui.label(egui::RichText::new(error).italics());
+                    }
+                    if let Some(rendered) = &mut self.rendered {
+                        let level = rendered.pyramid.level_for_view(ctx, PREVIEW_POINTS);
+                        let image = rendered.pyramid.level(level);
+                        let original = rendered.original.get(ctx, level, || image.clone()).clone();
+                        let simulated = rendered.simulated.get(ctx, level, || rendered.deficiency.simulate(image)).clone();
+                        ui.with_layout(panels::row_layout(lang).with_cross_align(egui::Align::Min), |ui| {
+                            for (label, texture) in [(lang.t("cvd_original"), original), (lang.t(self.deficiency.label_key()), simulated)] {
+                                ui.vertical(|ui| {
+                                    ui.label(egui::RichText::new(label).strong());
+                                    let size = texture.size_vec2();
+                                    let (rect, _) = ui.allocate_exact_size(size * (PREVIEW_POINTS / size.max_elem()), egui::Sense::hover());
+                                    self.backdrop_painter.paint(ui.painter(), rect, backdrop);
+                                    egui::Image::new(&texture).paint_at(ui, rect);
+                                });
+                            }
+                        });
+                    }
+                });
+            });
+    }
+}
+
+fn load(path: &Path, deficiency: Deficiency) -> Result<Rendered> {
+    Ok(Rendered {
+        path: path.to_path_buf(),
+        pyramid: Pyramid::new(load_image(path)?),
+        original: LevelTextures::new("cvd_original"),
+        deficiency,
+        simulated: LevelTextures::new("cvd_simulated"),
+    })
+}
+
+/// Loads a raster output, or renders an SVG output.
+pub(super) fn load_image(path: &Path) -> Result<RgbaImage> {
+    if path.extension().is_some_and(|e| e == "svg") {
+        let pixmap = rasterize::render_svg(path, |size| SVG_PX / size.width().max(size.height()))?;
+        return Ok(image::load_from_memory(&pixmap.encode_png()?)?.into_rgba8());
+    }
+    Ok(image::open(path)?.into_rgba8())
+}
@@ -0,0 +1,38 @@
+//! Native windows (egui viewports) the terminal log and the previews can be
+//! detached into, e.g. to put them on another monitor.
+
+use eframe::egui;
+
+/// Shows `content` in a native window titled `title`, or in a floating window
+/// inside the main one when the backend has a single viewport. Returns false
+/// once the user closes it, to put the content back in the main window.
+pub fn show(ctx: &egui::Context, id: &str, title: &str, size: [f32; 2], mut content: impl FnMut(&mut egui::Ui)) -> bool {
+    let mut open = true;
+    ctx.show_viewport_immediate(
+        egui::ViewportId::from_hash_of(id),
+        egui::ViewportBuilder::default().with_title(title).with_inner_size(size),
+        |ctx, class| {
+            if class == egui::ViewportClass::Embedded {
+                egui::Window::new(title)
+                    .id(egui::Id::new(id))
+                    .open(&mut open)
+                    .default_size(size)
+                    .show(ctx, |ui| content(ui));
+            } else {
+                egui::CentralPanel::default().show(ctx, |ui| content(ui));
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    open = false;
+                }
+            }
+        },
+    );
+    open
+}
+
+/// Button detaching a pane into its own window, or attaching it back.
+pub fn toggle(ui: &mut egui::Ui, detached: &mut bool, hint: String) {
+    let icon = if *detached { "⮌" } else { "⧉" };
+    if ui.small_button(icon).on_hover_text(hint).clicked() {
+        *detached = !*detached;
+    }
+}
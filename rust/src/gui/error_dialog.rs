@@ -0,0 +1,112 @@
+//! Modal dialog for failed jobs: the kind of failure, the file involved, the
+//! hint and the remedies the error suggests.
+//!
+//! Failures are collected while the queue goes on, and shown one after the
+//! other once it has finished or stopped, so one bad image does not hold up
+//! the rest of a batch.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use eframe::egui;
+
+use crate::error::Remedy;
+use crate::events::JobFailure;
+use crate::lang::LanguageManager;
+use super::history;
+use super::panels;
+
+/// What the user chose in the dialog.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DialogAction {
+    /// Run the failed job on this input again.
+    Retry(PathBuf),
+    /// Go on with the queue the failure stopped.
+    Skip,
+    /// Point the user to the model selector.
+    ChooseModel,
+    /// Close the dialog.
+    Dismiss,
+}
+
+/// The failures waiting to be shown, oldest first.
+#[derive(Default)]
+pub struct ErrorDialog {
+    failures: VecDeque<JobFailure>,
+    error: Option<String>,
+}
+
+impl ErrorDialog {
+    /// Adds a failure, shown after those before it.
+    pub fn push(&mut self, failure: JobFailure) {
+        self.failures.push_back(failure);
+    }
+
+    /// Shows the oldest failure unless `hold`, e.g. while the queue is still
+    /// running; `queued` is whether inputs wait in a stopped queue.
+    pub fn show(&mut self, ctx: &egui::Context, lang: &LanguageManager, hold: bool, queued: bool) -> Option<DialogAction> {
+        if hold {
+            return None;
+        }
+        let failure = self.failures.front()?;
+        let mut action = None;
+        let modal = egui::Modal::new(egui::Id::new("error_dialog")).show(ctx, |ui| {
+            ui.with_layout(panels::column_layout(lang), |ui| {
+                ui.set_max_width(460.0);
+                ui.heading(format!("⚠️ {}", failure.kind));
+                ui.label(lang.t_args("error_dialog_file", &[failure.path.display().to_string()]));
+                if let Some(hint) = &failure.hint {
+                    ui.add_space(6.0);
+                    ui.label(hint);
+                }
+                ui.add_space(6.0);
+                ui.collapsing(lang.t("error_dialog_details"), |ui| {
+                    ui.label(egui::RichText::new(&failure.message).monospace().size(12.0));
+                });
+                if let Some(error) = &self.error {
+                    ui.label(egui::RichText::new(error).italics());
+                }
+                if self.failures.len() > 1 {
+                    ui.label(egui::RichText::new(lang.t_args("error_dialog_more", &[self.failures.len() - 1])).weak());
+                }
+                ui.separator();
+                panels::row(ui, lang, |ui| {
+                    for remedy in &failure.remedies {
+                        let label = match remedy {
+                            Remedy::Retry => "error_dialog_retry",
+                            // Without a stopped queue there is nothing to go on with, and closing does the same
+                            Remedy::Skip if !queued => continue,
+                            Remedy::Skip => "error_dialog_skip",
+                            Remedy::OpenFolder => "error_dialog_open_folder",
+                            Remedy::ChooseModel => "error_dialog_choose_model",
+                        };
+                        if !ui.button(lang.t(label)).clicked() {
+                            continue;
+                        }
+                        match remedy {
+                            Remedy::Retry => action = Some(DialogAction::Retry(failure.input.clone())),
+                            Remedy::Skip => action = Some(DialogAction::Skip),
+                            Remedy::ChooseModel => action = Some(DialogAction::ChooseModel),
+                            Remedy::OpenFolder => {
+                                let folder = if failure.path.is_dir() { failure.path.as_path() } else { failure.path.parent().unwrap_or(&failure.path) };
+                                if let Err(e) = history::open_folder(folder) {
+                                    self.error = Some(lang.t_args("error_dialog_open_failed", &[format!("{:#}", e)]));
+                                }
+                            }
+                        }
+                    }
+                    if ui.button(lang.t("error_dialog_close")).clicked() {
+                        action = Some(DialogAction::Dismiss);
+                    }
+                });
+            });
+        });
+        if modal.should_close() && action.is_none() {
+            action = Some(DialogAction::Dismiss);
+        }
+        if action.is_some() {
+            self.failures.pop_front();
+            self.error = None;
+        }
+        action
+    }
+}
@@ -0,0 +1,121 @@
+//! UI fonts for scripts the bundled ones lack.
+//!
+//! egui bundles Ubuntu Light and Hack, which cover Latin, Greek and most of
+//! Cyrillic; Japanese, Chinese, Korean, Arabic, Hebrew, Devanagari or Thai text
+//! would show as empty boxes. For the current locale a font with its script is
+//! looked up among the system fonts and added as a fallback after the bundled
+//! ones. The files tried are the locale's `"fonts"` entry (comma-separated file
+//! names), or else those of [`script_fonts`] for its language. A font file picked
+//! in the preferences comes before all of them.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use eframe::egui;
+use walkdir::WalkDir;
+
+use crate::lang::LanguageManager;
+
+/// Name of the picked font in egui's font definitions.
+const CUSTOM_FONT: &str = "custom-ui-font";
+/// Name of the locale's fallback font in egui's font definitions.
+const SCRIPT_FONT: &str = "script-fallback";
+
+/// Installs the fonts for the current locale, with `custom` first if set.
+///
+/// A locale whose script font is not installed keeps the bundled fonts; an
+/// unreadable `custom` font is an error, and the other fonts are installed anyway.
+pub fn apply(ctx: &egui::Context, lang: &LanguageManager, custom: Option<&Path>) -> Result<()> {
+    let mut fonts = egui::FontDefinitions::default();
+
+    let mut names = lang.font_files();
+    if names.is_empty() {
+        names = script_fonts(&lang.current_lang()).iter().map(|s| s.to_string()).collect();
+    }
+    if let Some(path) = find_system_font(&names)
+        && let Ok(data) = std::fs::read(&path)
+    {
+        // Collections (.ttc) use their first face, which has the script's glyphs
+        fonts.font_data.insert(SCRIPT_FONT.into(), Arc::new(egui::FontData::from_owned(data)));
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts.families.entry(family).or_default().push(SCRIPT_FONT.into());
+        }
+    }
+
+    let result = match custom {
+        Some(path) => std::fs::read(path)
+            .with_context(|| format!("Could not read font {}", path.display()))
+            .map(|data| {
+                fonts.font_data.insert(CUSTOM_FONT.into(), Arc::new(egui::FontData::from_owned(data)));
+                fonts.families.entry(egui::FontFamily::Proportional).or_default().insert(0, CUSTOM_FONT.into());
+            }),
+        None => Ok(()),
+    };
+    ctx.set_fonts(fonts);
+    result
+}
+
+/// System font files with the script of `lang_code`, most common first.
+fn script_fonts(lang_code: &str) -> &'static [&'static str] {
+    let base = lang_code.split(['-', '_']).next().unwrap_or(lang_code);
+    match base {
+        "ja" => &["NotoSansCJK-Regular.ttc", "NotoSansCJKjp-Regular.otf", "NotoSansJP-Regular.otf",
+            "ヒラギノ角ゴシック W3.ttc", "YuGothR.ttc", "meiryo.ttc", "msgothic.ttc", "DroidSansFallbackFull.ttf"],
+        "zh" => &["NotoSansCJK-Regular.ttc", "NotoSansCJKsc-Regular.otf", "NotoSansSC-Regular.otf",
+            "wqy-microhei.ttc", "PingFang.ttc", "Hiragino Sans GB.ttc", "msyh.ttc", "simsun.ttc", "DroidSansFallbackFull.ttf"],
+        "ko" => &["NotoSansCJK-Regular.ttc", "NotoSansCJKkr-Regular.otf", "NotoSansKR-Regular.otf",
+            "NanumGothic.ttf", "AppleSDGothicNeo.ttc", "malgun.ttf"],
+        "ar" | "fa" | "ur" => &["NotoSansArabic-Regular.ttf", "NotoNaskhArabic-Regular.ttf", "DejaVuSans.ttf",
+            "GeezaPro.ttc", "segoeui.ttf", "arial.ttf"],
+        "he" | "yi" => &["NotoSansHebrew-Regular.ttf", "DejaVuSans.ttf", "ArialHB.ttc", "segoeui.ttf", "arial.ttf"],
+        "hi" | "mr" | "ne" => &["NotoSansDevanagari-Regular.ttf", "Lohit-Devanagari.ttf", "Kohinoor.ttc",
+            "DevanagariMT.ttc", "Nirmala.ttc", "Nirmala.ttf", "mangal.ttf"],
+        "th" => &["NotoSansThai-Regular.ttf", "Loma.ttf", "Thonburi.ttc", "LeelawUI.ttf", "tahoma.ttf"],
+        // Extended Cyrillic letters, such as Kazakh ә, beyond the bundled font
+        "ru" | "uk" | "be" | "bg" | "sr" | "mk" | "kk" | "ky" | "mn" => &["NotoSans-Regular.ttf", "DejaVuSans.ttf",
+            "Arial Unicode.ttf", "segoeui.ttf", "arial.ttf"],
+        _ => &[],
+    }
+}
+
+/// The first of `names` found in the system font directories.
+fn find_system_font(names: &[String]) -> Option<PathBuf> {
+    if names.is_empty() {
+        return None;
+    }
+    let found: Vec<PathBuf> = font_dirs().iter()
+        .flat_map(|dir| WalkDir::new(dir).max_depth(4).into_iter().flatten())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| names.iter().any(|name| entry.file_name().to_string_lossy().eq_ignore_ascii_case(name)))
+        .map(|entry| entry.into_path())
+        .collect();
+    names.iter().find_map(|name| {
+        found.iter().find(|path| path.file_name().is_some_and(|f| f.to_string_lossy().eq_ignore_ascii_case(name))).cloned()
+    })
+}
+
+/// Directories system and user fonts are installed in.
+fn font_dirs() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if cfg!(windows) {
+        let windir = std::env::var_os("WINDIR").map_or_else(|| PathBuf::from(r"C:\Windows"), PathBuf::from);
+        paths.push(windir.join("Fonts"));
+        if let Some(local) = dirs::data_local_dir() {
+            paths.push(local.join(r"Microsoft\Windows\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        paths.extend(["/System/Library/Fonts", "/Library/Fonts"].map(PathBuf::from));
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join("Library/Fonts"));
+        }
+    } else {
+        paths.extend(["/usr/share/fonts", "/usr/local/share/fonts"].map(PathBuf::from));
+        if let Some(data) = dirs::data_dir() {
+            paths.push(data.join("fonts"));
+        }
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join(".fonts"));
+        }
+    }
+    paths
+}
@@ -0,0 +1,127 @@
+//! History window listing previous runs.
+
+use eframe::egui;
+use std::path::Path;
+use std::process::Command;
+
+use crate::gui::processing::ProcessingConfig;
+use crate::history::{self, HistoryEntry};
+use crate::lang::LanguageManager;
+use super::panels;
+
+/// State of the history window.
+#[derive(Default)]
+pub struct HistoryWindow {
+    /// Loaded on first show and after each finished job.
+    entries: Option<Vec<HistoryEntry>>,
+    filter: String,
+    status: Option<String>,
+}
+
+impl HistoryWindow {
+    /// Reloads the entries the next time the window is shown.
+    pub fn refresh(&mut self) {
+        self.entries = None;
+    }
+
+    /// Shows the window while `open` is true. Returns the configuration to
+    /// re-run when its button was clicked; re-running is disabled while `busy`.
+    pub fn show(&mut self, ctx: &egui::Context, lang: &LanguageManager, open: &mut bool, busy: bool) -> Option<ProcessingConfig> {
+        let entries = self.entries.get_or_insert_with(history::load);
+        let mut rerun = None;
+
+        egui::Window::new(lang.t("history_title"))
+            .open(open)
+            .default_size([640.0, 480.0])
+            .show(ctx, |ui| {
+                ui.with_layout(panels::column_layout(lang), |ui| {
+                    panels::row(ui, lang, |ui| {
+                        let label = ui.label(lang.t("editor_filter"));
+                        ui.add(egui::TextEdit::singleline(&mut self.filter).desired_width(200.0)).labelled_by(label.id);
+                        if ui.button(lang.t("history_reload")).clicked() {
+                            *entries = history::load();
+                        }
+                    });
+                    if let Some(status) = &self.status {
+                        ui.label(egui::RichText::new(status).italics());
+                    }
+                    ui.separator();
+
+                    if entries.is_empty() {
+                        ui.label(lang.t("history_empty"));
+                        return;
+                    }
+
+                    let filter = self.filter.to_lowercase();
+                    egui::ScrollArea::vertical().id_salt("history_scroll").show(ui, |ui| {
+                        for (i, entry) in entries.iter().enumerate() {
+                            let input = entry.config.input.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                            if !filter.is_empty() && !input.to_lowercase().contains(&filter) {
+                                continue;
+                            }
+                            let mark = if entry.error.is_some() { "❌" } else { "✅" };
+                            egui::CollapsingHeader::new(format!("{} {}  {}", mark, entry.date(), input))
+                                .id_salt(("history_entry", i))
+                                .show(ui, |ui| {
+                                    render_entry(ui, lang, entry);
+                                    panels::row(ui, lang, |ui| {
+                                        if ui.button(lang.t("history_open_folder")).clicked()
+                                            && let Err(e) = open_folder(&entry.config.output)
+                                        {
+                                            self.status = Some(format!("Error: {}", e));
+                                        }
+                                        if ui.add_enabled(!busy, egui::Button::new(lang.t("history_rerun"))).clicked() {
+                                            rerun = Some(entry.config.clone());
+                                        }
+                                    });
+                                });
+                        }
+                    });
+                });
+            });
+        rerun
+    }
+}
+
+fn render_entry(ui: &mut egui::Ui, lang: &LanguageManager, entry: &HistoryEntry) {
+    let config = &entry.config;
+    let params: Vec<String> = config.params.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+    let generators: Vec<&str> = config.generators.iter().map(|k| k.suffix()).collect();
+
+    egui::Grid::new(("history_grid", entry.finished_at, &config.input)).num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+        let mut row = |label: &str, value: String| {
+            ui.label(egui::RichText::new(lang.t(label)).strong());
+            ui.add(egui::Label::new(value).wrap_mode(egui::TextWrapMode::Wrap));
+            ui.end_row();
+        };
+        row("history_input", config.input.display().to_string());
+        row("history_output", config.output.display().to_string());
+        row("history_model", config.selected_model.name());
+        row("history_generators", generators.join(", "));
+        if !params.is_empty() {
+            row("history_params", params.join(", "));
+        }
+        if let Some(factor) = config.upscale {
+            row("history_upscale", format!("×{}", factor));
+        }
+        let outputs: Vec<String> = entry.outputs.iter()
+            .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect();
+        row("history_outputs", outputs.join("\n"));
+        if let Some(error) = &entry.error {
+            row("history_error", error.clone());
+        }
+    });
+}
+
+/// Opens `dir` in the platform file manager.
+pub(super) fn open_folder(dir: &Path) -> std::io::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(dir).spawn().map(|_| ())
+}
@@ -0,0 +1,387 @@
+//! Preview of the input image, with transform buttons above it, a drag
+//! rectangle selecting the region of interest, zoom and a pixel inspector.
+
+use eframe::egui;
+use image::{DynamicImage, GrayImage, Luma, imageops::{self, FilterType}};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use anyhow::Result;
+
+use crate::generators::alpha::Roi;
+use crate::generators::mask_adjust::MaskAdjust;
+use crate::generators::transform::{self, Transform};
+use crate::lang::LanguageManager;
+use super::a11y;
+use super::backdrop::{self, Backdrop, BackdropPainter};
+use super::panels;
+use super::pyramid::{LevelTextures, Pyramid};
+
+/// Largest zoom, in points per input pixel.
+const MAX_ZOOM: f32 = 16.0;
+
+/// Loaded preview of one input file.
+struct Loaded {
+    path: PathBuf,
+    /// Untransformed input.
+    pyramid: Pyramid,
+    /// Transform the textures are rendered with.
+    transform: Transform,
+    textures: LevelTextures,
+    /// Levels with the model mask applied, and the adjustments they were rendered with.
+    masked: LevelTextures,
+    masked_with: Option<MaskAdjust>,
+    /// Size of the transformed input in pixels.
+    size: [u32; 2],
+}
+
+/// Alpha channel of the last cut-out, for the mask readout.
+struct CutoutMask {
+    path: PathBuf,
+    modified: SystemTime,
+    alpha: GrayImage,
+}
+
+/// State of the input preview.
+#[derive(Default)]
+pub struct InputPreview {
+    loaded: Option<Loaded>,
+    /// Input that failed to load, and why.
+    error: Option<(PathBuf, String)>,
+    /// Input pixel where the current drag started.
+    drag_start: Option<[u32; 2]>,
+    /// Points per input pixel, or None to fit the image in the pane.
+    zoom: Option<f32>,
+    /// Input pixel at the center of the pane while zoomed.
+    center: egui::Vec2,
+    /// Cut-out of the current input, if one was written.
+    cutout: Option<PathBuf>,
+    mask: Option<CutoutMask>,
+    /// Unadjusted model mask of the last job and its input, for the live mask preview.
+    model_mask: Option<(PathBuf, Arc<GrayImage>)>,
+    mask_adjust: MaskAdjust,
+    /// Draw the input through the adjusted model mask.
+    show_mask: bool,
+    backdrop: Backdrop,
+    backdrop_painter: BackdropPainter,
+}
+
+impl InputPreview {
+    /// Size of the loaded input in pixels, before the transform.
+    pub fn input_size(&self) -> Option<[u32; 2]> {
+        self.loaded.as_ref().map(|loaded| loaded.pyramid.size())
+    }
+
+    pub fn backdrop(&self) -> &Backdrop {
+        &self.backdrop
+    }
+
+    pub fn set_backdrop(&mut self, backdrop: Backdrop) {
+        self.backdrop = backdrop;
+    }
+
+    /// Sets the cut-out whose alpha the pixel inspector reports as the mask value.
+    pub fn set_cutout(&mut self, cutout: Option<PathBuf>) {
+        self.cutout = cutout;
+    }
+
+    /// Sets the model mask of `input` the mask preview is drawn with.
+    pub fn set_model_mask(&mut self, input: PathBuf, mask: Arc<GrayImage>) {
+        self.model_mask = Some((input, mask));
+        if let Some(loaded) = &mut self.loaded {
+            loaded.masked_with = None;
+        }
+    }
+
+    /// Sets the adjustments the mask preview applies to the model mask.
+    pub fn set_mask_adjust(&mut self, adjust: MaskAdjust) {
+        self.mask_adjust = adjust;
+    }
+
+    /// Shows the preview of `input` in a pane `width` wide, transformed by
+    /// `transform`; the toolbar edits it and dragging on the image sets `roi`.
+    /// Scrolling zooms, dragging with the right or middle button pans and
+    /// hovering reads out the pixel under the cursor.
+    pub fn show(&mut self, ui: &mut egui::Ui, lang: &LanguageManager, input: &str, transform: &mut Transform, roi: &mut Option<Roi>, width: f32) {
+        let path = Path::new(input);
+        if !path.is_file() {
+            self.loaded = None;
+            self.error = None;
+            return;
+        }
+        let stale = self.loaded.as_ref().is_none_or(|l| l.path != path);
+        if stale && self.error.as_ref().is_none_or(|(p, _)| p != path) {
+            self.zoom = None;
+            match load(path, *transform) {
+                Ok(loaded) => {
+                    self.loaded = Some(loaded);
+                    self.error = None;
+                }
+                Err(e) => {
+                    self.loaded = None;
+                    self.error = Some((path.to_path_buf(), format!("Error: {}", e)));
+                }
+            }
+        }
+        if let Some((_, error)) = &self.error {
+            ui.label(egui::RichText::new(error).italics());
+            return;
+        }
+        let Some(loaded) = &mut self.loaded else { return };
+
+        // The region refers to the transformed image, so it is cleared when the transform changes
+        if render_toolbar(ui, lang, transform) {
+            *roi = None;
+            self.zoom = None;
+            // The model mask was computed with the previous transform
+            self.model_mask = None;
+        }
+        if loaded.transform != *transform {
+            loaded.set_transform(*transform);
+        }
+        let size = egui::vec2(loaded.size[0] as f32, loaded.size[1] as f32);
+        let fit = (width / size.x).min(width / size.y);
+        let pixels_per_point = ui.ctx().pixels_per_point();
+
+        panels::row(ui, lang, |ui| {
+            if ui.add_enabled(self.zoom.is_some(), egui::Button::new(lang.t("zoom_fit")).small()).clicked() {
+                self.zoom = None;
+            }
+            if ui.small_button("100%").on_hover_text(lang.t("zoom_actual")).clicked() {
+                if self.zoom.is_none() {
+                    self.center = size / 2.0;
+                }
+                self.zoom = Some(1.0 / pixels_per_point);
+            }
+            let scale = self.zoom.unwrap_or(fit);
+            ui.label(format!("{}%", lang.format_number(f64::from(scale * pixels_per_point * 100.0), 0)));
+            backdrop::selector(ui, lang, &mut self.backdrop);
+            let has_mask = self.model_mask.as_ref().is_some_and(|(input, _)| input == path);
+            self.show_mask &= has_mask;
+            ui.add_enabled_ui(has_mask, |ui| {
+                ui.toggle_value(&mut self.show_mask, lang.t("mask_preview"))
+                    .on_hover_text(lang.t("mask_preview_hint"))
+                    .on_disabled_hover_text(lang.t("mask_preview_unavailable"));
+            });
+        });
+
+        let (rect, response) = ui.allocate_exact_size(size * fit, egui::Sense::click_and_drag());
+        a11y::describe(&response, lang.t("roi_hint"), egui::WidgetInfo::new(egui::WidgetType::Image));
+
+        // Zoom around the cursor, so the pixel under it stays put
+        if let Some(pointer) = response.hover_pos() {
+            let (scroll, pinch) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let factor = (scroll / 200.0).exp() * pinch;
+            if factor != 1.0 {
+                let scale = self.zoom.unwrap_or(fit);
+                let center = if self.zoom.is_some() { self.center } else { size / 2.0 };
+                let new_scale = (scale * factor).clamp(fit, MAX_ZOOM);
+                let offset = pointer - rect.center();
+                self.center = center + offset / scale - offset / new_scale;
+                self.zoom = (new_scale > fit).then_some(new_scale);
+            }
+        }
+        if self.zoom.is_some()
+            && (response.dragged_by(egui::PointerButton::Secondary) || response.dragged_by(egui::PointerButton::Middle))
+        {
+            self.center -= response.drag_delta() / self.zoom.unwrap_or(fit);
+        }
+
+        let scale = self.zoom.unwrap_or(fit);
+        // Keep the image covering the pane, or centered where it is smaller
+        let half_view = rect.size() / 2.0 / scale;
+        let clamp = |center: f32, size: f32, half: f32| if size <= 2.0 * half { size / 2.0 } else { center.clamp(half, size - half) };
+        self.center = match self.zoom {
+            Some(_) => egui::vec2(clamp(self.center.x, size.x, half_view.x), clamp(self.center.y, size.y, half_view.y)),
+            None => size / 2.0,
+        };
+        let origin = rect.center() - self.center * scale;
+        let image_rect = egui::Rect::from_min_size(origin, size * scale);
+
+        let level = loaded.level_for_view(ui.ctx(), size.x.max(size.y) * scale);
+        let texture = match &self.model_mask {
+            Some((_, mask)) if self.show_mask => loaded.masked_texture(ui.ctx(), level, mask, self.mask_adjust),
+            _ => loaded.texture(ui.ctx(), level),
+        };
+        let painter = ui.painter_at(rect);
+        self.backdrop_painter.paint(&painter, image_rect, &self.backdrop);
+        painter.image(texture.id(), image_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+
+        let loaded = &*loaded;
+        let to_pixel = |pos: egui::Pos2| {
+            let p = ((pos - origin) / scale).clamp(egui::Vec2::ZERO, size);
+            [p.x.round() as u32, p.y.round() as u32]
+        };
+
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            self.drag_start = response.interact_pointer_pos().map(to_pixel);
+        }
+        if response.dragged_by(egui::PointerButton::Primary)
+            && let (Some(start), Some(pos)) = (self.drag_start, response.interact_pointer_pos())
+        {
+            let end = to_pixel(pos);
+            let (x, y) = (start[0].min(end[0]), start[1].min(end[1]));
+            let (w, h) = (start[0].abs_diff(end[0]), start[1].abs_diff(end[1]));
+            *roi = (w > 0 && h > 0).then_some(Roi { x, y, width: w, height: h });
+        }
+        if response.drag_stopped() {
+            self.drag_start = None;
+        }
+
+        if let Some(r) = roi {
+            let to_screen = |x: u32, y: u32| origin + egui::vec2(x as f32, y as f32) * scale;
+            let selection = egui::Rect::from_min_max(to_screen(r.x, r.y), to_screen(r.x + r.width, r.y + r.height));
+            painter.rect_filled(selection, 0.0, egui::Color32::from_rgba_unmultiplied(100, 100, 255, 40));
+            painter.rect_stroke(selection, 0.0, egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 100, 255)), egui::StrokeKind::Middle);
+        }
+
+        // Pixel inspector, reading the full-resolution input and the cut-out's alpha
+        let hovered = response.hover_pos()
+            .map(|pos| (pos - origin) / scale)
+            .filter(|p| p.x >= 0.0 && p.y >= 0.0 && p.x < size.x && p.y < size.y);
+        match hovered {
+            Some(p) => {
+                let [x, y] = [p.x as u32, p.y as u32];
+                let source = loaded.pyramid.level(0);
+                let [r, g, b, a] = match loaded.transform.source_point(p.x, p.y, source.width(), source.height()) {
+                    Some((sx, sy)) => source.get_pixel((sx as u32).min(source.width() - 1), (sy as u32).min(source.height() - 1)).0,
+                    // The transparent corners added by straightening
+                    None => [0; 4],
+                };
+                ui.label(lang.t_args("inspect_pixel", &[x, y, r.into(), g.into(), b.into(), a.into()]));
+                let mask = self.mask_at(p / size);
+                ui.label(match mask {
+                    Some(m) => lang.t_args("inspect_alpha_mask", &[percent(lang, a), percent(lang, m)]),
+                    None => lang.t_args("inspect_alpha", &[percent(lang, a)]),
+                });
+            }
+            None => {
+                ui.label(egui::RichText::new(lang.t("zoom_hint")).weak());
+            }
+        }
+
+        panels::row(ui, lang, |ui| {
+            match roi {
+                Some(r) => ui.label(lang.t_args("roi_selected", &[*r])),
+                None => ui.label(lang.t("roi_none")),
+            };
+            if ui.add_enabled(roi.is_some(), egui::Button::new(lang.t("roi_clear"))).clicked() {
+                *roi = None;
+            }
+        });
+    }
+
+    /// Alpha of the cut-out at `relative` coordinates, loading it again when the file changed.
+    fn mask_at(&mut self, relative: egui::Vec2) -> Option<u8> {
+        let path = self.cutout.as_ref()?;
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        if self.mask.as_ref().is_none_or(|m| &m.path != path || m.modified != modified) {
+            let rgba = image::open(path).ok()?.into_rgba8();
+            let alpha = GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| Luma([rgba.get_pixel(x, y)[3]]));
+            self.mask = Some(CutoutMask { path: path.clone(), modified, alpha });
+        }
+        let alpha = &self.mask.as_ref()?.alpha;
+        let x = ((relative.x * alpha.width() as f32) as u32).min(alpha.width() - 1);
+        let y = ((relative.y * alpha.height() as f32) as u32).min(alpha.height() - 1);
+        Some(alpha.get_pixel(x, y)[0])
+    }
+}
+
+/// `value` of 255 as a localized percentage.
+fn percent(lang: &LanguageManager, value: u8) -> String {
+    format!("{}%", lang.format_number(f64::from(value) / 255.0 * 100.0, 0))
+}
+
+/// Rotate, flip and straighten controls. Returns true when the transform changed.
+fn render_toolbar(ui: &mut egui::Ui, lang: &LanguageManager, transform: &mut Transform) -> bool {
+    let before = *transform;
+    panels::row(ui, lang, |ui| {
+        let button = |ui: &mut egui::Ui, icon: &str, key: &str| {
+            let response = ui.small_button(icon).on_hover_text(lang.t(key));
+            a11y::describe(&response, lang.t(key), egui::WidgetInfo::new(egui::WidgetType::Button));
+            response
+        };
+        if button(ui, "⟲", "btn_rotate_left").clicked() {
+            transform.turn(false);
+        }
+        if button(ui, "⟳", "btn_rotate_right").clicked() {
+            transform.turn(true);
+        }
+        for (flip, icon, key) in [(&mut transform.flip_horizontal, "⇔", "btn_flip_h"), (&mut transform.flip_vertical, "⇕", "btn_flip_v")] {
+            let response = ui.toggle_value(flip, icon).on_hover_text(lang.t(key));
+            a11y::describe(&response, lang.t(key), egui::WidgetInfo { selected: Some(*flip), ..egui::WidgetInfo::new(egui::WidgetType::SelectableLabel) });
+        }
+        let straighten = ui.add(egui::DragValue::new(&mut transform.straighten)
+            .range(transform::STRAIGHTEN_RANGE)
+            .speed(0.1)
+            .fixed_decimals(1)
+            .suffix("°"))
+            .on_hover_text(lang.t("lbl_straighten"));
+        a11y::describe(&straighten, lang.t("lbl_straighten"), egui::WidgetInfo::drag_value(true, f64::from(transform.straighten)));
+        let reset = ui.add_enabled(!transform.is_identity(), egui::Button::new("↺").small()).on_hover_text(lang.t("btn_reset_transform"));
+        a11y::describe(&reset, lang.t("btn_reset_transform"), egui::WidgetInfo::new(egui::WidgetType::Button));
+        if reset.clicked() {
+            *transform = Transform::default();
+        }
+    });
+    *transform != before
+}
+
+impl Loaded {
+    /// Switches to `transform`; the textures are re-rendered as they are drawn.
+    fn set_transform(&mut self, transform: Transform) {
+        let [width, height] = self.pyramid.size();
+        let (width, height) = transform.output_size(width, height);
+        self.size = [width, height];
+        self.transform = transform;
+        self.textures.clear();
+        self.masked_with = None;
+    }
+
+    /// Pyramid level for drawing the image `points` long on its longest edge.
+    fn level_for_view(&self, ctx: &egui::Context, points: f32) -> usize {
+        self.pyramid.level_for_view(ctx, points)
+    }
+
+    /// Texture of `level`, transformed on first use so the full-resolution
+    /// input is only transformed when it is shown at full size.
+    fn texture(&mut self, ctx: &egui::Context, level: usize) -> egui::TextureHandle {
+        let (pyramid, transform) = (&self.pyramid, self.transform);
+        self.textures.get(ctx, level, || transform.apply(DynamicImage::ImageRgba8(pyramid.level(level).clone())).into_rgba8()).clone()
+    }
+
+    /// Texture of `level` with its alpha multiplied by the `mask` after `adjust`,
+    /// re-rendered whenever the adjustments change.
+    fn masked_texture(&mut self, ctx: &egui::Context, level: usize, mask: &GrayImage, adjust: MaskAdjust) -> egui::TextureHandle {
+        if self.masked_with != Some(adjust) {
+            self.masked.clear();
+            self.masked_with = Some(adjust);
+        }
+        let (pyramid, transform) = (&self.pyramid, self.transform);
+        self.masked.get(ctx, level, || {
+            let mut img = transform.apply(DynamicImage::ImageRgba8(pyramid.level(level).clone())).into_rgba8();
+            let mask = imageops::resize(mask, img.width(), img.height(), FilterType::Triangle);
+            let lut = adjust.lut();
+            for (pixel, m) in img.pixels_mut().zip(mask.pixels()) {
+                pixel[3] = (pixel[3] as u16 * lut[m[0] as usize] as u16 / 255) as u8;
+            }
+            img
+        }).clone()
+    }
+}
+
+fn load(path: &Path, transform: Transform) -> Result<Loaded> {
+    let img = image::open(path)?;
+    let mut loaded = Loaded {
+        path: path.to_path_buf(),
+        pyramid: Pyramid::new(img.into_rgba8()),
+        transform,
+        textures: LevelTextures::new("input_preview"),
+        masked: LevelTextures::new("mask_preview"),
+        masked_with: None,
+        size: [0, 0],
+    };
+    loaded.set_transform(transform);
+    Ok(loaded)
+}
@@ -2,20 +2,56 @@
 //! 
 //! This module provides the graphical user interface using `eframe` and `egui`.
 
+mod a11y;
+pub mod backdrop;
 mod styles;
 mod panels;
+mod translation_editor;
+mod history;
+mod stats;
+mod cvd_preview;
+mod detached;
+mod error_dialog;
+pub mod fonts;
+mod input_preview;
+mod pyramid;
+mod quick_preview;
+mod repaint;
+mod tray;
 pub mod processing;
 
 use eframe::egui;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Receiver;
 use anyhow::Result;
+use std::collections::VecDeque;
 use std::path::PathBuf;
 
-use crate::generators::{self, LogOutput, ModelState, ModelType};
+use crate::events::{AppEvent, EventBus, TrayAction};
+use crate::generators::{self, ModelState, ModelType, PreprocessOptions};
+use crate::generators::alpha::Roi;
+use crate::generators::mask_adjust::MaskAdjust;
+use crate::generators::params::Params;
+use crate::generators::registry;
+use crate::instance::{self, Instance};
+use crate::naming::OutputKind;
+use crate::estimate::{self, SizeEstimates};
 use crate::lang::LanguageManager;
+use crate::priority;
+use crate::settings::Settings;
+use crate::temp;
+use crate::update;
+
+/// Launches the GUI application with `files` queued, or hands them to the
+/// window already open, see [`instance`].
+pub fn run_gui(files: Vec<PathBuf>, lang: &LanguageManager) -> Result<()> {
+    let guard = match instance::acquire(&files)? {
+        Instance::Primary(guard) => guard,
+        Instance::Forwarded => {
+            println!("{}", lang.t_args("log_gui_forwarded", &[files.len().to_string()]));
+            return Ok(());
+        }
+    };
 
-/// Launches the GUI application.
-pub fn run_gui() -> Result<()> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_maximized(true)
@@ -26,8 +62,14 @@ pub fn run_gui() -> Result<()> {
         "Procesador Transparente - Rust",
         options,
         Box::new(|cc| {
-            styles::configure_styles(&cc.egui_ctx);
-            Ok(Box::new(MyApp::default()))
+            let app = MyApp::new(&cc.egui_ctx);
+            styles::configure_styles(&cc.egui_ctx, app.animate());
+            let bus = app.bus.clone();
+            guard.listen(move |files| bus.publish(AppEvent::OpenFiles(files)));
+            if !files.is_empty() {
+                app.bus.publish(AppEvent::OpenFiles(files));
+            }
+            Ok(Box::new(app))
         }),
     ).map_err(|e| anyhow::anyhow!("Eframe error: {}", e))
 }
@@ -37,91 +79,260 @@ struct MyApp {
     input_file: String,
     output_dir: String,
     
-    // Log system
-    log_sender: std::sync::mpsc::Sender<String>,
-    log_receiver: Arc<Mutex<std::sync::mpsc::Receiver<String>>>,
-    log_history: Arc<Mutex<Vec<String>>>,
-    
-    processing: Arc<Mutex<bool>>,
-    model_status: Arc<Mutex<ModelState>>,
+    // Event bus: background work publishes, `update()` drains
+    bus: EventBus,
+    events: Receiver<AppEvent>,
+    log_history: Vec<String>,
+
+    processing: bool,
+    /// A quick preview is running; it blocks new jobs like `processing`.
+    previewing: bool,
+    /// Inputs waiting for the running job, each processed with the form settings.
+    queue: VecDeque<PathBuf>,
+    /// Queued inputs wait until resumed, from the main window or the tray menu.
+    queue_paused: bool,
+    /// Completed and total steps of the running job.
+    progress: Option<(usize, usize)>,
+    model_status: ModelState,
     selected_model: ModelType,
+    /// Move the keyboard focus to the model selector on the next frame.
+    focus_model: bool,
     
     // Checkbox states
-    gen_alpha_transparency: bool,
-    gen_gray: bool,
-    gen_halftone: bool,
-    gen_lineart: bool,
-    gen_color_logo: bool,
-    gen_color_illus: bool,
-    gen_thumbnail: bool,
+    generators: Vec<OutputKind>,
+    params: Params,
+    /// Selected output presets; empty in the custom view.
+    profiles: Vec<String>,
+
+    preprocess: PreprocessOptions,
+    upscale: Option<u32>,
+    /// Trace the input as-is for the vector outputs.
+    vectorize_original: bool,
+    mask_adjust: MaskAdjust,
+    /// Region of the input selected on the preview.
+    roi: Option<Roi>,
+    input_preview: input_preview::InputPreview,
+    quick_preview: quick_preview::QuickPreview,
 
     output_filename: String,
 
     // I18n
     lang_manager: LanguageManager,
-    show_about: bool,
+    windows: panels::Windows,
+    translation_editor: translation_editor::TranslationEditor,
+    history: history::HistoryWindow,
+    stats: stats::StatsWindow,
+    cvd_preview: cvd_preview::CvdPreview,
+    error_dialog: error_dialog::ErrorDialog,
+
+    // Newer release found by the background update check (version, release page)
+    update_available: Option<(String, String)>,
+
+    repaint: repaint::RepaintScheduler,
+    tray: Option<tray::TrayIcon>,
+    /// Last status sent to the tray icon.
+    tray_status: tray::TrayStatus,
+    frame_times: repaint::FrameTimes,
+    /// Output sizes of earlier jobs, reloaded after each one.
+    size_estimates: SizeEstimates,
+
+    settings: Settings,
+    /// The desktop asks for reduced motion, detected at startup.
+    reduced_motion: bool,
 }
 
-impl Default for MyApp {
-    fn default() -> Self {
-        let lang_manager = LanguageManager::default();
+impl MyApp {
+    fn new(ctx: &egui::Context) -> Self {
+        let settings = Settings::load();
+        let mut lang_manager = LanguageManager::default();
+        if let Some(code) = &settings.language {
+            lang_manager.load_language(code);
+        }
         let initial_status = lang_manager.t("status_ready");
         
-        let (tx, rx) = std::sync::mpsc::channel();
+        let scheduler = repaint::RepaintScheduler::new(ctx);
+        let (bus, events) = EventBus::new();
+        let waker = scheduler.clone();
+        let bus = bus.with_waker(move || waker.wake());
+
+        if !settings.disable_update_check && std::env::var_os("ALPHASVG_NO_UPDATE_CHECK").is_none() {
+            let bus = bus.clone();
+            std::thread::spawn(move || {
+                if let Ok(Some(release)) = update::check_for_update() {
+                    bus.publish(AppEvent::UpdateAvailable { version: release.version().to_string(), url: release.html_url });
+                }
+            });
+        }
 
-        Self {
+        let mut app = Self {
             input_file: String::new(),
             output_dir: String::new(),
             
-            log_sender: tx,
-            log_receiver: Arc::new(Mutex::new(rx)),
-            log_history: Arc::new(Mutex::new(vec![initial_status])),
-            
-            processing: Arc::new(Mutex::new(false)),
-            model_status: Arc::new(Mutex::new(ModelState::Unloaded)),
+            bus,
+            events,
+            log_history: vec![initial_status],
+
+            processing: false,
+            previewing: false,
+            queue: VecDeque::new(),
+            queue_paused: false,
+            progress: None,
+            model_status: ModelState::Unloaded,
             selected_model: generators::ModelType::default(),
+            focus_model: false,
             
-            gen_alpha_transparency: true,
-            gen_gray: true,
-            gen_halftone: true,
-            gen_lineart: true,
-            gen_color_logo: true,
-            gen_color_illus: true,
-            gen_thumbnail: true,
+            generators: registry::default_kinds(),
+            params: Params::default(),
+            profiles: Vec::new(),
+
+            preprocess: PreprocessOptions::default(),
+            upscale: None,
+            vectorize_original: false,
+            mask_adjust: MaskAdjust::default(),
+            roi: None,
+            input_preview: input_preview::InputPreview::default(),
+            quick_preview: quick_preview::QuickPreview::default(),
 
             output_filename: String::new(),
 
             lang_manager,
-            show_about: false,
+            windows: panels::Windows::default(),
+            translation_editor: translation_editor::TranslationEditor::default(),
+            history: history::HistoryWindow::default(),
+            stats: stats::StatsWindow::default(),
+            cvd_preview: cvd_preview::CvdPreview::default(),
+            error_dialog: error_dialog::ErrorDialog::default(),
+
+            update_available: None,
+
+            repaint: scheduler,
+            tray: None,
+            tray_status: tray::TrayStatus::default(),
+            frame_times: repaint::FrameTimes::default(),
+            size_estimates: SizeEstimates::load(),
+
+            settings,
+            reduced_motion: a11y::prefers_reduced_motion(),
+        };
+        app.input_preview.set_backdrop(app.settings.backdrop.clone());
+        app.apply_fonts(ctx);
+        if app.settings.background_mode {
+            app.apply_background_mode();
         }
+        if app.settings.tray_icon {
+            app.apply_tray_icon();
+        }
+        app.restore_last_job();
+        app.clean_temp_files();
+        app
     }
 }
 
 impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Consume logs from channel
-        if let Ok(rx) = self.log_receiver.lock() {
-            let mut history = self.log_history.lock().unwrap();
-            while let Ok(msg) = rx.try_recv() {
-                history.push(msg);
-            }
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.repaint.begin_frame(ctx);
+        self.frame_times.record(ctx, frame);
+        self.drain_events(ctx);
+        // The pulsing model status indicator
+        if self.animate() && matches!(self.model_status, ModelState::Loading) {
+            self.repaint.wake();
         }
 
         // Menu Bar
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            let status = self.model_status.lock().unwrap().clone();
-            panels::render_menu_bar(ui, ctx, &mut self.lang_manager, &mut self.show_about, &status);
+            let previous_lang = self.lang_manager.current_lang();
+            let animations_were_off = self.settings.disable_animations;
+            let was_background = self.settings.background_mode;
+            let had_tray = self.settings.tray_icon;
+            let previous_font = self.settings.ui_font.clone();
+            let checked_updates = self.settings.disable_update_check;
+            panels::render_menu_bar(
+                ui, ctx, &mut self.lang_manager, &mut self.windows, &mut self.settings,
+                self.update_available.as_ref(), &self.model_status,
+            );
+            if self.settings.disable_animations != animations_were_off {
+                styles::set_animations(ctx, self.animate());
+                self.save_settings();
+            }
+            if self.settings.background_mode != was_background {
+                self.apply_background_mode();
+                self.save_settings();
+            }
+            if self.settings.tray_icon != had_tray {
+                self.apply_tray_icon();
+                self.save_settings();
+            }
+            if self.settings.disable_update_check != checked_updates {
+                self.save_settings();
+            }
+            if self.settings.ui_font != previous_font {
+                self.apply_fonts(ctx);
+                self.save_settings();
+            }
+            if self.lang_manager.current_lang() != previous_lang {
+                self.settings.language = Some(self.lang_manager.current_lang());
+                self.save_settings();
+                // The tray menu is labelled in the language it was created with
+                self.apply_tray_icon();
+                self.apply_fonts(ctx);
+            }
         });
 
         // About Window
-        if self.show_about {
+        if self.windows.about {
             egui::Window::new(self.lang_manager.t("about_title"))
-                .open(&mut self.show_about)
+                .open(&mut self.windows.about)
                 .show(ctx, |ui| {
-                    ui.label(self.lang_manager.t("about_text"));
+                    ui.with_layout(panels::column_layout(&self.lang_manager), |ui| {
+                        ui.label(self.lang_manager.t("about_text"));
+                    });
                 });
         }
 
+        // Translation Editor Window
+        if self.windows.translation_editor {
+            self.translation_editor.show(ctx, &mut self.lang_manager, &mut self.windows.translation_editor);
+        }
+
+        // History Window
+        if self.windows.history
+            && let Some(config) = self.history.show(ctx, &self.lang_manager, &mut self.windows.history, self.processing || self.previewing)
+        {
+            self.restore_job(&config);
+            self.start_processing();
+        }
+
+        // Statistics Window
+        if self.windows.stats && self.stats.show(ctx, &self.lang_manager, &mut self.windows.stats, &mut self.settings) {
+            self.save_settings();
+        }
+
+        // Colorblind Preview Window
+        if self.windows.cvd_preview {
+            let files = self.preview_files();
+            self.cvd_preview.show(ctx, &self.lang_manager, &mut self.windows.cvd_preview, &files, &self.settings.backdrop);
+        }
+
+        // Error Dialog
+        // Failures wait until the queue has finished or stopped
+        let running = self.processing || (!self.queue.is_empty() && !self.queue_paused);
+        match self.error_dialog.show(ctx, &self.lang_manager, running, !self.queue.is_empty()) {
+            Some(error_dialog::DialogAction::Retry(input)) => {
+                self.queue_paused = false;
+                match self.settings.last_job.clone() {
+                    Some(job) if job.input == input => {
+                        self.restore_job(&job);
+                        self.start_processing();
+                    }
+                    // An earlier input of the queue, run again with the form settings
+                    _ => self.queue.push_front(input),
+                }
+            }
+            Some(error_dialog::DialogAction::Skip) => self.queue_paused = false,
+            Some(error_dialog::DialogAction::ChooseModel) => self.focus_model = true,
+            Some(error_dialog::DialogAction::Dismiss) | None => {}
+        }
+
         // Main content
         let frame = egui::Frame::central_panel(&ctx.style()).inner_margin(24.0);
         egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
@@ -140,37 +351,51 @@ impl eframe::App for MyApp {
             let col3_width = safe_width * 0.32;
             let text_input_width = col1_width - 56.0;
 
-            ui.horizontal(|ui| {
+            let lang = self.lang_manager.clone();
+            let column_layout = panels::column_layout(&lang);
+            panels::row(ui, &lang, |ui| {
                 ui.spacing_mut().item_spacing.x = spacing;
 
                 // Column 1: INPUT / OUTPUT
-                ui.allocate_ui_with_layout(egui::vec2(col1_width, ui.available_height()), egui::Layout::top_down(egui::Align::Min), |ui| {
+                ui.allocate_ui_with_layout(egui::vec2(col1_width, ui.available_height()), column_layout, |ui| {
                     panels::render_io_column(
                         ui, col1_width, text_input_width, &self.lang_manager,
                         &mut self.input_file, &mut self.output_dir, &mut self.output_filename,
                     );
+                    self.input_preview.set_cutout(self.cutout_path());
+                    self.input_preview.set_mask_adjust(self.mask_adjust);
+                    if self.windows.preview_detached {
+                        panels::row(ui, &self.lang_manager, |ui| {
+                            ui.label(egui::RichText::new(self.lang_manager.t("preview_detached")).italics());
+                            detached::toggle(ui, &mut self.windows.preview_detached, self.lang_manager.t("btn_attach"));
+                        });
+                    } else {
+                        detached::toggle(ui, &mut self.windows.preview_detached, self.lang_manager.t("btn_detach"));
+                        self.show_previews(ui, col1_width - 16.0);
+                    }
                 });
 
                 // Column 2: AI PROCESSING
-                let mut should_start = false;
-                ui.allocate_ui_with_layout(egui::vec2(col2_width, ui.available_height()), egui::Layout::top_down(egui::Align::Min), |ui| {
-                    let is_processing = *self.processing.lock().unwrap();
-                    should_start = panels::render_ai_column(
-                        ui, col2_width, &self.lang_manager, &mut self.selected_model, is_processing,
+                let mut request = None;
+                ui.allocate_ui_with_layout(egui::vec2(col2_width, ui.available_height()), column_layout, |ui| {
+                    request = panels::render_ai_column(
+                        ui, col2_width, &self.lang_manager, &mut self.selected_model, &mut self.focus_model, self.processing || self.previewing,
                     );
+                    panels::render_mask_group(ui, &self.lang_manager, &mut self.mask_adjust);
                 });
-                if should_start {
-                    self.start_processing(ctx.clone());
+                match request {
+                    Some(panels::RunRequest::Full) => self.start_processing(),
+                    Some(panels::RunRequest::Preview) => self.start_quick_preview(),
+                    None => {}
                 }
 
                 // Column 3: CONVERSION OPTIONS
-                ui.allocate_ui_with_layout(egui::vec2(col3_width, ui.available_height()), egui::Layout::top_down(egui::Align::Min), |ui| {
-                    panels::render_options_column(
-                        ui, col3_width, &self.lang_manager,
-                        &mut self.gen_alpha_transparency, &mut self.gen_thumbnail,
-                        &mut self.gen_gray, &mut self.gen_halftone, &mut self.gen_lineart,
-                        &mut self.gen_color_logo, &mut self.gen_color_illus,
-                    );
+                ui.allocate_ui_with_layout(egui::vec2(col3_width, ui.available_height()), column_layout, |ui| {
+                    panels::render_options_column(ui, col3_width, &self.lang_manager, &mut self.generators, &mut self.params, &mut self.profiles);
+                    panels::render_preprocess_group(ui, &self.lang_manager, &mut self.preprocess, &mut self.upscale, &mut self.vectorize_original);
+                    if let Some([width, height]) = self.input_preview.input_size() {
+                        panels::render_size_estimate(ui, &self.lang_manager, &self.size_estimates, &self.generators, estimate::megapixels_of(width, height));
+                    }
                 });
             });
 
@@ -179,36 +404,313 @@ impl eframe::App for MyApp {
             // Terminal Log
             ui.vertical(|ui| {
                 ui.set_width(available);
-                let logs = self.log_history.lock().unwrap();
-                panels::render_terminal_log(ui, &logs);
+                if let Some((done, total)) = self.progress {
+                    ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32).show_percentage());
+                }
+                if !self.queue.is_empty() || self.queue_paused {
+                    let toggled = panels::row(ui, &self.lang_manager, |ui| {
+                        ui.label(self.lang_manager.t_args("status_queue", &[self.queue.len().to_string()]));
+                        let key = if self.queue_paused { "btn_queue_resume" } else { "btn_queue_pause" };
+                        ui.button(self.lang_manager.t(key)).clicked()
+                    }).inner;
+                    if toggled {
+                        self.toggle_queue_pause();
+                    }
+                }
+                if !self.windows.log_detached {
+                    panels::render_terminal_log(ui, &self.lang_manager, &self.log_history, &mut self.windows.log_detached, 200.0);
+                }
             });
         });
+
+        // Detached panes; closing their window puts them back
+        let layout = panels::column_layout(&self.lang_manager);
+        if self.windows.preview_detached {
+            let title = self.lang_manager.t("preview_window_title");
+            let open = detached::show(ctx, "preview_window", &title, [720.0, 820.0], |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let width = ui.available_width() - 16.0;
+                    ui.with_layout(layout, |ui| self.show_previews(ui, width));
+                });
+            });
+            self.windows.preview_detached &= open;
+        }
+        if self.windows.log_detached {
+            let title = self.lang_manager.t("log_window_title");
+            let open = detached::show(ctx, "log_window", &title, [760.0, 420.0], |ui| {
+                ui.with_layout(layout, |ui| {
+                    panels::render_terminal_log(ui, &self.lang_manager, &self.log_history, &mut self.windows.log_detached, f32::INFINITY);
+                });
+            });
+            self.windows.log_detached &= open;
+        }
+
+        self.update_tray();
+        a11y::paint_focus_outline(ctx);
+        if self.windows.frame_times {
+            self.frame_times.show(ctx, &self.lang_manager);
+        }
     }
 }
 
 impl MyApp {
-    fn start_processing(&mut self, ctx: egui::Context) {
-        let config = processing::ProcessingConfig {
+    /// Applies the events published since the last frame.
+    fn drain_events(&mut self, ctx: &egui::Context) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                AppEvent::LogLine(line) => self.log_history.push(line),
+                AppEvent::Progress { done, total } => self.progress = Some((done, total)),
+                AppEvent::ModelStateChanged(state) => self.model_status = state,
+                AppEvent::JobFinished { failure } => {
+                    self.processing = false;
+                    self.progress = None;
+                    self.history.refresh();
+                    self.stats.refresh();
+                    self.size_estimates = SizeEstimates::load();
+                    if let Some(failure) = failure {
+                        self.log_history.push(self.lang_manager.t_args("log_job_failed", &[
+                            self.lang_manager.format_file_name(&failure.input),
+                            failure.message.clone(),
+                        ]));
+                        if let Some(hint) = &failure.hint {
+                            self.log_history.push(hint.clone());
+                        }
+                        // Other failures are collected and the queue goes on
+                        if failure.aborts_queue && !self.queue.is_empty() {
+                            self.queue_paused = true;
+                            self.log_history.push(self.lang_manager.t_args("log_queue_failed_paused", &[self.queue.len()]));
+                        }
+                        self.error_dialog.push(failure);
+                    }
+                }
+                AppEvent::UpdateAvailable { version, url } => self.update_available = Some((version, url)),
+                AppEvent::OpenFiles(files) => {
+                    // A single image opened with the app fills the form, to be reviewed before starting
+                    if let [file] = &files[..] && !self.processing && self.queue.is_empty() {
+                        self.open_file(file);
+                    } else {
+                        for file in files {
+                            self.log_history.push(self.lang_manager.t_args("log_queue_added", &[file.display().to_string()]));
+                            self.queue.push_back(file);
+                        }
+                    }
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                AppEvent::MaskReady { input, mask } => self.input_preview.set_model_mask(input, mask),
+                AppEvent::Tray(action) => self.tray_action(ctx, action),
+                AppEvent::PreviewReady { input, images, error } => {
+                    self.previewing = false;
+                    self.progress = None;
+                    self.quick_preview.set_results(input, images, error);
+                }
+            }
+        }
+        self.start_queued();
+    }
+
+    /// Starts the next queued input once no job is running.
+    fn start_queued(&mut self) {
+        while !self.processing && !self.previewing && !self.queue_paused && let Some(file) = self.queue.pop_front() {
+            self.input_file = file.to_string_lossy().into_owned();
+            // The region was selected on the previous input
+            self.roi = None;
+            self.start_processing();
+            if !self.processing && !self.queue.is_empty() {
+                // A missing output folder would fail the rest the same way
+                self.queue_paused = true;
+                self.log_history.push(self.lang_manager.t_args("log_queue_stopped", &[
+                    self.lang_manager.format_file_name(&file),
+                    self.queue.len().to_string(),
+                ]));
+            }
+        }
+    }
+
+    /// Enters or leaves background mode as the settings say.
+    fn apply_background_mode(&mut self) {
+        let nice = if self.settings.background_mode { priority::DEFAULT_NICE } else { 0 };
+        let line = match priority::set_background(nice) {
+            Ok(()) if nice > 0 => self.lang_manager.t_args("log_background_mode", &[nice.to_string()]),
+            Ok(()) => self.lang_manager.t("log_background_mode_off"),
+            Err(e) => self.lang_manager.t_args("log_background_failed", &[format!("{:#}", e)]),
+        };
+        self.log_history.push(line);
+    }
+
+    /// Installs the fonts of the current locale and the picked UI font.
+    fn apply_fonts(&mut self, ctx: &egui::Context) {
+        if let Err(e) = fonts::apply(ctx, &self.lang_manager, self.settings.ui_font.as_deref()) {
+            self.log_history.push(self.lang_manager.t_args("log_font_failed", &[format!("{:#}", e)]));
+        }
+    }
+
+    /// Loads `file` into the form with the output folder next to it.
+    fn open_file(&mut self, file: &std::path::Path) {
+        let file = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        self.input_file = file.to_string_lossy().into_owned();
+        if let Some(dir) = file.parent() {
+            self.output_dir = dir.to_string_lossy().into_owned();
+        }
+        // The region was selected on the previous input
+        self.roi = None;
+        self.log_history.push(self.lang_manager.t_args("log_file_opened", &[file.display().to_string(), self.lang_manager.t("btn_start")]));
+    }
+
+    /// Input preview and quick preview results, `width` wide.
+    fn show_previews(&mut self, ui: &mut egui::Ui, width: f32) {
+        self.input_preview.show(ui, &self.lang_manager, &self.input_file, &mut self.preprocess.transform, &mut self.roi, width);
+        if self.input_preview.backdrop() != &self.settings.backdrop {
+            self.settings.backdrop = self.input_preview.backdrop().clone();
+            self.save_settings();
+        }
+        self.quick_preview.show(ui, &self.lang_manager, &self.input_file, &self.settings.backdrop, width);
+    }
+
+    /// Shows or removes the tray icon as the settings say; a shown one is recreated.
+    fn apply_tray_icon(&mut self) {
+        self.tray = None;
+        if !self.settings.tray_icon {
+            return;
+        }
+        match tray::spawn(self.lang_manager.clone(), self.bus.clone()) {
+            Ok(icon) => {
+                icon.update(self.tray_status.clone());
+                self.tray = Some(icon);
+            }
+            Err(e) => self.log_history.push(self.lang_manager.t_args("log_tray_failed", &[e.to_string()])),
+        }
+    }
+
+    /// Sends the job progress and the queue to the tray icon when they change.
+    fn update_tray(&mut self) {
+        let status = tray::TrayStatus { progress: self.progress, queued: self.queue.len(), paused: self.queue_paused };
+        if let Some(icon) = &self.tray && status != self.tray_status {
+            icon.update(status.clone());
+        }
+        self.tray_status = status;
+    }
+
+    fn tray_action(&mut self, ctx: &egui::Context, action: TrayAction) {
+        match action {
+            TrayAction::ShowWindow => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            TrayAction::QueueFiles => {
+                let files = rfd::FileDialog::new().add_filter("Images", &["png", "jpg", "jpeg", "webp", "bmp", "tif", "tiff"]).pick_files();
+                for file in files.unwrap_or_default() {
+                    self.log_history.push(self.lang_manager.t_args("log_queue_added", &[file.display().to_string()]));
+                    self.queue.push_back(file);
+                }
+            }
+            TrayAction::TogglePause => self.toggle_queue_pause(),
+            TrayAction::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+        }
+    }
+
+    /// Pauses the queue, letting the running job finish, or resumes it.
+    fn toggle_queue_pause(&mut self) {
+        self.queue_paused = !self.queue_paused;
+        let key = if self.queue_paused { "log_queue_paused" } else { "log_queue_resumed" };
+        self.log_history.push(self.lang_manager.t(key));
+    }
+
+    /// Whether to animate: neither the desktop nor the settings turn animations off.
+    fn animate(&self) -> bool {
+        !self.reduced_motion && !self.settings.disable_animations
+    }
+
+    /// Restores the form from the last job saved in the settings.
+    fn restore_last_job(&mut self) {
+        if let Some(job) = self.settings.last_job.clone() {
+            self.restore_job(&job);
+        }
+    }
+
+    /// Removes, in the background, the stale temporary files killed jobs left in the output folder.
+    fn clean_temp_files(&self) {
+        let dir = PathBuf::from(&self.output_dir);
+        if !dir.is_dir() {
+            return;
+        }
+        let (bus, lang) = (self.bus.clone(), self.lang_manager.clone());
+        std::thread::spawn(move || match temp::clean(&dir, &temp::CleanOptions::default()) {
+            Ok(removed) if !removed.is_empty() => bus.publish(AppEvent::LogLine(lang.t_args("cli_temp_cleaned", &[removed.len()]))),
+            Ok(_) => {}
+            Err(e) => bus.publish(AppEvent::LogLine(format!("⚠️ {:#}", e))),
+        });
+    }
+
+    /// Fills the form with the settings of `job`.
+    fn restore_job(&mut self, job: &processing::ProcessingConfig) {
+        self.input_file = job.input.to_string_lossy().into_owned();
+        self.output_dir = job.output.to_string_lossy().into_owned();
+        self.output_filename = job.custom_filename.clone();
+        self.generators = job.generators.clone();
+        self.params = job.params.clone();
+        self.profiles = job.profiles.clone();
+        self.selected_model = job.selected_model;
+        self.preprocess = job.preprocess;
+        self.upscale = job.upscale;
+        self.roi = job.roi;
+        self.vectorize_original = job.vectorize_original;
+        self.mask_adjust = job.mask;
+    }
+
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            self.bus.publish(AppEvent::LogLine(format!("⚠️ Could not save settings: {}", e)));
+        }
+    }
+
+    /// Existing PNG, JPEG and SVG outputs of the current form, then its input.
+    fn preview_files(&self) -> Vec<PathBuf> {
+        let config = self.current_config();
+        let mut files: Vec<PathBuf> = processing::output_paths(&config).unwrap_or_default().into_iter()
+            .map(|(_, path)| path)
+            .filter(|path| path.is_file() && path.extension().is_some_and(|e| e == "png" || e == "jpg" || e == "svg"))
+            .collect();
+        if config.input.is_file() {
+            files.push(config.input);
+        }
+        files
+    }
+
+    /// Cut-out written for the current form, if any.
+    fn cutout_path(&self) -> Option<PathBuf> {
+        processing::output_paths(&self.current_config()).ok()?.into_iter()
+            .find(|(kind, path)| *kind == OutputKind::Alpha && path.is_file())
+            .map(|(_, path)| path)
+    }
+
+    /// Job configuration from the current form.
+    fn current_config(&self) -> processing::ProcessingConfig {
+        processing::ProcessingConfig {
             input: PathBuf::from(&self.input_file),
             output: PathBuf::from(&self.output_dir),
             custom_filename: self.output_filename.trim().to_string(),
-            gen_alpha: self.gen_alpha_transparency,
-            gen_gray: self.gen_gray,
-            gen_halftone: self.gen_halftone,
-            gen_lineart: self.gen_lineart,
-            gen_logo: self.gen_color_logo,
-            gen_illus: self.gen_color_illus,
-            gen_thumbnail: self.gen_thumbnail,
+            generators: self.generators.clone(),
+            params: self.params.clone(),
+            profiles: self.profiles.clone(),
             selected_model: self.selected_model,
-        };
+            preprocess: self.preprocess,
+            upscale: self.upscale,
+            roi: self.roi,
+            watermark: self.settings.watermark.clone(),
+            vectorize_original: self.vectorize_original,
+            mask: self.mask_adjust,
+        }
+    }
+
+    fn start_processing(&mut self) {
+        let config = self.current_config();
+        self.settings.last_job = Some(config.clone());
+        self.save_settings();
+
+        self.processing = processing::start_processing(config, self.lang_manager.clone(), self.bus.clone());
+    }
 
-        processing::start_processing(
-            config,
-            self.lang_manager.clone(),
-            LogOutput::Channel(self.log_sender.clone()),
-            Arc::clone(&self.processing),
-            Arc::clone(&self.model_status),
-            ctx,
-        );
+    fn start_quick_preview(&mut self) {
+        self.previewing = processing::start_quick_preview(self.current_config(), self.lang_manager.clone(), self.bus.clone());
     }
 }
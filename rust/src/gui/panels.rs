@@ -3,73 +3,184 @@
 use eframe::egui;
 use rfd::FileDialog;
 
-use crate::generators::{self, ModelState, ModelType};
+use crate::generators::{self, ModelState, ModelType, PreprocessOptions, preprocess};
+use crate::generators::{color_pop, presets};
+use crate::generators::mask_adjust::{HoleFill, MaskAdjust};
+use crate::generators::params::{ParamKind, ParamSpec, ParamValue, Params};
+use crate::generators::registry::{self, Section};
+use crate::estimate::SizeEstimates;
+use crate::naming::OutputKind;
 use crate::lang::LanguageManager;
-use super::processing;
+use crate::settings::Settings;
+use super::{a11y, detached, processing};
+
+/// Run asked for with the buttons of the AI column.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RunRequest {
+    /// Full-resolution job writing to the output folder.
+    Full,
+    /// Quick preview on a downscaled copy, see [`processing::start_quick_preview`].
+    Preview,
+}
+
+/// Open state of the secondary windows.
+#[derive(Default)]
+pub struct Windows {
+    pub about: bool,
+    pub translation_editor: bool,
+    pub history: bool,
+    pub cvd_preview: bool,
+    pub stats: bool,
+    /// Frame-time overlay from the Help > Debug menu.
+    pub frame_times: bool,
+    /// Terminal log in its own native window.
+    pub log_detached: bool,
+    /// Input and quick previews in their own native window.
+    pub preview_detached: bool,
+}
 
 /// Renders the menu bar with file, preferences, help menus and model status indicator.
 pub fn render_menu_bar(
     ui: &mut egui::Ui,
     ctx: &egui::Context,
     lang: &mut LanguageManager,
-    show_about: &mut bool,
+    windows: &mut Windows,
+    settings: &mut Settings,
+    update_available: Option<&(String, String)>,
     model_status: &ModelState,
 ) {
+    let rtl = lang.is_rtl();
     egui::MenuBar::new().ui(ui, |ui: &mut egui::Ui| {
-        ui.menu_button(lang.t("menu_file"), |ui: &mut egui::Ui| {
-            if ui.button(lang.t("menu_quit")).clicked() {
-                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-            }
-        });
+        if rtl {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                render_menus(ui, ctx, lang, windows, settings, update_available);
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    render_model_status_indicator(ui, model_status);
+                });
+            });
+        } else {
+            render_menus(ui, ctx, lang, windows, settings, update_available);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                render_model_status_indicator(ui, model_status);
+            });
+        }
+    });
+}
 
-        ui.menu_button(lang.t("menu_prefs"), |ui: &mut egui::Ui| {
-            ui.menu_button(lang.t("menu_lang"), |ui: &mut egui::Ui| {
-                if ui.button("Español").clicked() {
-                    lang.load_language("es");
-                    ui.close();
-                }
-                if ui.button("English").clicked() {
-                    lang.load_language("en");
-                    ui.close();
-                }
-                if ui.button("Euskara").clicked() {
-                    lang.load_language("eu");
-                    ui.close();
-                }
-                if ui.button("Latina").clicked() {
-                    lang.load_language("la");
+fn render_menus(
+    ui: &mut egui::Ui,
+    ctx: &egui::Context,
+    lang: &mut LanguageManager,
+    windows: &mut Windows,
+    settings: &mut Settings,
+    update_available: Option<&(String, String)>,
+) {
+    ui.menu_button(lang.t("menu_file"), |ui: &mut egui::Ui| {
+        if ui.button(lang.t("menu_history")).clicked() {
+            windows.history = true;
+            ui.close();
+        }
+        if ui.button(lang.t("menu_cvd_preview")).clicked() {
+            windows.cvd_preview = true;
+            ui.close();
+        }
+        if ui.button(lang.t("menu_stats")).clicked() {
+            windows.stats = true;
+            ui.close();
+        }
+        if ui.button(lang.t("menu_quit")).clicked() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    });
+
+    ui.menu_button(lang.t("menu_prefs"), |ui: &mut egui::Ui| {
+        ui.menu_button(lang.t("menu_lang"), |ui: &mut egui::Ui| {
+            for (code, name) in lang.available_languages() {
+                if ui.button(name).clicked() {
+                    lang.load_language(&code);
                     ui.close();
                 }
-            });
-        });
-
-        ui.menu_button(lang.t("menu_help"), |ui: &mut egui::Ui| {
-            if ui.button(lang.t("menu_about")).clicked() {
-                *show_about = true;
-                ui.close();
             }
         });
+        if ui.button(lang.t("menu_translation_editor")).clicked() {
+            windows.translation_editor = true;
+            ui.close();
+        }
+        if ui.button(lang.t("menu_ui_font")).on_hover_text(lang.t("menu_ui_font_hint")).clicked() {
+            if let Some(path) = FileDialog::new().add_filter("Fonts", &["ttf", "otf", "ttc"]).pick_file() {
+                settings.ui_font = Some(path);
+            }
+            ui.close();
+        }
+        if settings.ui_font.is_some() && ui.button(lang.t("menu_ui_font_reset")).clicked() {
+            settings.ui_font = None;
+            ui.close();
+        }
+        ui.checkbox(&mut settings.disable_animations, lang.t("menu_disable_animations"));
+        ui.checkbox(&mut settings.disable_update_check, lang.t("menu_disable_update_check"));
+        ui.checkbox(&mut settings.background_mode, lang.t("menu_background_mode"))
+            .on_hover_text(lang.t("menu_background_mode_hint"));
+        if cfg!(all(feature = "tray", target_os = "linux")) {
+            ui.checkbox(&mut settings.tray_icon, lang.t("menu_tray_icon"));
+        }
+    });
 
-        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-            render_model_status_indicator(ui, ctx, model_status);
+    let help_title = if update_available.is_some() {
+        format!("{} 🆕", lang.t("menu_help"))
+    } else {
+        lang.t("menu_help")
+    };
+    ui.menu_button(help_title, |ui: &mut egui::Ui| {
+        if ui.button(lang.t("menu_about")).clicked() {
+            windows.about = true;
+            ui.close();
+        }
+        if let Some((version, url)) = update_available {
+            ui.separator();
+            ui.hyperlink_to(lang.t_args("menu_update_available", &[version]), url);
+        }
+        ui.separator();
+        ui.menu_button(lang.t("menu_debug"), |ui: &mut egui::Ui| {
+            ui.checkbox(&mut windows.frame_times, lang.t("menu_frame_times"));
         });
     });
 }
 
-fn render_model_status_indicator(ui: &mut egui::Ui, ctx: &egui::Context, status: &ModelState) {
+/// Lays out a row of widgets like `ui.horizontal`, mirrored for right-to-left locales.
+pub fn row<R>(ui: &mut egui::Ui, lang: &LanguageManager, add_contents: impl FnOnce(&mut egui::Ui) -> R) -> egui::InnerResponse<R> {
+    let size = egui::vec2(ui.available_width(), ui.spacing().interact_size.y);
+    ui.allocate_ui_with_layout(size, row_layout(lang), add_contents)
+}
+
+/// Layout of a [`row`], right to left for right-to-left locales.
+pub fn row_layout(lang: &LanguageManager) -> egui::Layout {
+    if lang.is_rtl() {
+        egui::Layout::right_to_left(egui::Align::Center)
+    } else {
+        egui::Layout::left_to_right(egui::Align::Center)
+    }
+}
+
+/// Layout for the content of a column, right-aligned for right-to-left locales.
+pub fn column_layout(lang: &LanguageManager) -> egui::Layout {
+    let align = if lang.is_rtl() { egui::Align::Max } else { egui::Align::Min };
+    egui::Layout::top_down(align)
+}
+
+/// Shows the model state; while loading the dot pulses unless animations are
+/// off (see [`super::styles::set_animations`]).
+fn render_model_status_indicator(ui: &mut egui::Ui, status: &ModelState) {
+    let animate = ui.style().animation_time > 0.0;
     let color = match status {
         ModelState::Unloaded => egui::Color32::RED,
-        ModelState::Loading => {
+        ModelState::Loading if animate => {
             let time = ui.input(|i| i.time);
             let alpha = ((time * 6.0).sin() * 0.5 + 0.5) as f32;
             egui::Color32::from_rgba_unmultiplied(255, 140, 0, (alpha * 255.0) as u8)
         },
+        ModelState::Loading => egui::Color32::from_rgb(255, 140, 0),
         ModelState::Ready(_) => egui::Color32::from_rgb(0, 255, 0),
     };
-    
-    if matches!(status, ModelState::Loading) {
-        ctx.request_repaint();
-    }
 
     ui.horizontal(|ui| {
         ui.label(egui::RichText::new("AI MODEL ").size(18.0).strong());
@@ -127,13 +238,15 @@ pub fn render_io_column(
     // Input file group
     ui.group(|ui| {
         ui.set_width(col_width - 16.0);
-        ui.add(egui::Label::new(
+        let heading = ui.add(egui::Label::new(
             egui::RichText::new(format!("1. {}", lang.t("input_group")))
                 .size(18.0).strong()
         ).wrap_mode(egui::TextWrapMode::Wrap));
-        ui.horizontal(|ui| {
-            ui.add(egui::TextEdit::singleline(input_file).desired_width(text_input_width));
-            if ui.button("📂").on_hover_text(lang.t("btn_search_file")).clicked() {
+        row(ui, lang, |ui| {
+            ui.add(egui::TextEdit::singleline(input_file).desired_width(text_input_width)).labelled_by(heading.id);
+            let browse = ui.button("📂").on_hover_text(lang.t("btn_search_file"));
+            a11y::describe(&browse, lang.t("btn_search_file"), egui::WidgetInfo::new(egui::WidgetType::Button));
+            if browse.clicked() {
                 if let Some(path) = FileDialog::new()
                     .add_filter("Images", &["png", "jpg", "jpeg"])
                     .pick_file() 
@@ -152,13 +265,15 @@ pub fn render_io_column(
     // Output directory group
     ui.group(|ui| {
         ui.set_width(col_width - 16.0);
-        ui.add(egui::Label::new(
+        let heading = ui.add(egui::Label::new(
             egui::RichText::new(format!("2. {}", lang.t("output_group")))
                 .size(18.0).strong()
         ).wrap_mode(egui::TextWrapMode::Wrap));
-        ui.horizontal(|ui| {
-            ui.add(egui::TextEdit::singleline(output_dir).desired_width(text_input_width));
-            if ui.button("📁").on_hover_text(lang.t("btn_choose_folder")).clicked() {
+        row(ui, lang, |ui| {
+            ui.add(egui::TextEdit::singleline(output_dir).desired_width(text_input_width)).labelled_by(heading.id);
+            let browse = ui.button("📁").on_hover_text(lang.t("btn_choose_folder"));
+            a11y::describe(&browse, lang.t("btn_choose_folder"), egui::WidgetInfo::new(egui::WidgetType::Button));
+            if browse.clicked() {
                 if let Some(path) = FileDialog::new().pick_folder() {
                     *output_dir = path.display().to_string();
                 }
@@ -169,14 +284,15 @@ pub fn render_io_column(
     // Output filename group
     ui.group(|ui| {
         ui.set_width(col_width - 16.0);
-        ui.add(egui::Label::new(
+        let heading = ui.add(egui::Label::new(
             egui::RichText::new(format!("3. {}", lang.t("lbl_output_filename")))
                 .size(18.0).strong()
         ).wrap_mode(egui::TextWrapMode::Wrap));
-        ui.horizontal(|ui| {
+        row(ui, lang, |ui| {
             ui.add(egui::TextEdit::singleline(output_filename)
                 .hint_text("Default")
-                .desired_width(text_input_width));
+                .desired_width(text_input_width))
+                .labelled_by(heading.id);
             ui.add_space(36.0);
         });
     });
@@ -189,9 +305,10 @@ pub fn render_ai_column(
     col_width: f32,
     lang: &LanguageManager,
     selected_model: &mut ModelType,
+    focus_model: &mut bool,
     is_processing: bool,
-) -> bool {
-    let mut start_clicked = false;
+) -> Option<RunRequest> {
+    let mut request = None;
     
     ui.set_max_width(col_width);
     ui.spacing_mut().item_spacing.y = 4.0;
@@ -201,26 +318,26 @@ pub fn render_ai_column(
 
     ui.group(|ui| {
         ui.set_width(ui.available_width());
-        ui.add(egui::Label::new(
+        let heading = ui.add(egui::Label::new(
             egui::RichText::new(format!("4. {}", lang.t("label_ai_model")))
                 .size(18.0).strong()
         ).wrap_mode(egui::TextWrapMode::Wrap));
         
-        egui::ComboBox::from_id_salt("model_select")
+        let select = egui::ComboBox::from_id_salt("model_select")
             .selected_text(format!("{:?}", selected_model))
             .width(ui.available_width() - 10.0)
             .show_ui(ui, |ui| {
-                use generators::ModelType::*;
-                let models = [
-                    U2Net, U2NetP, U2NetHumanSeg, U2NetClothSeg, Silueta,
-                    IsNetGeneralUse, IsNetAnime, Sam, BiRefNetGeneral,
-                    BiRefNetGeneralLite, BiRefNetPortrait, BiRefNetDis,
-                    BiRefNetHrsod, BiRefNetCod, BiRefNetMassive, BriaRmbg
-                ];
-                for model in models {
+                for model in generators::ModelType::ALL {
                     ui.selectable_value(selected_model, model, format!("{:?}", model));
                 }
-            });
+            })
+            .response
+            .labelled_by(heading.id);
+        // Asked for by the error dialog after a model failure
+        if std::mem::take(focus_model) {
+            select.request_focus();
+            select.scroll_to_me(Some(egui::Align::Center));
+        }
         
         ui.add_space(2.0);
         ui.separator();
@@ -228,8 +345,18 @@ pub fn render_ai_column(
         ui.add(egui::Label::new(egui::RichText::new(processing::get_model_description_localized(lang, *selected_model))
             .italics()
             .size(14.0)
-            .color(egui::Color32::DARK_GRAY))
+            .color(ui.visuals().weak_text_color()))
             .wrap_mode(egui::TextWrapMode::Wrap));
+        // Auto picks a model per image, so there is no single license to show
+        if *selected_model != ModelType::Auto {
+            let config = generators::models::get_model_config(*selected_model);
+            let license = egui::RichText::new(lang.t_args("model_license", &[config.license])).size(13.0);
+            ui.label(if config.non_commercial() { license.color(ui.visuals().warn_fg_color) } else { license });
+            ui.add(egui::Label::new(egui::RichText::new(lang.t_args("model_citation", &[config.citation]))
+                .size(12.0)
+                .color(ui.visuals().weak_text_color()))
+                .wrap_mode(egui::TextWrapMode::Wrap));
+        }
     });
 
     ui.add_space(5.0);
@@ -239,26 +366,20 @@ pub fn render_ai_column(
             .min_size(egui::vec2(100.0, 28.0))
             .fill(egui::Color32::from_rgb(60, 120, 255));
         if ui.add_enabled(!is_processing, btn).clicked() {
-            start_clicked = true;
+            request = Some(RunRequest::Full);
+        }
+        let preview = egui::Button::new(lang.t("btn_quick_preview")).min_size(egui::vec2(100.0, 24.0));
+        if ui.add_enabled(!is_processing, preview).on_hover_text(lang.t("btn_quick_preview_hint")).clicked() {
+            request = Some(RunRequest::Preview);
         }
     });
     
-    start_clicked
+    request
 }
 
-/// Renders the conversion options column (column 3).
-pub fn render_options_column(
-    ui: &mut egui::Ui,
-    col_width: f32,
-    lang: &LanguageManager,
-    gen_alpha: &mut bool,
-    gen_thumbnail: &mut bool,
-    gen_gray: &mut bool,
-    gen_halftone: &mut bool,
-    gen_lineart: &mut bool,
-    gen_color_logo: &mut bool,
-    gen_color_illus: &mut bool,
-) {
+/// Renders the conversion options column (column 3): output profile chips, and
+/// a checkbox per registered generator in the custom view.
+pub fn render_options_column(ui: &mut egui::Ui, col_width: f32, lang: &LanguageManager, enabled: &mut Vec<OutputKind>, params: &mut Params, profiles: &mut Vec<String>) {
     ui.set_max_width(col_width);
     ui.spacing_mut().item_spacing.y = 4.0;
     ui.vertical_centered(|ui| {
@@ -271,13 +392,24 @@ pub fn render_options_column(
             egui::RichText::new(format!("5. {}", lang.t("options_group")))
                 .size(18.0).strong()
         ).wrap_mode(egui::TextWrapMode::Wrap));
-        
+
+        render_profile_chips(ui, lang, enabled, params, profiles);
+        if !profiles.is_empty() {
+            let labels: Vec<String> = registry::REGISTRY.iter()
+                .filter(|g| enabled.contains(&g.kind()))
+                .map(|g| lang.t(g.label_key()))
+                .collect();
+            ui.add(egui::Label::new(egui::RichText::new(lang.t_args("profile_includes", &[labels.join(", ")])).size(13.0).italics())
+                .wrap_mode(egui::TextWrapMode::Wrap));
+            return;
+        }
+        ui.add_space(2.0);
+
         // PNG Subcategory
         ui.add(egui::Label::new(egui::RichText::new("PNG").strong().size(14.0)));
         ui.indent("png_indent", |ui| {
             ui.spacing_mut().item_spacing.y = 1.0;
-            ui.checkbox(gen_alpha, egui::RichText::new(lang.t("chk_transparent")).size(14.0));
-            ui.checkbox(gen_thumbnail, egui::RichText::new(lang.t("chk_thumbnail")).size(14.0));
+            render_generator_checkboxes(ui, lang, enabled, params, Section::Png);
         });
 
         ui.add_space(2.0);
@@ -286,29 +418,266 @@ pub fn render_options_column(
         ui.add(egui::Label::new(egui::RichText::new(lang.t("group_svg")).strong().size(14.0)));
         ui.indent("svg_indent", |ui| {
             ui.spacing_mut().item_spacing.y = 1.0;
-            // Black and White subgroup
             ui.add(egui::Label::new(egui::RichText::new(lang.t("subgroup_bw")).size(14.0)));
-            ui.checkbox(gen_gray, egui::RichText::new(lang.t("chk_grayscale")).size(14.0));
-            ui.checkbox(gen_halftone, egui::RichText::new(lang.t("chk_halftone")).size(14.0));
-            ui.checkbox(gen_lineart, egui::RichText::new(lang.t("chk_lineart")).size(14.0));
-            // Color subgroup
+            render_generator_checkboxes(ui, lang, enabled, params, Section::SvgMono);
             ui.add(egui::Label::new(egui::RichText::new(lang.t("subgroup_color")).size(14.0)));
-            ui.checkbox(gen_color_logo, egui::RichText::new(lang.t("chk_logo")).size(14.0));
-            ui.checkbox(gen_color_illus, egui::RichText::new(lang.t("chk_illus")).size(14.0));
+            render_generator_checkboxes(ui, lang, enabled, params, Section::SvgColor);
         });
     });
 }
 
-/// Renders the terminal log panel at the bottom.
-pub fn render_terminal_log(ui: &mut egui::Ui, logs: &[String]) {
+/// Chips toggling the output presets, plus "Custom" for the per-generator checkboxes.
+/// Selecting presets replaces the generators and parameters with their combination.
+fn render_profile_chips(ui: &mut egui::Ui, lang: &LanguageManager, enabled: &mut Vec<OutputKind>, params: &mut Params, profiles: &mut Vec<String>) {
+    ui.with_layout(row_layout(lang).with_main_wrap(true), |ui| {
+        for preset in presets::PRESETS {
+            let mut on = profiles.iter().any(|p| p == preset.name);
+            if ui.toggle_value(&mut on, egui::RichText::new(lang.t(preset.label_key)).size(14.0)).changed() {
+                profiles.retain(|p| p != preset.name);
+                if on {
+                    profiles.push(preset.name.to_string());
+                }
+                if !profiles.is_empty() {
+                    (*enabled, *params) = presets::combine(profiles);
+                }
+            }
+        }
+        // The custom view starts from the current selection
+        let mut custom = profiles.is_empty();
+        if ui.toggle_value(&mut custom, egui::RichText::new(lang.t("profile_custom")).size(14.0)).changed() && custom {
+            profiles.clear();
+        }
+    });
+}
+
+fn render_generator_checkboxes(ui: &mut egui::Ui, lang: &LanguageManager, enabled: &mut Vec<OutputKind>, params: &mut Params, section: Section) {
+    for &generator in registry::REGISTRY.iter().filter(|g| g.section() == section) {
+        let kind = generator.kind();
+        let mut on = enabled.contains(&kind);
+        if ui.checkbox(&mut on, egui::RichText::new(lang.t(generator.label_key())).size(14.0)).changed() {
+            enabled.retain(|k| *k != kind);
+            if on {
+                enabled.push(kind);
+            }
+        }
+        if on && !generator.params().is_empty() {
+            ui.indent(("advanced_indent", generator.name()), |ui| {
+                egui::CollapsingHeader::new(egui::RichText::new(lang.t("lbl_advanced")).size(12.0))
+                    .id_salt(("advanced", generator.name()))
+                    .show(ui, |ui| {
+                        for spec in generator.params() {
+                            render_param(ui, lang, params.entry(generator, spec), spec);
+                        }
+                    });
+            });
+        }
+    }
+}
+
+/// Renders the control matching a parameter's kind.
+fn render_param(ui: &mut egui::Ui, lang: &LanguageManager, value: &mut ParamValue, spec: &ParamSpec) {
+    let label = egui::RichText::new(lang.t(spec.label_key)).size(14.0);
+    match (spec.kind, value) {
+        (ParamKind::Int { min, max, .. }, ParamValue::Number(n)) => {
+            let mut v = *n as i64;
+            ui.add(egui::Slider::new(&mut v, min..=max).text(label));
+            *n = v as f64;
+        }
+        (ParamKind::Float { min, max, step, .. }, ParamValue::Number(n)) => {
+            ui.add(egui::Slider::new(n, min..=max).step_by(step).text(label));
+        }
+        (ParamKind::Choice { options, .. }, ParamValue::Text(t)) => {
+            row(ui, lang, |ui| {
+                let label = ui.label(label);
+                egui::ComboBox::from_id_salt(spec.label_key)
+                    .selected_text(t.as_str())
+                    .show_ui(ui, |ui| {
+                        for option in options {
+                            ui.selectable_value(t, option.to_string(), *option);
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            });
+        }
+        (ParamKind::Color { .. }, ParamValue::Text(t)) => {
+            row(ui, lang, |ui| {
+                let mut rgb = color_pop::parse_tint(t).unwrap_or([255, 255, 255]);
+                let button = ui.color_edit_button_srgb(&mut rgb);
+                a11y::describe(&button, lang.t(spec.label_key), egui::WidgetInfo::new(egui::WidgetType::ColorButton));
+                if button.changed() {
+                    *t = format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]);
+                }
+                ui.label(label);
+            });
+        }
+        // `Params::entry` resets mistyped values to the default
+        _ => {}
+    }
+}
+
+/// Renders the input pre-processing, upscaling and vector source controls below the conversion options.
+pub fn render_preprocess_group(ui: &mut egui::Ui, lang: &LanguageManager, opts: &mut PreprocessOptions, upscale: &mut Option<u32>, vectorize_original: &mut bool) {
+    ui.group(|ui| {
+        ui.set_width(ui.available_width());
+        ui.add(egui::Label::new(egui::RichText::new(lang.t("group_preprocess")).strong().size(14.0)));
+        ui.indent("preprocess_indent", |ui| {
+            ui.spacing_mut().item_spacing.y = 1.0;
+            ui.checkbox(&mut opts.auto_levels, egui::RichText::new(lang.t("chk_auto_levels")).size(14.0));
+            ui.checkbox(&mut opts.white_balance, egui::RichText::new(lang.t("chk_white_balance")).size(14.0));
+            ui.add(egui::Slider::new(&mut opts.exposure_ev, preprocess::EXPOSURE_RANGE)
+                .step_by(0.1)
+                .text(egui::RichText::new(lang.t("lbl_exposure")).size(14.0)));
+            ui.checkbox(&mut opts.deblock, egui::RichText::new(lang.t("chk_deblock")).size(14.0));
+            ui.add(egui::Slider::new(&mut opts.denoise, 0.0..=1.0)
+                .step_by(0.05)
+                .text(egui::RichText::new(lang.t("lbl_denoise")).size(14.0)));
+            row(ui, lang, |ui| {
+                let heading = ui.label(egui::RichText::new(lang.t("lbl_upscale")).size(14.0));
+                let label = |factor: Option<u32>| factor.map_or_else(|| lang.t("upscale_off"), |f| format!("{}×", f));
+                egui::ComboBox::from_id_salt("upscale_combo")
+                    .selected_text(label(*upscale))
+                    .show_ui(ui, |ui| {
+                        for factor in [None, Some(2), Some(4)] {
+                            ui.selectable_value(upscale, factor, label(factor));
+                        }
+                    })
+                    .response
+                    .labelled_by(heading.id);
+            });
+            ui.checkbox(vectorize_original, egui::RichText::new(lang.t("chk_vectorize_original")).size(14.0))
+                .on_hover_text(lang.t("chk_vectorize_original_hint"));
+        });
+    });
+}
+
+/// Renders the mask strength, levels and threshold curve controls below the model choice.
+pub fn render_mask_group(ui: &mut egui::Ui, lang: &LanguageManager, mask: &mut MaskAdjust) {
     ui.group(|ui| {
         ui.set_width(ui.available_width());
-        ui.label(egui::RichText::new("Terminal Log").strong());
+        ui.add(egui::Label::new(egui::RichText::new(lang.t("group_mask")).strong().size(14.0)));
+        ui.indent("mask_indent", |ui| {
+            ui.spacing_mut().item_spacing.y = 1.0;
+            let mut percent = mask.strength * 100.0;
+            if ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).step_by(1.0).suffix("%")
+                .text(egui::RichText::new(lang.t("lbl_mask_strength")).size(14.0)))
+                .on_hover_text(lang.t("lbl_mask_strength_hint"))
+                .changed()
+            {
+                mask.strength = percent / 100.0;
+            }
+            row(ui, lang, |ui| {
+                mask_curve_editor(ui, mask);
+                ui.vertical(|ui| {
+                    ui.add(egui::Slider::new(&mut mask.threshold, 0.0..=1.0).step_by(0.01)
+                        .text(egui::RichText::new(lang.t("lbl_mask_threshold")).size(14.0)));
+                    ui.add(egui::Slider::new(&mut mask.softness, 0.0..=1.0).step_by(0.01)
+                        .text(egui::RichText::new(lang.t("lbl_mask_softness")).size(14.0)));
+                    if ui.add_enabled(!mask.is_identity(), egui::Button::new(lang.t("btn_reset_mask"))).clicked() {
+                        *mask = MaskAdjust::default();
+                    }
+                });
+            });
+            ui.add(egui::Slider::new(&mut mask.black, 0.0..=0.99).step_by(0.01)
+                .text(egui::RichText::new(lang.t("lbl_mask_black")).size(14.0)));
+            ui.add(egui::Slider::new(&mut mask.white, 0.01..=1.0).step_by(0.01)
+                .text(egui::RichText::new(lang.t("lbl_mask_white")).size(14.0)));
+            ui.add(egui::Slider::new(&mut mask.gamma, 0.2..=5.0).logarithmic(true)
+                .text(egui::RichText::new(lang.t("lbl_mask_gamma")).size(14.0)))
+                .on_hover_text(lang.t("lbl_mask_gamma_hint"));
+            // Keep the black point below the white point whichever slider moved
+            if mask.black >= mask.white {
+                mask.black = (mask.white - 0.01).max(0.0);
+            }
+            ui.checkbox(&mut mask.invert, egui::RichText::new(lang.t("chk_invert_mask")).size(14.0))
+                .on_hover_text(lang.t("chk_invert_mask_hint"));
+            ui.add_enabled_ui(mask.invert, |ui| hole_fill_selector(ui, lang, &mut mask.fill));
+        });
+    });
+}
+
+/// Fill for the subject hole of an inverted mask: a combo box, plus a color
+/// button for the solid fill.
+fn hole_fill_selector(ui: &mut egui::Ui, lang: &LanguageManager, fill: &mut HoleFill) {
+    let label_key = |fill: &HoleFill| match fill {
+        HoleFill::None => "hole_fill_none",
+        HoleFill::Blur => "hole_fill_blur",
+        HoleFill::Solid(_) => "hole_fill_solid",
+    };
+    row(ui, lang, |ui| {
+        ui.label(egui::RichText::new(lang.t("lbl_hole_fill")).size(14.0));
+        egui::ComboBox::from_id_salt("hole_fill")
+            .selected_text(lang.t(label_key(fill)))
+            .show_ui(ui, |ui| {
+                for choice in [HoleFill::None, HoleFill::Blur] {
+                    ui.selectable_value(fill, choice, lang.t(label_key(&choice)));
+                }
+                if ui.selectable_label(matches!(fill, HoleFill::Solid(_)), lang.t("hole_fill_solid")).clicked()
+                    && !matches!(fill, HoleFill::Solid(_))
+                {
+                    *fill = HoleFill::Solid([128, 128, 128]);
+                }
+            });
+        if let HoleFill::Solid(color) = fill {
+            ui.color_edit_button_srgb(color);
+        }
+    });
+}
+
+/// Plot of the mask curve (model mask across, applied alpha up). Dragging moves
+/// the threshold sideways and the softness up and down.
+fn mask_curve_editor(ui: &mut egui::Ui, mask: &mut MaskAdjust) {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(72.0, 72.0), egui::Sense::drag());
+    if response.dragged() {
+        let delta = response.drag_delta() / rect.size();
+        mask.threshold = (mask.threshold + delta.x).clamp(0.0, 1.0);
+        mask.softness = (mask.softness - delta.y).clamp(0.0, 1.0);
+    }
+
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    painter.line_segment([rect.left_bottom(), rect.right_top()], egui::Stroke::new(1.0, visuals.weak_text_color()));
+    let to_screen = |x: f32, y: f32| egui::pos2(rect.left() + x * rect.width(), rect.bottom() - y * rect.height());
+    let points: Vec<egui::Pos2> = (0..=64)
+        .map(|i| i as f32 / 64.0)
+        .map(|x| to_screen(x, mask.transfer(x)))
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(2.0, visuals.selection.bg_fill)));
+    painter.rect_stroke(rect, 2.0, visuals.widgets.noninteractive.bg_stroke, egui::StrokeKind::Inside);
+}
+
+/// Shows the estimated size of the enabled outputs for an input of `megapixels`,
+/// each output's estimate on hover.
+pub fn render_size_estimate(ui: &mut egui::Ui, lang: &LanguageManager, estimates: &SizeEstimates, kinds: &[OutputKind], megapixels: f64) {
+    let (total, unknown) = estimates.total(kinds, megapixels);
+    let mut text = lang.t_args("size_estimate", &[lang.format_size(total)]);
+    if !unknown.is_empty() {
+        text = format!("{} {}", text, lang.t_args("size_estimate_unknown", &[unknown.len()]));
+    }
+    let details: Vec<String> = kinds.iter()
+        .map(|&kind| match estimates.estimate(kind, megapixels) {
+            Some(bytes) => format!("{}: ~{}", kind.suffix(), lang.format_size(bytes)),
+            None => format!("{}: ?", kind.suffix()),
+        })
+        .collect();
+    ui.label(egui::RichText::new(text).size(14.0)).on_hover_text(details.join("\n"));
+}
+
+/// Renders the terminal log panel at the bottom, with a button detaching it into its own window or
+/// attaching it back; `max_height` limits the scrolled area.
+pub fn render_terminal_log(ui: &mut egui::Ui, lang: &LanguageManager, logs: &[String], detached: &mut bool, max_height: f32) {
+    ui.group(|ui| {
+        ui.set_width(ui.available_width());
+        row(ui, lang, |ui| {
+            ui.label(egui::RichText::new("Terminal Log").strong());
+            detached::toggle(ui, detached, lang.t(if *detached { "btn_attach" } else { "btn_detach" }));
+        });
         
         egui::ScrollArea::vertical()
             .id_salt("log_scroll")
             .stick_to_bottom(true)
-            .max_height(200.0)
+            .max_height(max_height)
             .show(ui, |ui| {
                 ui.set_min_width(ui.available_width());
                 ui.set_min_height(100.0);
@@ -1,18 +1,40 @@
 //! Processing logic for the GUI.
 
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::thread;
-use anyhow::{Result, Context};
-use eframe::egui;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use image::RgbaImage;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
 
-use crate::generators::{self, LogOutput, ModelState, ModelType};
+use crate::error::AlphasvgError;
+use crate::estimate::{self, SizeEstimates};
+use crate::events::{AppEvent, EventBus, JobFailure};
+use crate::generators::{self, LogOutput, ModelType, PreprocessOptions, preprocess, upscale, watermark};
+use crate::generators::alpha::Roi;
+use crate::generators::mask_adjust::MaskAdjust;
+use crate::generators::metadata::{ImageMetadata, MetadataMapping};
+use crate::generators::params::Params;
+use crate::generators::registry::{self, GenerateContext, Generator};
+use crate::generators::watermark::WatermarkOptions;
+use crate::history::{self, HistoryEntry};
 use crate::lang::LanguageManager;
+use crate::naming::{self, OutputKind, OutputNaming};
+use crate::provenance::Provenance;
+use crate::stats;
+use super::cvd_preview;
+
+/// Longest edge, in pixels, of the input copy a quick preview runs on.
+pub const QUICK_PREVIEW_PX: u32 = 512;
 
 /// Returns the localized description for a given AI model type.
 pub fn get_model_description_localized(lang: &LanguageManager, model: ModelType) -> String {
     use ModelType::*;
     let key = match model {
+        Auto => "desc_auto",
         U2Net => "desc_u2net",
         U2NetP => "desc_u2netp",
         U2NetHumanSeg => "desc_u2net_human",
@@ -34,121 +56,233 @@ pub fn get_model_description_localized(lang: &LanguageManager, model: ModelType)
 }
 
 /// Configuration for starting a processing job.
+///
+/// The last one started from the GUI is persisted in the settings and can be
+/// replayed headless with `run-last`.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProcessingConfig {
     pub input: PathBuf,
     pub output: PathBuf,
     pub custom_filename: String,
-    pub gen_alpha: bool,
-    pub gen_gray: bool,
-    pub gen_halftone: bool,
-    pub gen_lineart: bool,
-    pub gen_logo: bool,
-    pub gen_illus: bool,
-    pub gen_thumbnail: bool,
+    /// Selected outputs, see [`registry`].
+    #[serde(default = "registry::default_kinds")]
+    pub generators: Vec<OutputKind>,
+    /// Generator parameters, see [`crate::generators::params`].
+    #[serde(default)]
+    pub params: Params,
+    /// Output presets the generators and parameters were taken from; empty when picked one by one.
+    #[serde(default)]
+    pub profiles: Vec<String>,
     pub selected_model: ModelType,
+    #[serde(default)]
+    pub preprocess: PreprocessOptions,
+    /// Super-resolution factor (2 or 4) applied before the SVG generators.
+    #[serde(default)]
+    pub upscale: Option<u32>,
+    /// Region of the input to process, the whole image when unset.
+    #[serde(default)]
+    pub roi: Option<Roi>,
+    /// Watermark taken from the settings when the job started.
+    #[serde(default)]
+    pub watermark: Option<WatermarkOptions>,
+    /// Vector outputs trace the input as-is instead of the cut-out.
+    #[serde(default)]
+    pub vectorize_original: bool,
+    /// Threshold curve and strength applied to the mask.
+    #[serde(default)]
+    pub mask: MaskAdjust,
+}
+
+impl ProcessingConfig {
+    /// Selected generators, in registry order.
+    fn enabled(&self) -> impl Iterator<Item = &'static dyn Generator> + '_ {
+        registry::REGISTRY.iter().copied().filter(|g| self.generators.contains(&g.kind()))
+    }
+}
+
+/// Returns the output files the GUI produces for `config`.
+pub fn output_paths(config: &ProcessingConfig) -> Result<Vec<(OutputKind, PathBuf)>> {
+    let naming = OutputNaming::new(&config.input, Some(&config.custom_filename), naming::DEFAULT_TEMPLATE)?;
+    Ok(config.enabled()
+        .map(|g| (g.kind(), naming.path(&config.output, g.kind()).with_extension(g.extension(&config.params))))
+        .collect())
 }
 
-/// Spawns a background thread to process the image.
-pub fn start_processing(
-    config: ProcessingConfig,
-    lang: LanguageManager,
-    logger: LogOutput,
-    processing: Arc<Mutex<bool>>,
-    model_status: Arc<Mutex<ModelState>>,
-    ctx: egui::Context,
-) {
+/// Spawns a background thread to process the image, publishing its log,
+/// progress and completion on `bus`. Returns false when the paths are invalid.
+pub fn start_processing(config: ProcessingConfig, lang: LanguageManager, bus: EventBus) -> bool {
+    let logger = LogOutput::Events(bus.clone());
     if !config.input.exists() || !config.output.exists() {
         logger.send(lang.t("error_invalid_paths"));
-        return;
+        return false;
     }
 
-    *processing.lock().unwrap() = true;
     logger.send(lang.t("status_processing"));
 
     thread::spawn(move || {
-        let res = run_processing_pipeline(&config, &lang, &logger, &model_status, &ctx);
+        let start = Instant::now();
+        let failure = run_pipeline(&config, &lang, &logger).err().map(|e| JobFailure::new(&e, &config.input, &lang));
+        record_history(config, start.elapsed(), failure.as_ref().map(|f| f.message.clone()));
+        bus.publish(AppEvent::JobFinished { failure });
+    });
+    true
+}
 
-        if let Err(e) = res {
-            logger.send(format!("Error: {}", e));
-        }
-        
-        *processing.lock().unwrap() = false;
-        ctx.request_repaint();
+/// Appends a finished run to the history with the outputs it left on disk, and
+/// a successful one to the usage statistics when they are on.
+pub fn record_history(config: ProcessingConfig, elapsed: Duration, error: Option<String>) {
+    let outputs: Vec<PathBuf> = output_paths(&config).unwrap_or_default().into_iter()
+        .map(|(_, path)| path)
+        .filter(|path| path.exists())
+        .collect();
+    // History and statistics are informational; failing to write them must not fail the job
+    if error.is_none() {
+        let _ = stats::record_job(&config.input, &config.selected_model.name(), elapsed, outputs.clone());
+    }
+    let _ = history::record(&HistoryEntry::new(config, outputs, error));
+}
+
+/// Spawns a background thread running the selected generators on a copy of the
+/// input downscaled to [`QUICK_PREVIEW_PX`], publishing the rendered results on
+/// `bus`. Everything is written to a temporary directory: the output folder,
+/// the history and the statistics are left alone. Returns false when the input
+/// is invalid.
+pub fn start_quick_preview(config: ProcessingConfig, lang: LanguageManager, bus: EventBus) -> bool {
+    let logger = LogOutput::Events(bus.clone());
+    if !config.input.is_file() {
+        logger.send(lang.t("error_invalid_paths"));
+        return false;
+    }
+
+    logger.send(lang.t_args("status_quick_preview", &[QUICK_PREVIEW_PX.to_string()]));
+
+    thread::spawn(move || {
+        let (images, error) = match run_quick_preview(&config, &lang, &logger) {
+            Ok(images) => (images, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        bus.publish(AppEvent::PreviewReady { input: config.input, images, error });
     });
+    true
 }
 
-fn run_processing_pipeline(
-    config: &ProcessingConfig,
-    lang: &LanguageManager,
-    logger: &LogOutput,
-    model_status: &Arc<Mutex<ModelState>>,
-    ctx: &egui::Context,
-) -> Result<()> {
-    let file_stem = config.input.file_stem().context("No filename")?.to_str().context("Decodification error")?;
-    let base_name = if config.custom_filename.is_empty() {
-        file_stem.to_string()
-    } else {
-        config.custom_filename.clone()
-    };
-    
-    let paths = [
-        ("alpha", config.output.join(format!("{}_alpha.png", base_name))),
-        ("gray", config.output.join(format!("{}_gray.svg", base_name))),
-        ("halftone", config.output.join(format!("{}_halftone.svg", base_name))),
-        ("lineart", config.output.join(format!("{}_lineart.svg", base_name))),
-        ("color_logo", config.output.join(format!("{}_logo.svg", base_name))),
-        ("color_illus", config.output.join(format!("{}_illustration.svg", base_name))),
-        ("thumb", config.output.join(format!("{}_thumb.png", base_name))),
-    ];
-
-    let any_conversion = config.gen_gray || config.gen_halftone || config.gen_lineart || config.gen_logo || config.gen_illus;
-    let needs_alpha_gen = config.gen_alpha || any_conversion;
+/// Runs `config` on a downscaled copy of its input and loads the outputs that
+/// can be shown: rasters as they are, SVGs rendered.
+fn run_quick_preview(config: &ProcessingConfig, lang: &LanguageManager, logger: &LogOutput) -> Result<Vec<(OutputKind, Arc<RgbaImage>)>> {
+    let dir = tempfile::tempdir().map_err(AlphasvgError::io(&std::env::temp_dir()))?;
+    let img = image::open(&config.input).map_err(|e| AlphasvgError::decode(&config.input, e))?;
+    let factor = (QUICK_PREVIEW_PX as f32 / img.width().max(img.height()) as f32).min(1.0);
+    let small = if factor < 1.0 { img.resize(QUICK_PREVIEW_PX, QUICK_PREVIEW_PX, FilterType::Triangle) } else { img };
+    let stem = config.input.file_stem().unwrap_or_default();
+    let input = dir.path().join(stem).with_extension("png");
+    small.save(&input).map_err(|e| AlphasvgError::encode(&input, e))?;
 
-    let img = if needs_alpha_gen {
-         logger.send(lang.t("status_gen_alpha"));
-         ctx.request_repaint();
-         let out_path = if config.gen_alpha { Some(paths[0].1.as_path()) } else { None };
-         generators::generate_alpha_png(&config.input, out_path, lang, logger, model_status, config.selected_model)?
-    } else {
-         image::open(&config.input).context("Failed to open input image")?
+    let scale = |v: u32| (v as f32 * factor).round() as u32;
+    let preview = ProcessingConfig {
+        input,
+        output: dir.path().to_path_buf(),
+        // The JPEG block grid does not survive the downscale
+        preprocess: PreprocessOptions { deblock: false, ..config.preprocess },
+        upscale: None,
+        roi: config.roi.map(|r| Roi { x: scale(r.x), y: scale(r.y), width: scale(r.width).max(1), height: scale(r.height).max(1) }),
+        watermark: None,
+        ..config.clone()
     };
+    let outputs = generate_outputs(&preview, lang, logger)?;
+    logger.send(lang.t("status_done"));
 
-    if config.gen_gray {
-        logger.send(lang.t("status_gen_gray"));
-        ctx.request_repaint();
-        generators::generate_grayscale_svg(&img, &paths[1].1, 8, lang, logger)?;
+    let mut images = Vec::new();
+    for generator in preview.enabled() {
+        // Outputs that are not images (PDF, DXF...) have nothing to show
+        if let Some(path) = outputs.get(&generator.kind())
+            && let Ok(image) = cvd_preview::load_image(path)
+        {
+            images.push((generator.kind(), Arc::new(image)));
+        }
     }
+    Ok(images)
+}
 
-    if config.gen_halftone {
-        logger.send(lang.t("status_gen_halftone"));
-        ctx.request_repaint();
-        generators::generate_halftone_svg(&img, &paths[2].1, lang, logger)?;
+/// Runs the configured generators synchronously, reporting progress through `logger`.
+#[tracing::instrument(name = "image", skip_all, fields(file = %config.input.display()))]
+pub fn run_pipeline(config: &ProcessingConfig, lang: &LanguageManager, logger: &LogOutput) -> Result<()> {
+    let outputs = generate_outputs(config, lang, logger)?;
+    if let Some(megapixels) = estimate::megapixels(&config.input) {
+        let written: Vec<(OutputKind, PathBuf)> = outputs.iter().map(|(kind, path)| (*kind, path.clone())).collect();
+        // Estimates are informational; failing to store them must not fail the job
+        let _ = SizeEstimates::record(&written, megapixels);
     }
 
-    if config.gen_lineart {
-        logger.send(lang.t("status_gen_lineart"));
-        ctx.request_repaint();
-        generators::generate_lineart_svg(&img, &paths[3].1, lang, logger)?;
+    logger.send(lang.t("status_done"));
+    // The alpha stage stamped its PNG while writing it
+    if let Some(mark) = &config.watermark {
+        let stamped = outputs.iter().filter(|(kind, _)| **kind != OutputKind::Alpha).map(|(_, path)| path);
+        for path in stamped.filter(|p| watermark::is_stampable(p)) {
+            watermark::stamp_file(path, mark)?;
+        }
     }
 
-    if config.gen_logo {
-        logger.send(lang.t("status_gen_logo"));
-        ctx.request_repaint();
-        generators::generate_logo(&img, &paths[4].1, lang, logger)?;
-    }
+    Ok(())
+}
 
-    if config.gen_illus {
-        logger.send(lang.t("status_gen_illus"));
-        ctx.request_repaint();
-        generators::generate_illustration(&img, &paths[5].1, lang, logger)?;
-    }
+/// Writes the outputs of the selected generators, returning where each went.
+fn generate_outputs(config: &ProcessingConfig, lang: &LanguageManager, logger: &LogOutput) -> Result<HashMap<OutputKind, PathBuf>> {
+    let outputs: HashMap<OutputKind, PathBuf> = output_paths(config)?.into_iter().collect();
 
-    if config.gen_thumbnail {
-        logger.send(lang.t("status_gen_thumb"));
-        ctx.request_repaint();
-        generators::generate_thumbnail(&img, &paths[6].1, lang, logger)?;
-    }
+    let any_conversion = config.enabled().any(|g| g.is_vector());
+    let needs_alpha_gen = config.enabled().any(|g| g.needs_cutout() && !(config.vectorize_original && g.is_vector()));
 
-    logger.send(lang.t("status_done"));
-    Ok(())
+    // The alpha stage writes the cut-out itself, so it is not run again in the loop
+    let steps: Vec<&dyn Generator> = config.enabled().filter(|g| g.kind() != OutputKind::Alpha).collect();
+    let total = steps.len() + usize::from(needs_alpha_gen);
+    let mut done = 0;
+
+    let img = if needs_alpha_gen {
+         logger.send(lang.t("status_gen_alpha"));
+         logger.progress(done, total);
+         let out_path = outputs.get(&OutputKind::Alpha).map(PathBuf::as_path);
+         let alpha_opts = generators::AlphaOptions {
+             preprocess: config.preprocess,
+             roi: config.roi,
+             mask: config.mask,
+             watermark: config.watermark.clone(),
+             ..Default::default()
+         };
+         let mut alpha = generators::generate_alpha_png(&config.input, out_path, &alpha_opts, lang, logger, config.selected_model)?;
+         if let Some(mask) = alpha.mask.take() {
+             logger.mask_ready(&config.input, mask);
+         }
+         done += 1;
+         alpha.image
+    } else {
+         preprocess::open(&config.input, &config.preprocess, lang, logger)?
+    };
+
+    // Without a cut-out, `img` already is the corrected input
+    let original = if config.vectorize_original && any_conversion && needs_alpha_gen {
+        Some(preprocess::open(&config.input, &config.preprocess, lang, logger)?)
+    } else {
+        None
+    };
+    let vector_base = original.as_ref().unwrap_or(&img);
+    let upscaled = match config.upscale {
+        Some(factor) if any_conversion => upscale::upscale(vector_base, factor, generators::SvgOptions::default().max_working_px, lang, logger)?,
+        _ => None,
+    };
+    let svg_source = upscaled.as_ref().unwrap_or(vector_base);
+
+    let metadata = ImageMetadata::read(&config.input).ok().and_then(|meta| MetadataMapping::default().apply(&meta));
+    let provenance = Provenance::collect(Vec::new(), serde_json::to_value(config).unwrap_or_default());
+    let svg_opts = generators::SvgOptions { metadata, provenance: Some(provenance), ..Default::default() };
+    let ctx = GenerateContext { input: &config.input, transform: config.preprocess.transform, cutout: &img, svg: &svg_opts, params: &config.params, lang, logger };
+    for generator in steps {
+        logger.send(lang.t(generator.status_key()));
+        logger.progress(done, total);
+        let source = if generator.is_vector() { svg_source } else { &img };
+        let _span = tracing::info_span!("generate", kind = ?generator.kind()).entered();
+        generator.generate(source, &outputs[&generator.kind()], &ctx)?;
+        done += 1;
+    }
+    logger.progress(done, total);
+    Ok(outputs)
 }
@@ -0,0 +1,99 @@
+//! Mipmap-style image pyramids for the previews.
+//!
+//! A [`Pyramid`] keeps an image at full resolution and halved again and again
+//! down to [`SMALLEST_EDGE`]. Views draw the smallest level that still covers
+//! the pixels they fill, uploaded once through [`LevelTextures`], so repainting
+//! a 50 MP input costs no more than repainting a thumbnail.
+
+use eframe::egui;
+use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
+
+/// Longest edge below which no further level is made.
+const SMALLEST_EDGE: u32 = 256;
+
+pub struct Pyramid {
+    /// Full resolution first, each level half the size of the one before.
+    levels: Vec<RgbaImage>,
+}
+
+impl Pyramid {
+    pub fn new(image: RgbaImage) -> Self {
+        let mut levels = vec![image];
+        while let Some(last) = levels.last()
+            && last.width().max(last.height()) > SMALLEST_EDGE
+        {
+            levels.push(halve(last));
+        }
+        Self { levels }
+    }
+
+    /// Size of the full-resolution image.
+    pub fn size(&self) -> [u32; 2] {
+        [self.levels[0].width(), self.levels[0].height()]
+    }
+
+    pub fn level(&self, index: usize) -> &RgbaImage {
+        &self.levels[index]
+    }
+
+    /// Index of the smallest level whose longest edge still covers `pixels`
+    /// physical pixels and fits in a texture of `max_side`.
+    pub fn level_for(&self, pixels: f32, max_side: usize) -> usize {
+        let edge = |img: &RgbaImage| img.width().max(img.height());
+        let fits = self.levels.iter().position(|img| edge(img) as usize <= max_side).unwrap_or(self.levels.len() - 1);
+        (fits..self.levels.len())
+            .take_while(|&i| edge(&self.levels[i]) as f32 >= pixels)
+            .last()
+            .unwrap_or(fits)
+    }
+
+    /// Level to draw for a view `points` long on its longest edge.
+    pub fn level_for_view(&self, ctx: &egui::Context, points: f32) -> usize {
+        let max_side = ctx.input(|i| i.max_texture_side);
+        self.level_for(points * ctx.pixels_per_point(), max_side)
+    }
+}
+
+/// Averages 2×2 blocks; an odd last row or column is averaged with itself.
+fn halve(img: &RgbaImage) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    RgbaImage::from_fn(width.div_ceil(2), height.div_ceil(2), |x, y| {
+        let (x0, y0) = (x * 2, y * 2);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let mut sum = [0u32; 4];
+        for (px, py) in [(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+            for (s, c) in sum.iter_mut().zip(img.get_pixel(px, py).0) {
+                *s += c as u32;
+            }
+        }
+        Rgba(sum.map(|s| ((s + 2) / 4) as u8))
+    })
+}
+
+/// Textures of the levels of one pyramid, each uploaded the first time it is
+/// drawn.
+pub struct LevelTextures {
+    name: &'static str,
+    textures: HashMap<usize, egui::TextureHandle>,
+}
+
+impl LevelTextures {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, textures: HashMap::new() }
+    }
+
+    /// Texture of `level`, uploading the image made by `render` if it has none yet.
+    pub fn get(&mut self, ctx: &egui::Context, level: usize, render: impl FnOnce() -> RgbaImage) -> &egui::TextureHandle {
+        self.textures.entry(level).or_insert_with(|| {
+            let img = render();
+            let size = [img.width() as usize, img.height() as usize];
+            ctx.load_texture(self.name, egui::ColorImage::from_rgba_unmultiplied(size, img.as_raw()), egui::TextureOptions::LINEAR)
+        })
+    }
+
+    /// Drops the textures, e.g. when what `render` draws has changed.
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
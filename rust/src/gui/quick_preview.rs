@@ -0,0 +1,76 @@
+//! Results of the last quick preview, shown under the input preview.
+
+use eframe::egui;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::generators::registry;
+use crate::lang::LanguageManager;
+use crate::naming::OutputKind;
+use super::a11y;
+use super::backdrop::{Backdrop, BackdropPainter};
+use super::processing::QUICK_PREVIEW_PX;
+
+/// Outputs of a quick preview run, one of which is shown.
+#[derive(Default)]
+pub struct QuickPreview {
+    input: Option<PathBuf>,
+    images: Vec<(OutputKind, Arc<RgbaImage>)>,
+    error: Option<String>,
+    selected: Option<OutputKind>,
+    texture: Option<(OutputKind, egui::TextureHandle)>,
+    backdrop_painter: BackdropPainter,
+}
+
+impl QuickPreview {
+    /// Replaces the shown results with those of a finished run on `input`.
+    pub fn set_results(&mut self, input: PathBuf, images: Vec<(OutputKind, Arc<RgbaImage>)>, error: Option<String>) {
+        if self.selected.is_none_or(|kind| !images.iter().any(|(k, _)| *k == kind)) {
+            self.selected = images.first().map(|(kind, _)| *kind);
+        }
+        self.input = Some(input);
+        self.images = images;
+        self.error = error;
+        self.texture = None;
+    }
+
+    /// Shows the results `width` points wide over `backdrop`, while they are of `input`.
+    pub fn show(&mut self, ui: &mut egui::Ui, lang: &LanguageManager, input: &str, backdrop: &Backdrop, width: f32) {
+        if self.input.as_deref() != Some(Path::new(input)) {
+            return;
+        }
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new(lang.t_args("quick_preview_title", &[QUICK_PREVIEW_PX.to_string()])).strong());
+        if let Some(error) = &self.error {
+            ui.label(egui::RichText::new(lang.t_args("quick_preview_failed", std::slice::from_ref(error))).italics());
+            return;
+        }
+        if self.images.is_empty() {
+            ui.label(egui::RichText::new(lang.t("quick_preview_empty")).italics());
+            return;
+        }
+
+        let name = |kind: OutputKind| registry::get(kind).map(|g| lang.t(g.label_key())).unwrap_or_default();
+        let combo = egui::ComboBox::from_id_salt("quick_preview_output")
+            .selected_text(self.selected.map(name).unwrap_or_default())
+            .show_ui(ui, |ui| {
+                for (kind, _) in &self.images {
+                    ui.selectable_value(&mut self.selected, Some(*kind), name(*kind));
+                }
+            });
+        a11y::describe(&combo.response, lang.t("quick_preview_output"), egui::WidgetInfo::new(egui::WidgetType::ComboBox));
+
+        let Some((kind, image)) = self.images.iter().find(|(k, _)| Some(*k) == self.selected) else { return };
+        if self.texture.as_ref().is_none_or(|(k, _)| k != kind) {
+            let size = [image.width() as usize, image.height() as usize];
+            let texture = ui.ctx().load_texture("quick_preview", egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()), egui::TextureOptions::LINEAR);
+            self.texture = Some((*kind, texture));
+        }
+        let Some((_, texture)) = &self.texture else { return };
+        let size = texture.size_vec2();
+        let (rect, _) = ui.allocate_exact_size(size * (width / size.max_elem()), egui::Sense::hover());
+        self.backdrop_painter.paint(ui.painter(), rect, backdrop);
+        egui::Image::new(texture).paint_at(ui, rect);
+    }
+}
@@ -0,0 +1,82 @@
+//! Repaint scheduling and the frame-time overlay.
+//!
+//! Background work wakes the GUI through [`RepaintScheduler::wake`], which asks
+//! for a frame within [`BUSY_INTERVAL`] rather than right away, so bursts of
+//! progress events and the loading animation run at about 10 fps. While the
+//! window is minimized the interval grows to one second: frames keep coming to
+//! drain the events and start queued files, just fewer of them. When nothing
+//! happens no repaint is requested and egui stays idle.
+
+use eframe::egui;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::lang::LanguageManager;
+
+/// Frame interval while background work is running.
+pub const BUSY_INTERVAL: Duration = Duration::from_millis(100);
+const MINIMIZED_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Throttled repaint requests; cheap to clone into the event bus waker.
+#[derive(Clone)]
+pub struct RepaintScheduler {
+    ctx: egui::Context,
+    minimized: Arc<AtomicBool>,
+}
+
+impl RepaintScheduler {
+    pub fn new(ctx: &egui::Context) -> Self {
+        Self { ctx: ctx.clone(), minimized: Arc::default() }
+    }
+
+    /// Asks for a frame soon; requests made before it is painted are merged into it.
+    pub fn wake(&self) {
+        let interval = if self.minimized.load(Ordering::Relaxed) { MINIMIZED_INTERVAL } else { BUSY_INTERVAL };
+        self.ctx.request_repaint_after(interval);
+    }
+
+    /// Picks up whether the window is minimized; called at the start of each frame.
+    pub fn begin_frame(&self, ctx: &egui::Context) {
+        let minimized = ctx.input(|i| i.viewport().minimized.unwrap_or(false));
+        self.minimized.store(minimized, Ordering::Relaxed);
+    }
+}
+
+/// Times of the frames painted during the last second, for the overlay.
+#[derive(Default)]
+pub struct FrameTimes {
+    /// (start of the frame in seconds, CPU time of the frame in seconds)
+    frames: VecDeque<(f64, f32)>,
+}
+
+impl FrameTimes {
+    /// Records the current frame; eframe reports the CPU time of the previous one.
+    pub fn record(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let now = ctx.input(|i| i.time);
+        self.frames.push_back((now, frame.info().cpu_usage.unwrap_or_default()));
+        while self.frames.front().is_some_and(|(time, _)| now - time > 1.0) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Shows frames per second and CPU time per frame in a corner of the window.
+    pub fn show(&self, ctx: &egui::Context, lang: &LanguageManager) {
+        let count = self.frames.len().max(1) as f32;
+        let mean = self.frames.iter().map(|(_, cpu)| cpu).sum::<f32>() / count;
+        let max = self.frames.iter().map(|(_, cpu)| *cpu).fold(0.0, f32::max);
+        egui::Area::new(egui::Id::new("frame_times"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(lang.t_args("frame_times_fps", &[self.frames.len().to_string()]));
+                    ui.label(lang.t_args("frame_times_cpu", &[
+                        lang.format_number((mean * 1000.0) as f64, 1),
+                        lang.format_number((max * 1000.0) as f64, 1),
+                    ]));
+                });
+            });
+    }
+}
@@ -0,0 +1,80 @@
+//! Usage statistics window.
+
+use eframe::egui;
+use std::time::Duration;
+
+use crate::lang::LanguageManager;
+use crate::settings::Settings;
+use crate::stats::UsageStats;
+use super::panels;
+
+/// State of the statistics window.
+#[derive(Default)]
+pub struct StatsWindow {
+    /// Loaded on first show and after each finished job.
+    stats: Option<UsageStats>,
+}
+
+impl StatsWindow {
+    /// Reloads the statistics the next time the window is shown.
+    pub fn refresh(&mut self) {
+        self.stats = None;
+    }
+
+    /// Shows the window while `open` is true. Returns true when the opt-in was toggled.
+    pub fn show(&mut self, ctx: &egui::Context, lang: &LanguageManager, open: &mut bool, settings: &mut Settings) -> bool {
+        let mut toggled = false;
+
+        egui::Window::new(lang.t("stats_title"))
+            .open(open)
+            .default_size([420.0, 360.0])
+            .show(ctx, |ui| {
+                ui.with_layout(panels::column_layout(lang), |ui| {
+                    toggled = ui.checkbox(&mut settings.usage_stats, lang.t("stats_enable")).changed();
+                    ui.label(egui::RichText::new(lang.t("stats_note")).italics());
+                    ui.separator();
+                    if !settings.usage_stats {
+                        ui.label(lang.t("stats_disabled"));
+                        return;
+                    }
+
+                    if ui.button(lang.t("stats_reload")).clicked() {
+                        self.stats = None;
+                    }
+                    let stats = self.stats.get_or_insert_with(UsageStats::load);
+                    if stats.images == 0 {
+                        ui.label(lang.t("stats_empty"));
+                        return;
+                    }
+
+                    egui::Grid::new("stats_totals").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(egui::RichText::new(lang.t(label)).strong());
+                            ui.label(value);
+                            ui.end_row();
+                        };
+                        row("stats_images", lang.format_number(stats.images as f64, 0));
+                        row("stats_folders", lang.format_number(stats.folders as f64, 0));
+                        row("stats_total_time", lang.format_duration(Duration::from_millis(stats.total_ms)));
+                        row("stats_average_time", lang.format_duration(stats.average()));
+                        row("stats_output_size", lang.format_size(stats.output_bytes));
+                    });
+
+                    ui.add_space(8.0);
+                    egui::Grid::new("stats_models").num_columns(3).striped(true).spacing([12.0, 4.0]).show(ui, |ui| {
+                        for header in ["stats_model", "stats_images", "stats_average_time"] {
+                            ui.label(egui::RichText::new(lang.t(header)).strong());
+                        }
+                        ui.end_row();
+                        for (model, usage) in &stats.models {
+                            ui.label(model);
+                            ui.label(lang.format_number(usage.images as f64, 0));
+                            ui.label(lang.format_duration(usage.average()));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        toggled
+    }
+}
@@ -2,59 +2,102 @@
 
 use eframe::egui;
 
-/// Configures the visual styles for the application.
+/// Duration of egui's widget transitions when animations are on.
+const ANIMATION_TIME: f32 = 6.0 / 60.0;
+
+/// Colors the light and dark themes differ in.
+struct Palette {
+    base: egui::Visuals,
+    window_fill: egui::Color32,
+    widget_fill: egui::Color32,
+    subtle_bg: egui::Color32,
+    border: egui::Color32,
+    hovered: egui::Color32,
+    active: egui::Color32,
+    selection: egui::Color32,
+}
+
+/// Configures the light and dark styles and follows the system theme.
 /// Sets up an elegant color palette with rounded corners and subtle shadows.
-pub fn configure_styles(ctx: &egui::Context) {
-    let mut visuals = egui::Visuals::light();
-    
+pub fn configure_styles(ctx: &egui::Context, animate: bool) {
+    ctx.set_visuals_of(egui::Theme::Light, visuals(Palette {
+        base: egui::Visuals::light(),
+        window_fill: egui::Color32::WHITE,
+        widget_fill: egui::Color32::WHITE,
+        subtle_bg: egui::Color32::from_rgb(248, 250, 252),
+        border: egui::Color32::from_rgb(226, 232, 240),
+        hovered: egui::Color32::from_rgb(238, 242, 255),
+        active: egui::Color32::from_rgb(224, 231, 255),
+        selection: egui::Color32::from_rgb(199, 210, 254),
+    }));
+    ctx.set_visuals_of(egui::Theme::Dark, visuals(Palette {
+        base: egui::Visuals::dark(),
+        window_fill: egui::Color32::from_rgb(30, 41, 59),
+        widget_fill: egui::Color32::from_rgb(30, 41, 59),
+        subtle_bg: egui::Color32::from_rgb(15, 23, 42),
+        border: egui::Color32::from_rgb(51, 65, 85),
+        hovered: egui::Color32::from_rgb(49, 46, 129),
+        active: egui::Color32::from_rgb(55, 48, 163),
+        selection: egui::Color32::from_rgb(67, 56, 202),
+    }));
+    ctx.set_theme(egui::ThemePreference::System);
+
+    ctx.all_styles_mut(|style| {
+        style.spacing.item_spacing = egui::vec2(12.0, 12.0);
+        style.spacing.button_padding = egui::vec2(24.0, 10.0);
+        style.spacing.window_margin = egui::Margin::same(16);
+    });
+    set_animations(ctx, animate);
+}
+
+/// Turns egui's widget transitions (collapsing headers, toggles, scrolling) on or off.
+pub fn set_animations(ctx: &egui::Context, animate: bool) {
+    ctx.all_styles_mut(|style| style.animation_time = if animate { ANIMATION_TIME } else { 0.0 });
+}
+
+fn visuals(palette: Palette) -> egui::Visuals {
+    let mut visuals = palette.base;
+
     // Elegant color palette
     let accent_color = egui::Color32::from_rgb(79, 70, 229); // Indigo
-    let subtle_bg = egui::Color32::from_rgb(248, 250, 252);
-    let border_color = egui::Color32::from_rgb(226, 232, 240);
-    
+
     // Window styling
     visuals.window_corner_radius = egui::CornerRadius::same(12);
-    visuals.window_fill = egui::Color32::WHITE;
-    visuals.window_stroke = egui::Stroke::new(1.0, border_color);
+    visuals.window_fill = palette.window_fill;
+    visuals.window_stroke = egui::Stroke::new(1.0, palette.border);
     visuals.window_shadow = egui::Shadow {
         offset: [0, 4],
         blur: 16,
         spread: 0,
         color: egui::Color32::from_black_alpha(20),
     };
-    
+
     // Widget styling - rounded and elegant
     visuals.widgets.noninteractive.corner_radius = egui::CornerRadius::same(8);
-    visuals.widgets.noninteractive.bg_fill = subtle_bg;
-    visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(1.0, border_color);
-    
+    visuals.widgets.noninteractive.bg_fill = palette.subtle_bg;
+    visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(1.0, palette.border);
+
     visuals.widgets.inactive.corner_radius = egui::CornerRadius::same(8);
-    visuals.widgets.inactive.bg_fill = egui::Color32::WHITE;
-    visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.0, border_color);
-    
+    visuals.widgets.inactive.bg_fill = palette.widget_fill;
+    visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.0, palette.border);
+
     visuals.widgets.hovered.corner_radius = egui::CornerRadius::same(8);
-    visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(238, 242, 255);
+    visuals.widgets.hovered.bg_fill = palette.hovered;
     visuals.widgets.hovered.bg_stroke = egui::Stroke::new(1.5, accent_color);
-    
+
     visuals.widgets.active.corner_radius = egui::CornerRadius::same(8);
-    visuals.widgets.active.bg_fill = egui::Color32::from_rgb(224, 231, 255);
+    visuals.widgets.active.bg_fill = palette.active;
     visuals.widgets.active.bg_stroke = egui::Stroke::new(2.0, accent_color);
-    
+
     visuals.widgets.open.corner_radius = egui::CornerRadius::same(8);
-    visuals.widgets.open.bg_fill = egui::Color32::WHITE;
-    
+    visuals.widgets.open.bg_fill = palette.widget_fill;
+
     // Selection highlight
-    visuals.selection.bg_fill = egui::Color32::from_rgb(199, 210, 254);
+    visuals.selection.bg_fill = palette.selection;
     visuals.selection.stroke = egui::Stroke::new(1.5, accent_color);
-    
+
     // Text cursor
     visuals.text_cursor.stroke = egui::Stroke::new(2.0, accent_color);
-    
-    ctx.set_visuals(visuals);
-
-    let mut style = (*ctx.style()).clone();
-    style.spacing.item_spacing = egui::vec2(12.0, 12.0);
-    style.spacing.button_padding = egui::vec2(24.0, 10.0);
-    style.spacing.window_margin = egui::Margin::same(16);
-    ctx.set_style(style);
+
+    visuals
 }
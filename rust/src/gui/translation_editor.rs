@@ -0,0 +1,144 @@
+//! In-app editor for the user-editable locale files.
+
+use eframe::egui;
+use std::collections::HashMap;
+
+use crate::lang::LanguageManager;
+use super::panels;
+
+/// State of the translation editor window.
+#[derive(Default)]
+pub struct TranslationEditor {
+    lang_code: String,
+    reference: Vec<(String, String)>,
+    values: HashMap<String, String>,
+    loaded_code: Option<String>,
+    only_missing: bool,
+    filter: String,
+    status: Option<String>,
+}
+
+impl TranslationEditor {
+    /// Loads the reference and the values of `lang_code` into the editor.
+    fn load(&mut self, lang: &LanguageManager, lang_code: &str) {
+        self.lang_code = lang_code.to_string();
+        self.reference = lang.reference_entries();
+        self.values = lang.locale_entries(lang_code);
+        self.loaded_code = Some(lang_code.to_string());
+        self.status = None;
+    }
+
+    fn missing_count(&self) -> usize {
+        self.reference.iter()
+            .filter(|(key, _)| self.values.get(key).is_none_or(|v| v.trim().is_empty()))
+            .count()
+    }
+
+    /// Builds the entries to save: reference order first, then extra keys only present in the locale.
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = self.reference.iter()
+            .filter_map(|(key, _)| {
+                self.values.get(key)
+                    .filter(|v| !v.trim().is_empty())
+                    .map(|v| (key.clone(), v.clone()))
+            })
+            .collect();
+
+        let mut extra: Vec<(String, String)> = self.values.iter()
+            .filter(|(key, _)| !self.reference.iter().any(|(k, _)| k == *key))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        extra.sort();
+        entries.extend(extra);
+        entries
+    }
+
+    /// Shows the editor window while `open` is true.
+    pub fn show(&mut self, ctx: &egui::Context, lang: &mut LanguageManager, open: &mut bool) {
+        if self.loaded_code.is_none() {
+            self.load(lang, &lang.current_lang());
+        }
+
+        let mut save = false;
+        egui::Window::new(lang.t("editor_title"))
+            .open(open)
+            .default_size([720.0, 520.0])
+            .show(ctx, |ui| {
+                ui.with_layout(panels::column_layout(lang), |ui| {
+                    panels::row(ui, lang, |ui| {
+                        let label = ui.label(lang.t("editor_lang_code"));
+                        ui.add(egui::TextEdit::singleline(&mut self.lang_code).desired_width(60.0)).labelled_by(label.id);
+                        if ui.button(lang.t("editor_load")).clicked() {
+                            let code = self.lang_code.trim().to_string();
+                            if !code.is_empty() {
+                                self.load(lang, &code);
+                            }
+                        }
+                        save = ui.button(lang.t("editor_save")).clicked();
+                    });
+
+                    panels::row(ui, lang, |ui| {
+                        let label = ui.label(lang.t("editor_filter"));
+                        ui.add(egui::TextEdit::singleline(&mut self.filter).desired_width(200.0)).labelled_by(label.id);
+                        ui.checkbox(&mut self.only_missing, lang.t("editor_only_missing"));
+                        let missing = self.missing_count();
+                        let color = if missing > 0 { egui::Color32::from_rgb(200, 80, 0) } else { egui::Color32::from_rgb(0, 140, 0) };
+                        ui.label(egui::RichText::new(lang.t_args("editor_missing", &[missing])).color(color));
+                    });
+
+                    if let Some(status) = &self.status {
+                        ui.label(egui::RichText::new(status).italics());
+                    }
+
+                    ui.separator();
+
+                    let filter = self.filter.to_lowercase();
+                    egui::ScrollArea::vertical().id_salt("translation_scroll").show(ui, |ui| {
+                        egui::Grid::new("translation_grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .spacing([12.0, 6.0])
+                            .show(ui, |ui| {
+                                ui.label(egui::RichText::new(lang.t("editor_key")).strong());
+                                ui.label(egui::RichText::new(lang.t("editor_reference")).strong());
+                                ui.label(egui::RichText::new(lang.t("editor_value")).strong());
+                                ui.end_row();
+
+                                for (key, reference) in &self.reference {
+                                    let value = self.values.entry(key.clone()).or_default();
+                                    let missing = value.trim().is_empty();
+                                    if self.only_missing && !missing {
+                                        continue;
+                                    }
+                                    if !filter.is_empty()
+                                        && !key.to_lowercase().contains(&filter)
+                                        && !reference.to_lowercase().contains(&filter)
+                                    {
+                                        continue;
+                                    }
+
+                                    let key_text = egui::RichText::new(key).monospace();
+                                    let label = ui.label(if missing { key_text.color(egui::Color32::from_rgb(200, 80, 0)) } else { key_text });
+                                    ui.add(egui::Label::new(reference).wrap_mode(egui::TextWrapMode::Wrap));
+                                    ui.add(egui::TextEdit::multiline(value).desired_rows(1).desired_width(280.0)).labelled_by(label.id);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                });
+            });
+
+        // Saving reloads the current locale, so it waits until the window no longer borrows it
+        if save {
+            let code = self.lang_code.trim().to_string();
+            self.status = Some(if !LanguageManager::is_valid_code(&code) {
+                lang.t_args("editor_invalid_code", &[&code])
+            } else {
+                match lang.save_language(&code, &self.entries()) {
+                    Ok(path) => format!("{} {}", lang.t("editor_saved"), path.display()),
+                    Err(e) => lang.t_args("editor_save_failed", &[format!("{:#}", e)]),
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! System tray icon, built with the `tray` cargo feature on Linux.
+//!
+//! The icon is a StatusNotifierItem, shown by KDE, most other desktops and
+//! GNOME with the AppIndicator extension. Its tooltip follows the running job
+//! and the queue, and its menu brings the window back, queues more images with
+//! the current form (the last job's settings at startup), pauses the queue or
+//! quits. Tray icons cannot take dropped files, so images are queued through a
+//! file picker. The item runs on its own thread, as ksni's blocking API cannot
+//! run inside the tokio runtime the GUI is started from.
+
+use std::sync::mpsc::Sender;
+use anyhow::Result;
+
+use crate::events::EventBus;
+#[cfg(all(feature = "tray", target_os = "linux"))]
+use crate::events::{AppEvent, TrayAction};
+use crate::lang::LanguageManager;
+
+/// What the tooltip and the menu show.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrayStatus {
+    /// Completed and total steps of the running job.
+    pub progress: Option<(usize, usize)>,
+    pub queued: usize,
+    pub paused: bool,
+}
+
+/// Handle to the tray icon; dropping it removes the icon.
+pub struct TrayIcon {
+    updates: Sender<TrayStatus>,
+}
+
+impl TrayIcon {
+    pub fn update(&self, status: TrayStatus) {
+        // The tray thread is gone only if the icon could not be shown, which it logged
+        let _ = self.updates.send(status);
+    }
+}
+
+/// Shows the tray icon, whose menu publishes [`crate::events::TrayAction`]s on `bus`.
+#[cfg(all(feature = "tray", target_os = "linux"))]
+pub fn spawn(lang: LanguageManager, bus: EventBus) -> Result<TrayIcon> {
+    use ksni::blocking::TrayMethods;
+    use std::sync::mpsc;
+
+    let (updates, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let tray = AppTray { lang: lang.clone(), bus: bus.clone(), status: TrayStatus::default() };
+        let handle = match tray.spawn() {
+            Ok(handle) => handle,
+            Err(e) => {
+                bus.publish(AppEvent::LogLine(lang.t_args("log_tray_failed", &[e.to_string()])));
+                return;
+            }
+        };
+        for status in rx {
+            handle.update(|tray| tray.status = status);
+        }
+        handle.shutdown().wait();
+    });
+    Ok(TrayIcon { updates })
+}
+
+/// Without the `tray` feature, or off Linux, there is no tray icon.
+#[cfg(not(all(feature = "tray", target_os = "linux")))]
+pub fn spawn(_lang: LanguageManager, _bus: EventBus) -> Result<TrayIcon> {
+    anyhow::bail!("alphasvg was built without the tray feature")
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+struct AppTray {
+    lang: LanguageManager,
+    bus: EventBus,
+    status: TrayStatus,
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl AppTray {
+    /// Menu entry publishing `action`.
+    fn item(&self, key: &str, action: TrayAction) -> ksni::MenuItem<Self> {
+        ksni::menu::StandardItem {
+            label: self.lang.t(key),
+            activate: Box::new(move |tray: &mut Self| tray.bus.publish(AppEvent::Tray(action))),
+            ..Default::default()
+        }.into()
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+impl ksni::Tray for AppTray {
+    fn id(&self) -> String {
+        env!("CARGO_PKG_NAME").into()
+    }
+
+    fn title(&self) -> String {
+        self.lang.t("app_title")
+    }
+
+    fn icon_name(&self) -> String {
+        "applications-graphics".into()
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.bus.publish(AppEvent::Tray(TrayAction::ShowWindow));
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let mut lines = vec![match self.status.progress {
+            Some((done, total)) => self.lang.t_args("tray_progress", &[done.to_string(), total.to_string()]),
+            None => self.lang.t("tray_idle"),
+        }];
+        if self.status.queued > 0 {
+            lines.push(self.lang.t_args("status_queue", &[self.status.queued.to_string()]));
+        }
+        if self.status.paused {
+            lines.push(self.lang.t("tray_paused"));
+        }
+        ksni::ToolTip { title: self.title(), description: lines.join("\n"), ..Default::default() }
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        vec![
+            self.item("tray_show_window", TrayAction::ShowWindow),
+            self.item("tray_queue_files", TrayAction::QueueFiles),
+            ksni::menu::CheckmarkItem {
+                label: self.lang.t("tray_pause_queue"),
+                checked: self.status.paused,
+                activate: Box::new(|tray: &mut Self| tray.bus.publish(AppEvent::Tray(TrayAction::TogglePause))),
+                ..Default::default()
+            }.into(),
+            ksni::MenuItem::Separator,
+            self.item("menu_quit", TrayAction::Quit),
+        ]
+    }
+}
@@ -0,0 +1,81 @@
+//! Run history shown in the GUI "History" window.
+//!
+//! Every job run through the processing pipeline (GUI, `run-last` and IPC) is
+//! appended as one JSON line to `history.jsonl` in the config directory, with
+//! its full configuration so it can be inspected and re-run later. CLI batches
+//! keep their own journal in the output directory, see [`crate::journal`].
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::gui::processing::ProcessingConfig;
+use crate::settings::Settings;
+
+const HISTORY_FILE: &str = "history.jsonl";
+/// Most recent runs returned by [`load`].
+const MAX_ENTRIES: usize = 200;
+
+/// A finished run.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub finished_at: u64,
+    pub config: ProcessingConfig,
+    pub outputs: Vec<PathBuf>,
+    /// Error of a failed run.
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(config: ProcessingConfig, outputs: Vec<PathBuf>, error: Option<String>) -> Self {
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self { finished_at, config, outputs, error }
+    }
+
+    /// Finish time as `YYYY-MM-DD HH:MM` (UTC).
+    pub fn date(&self) -> String {
+        let (year, month, day) = civil_date(self.finished_at);
+        let minutes = (self.finished_at % 86_400) / 60;
+        format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+    }
+}
+
+/// UTC year, month and day of a Unix timestamp (Howard Hinnant's algorithm).
+pub fn civil_date(unix_secs: u64) -> (i64, i64, i64) {
+    let z = (unix_secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Appends `entry` to the history file.
+pub fn record(entry: &HistoryEntry) -> Result<()> {
+    let dir = Settings::config_dir().context("Could not find config directory")?;
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(dir.join(HISTORY_FILE))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Most recent runs first; unreadable lines are skipped.
+pub fn load() -> Vec<HistoryEntry> {
+    let Some(content) = Settings::config_dir().and_then(|dir| fs::read_to_string(dir.join(HISTORY_FILE)).ok()) else {
+        return Vec::new();
+    };
+    content.lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(MAX_ENTRIES)
+        .collect()
+}
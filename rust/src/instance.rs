@@ -0,0 +1,112 @@
+//! Single-instance guard for the GUI.
+//!
+//! The first GUI instance listens on a loopback TCP port and records it, with a
+//! random token, in `instance.json` in the config directory. A later launch
+//! connects to that port and hands its file arguments over to the running
+//! window, which opens or queues them, instead of opening a second window that would
+//! load the models again. A port file left behind by a crashed instance is
+//! detected because nobody answers on it, and is then replaced.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{self, Settings};
+
+const INSTANCE_FILE: &str = "instance.json";
+/// How long a launch waits for the running instance to acknowledge its files.
+const HANDOFF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where the running instance can be reached.
+#[derive(Serialize, Deserialize)]
+struct Endpoint {
+    port: u16,
+    /// Shared secret so other local users cannot push files into the queue.
+    token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Handoff {
+    token: String,
+    files: Vec<PathBuf>,
+}
+
+pub enum Instance {
+    /// No other GUI is running; this process owns the window.
+    Primary(InstanceGuard),
+    /// The files were handed to the running GUI; this process should exit.
+    Forwarded,
+}
+
+/// Listening side held by the primary instance.
+pub struct InstanceGuard {
+    listener: TcpListener,
+    token: String,
+}
+
+/// Hands `files` to the running GUI if there is one, otherwise claims the instance.
+pub fn acquire(files: &[PathBuf]) -> Result<Instance> {
+    let dir = Settings::config_dir().context("Could not find config directory")?;
+    let path = dir.join(INSTANCE_FILE);
+    if let Some(endpoint) = read_endpoint(&path) && forward(&endpoint, files).is_ok() {
+        return Ok(Instance::Forwarded);
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).context("Failed to open the instance port")?;
+    let mut rng = rand::rng();
+    let token: String = (0..32).map(|_| format!("{:x}", rng.random_range(0..16u8))).collect();
+    let endpoint = Endpoint { port: listener.local_addr()?.port(), token: token.clone() };
+    fs::create_dir_all(&dir)?;
+    settings::write_private(&path, &serde_json::to_string(&endpoint)?)?;
+    Ok(Instance::Primary(InstanceGuard { listener, token }))
+}
+
+impl InstanceGuard {
+    /// Calls `on_files` on a background thread for every launch that hands files over.
+    pub fn listen(self, on_files: impl Fn(Vec<PathBuf>) + Send + 'static) {
+        thread::spawn(move || {
+            for stream in self.listener.incoming().flatten() {
+                // A misbehaving client only loses its own handoff
+                if let Ok(files) = receive(stream, &self.token) {
+                    on_files(files);
+                }
+            }
+        });
+    }
+}
+
+fn read_endpoint(path: &Path) -> Option<Endpoint> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Sends `files` to the instance at `endpoint` and waits for its acknowledgement.
+fn forward(endpoint: &Endpoint, files: &[PathBuf]) -> Result<()> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port));
+    let mut stream = TcpStream::connect_timeout(&addr, HANDOFF_TIMEOUT)?;
+    stream.set_read_timeout(Some(HANDOFF_TIMEOUT))?;
+    // The running instance has its own working directory
+    let files = files.iter().map(std::path::absolute).collect::<std::io::Result<Vec<_>>>()?;
+    let handoff = Handoff { token: endpoint.token.clone(), files };
+    writeln!(stream, "{}", serde_json::to_string(&handoff)?)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    anyhow::ensure!(reply.trim() == "ok", "Unexpected reply from the running instance");
+    Ok(())
+}
+
+fn receive(stream: TcpStream, token: &str) -> Result<Vec<PathBuf>> {
+    stream.set_read_timeout(Some(HANDOFF_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let handoff: Handoff = serde_json::from_str(&line)?;
+    anyhow::ensure!(handoff.token == token, "Invalid instance token");
+    writeln!(&stream, "ok")?;
+    Ok(handoff.files)
+}
@@ -0,0 +1,150 @@
+//! Headless IPC mode (`transparente_rust ipc`).
+//!
+//! GUI wrappers and editor plugins run the engine as a long-lived child
+//! process, so loaded models stay warm between jobs. Commands are read from
+//! stdin and events written to stdout, one JSON object per line:
+//!
+//! ```text
+//! → {"cmd":"process","id":1,"input":"a.jpg","output":"out","generators":["alpha","logo"],"model":"auto"}
+//! ← {"event":"progress","id":1,"done":1,"total":2}
+//! ← {"event":"done","id":1,"outputs":["out/a_alpha.png","out/a_logo.svg"]}
+//! → {"cmd":"shutdown"}
+//! ```
+//!
+//! `id` is optional and echoed on every event of the job. Besides `progress`
+//! and `done`, a job emits `log` (`message`), `model` (`state`, `model`) and
+//! `error` (`message`, and `hint` when the failure has a known fix) events;
+//! `ready` (`version`) is sent once at startup.
+//! Jobs run one at a time in the order received.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::events::{AppEvent, EventBus};
+use crate::generators::{ModelState, ModelType};
+use crate::generators::alpha::Roi;
+use crate::generators::params::Params;
+use crate::generators::registry;
+use crate::gui::processing::{self, ProcessingConfig};
+use crate::lang::LanguageManager;
+use crate::naming::OutputKind;
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Process(Box<ProcessRequest>),
+    Shutdown,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProcessRequest {
+    #[serde(default)]
+    id: Value,
+    input: PathBuf,
+    output: PathBuf,
+    /// Output base name, the input file stem when empty.
+    #[serde(default)]
+    name: String,
+    #[serde(default = "registry::default_kinds")]
+    generators: Vec<OutputKind>,
+    #[serde(default)]
+    params: Params,
+    /// Model name as accepted by `--model`.
+    #[serde(default = "auto_model")]
+    model: String,
+    /// Region of the input to process, `{"x", "y", "width", "height"}`.
+    #[serde(default)]
+    roi: Option<Roi>,
+    /// Trace the input as-is for the vector outputs, skipping background removal for them.
+    #[serde(default)]
+    vectorize_original: bool,
+}
+
+fn auto_model() -> String {
+    ModelType::Auto.name()
+}
+
+/// Serves commands from stdin until it is closed or a `shutdown` command arrives.
+pub fn run(lang: &LanguageManager) -> Result<()> {
+    emit(json!({ "event": "ready", "version": env!("CARGO_PKG_VERSION") }))?;
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Process(request)) => process(*request, lang)?,
+            Ok(Request::Shutdown) => break,
+            Err(e) => emit(json!({ "event": "error", "id": Value::Null, "message": format!("invalid command: {}", e) }))?,
+        }
+    }
+    Ok(())
+}
+
+/// Runs one job, forwarding its events until it finishes.
+fn process(request: ProcessRequest, lang: &LanguageManager) -> Result<()> {
+    let id = request.id.clone();
+    let config = match job_config(request) {
+        Ok(config) => config,
+        Err(message) => return emit(json!({ "event": "error", "id": id, "message": message })),
+    };
+    // A failure here fails the job as well, which reports the error
+    let outputs: Vec<PathBuf> = processing::output_paths(&config).unwrap_or_default().into_iter().map(|(_, p)| p).collect();
+
+    let (bus, events) = EventBus::new();
+    if !processing::start_processing(config, lang.clone(), bus) {
+        return emit(json!({ "event": "error", "id": id, "message": lang.t("error_invalid_paths") }));
+    }
+
+    for event in events {
+        let finished = matches!(event, AppEvent::JobFinished { .. });
+        let line = match event {
+            AppEvent::LogLine(message) => json!({ "event": "log", "id": id, "message": message }),
+            AppEvent::Progress { done, total } => json!({ "event": "progress", "id": id, "done": done, "total": total }),
+            AppEvent::ModelStateChanged(ModelState::Ready(model)) => json!({ "event": "model", "id": id, "state": "ready", "model": model }),
+            AppEvent::ModelStateChanged(ModelState::Loading) => json!({ "event": "model", "id": id, "state": "loading" }),
+            AppEvent::ModelStateChanged(ModelState::Unloaded) | AppEvent::UpdateAvailable { .. } | AppEvent::OpenFiles(_)
+                | AppEvent::MaskReady { .. } | AppEvent::PreviewReady { .. } | AppEvent::Tray(_) => continue,
+            AppEvent::JobFinished { failure: Some(failure) } => json!({ "event": "error", "id": id, "message": failure.message, "hint": failure.hint }),
+            AppEvent::JobFinished { failure: None } => json!({ "event": "done", "id": id, "outputs": outputs }),
+        };
+        emit(line)?;
+        if finished {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Validates a request into the job configuration shared with the GUI.
+fn job_config(request: ProcessRequest) -> Result<ProcessingConfig, String> {
+    let selected_model: ModelType = request.model.parse()?;
+    std::fs::create_dir_all(&request.output).map_err(|e| format!("cannot create {}: {}", request.output.display(), e))?;
+    Ok(ProcessingConfig {
+        input: request.input,
+        output: request.output,
+        custom_filename: request.name,
+        generators: request.generators,
+        params: request.params,
+        profiles: Vec::new(),
+        selected_model,
+        preprocess: Default::default(),
+        upscale: None,
+        roi: request.roi,
+        watermark: None,
+        vectorize_original: request.vectorize_original,
+        mask: Default::default(),
+    })
+}
+
+fn emit(event: Value) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", event)?;
+    stdout.flush()?;
+    Ok(())
+}
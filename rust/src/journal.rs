@@ -0,0 +1,110 @@
+//! Job journal for resumable batches.
+//!
+//! Every completed input is appended as one JSON line to a journal file in the
+//! output directory, so an interrupted batch can be resumed with `--resume`.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub const JOURNAL_FILE: &str = ".alphasvg_journal.jsonl";
+
+/// A completed input as recorded in the journal.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub input: PathBuf,
+    pub input_size: u64,
+    pub input_modified: u64,
+    pub finished_at: u64,
+    pub model: String,
+    pub duration_ms: u64,
+    pub outputs: Vec<PathBuf>,
+}
+
+impl JournalEntry {
+    /// Creates an entry for `input`, fingerprinting it by size and modification time.
+    pub fn new(input: &Path, model: &str, duration_ms: u64, outputs: Vec<PathBuf>) -> Result<Self> {
+        let (input_size, input_modified) = fingerprint(input)?;
+        Ok(Self {
+            input: input.to_path_buf(),
+            input_size,
+            input_modified,
+            finished_at: unix_now(),
+            model: model.to_string(),
+            duration_ms,
+            outputs,
+        })
+    }
+}
+
+/// Append-only journal of completed inputs for one output directory.
+pub struct Journal {
+    path: PathBuf,
+    completed: HashSet<(PathBuf, u64, u64)>,
+}
+
+impl Journal {
+    /// Opens (or creates) the journal in `output_dir`, loading completed entries.
+    pub fn open(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(JOURNAL_FILE);
+        let completed = read_entries(&path)?
+            .into_iter()
+            .map(|e| (e.input, e.input_size, e.input_modified))
+            .collect();
+        Ok(Self { path, completed })
+    }
+
+    /// Returns true if `input` was completed and has not changed since.
+    pub fn is_completed(&self, input: &Path) -> bool {
+        match fingerprint(input) {
+            Ok((size, modified)) => self.completed.contains(&(input.to_path_buf(), size, modified)),
+            Err(_) => false,
+        }
+    }
+
+    /// Appends an entry and syncs it to disk so it survives a crash.
+    pub fn record(&mut self, entry: &JournalEntry) -> Result<()> {
+        append_entry(&self.path, entry)?;
+        self.completed.insert((entry.input.clone(), entry.input_size, entry.input_modified));
+        Ok(())
+    }
+}
+
+/// Appends `entry` to the journal file at `path`, syncing it to disk.
+pub fn append_entry(path: &Path, entry: &JournalEntry) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Reads all valid entries of a journal file, ignoring a truncated trailing line.
+pub fn read_entries(path: &Path) -> Result<Vec<JournalEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn fingerprint(input: &Path) -> Result<(u64, u64)> {
+    let meta = fs::metadata(input)?;
+    let modified = meta.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((meta.len(), modified))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
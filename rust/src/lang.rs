@@ -1,5 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, bail};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 // Embed translations into the binary for fallback/standalone use
 static SUB_LOCALE_ES: &str = include_str!("../locales/es.json");
@@ -7,55 +10,388 @@ static SUB_LOCALE_EN: &str = include_str!("../locales/en.json");
 static SUB_LOCALE_EU: &str = include_str!("../locales/eu.json");
 static SUB_LOCALE_LA: &str = include_str!("../locales/la.json");
 
+/// Embedded locales, in the order they are listed in the language menu.
+static EMBEDDED_LOCALES: &[(&str, &str)] = &[
+    ("es", SUB_LOCALE_ES),
+    ("en", SUB_LOCALE_EN),
+    ("eu", SUB_LOCALE_EU),
+    ("la", SUB_LOCALE_LA),
+];
+
+const LOCALES_DIR: &str = "locales";
+/// Locale every chain ends in; it defines the reference key set.
+const FALLBACK_LANG: &str = "en";
+/// Set to any non-empty value to report keys missing from the current locale.
+const DEBUG_ENV: &str = "ALPHASVG_I18N_DEBUG";
+
 #[derive(Clone)]
 pub struct LanguageManager {
     translations: Arc<Mutex<HashMap<String, String>>>,
+    /// English entries used key by key when the current locale lacks one.
+    fallback: Arc<HashMap<String, String>>,
     current_lang: Arc<Mutex<String>>,
+    /// Keys looked up but missing from the current locale, collected in debug mode.
+    missing: Arc<Mutex<BTreeSet<String>>>,
+    debug_missing: bool,
 }
 
 impl Default for LanguageManager {
     fn default() -> Self {
         let mut manager = Self {
             translations: Arc::new(Mutex::new(HashMap::new())),
-            current_lang: Arc::new(Mutex::new("en".to_string())),
+            fallback: Arc::new(parse_locale(FALLBACK_LANG).unwrap_or_default()),
+            current_lang: Arc::new(Mutex::new(FALLBACK_LANG.to_string())),
+            missing: Arc::new(Mutex::new(BTreeSet::new())),
+            debug_missing: std::env::var(DEBUG_ENV).is_ok_and(|v| !v.is_empty()),
         };
         // Load default English immediately
-        manager.load_language("en"); 
+        manager.load_language(FALLBACK_LANG);
         manager
     }
 }
 
 impl LanguageManager {
+    /// Switches to `lang_code`, following its fallback chain.
+    ///
+    /// A regional code such as `es-MX` is layered over its base language `es`;
+    /// keys missing from both are then taken from English one by one.
     pub fn load_language(&mut self, lang_code: &str) {
         // Try to load from external file "locales/{code}.json" to allow user editing.
         // If not found, use the embedded version (compile-time).
-        
-        let path = format!("locales/{}.json", lang_code);
-        let content = std::fs::read_to_string(&path)
-            .unwrap_or_else(|_| {
-                // Fallback to embedded files
-                match lang_code {
-                    "es" => SUB_LOCALE_ES.to_string(),
-                    "en" => SUB_LOCALE_EN.to_string(),
-                    "eu" => SUB_LOCALE_EU.to_string(),
-                    "la" => SUB_LOCALE_LA.to_string(),
-                    _ => "{}".to_string()
+        let mut map = HashMap::new();
+        for code in locale_chain(lang_code) {
+            map.extend(parse_locale(&code).unwrap_or_default());
+        }
+
+        *self.translations.lock().unwrap() = map;
+        *self.current_lang.lock().unwrap() = lang_code.to_string();
+        self.missing.lock().unwrap().clear();
+    }
+
+    /// Returns true when `lang_code` or its base language has a locale file.
+    pub fn supports(&self, lang_code: &str) -> bool {
+        locale_chain(lang_code).iter().any(|code| read_locale(code).is_some())
+    }
+
+    /// Enables or disables reporting of keys missing from the current locale.
+    ///
+    /// Also enabled by setting `ALPHASVG_I18N_DEBUG`. Each missing key is
+    /// printed to stderr once and kept for [`missing_keys`](Self::missing_keys).
+    pub fn set_debug_missing(&mut self, enabled: bool) {
+        self.debug_missing = enabled;
+    }
+
+    /// Keys that fell back to English (or to the raw key) since the locale was loaded, sorted.
+    pub fn missing_keys(&self) -> Vec<String> {
+        self.missing.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Lists the available languages as `(code, display name)` pairs.
+    ///
+    /// Embedded locales come first, followed by any extra `locales/*.json`
+    /// files found at runtime. The display name is read from the `lang_name`
+    /// key of each locale, falling back to the language code.
+    pub fn available_languages(&self) -> Vec<(String, String)> {
+        let mut codes: Vec<String> = EMBEDDED_LOCALES.iter().map(|(code, _)| code.to_string()).collect();
+
+        let mut discovered = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(LOCALES_DIR) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                    continue;
+                }
+                if let Some(code) = path.file_stem().and_then(|s| s.to_str())
+                    && !codes.iter().any(|c| c == code)
+                {
+                    discovered.push(code.to_string());
                 }
-            });
+            }
+        }
+        discovered.sort();
+        codes.extend(discovered);
 
-        if let Ok(map) = serde_json::from_str::<HashMap<String, String>>(&content) {
-            *self.translations.lock().unwrap() = map;
-            *self.current_lang.lock().unwrap() = lang_code.to_string();
-        } 
+        codes.into_iter()
+            .map(|code| {
+                let name = read_language_name(&code).unwrap_or_else(|| code.clone());
+                (code, name)
+            })
+            .collect()
     }
 
     pub fn t(&self, key: &str) -> String {
-        let guard = self.translations.lock().unwrap();
-        guard.get(key).cloned().unwrap_or_else(|| key.to_string())
+        self.lookup(&[key]).unwrap_or_else(|| key.to_string())
+    }
+
+    /// Translates `key` and substitutes `{0}`, `{1}`, ... with `args`.
+    ///
+    /// When the first argument is a number, the `{key}_one` / `{key}_other`
+    /// plural forms are preferred if the locale defines them.
+    pub fn t_args<T: ToString>(&self, key: &str, args: &[T]) -> String {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+
+        let plural_key = args.first()
+            .and_then(|first| first.parse::<f64>().ok())
+            .map(|n| if n == 1.0 { format!("{}_one", key) } else { format!("{}_other", key) });
+
+        let template = match &plural_key {
+            Some(plural) => self.lookup(&[plural, key]),
+            None => self.lookup(&[key]),
+        }.unwrap_or_else(|| key.to_string());
+
+        args.iter().enumerate().fold(template, |acc, (i, arg)| {
+            acc.replace(&format!("{{{}}}", i), arg)
+        })
     }
-    
-    #[allow(dead_code)]
+
+    /// Returns the English reference entries in file order.
+    pub fn reference_entries(&self) -> Vec<(String, String)> {
+        read_locale("en")
+            .and_then(|content| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content).ok())
+            .map(|map| {
+                map.into_iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k, v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the raw entries of a locale without switching to it.
+    pub fn locale_entries(&self, lang_code: &str) -> HashMap<String, String> {
+        read_locale(lang_code)
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether `lang_code` can name a locale file: ASCII letters, digits, `-` and `_`.
+    pub fn is_valid_code(lang_code: &str) -> bool {
+        !lang_code.is_empty() && lang_code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    /// Writes a locale to `locales/{code}.json` and reloads it if it is the active one.
+    pub fn save_language(&mut self, lang_code: &str, entries: &[(String, String)]) -> Result<PathBuf> {
+        // The code becomes a file name, so it must not reach outside the locales folder
+        if !Self::is_valid_code(lang_code) {
+            bail!("Invalid language code: {}", lang_code);
+        }
+        let map: serde_json::Map<String, serde_json::Value> = entries.iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect();
+
+        std::fs::create_dir_all(LOCALES_DIR)?;
+        let path = Path::new(LOCALES_DIR).join(format!("{}.json", lang_code));
+        let mut content = serde_json::to_string_pretty(&map)?;
+        content.push('\n');
+        std::fs::write(&path, content)?;
+
+        if self.current_lang() == lang_code {
+            self.load_language(lang_code);
+        }
+        Ok(path)
+    }
+
+    /// Returns true when the current locale declares `"direction": "rtl"`.
+    ///
+    /// egui draws glyphs in logical order without bidi reordering, so this
+    /// drives layout mirroring and alignment rather than text shaping.
+    pub fn is_rtl(&self) -> bool {
+        self.separator("direction", "ltr") == "rtl"
+    }
+
+    /// System font files the current locale asks for in its `"fonts"` entry,
+    /// a comma-separated list of file names, see [`crate::gui::fonts`].
+    pub fn font_files(&self) -> Vec<String> {
+        self.separator("fonts", "").split(',').map(str::trim).filter(|f| !f.is_empty()).map(String::from).collect()
+    }
+
     pub fn current_lang(&self) -> String {
         self.current_lang.lock().unwrap().clone()
     }
+
+    /// Formats a number with the locale's decimal and thousands separators.
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let decimal_sep = self.separator("fmt_decimal_sep", ".");
+        let thousands_sep = self.separator("fmt_thousands_sep", ",");
+
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (int_part, frac_part) = match formatted.split_once('.') {
+            Some((i, f)) => (i.to_string(), Some(f.to_string())),
+            None => (formatted, None),
+        };
+
+        let mut grouped = String::new();
+        for (i, c) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                grouped.push_str(&thousands_sep);
+            }
+            grouped.push(c);
+        }
+
+        let mut result = if value < 0.0 { format!("-{}", grouped) } else { grouped };
+        if let Some(frac) = frac_part {
+            result.push_str(&decimal_sep);
+            result.push_str(&frac);
+        }
+        result
+    }
+
+    /// Formats a byte count as a human readable size (KB, MB, GB).
+    pub fn format_size(&self, bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        let decimals = if unit == 0 { 0 } else { 1 };
+        format!("{} {}", self.format_number(value, decimals), UNITS[unit])
+    }
+
+    /// Formats a duration as milliseconds, seconds or `m:ss`.
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs_f64();
+        if secs < 1.0 {
+            format!("{} ms", duration.as_millis())
+        } else if secs < 60.0 {
+            format!("{} s", self.format_number(secs, 1))
+        } else {
+            let total = duration.as_secs();
+            format!("{}:{:02}", total / 60, total % 60)
+        }
+    }
+
+    /// Formats the file name of `path` for messages, or the whole path when it has none.
+    pub fn format_file_name(&self, path: &Path) -> String {
+        path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned())
+    }
+
+    /// Returns the first of `keys` defined by the current locale, else the first defined in English.
+    ///
+    /// The last key is the one reported as missing.
+    fn lookup(&self, keys: &[&str]) -> Option<String> {
+        if let Some(value) = {
+            let guard = self.translations.lock().unwrap();
+            keys.iter().find_map(|k| guard.get(*k).cloned())
+        } {
+            return Some(value);
+        }
+
+        if self.debug_missing && let Some(key) = keys.last() {
+            let mut missing = self.missing.lock().unwrap();
+            if missing.insert(key.to_string()) {
+                eprintln!("🌐 [{}] missing translation: {}", self.current_lang(), key);
+            }
+        }
+        keys.iter().find_map(|k| self.fallback.get(*k).cloned())
+    }
+
+    fn separator(&self, key: &str, default: &str) -> String {
+        let guard = self.translations.lock().unwrap();
+        guard.get(key).cloned().unwrap_or_else(|| default.to_string())
+    }
+}
+
+/// Reads a locale from `locales/{code}.json`, falling back to the embedded copy.
+fn read_locale(code: &str) -> Option<String> {
+    let path = Path::new(LOCALES_DIR).join(format!("{}.json", code));
+    std::fs::read_to_string(path).ok().or_else(|| {
+        EMBEDDED_LOCALES.iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, content)| content.to_string())
+    })
+}
+
+/// Parses a locale into its entries.
+fn parse_locale(code: &str) -> Option<HashMap<String, String>> {
+    serde_json::from_str(&read_locale(code)?).ok()
+}
+
+/// Codes to layer for `code`, from the most general to the most specific:
+/// `pt-BR` (or `pt_BR`) gives `["pt", "pt-BR"]`.
+fn locale_chain(code: &str) -> Vec<String> {
+    match code.split_once(['-', '_']) {
+        Some((base, _)) if !base.is_empty() => vec![base.to_string(), code.to_string()],
+        _ => vec![code.to_string()],
+    }
+}
+
+/// Reads the `lang_name` entry of a locale without switching to it.
+fn read_language_name(code: &str) -> Option<String> {
+    let content = read_locale(code)?;
+    let map = serde_json::from_str::<HashMap<String, String>>(&content).ok()?;
+    map.get("lang_name").cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedded(code: &str) -> HashMap<String, String> {
+        let (_, content) = EMBEDDED_LOCALES.iter().find(|(c, _)| *c == code).unwrap();
+        serde_json::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn all_locales_cover_reference_keys() {
+        let reference = embedded(FALLBACK_LANG);
+        for (code, _) in EMBEDDED_LOCALES {
+            let entries = embedded(code);
+            let mut missing: Vec<_> = reference.keys().filter(|k| !entries.contains_key(*k)).collect();
+            missing.sort();
+            assert!(missing.is_empty(), "locale {} is missing {:?}", code, missing);
+        }
+    }
+
+    #[test]
+    fn language_codes_are_plain_names() {
+        assert!(LanguageManager::is_valid_code("es"));
+        assert!(LanguageManager::is_valid_code("pt-BR"));
+        assert!(LanguageManager::is_valid_code("zh_Hant"));
+        assert!(!LanguageManager::is_valid_code(""));
+        assert!(!LanguageManager::is_valid_code("../settings"));
+        assert!(!LanguageManager::is_valid_code("es/x"));
+        assert!(!LanguageManager::is_valid_code("es.json"));
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_english() {
+        let mut lang = LanguageManager::default();
+        lang.set_debug_missing(true);
+        lang.load_language("es");
+        lang.translations.lock().unwrap().remove("btn_process");
+
+        assert_eq!(lang.t("btn_process"), embedded("en")["btn_process"]);
+        assert_eq!(lang.t("no_such_key"), "no_such_key");
+        assert_eq!(lang.missing_keys(), ["btn_process", "no_such_key"]);
+    }
+
+    #[test]
+    fn regional_code_uses_base_language() {
+        let mut lang = LanguageManager::default();
+        assert!(lang.supports("es-MX"));
+        lang.load_language("es-MX");
+        assert_eq!(lang.current_lang(), "es-MX");
+        assert_eq!(lang.t("btn_process"), embedded("es")["btn_process"]);
+    }
+
+    #[test]
+    fn font_files_are_listed_by_the_locale() {
+        let lang = LanguageManager::default();
+        assert!(lang.font_files().is_empty());
+        lang.translations.lock().unwrap().insert("fonts".into(), "NotoSansJP-Regular.otf, meiryo.ttc,".into());
+        assert_eq!(lang.font_files(), ["NotoSansJP-Regular.otf", "meiryo.ttc"]);
+    }
+
+    #[test]
+    fn sizes_durations_and_file_names_follow_locale() {
+        let mut lang = LanguageManager::default();
+        lang.load_language("es");
+        assert_eq!(lang.format_size(176 * 1024 * 1024), "176,0 MB");
+        assert_eq!(lang.format_size(1536 * 1024 * 1024), "1,5 GB");
+        assert_eq!(lang.format_duration(Duration::from_millis(2500)), "2,5 s");
+        assert_eq!(lang.format_duration(Duration::from_secs(125)), "2:05");
+        assert_eq!(lang.format_file_name(Path::new("out/foto_alpha.png")), "foto_alpha.png");
+        assert_eq!(lang.t_args("log_thumb_ok", &["foto_thumb.png"]), "🖼 Miniatura OK: foto_thumb.png");
+    }
 }
@@ -0,0 +1,45 @@
+//! Transparente engine shared by the `transparente_rust` binary and the C ABI.
+//!
+//! Built as both an `rlib` (for the binary) and a `cdylib` exposing [`ffi`] to
+//! desktop apps written in other languages. For `wasm32` only the modules the
+//! browser build (`web`) uses are compiled.
+
+/// Declares items compiled only for native targets.
+macro_rules! native {
+    ($($item:item)*) => {
+        $(#[cfg(not(target_arch = "wasm32"))] $item)*
+    };
+}
+
+native! {
+    pub mod cli;
+    pub mod config;
+    pub mod dedup;
+    pub mod diff;
+    pub mod error;
+    pub mod estimate;
+    pub mod events;
+    pub mod ffi;
+    pub mod gui;
+    pub mod history;
+    pub mod instance;
+    pub mod ipc;
+    pub mod journal;
+    pub mod naming;
+    pub mod organize;
+    pub mod power;
+    pub mod priority;
+    pub mod profile;
+    pub mod provenance;
+    pub mod report;
+    pub mod settings;
+    pub mod stats;
+    pub mod temp;
+    pub mod update;
+    pub mod upload;
+}
+
+pub mod generators;
+pub mod lang;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
@@ -2,21 +2,50 @@
 //!
 //! This application provides both a GUI and CLI interface for processing images.
 
-mod config;
-mod generators;
-mod gui;
-mod lang;
-mod cli;
-
-use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
 use anyhow::Result;
 
-use crate::lang::LanguageManager;
-use crate::generators::LogOutput;
+use alphasvg::{cli, config, diff, gui, ipc, naming, priority, profile, temp, update, upload};
+use alphasvg::cli::BatchStatus;
+use alphasvg::dedup::DedupMode;
+use alphasvg::error::AlphasvgError;
+use alphasvg::lang::LanguageManager;
+use alphasvg::generators::{AlphaOptions, LogOutput, ModelType, PreprocessOptions, SvgOptions, Verbosity, preprocess};
+use alphasvg::generators::alpha::{self, Ensemble, MaskFusion};
+use alphasvg::generators::svg::{self, SvgUnit};
+use alphasvg::generators::text_regions::TextMode;
+use alphasvg::generators::rasterize::RasterScale;
+use alphasvg::generators::hpgl::{self, HpglOptions};
+use alphasvg::generators::embroidery::{EmbroideryFormat, EmbroideryOptions};
+use alphasvg::generators::{denoise, encode, models, regions, upscale};
+use alphasvg::generators::mask_adjust::{self, HoleFill, MaskAdjust};
+use alphasvg::generators::transform::{self, Transform};
+use alphasvg::generators::bokeh::BokehOptions;
+use alphasvg::generators::color_pop;
+use alphasvg::generators::metadata::MetadataMapping;
+use alphasvg::generators::params::{self, ParamValue, Params};
+use alphasvg::generators::registry;
+use alphasvg::generators::watermark::WatermarkOptions;
+use alphasvg::report::SummaryFormat;
+use alphasvg::settings::Settings;
+use alphasvg::naming::OutputKind;
+use alphasvg::organize::OrganizeRules;
+
+/// Environment variable selecting the CLI language when `--lang` is not given.
+const LANG_ENV: &str = "ALPHASVG_LANG";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Procesador de imágenes por lotes (Rust Edition)", long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Imágenes a abrir en la interfaz gráfica: una sola se carga en el formulario, con la salida junto a ella; varias se añaden a la cola (de la ventana ya abierta, si la hay)
+    #[arg(value_name = "FILE")]
+    files: Vec<PathBuf>,
+
     /// Carpeta con las imágenes originales
     #[arg(short, long)]
     input: Option<String>,
@@ -24,23 +53,631 @@ struct Args {
     /// Carpeta donde se guardarán los resultados
     #[arg(short, long)]
     output: Option<String>,
+
+    /// Modelo de IA a usar ("auto" elige según el contenido de cada imagen)
+    #[arg(long, default_value = "auto")]
+    model: ModelType,
+
+    /// Lista los modelos disponibles con su tamaño, resolución, licencia y cita, y termina
+    #[arg(long)]
+    list_models: bool,
+
+    /// Combina las máscaras de dos modelos (p. ej. "u2net+isnet-general-use")
+    #[arg(long, value_name = "A+B", value_parser = alpha::parse_ensemble_models)]
+    model_ensemble: Option<[ModelType; 2]>,
+
+    /// Forma de combinar las máscaras del conjunto de modelos
+    #[arg(long, value_enum, default_value_t = MaskFusion::Average)]
+    ensemble_fusion: MaskFusion,
+
+    /// Ajusta automáticamente los niveles (contraste) antes de procesar
+    #[arg(long)]
+    auto_levels: bool,
+
+    /// Corrige el balance de blancos antes de procesar
+    #[arg(long)]
+    white_balance: bool,
+
+    /// Compensación de exposición en pasos EV (de -3 a 3)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true, value_parser = preprocess::parse_exposure)]
+    exposure: f32,
+
+    /// Suaviza los bloques de compresión JPEG antes de procesar
+    #[arg(long)]
+    deblock: bool,
+
+    /// Intensidad de la reducción de ruido previa, de 0 (desactivada) a 1
+    #[arg(long, default_value_t = 0.0, value_parser = denoise::parse_strength)]
+    denoise: f32,
+
+    /// Reducción de ruido adicional para un generador (p. ej. "lineart=0.8"); repetible
+    #[arg(long, value_name = "GENERADOR=INTENSIDAD", value_parser = denoise::parse_generator_strength)]
+    denoise_for: Vec<(OutputKind, f32)>,
+
+    /// Gira la imagen de entrada en sentido horario (0, 90, 180 o 270 grados)
+    #[arg(long, value_name = "GRADOS", default_value = "0", value_parser = transform::parse_rotation)]
+    rotate: u8,
+
+    /// Voltea la imagen de entrada horizontalmente
+    #[arg(long)]
+    flip_h: bool,
+
+    /// Voltea la imagen de entrada verticalmente
+    #[arg(long)]
+    flip_v: bool,
+
+    /// Endereza la imagen girándola estos grados en sentido horario (de -45 a 45); el lienzo se amplía
+    #[arg(long, value_name = "GRADOS", default_value_t = 0.0, allow_hyphen_values = true, value_parser = transform::parse_straighten)]
+    straighten: f32,
+
+    /// Amplía la imagen (2 o 4) con Real-ESRGAN antes de generar los SVG, sin pasar de --max-vector-px
+    #[arg(long, value_name = "FACTOR", value_parser = upscale::parse_factor)]
+    upscale: Option<u32>,
+
+    /// Genera también la foto original con el fondo desenfocado ({stem}_bokeh.jpg)
+    #[arg(long)]
+    bokeh: bool,
+
+    /// Radio del desenfoque de fondo en píxeles
+    #[arg(long, default_value_t = 12.0)]
+    bokeh_radius: f32,
+
+    /// Ancho del difuminado del borde del sujeto en píxeles
+    #[arg(long, default_value_t = 2.0)]
+    bokeh_feather: f32,
+
+    /// Ajusta un parámetro de una salida (p. ej. "gray.tones=12"); se puede repetir
+    #[arg(long = "set", value_name = "SALIDA.PARÁMETRO=VALOR", value_parser = params::parse_assignment)]
+    set: Vec<(String, ParamValue)>,
+
+    /// Genera solo estas salidas, separadas por comas (p. ej. "alpha,logo,thumb")
+    #[arg(long, value_name = "NOMBRES", value_delimiter = ',', value_parser = registry::parse_kind)]
+    only: Option<Vec<OutputKind>>,
+
+    /// Genera también el sujeto en color sobre el fondo en grises ({stem}_colorpop.png)
+    #[arg(long)]
+    color_pop: bool,
+
+    /// Tiñe el fondo en grises del color indicado (p. ej. "#c0a080"); equivale a --set colorpop.tint=
+    #[arg(long, value_name = "#RRGGBB", value_parser = color_pop::parse_tint)]
+    color_pop_tint: Option<[u8; 3]>,
+
+    /// Guarda el color selectivo como JPEG en lugar de PNG; equivale a --set colorpop.format=jpg
+    #[arg(long)]
+    color_pop_jpeg: bool,
+
+    /// Formato de la miniatura y el color selectivo, que no necesitan transparencia; equivale a --set thumb.format= y colorpop.format=
+    #[arg(long, value_name = "FORMATO", value_parser = clap::builder::PossibleValuesParser::new(encode::FORMATS))]
+    raster_format: Option<String>,
+
+    /// Calidad JPEG (1-100) de la miniatura, el color selectivo y el fondo desenfocado
+    #[arg(long, value_name = "CALIDAD", value_parser = encode::parse_quality)]
+    raster_quality: Option<u8>,
+
+    /// Marca de agua definida en un archivo JSON (por defecto, la de los ajustes)
+    #[arg(long, value_name = "ARCHIVO")]
+    watermark: Option<PathBuf>,
+
+    /// Desactiva la marca de agua de los ajustes
+    #[arg(long, conflicts_with = "watermark")]
+    no_watermark: bool,
+
+    /// Continúa un lote interrumpido, omitiendo las imágenes ya completadas
+    #[arg(long)]
+    resume: bool,
+
+    /// Muestra las salidas que se escribirían y su tamaño estimado según trabajos anteriores, sin procesar nada
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Omite las imágenes casi idénticas de un mismo lote (skip) o les enlaza las salidas de la primera (link)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "skip", value_name = "MODO")]
+    dedup: Option<DedupMode>,
+
+    /// Diferencia máxima, en bits del hash perceptual, para considerar dos imágenes duplicadas (0-64)
+    #[arg(long, default_value_t = config::DEDUP_MAX_DISTANCE, value_parser = clap::value_parser!(u32).range(0..=64))]
+    dedup_distance: u32,
+
+    /// Imágenes recortadas que pueden esperar a sus salidas mientras la siguiente pasa por el modelo en otro hilo (0 procesa una a una)
+    #[arg(long, value_name = "N", default_value_t = config::PIPELINE_DEPTH)]
+    pipeline_depth: usize,
+
+    /// Vectoriza la imagen original tal cual, sin quitar el fondo, en las salidas SVG
+    #[arg(long)]
+    vectorize_original: bool,
+
+    /// Megapíxeles máximos antes de reducir la imagen de entrada (0 desactiva el límite)
+    #[arg(long, default_value_t = config::MAX_MEGAPIXELS)]
+    max_megapixels: f32,
+
+    /// Aplica la máscara reescalada a la imagen original a resolución completa
+    #[arg(long)]
+    upscale_mask: bool,
+
+    /// Si la inferencia se queda sin memoria, reintenta a una resolución menor del modelo en lugar de fallar
+    #[arg(long)]
+    allow_downscale_retry: bool,
+
+    /// Fuerza de la máscara en porcentaje: 100 recorta por completo, valores menores conservan parte del fondo (vidrio, velos)
+    #[arg(long, value_name = "PORCENTAJE", default_value = "100", value_parser = mask_adjust::parse_percent)]
+    mask_strength: f32,
+
+    /// Valor de la máscara (0-1) en el centro de la curva de umbral
+    #[arg(long, default_value_t = 0.5, value_parser = mask_adjust::parse_fraction)]
+    mask_threshold: f32,
+
+    /// Suavidad de la curva de umbral, de 0 (corte duro) a 1 (máscara sin cambios)
+    #[arg(long, default_value_t = 1.0, value_parser = mask_adjust::parse_fraction)]
+    mask_softness: f32,
+
+    /// Niveles de la máscara "negro,blanco,gamma" (p. ej. "0.1,0.9,1.2"), aplicados antes de la curva de umbral
+    #[arg(long, value_name = "NEGRO,BLANCO,GAMMA", value_parser = mask_adjust::parse_levels)]
+    mask_levels: Option<[f32; 3]>,
+
+    /// Invierte la máscara: conserva el fondo y elimina el sujeto (PNG "solo fondo" para fondos y placas)
+    #[arg(long)]
+    invert_mask: bool,
+
+    /// Relleno del hueco del sujeto con --invert-mask: none (transparente), blur (fondo difuminado) o un color #RRGGBB
+    #[arg(long, value_name = "RELLENO", default_value = "none", value_parser = mask_adjust::parse_hole_fill, requires = "invert_mask")]
+    hole_fill: HoleFill,
+
+    /// Procesa solo la región "x,y,ancho,alto" (en píxeles); el resto queda transparente
+    #[arg(long, value_name = "X,Y,ANCHO,ALTO")]
+    roi: Option<alpha::Roi>,
+
+    /// Lado mayor máximo, en píxeles, al que se vectorizan las salidas SVG (0 desactiva el límite)
+    #[arg(long, default_value_t = config::MAX_VECTOR_PX)]
+    max_vector_px: u32,
+
+    /// Unidad del ancho/alto de los SVG (px, mm, cm, in)
+    #[arg(long, value_enum, default_value_t = SvgUnit::Px)]
+    svg_unit: SvgUnit,
+
+    /// Resolución supuesta al convertir píxeles a unidades físicas
+    #[arg(long, default_value_t = config::SVG_DPI, value_parser = svg::parse_dpi)]
+    dpi: f32,
+
+    /// Valor de preserveAspectRatio de los SVG (p. ej. "xMidYMid meet")
+    #[arg(long, value_parser = svg::parse_preserve_aspect_ratio)]
+    preserve_aspect_ratio: Option<String>,
+
+    /// Escribe además cada capa SVG en su propio archivo con marcas de registro
+    #[arg(long)]
+    separate_layers: bool,
+
+    /// Genera la trama en CMYK con un fotolito SVG por canal
+    #[arg(long)]
+    cmyk: bool,
+
+    /// Incrusta una imagen PNG de respaldo detrás de las capas vectoriales
+    #[arg(long)]
+    embed_raster: bool,
+
+    /// Área mínima de las regiones de los SVG en color y en grises, en píxeles (50) o en porcentaje de la imagen (0.1%); las menores se funden con su vecina más grande
+    #[arg(long, value_name = "ÁREA", value_parser = regions::parse_min_area)]
+    min_region_area: Option<regions::MinArea>,
+
+    /// Dibuja los degradados suaves de los SVG en color (logo, ilustración) como rellenos linearGradient/radialGradient en lugar de bandas planas
+    #[arg(long)]
+    gradients: bool,
+
+    /// Detecta líneas de texto en los SVG en color y las vuelve a trazar con más detalle (preserve) o las incrusta como imagen (raster); se anotan en el informe
+    #[arg(long, value_name = "MODO", num_args = 0..=1, default_missing_value = "preserve")]
+    text_regions: Option<TextMode>,
+
+    /// Reconoce el texto de los SVG en color con tesseract y lo escribe como elementos <text> editables en una capa propia (código de idioma de tesseract: eng, spa, eus...)
+    #[cfg(feature = "ocr")]
+    #[arg(long, value_name = "IDIOMA", num_args = 0..=1, default_missing_value = "eng")]
+    ocr: Option<String>,
+
+    /// Escribe los SVG comprimidos con gzip (.svgz)
+    #[arg(long)]
+    svgz: bool,
+
+    /// Renderiza cada SVG a PNG a la resolución indicada (p. ej. 300dpi o 2x)
+    #[arg(long, value_name = "ESCALA")]
+    rasterize_svg: Option<RasterScale>,
+
+    /// Exporta cada SVG también como modelo de trazados JSON ({stem}.paths.json) para plugins de Figma/Sketch
+    #[arg(long)]
+    path_json: bool,
+
+    /// Genera un mapa de calor de la confianza de la máscara ({stem}_confidence.png)
+    #[arg(long)]
+    confidence: bool,
+
+    /// No genera los SVG de los recortes sospechosos (casi vacíos, casi llenos o muy fragmentados)
+    #[arg(long)]
+    skip_suspect_svg: bool,
+
+    /// Rasteriza cada SVG, salvo semitono y lineart, y lo compara con su imagen de origen (SSIM y ΔE); los de baja fidelidad se marcan en el informe
+    #[arg(long)]
+    fidelity: bool,
+
+    /// Pausa el lote mientras el equipo funciona con batería o reduce la velocidad por temperatura, y lo reanuda solo
+    #[arg(long)]
+    pause_on_battery: bool,
+
+    /// Crea miniaturas freedesktop de las salidas en ~/.cache/thumbnails para los gestores de archivos
+    #[arg(long)]
+    desktop_thumbnails: bool,
+
+    /// Exporta también el dibujo de líneas en HPGL para plotters y plotters de corte
+    #[arg(long)]
+    hpgl: bool,
+
+    /// Pluma (herramienta) seleccionada en la salida HPGL
+    #[arg(long, default_value_t = 1)]
+    hpgl_pen: u8,
+
+    /// Unidades de plotter por milímetro en la salida HPGL
+    #[arg(long, default_value_t = hpgl::HPGL_UNITS_PER_MM)]
+    hpgl_units_per_mm: f32,
+
+    /// (Experimental) Exporta las regiones de color a bordado, con columnas de puntada satén en las regiones estrechas y relleno tatami en el resto
+    #[arg(long)]
+    embroidery: bool,
+
+    /// Formato del archivo de bordado: DST, o PES (solo la sección PEC, la que leen las máquinas)
+    #[arg(long, value_enum, default_value_t = EmbroideryFormat::Dst)]
+    embroidery_format: EmbroideryFormat,
+
+    /// Anchura máxima, en mm, de una región cosida en puntada satén (0 rellena todas)
+    #[arg(long, default_value_t = EmbroideryOptions::default().satin_max_width_mm)]
+    satin_width: f32,
+
+    /// Separación entre filas de puntada de relleno, en mm
+    #[arg(long, default_value_t = EmbroideryOptions::default().row_spacing_mm)]
+    stitch_spacing: f32,
+
+    /// Longitud máxima de puntada, en mm
+    #[arg(long, default_value_t = EmbroideryOptions::default().max_stitch_mm)]
+    max_stitch: f32,
+
+    /// Formato del resumen final: tabla, o tabla y CSV en la carpeta de salida
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Table)]
+    summary: SummaryFormat,
+
+    /// Plantilla de nombres de salida con {stem} y {suffix}
+    #[arg(long, default_value = naming::DEFAULT_TEMPLATE)]
+    name_template: String,
+
+    /// Reglas JSON para organizar los archivos tras procesar cada imagen (mover originales, prefijo de trabajo, carpetas por fecha)
+    #[arg(long, value_name = "ARCHIVO")]
+    organize: Option<PathBuf>,
+
+    /// Asignación JSON de los campos IPTC/XMP de la entrada a los metadatos de los SVG
+    #[arg(long, value_name = "ARCHIVO")]
+    metadata_map: Option<PathBuf>,
+
+    /// Sube las salidas al terminar: s3://bucket/prefijo o una URL WebDAV (credenciales por entorno o ajustes)
+    #[arg(long, value_name = "URL")]
+    upload: Option<upload::Destination>,
+
+    /// Subidas simultáneas
+    #[arg(long, default_value_t = upload::DEFAULT_JOBS, value_parser = clap::value_parser!(usize))]
+    upload_jobs: usize,
+
+    /// Idioma de los mensajes (es, en, eu, la); también ALPHASVG_LANG
+    #[arg(long, value_name = "CODE")]
+    lang: Option<String>,
+
+    /// Solo muestra errores
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Aumenta el detalle de los mensajes (-v, -vv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Muestra el tiempo de cada etapa al terminar y guarda una traza para chrome://tracing o Perfetto
+    #[arg(long, value_name = "TRAZA", num_args = 0..=1, default_missing_value = "alphasvg-trace.json")]
+    profile: Option<PathBuf>,
+
+    /// Modo en segundo plano: baja la prioridad del proceso (nice de 1 a 19) y usa como mucho la mitad de los núcleos
+    #[arg(long, visible_alias = "low-priority", value_name = "NIVEL", num_args = 0..=1,
+          default_missing_value = "10", value_parser = clap::value_parser!(i32).range(1..=19))]
+    nice: Option<i32>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Descarga e instala la última versión publicada
+    SelfUpdate,
+    /// Repite el último trabajo lanzado desde la interfaz gráfica sin abrir ventana
+    RunLast,
+    /// Compara dos imágenes: mapa de diferencias, diferencia de alfa y SSIM
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+        /// Carpeta donde guardar los mapas de diferencias
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// Termina con error si el SSIM es menor que este valor
+        #[arg(long, value_name = "SSIM")]
+        min_ssim: Option<f64>,
+    },
+    /// Modo IPC sin interfaz: lee órdenes JSON por stdin y escribe eventos JSON por stdout
+    Ipc,
+    /// Borra los archivos temporales (.temp., .vtrace_temp.) que dejaron ejecuciones interrumpidas
+    Clean {
+        /// Carpeta de salida a limpiar
+        output_dir: PathBuf,
+        /// Borra también los de menos de una hora, que pueden ser de un proceso en marcha
+        #[arg(long)]
+        all: bool,
+        /// Recorre también las subcarpetas
+        #[arg(short, long)]
+        recursive: bool,
+        /// Solo lista los archivos que se borrarían
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let args = Args::parse();
-    let lang = LanguageManager::default();
-    let logger = LogOutput::StdOut;
+    let mut lang = LanguageManager::default();
+    match run(args, &mut lang) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", lang.t_args("cli_error", &[format!("{:#}", e)]));
+            match AlphasvgError::find(&e) {
+                Some(_) => BatchStatus::for_error(&e).into(),
+                None => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+fn run(args: Args, lang: &mut LanguageManager) -> Result<ExitCode> {
+    let verbosity = match (args.quiet, args.verbose) {
+        (true, _) => Verbosity::Quiet,
+        (false, 0) => Verbosity::Normal,
+        (false, 1) => Verbosity::Verbose,
+        (false, _) => Verbosity::Debug,
+    };
+    let logger = LogOutput::StdOut(verbosity);
+    if verbosity == Verbosity::Debug {
+        lang.set_debug_missing(true);
+    }
+
+    let code = args.lang.clone()
+        .or_else(|| std::env::var(LANG_ENV).ok().filter(|c| !c.trim().is_empty()))
+        .or_else(|| Settings::load().language);
+    if let Some(code) = code {
+        if lang.supports(&code) {
+            lang.load_language(&code);
+        } else {
+            logger.error(lang.t_args("cli_unknown_lang", &[code]));
+        }
+    }
+
+    if let Some(nice) = args.nice {
+        match priority::set_background(nice) {
+            Ok(()) => logger.send(lang.t_args("log_background_mode", &[nice.to_string()])),
+            Err(e) => logger.error(lang.t_args("log_background_failed", &[format!("{:#}", e)])),
+        }
+    }
+
+    let profiler = args.profile.as_deref().map(profile::Profiler::start).transpose()?;
+    let result = execute(args, lang, &logger);
+    if let Some(profiler) = profiler {
+        print_profile(profiler, lang, &logger);
+    }
+    result
+}
+
+/// Prints the time spent per stage, slowest first, and where the trace went.
+fn print_profile(profiler: profile::Profiler, lang: &LanguageManager, logger: &LogOutput) {
+    let (trace, stages) = profiler.finish();
+    logger.send(lang.t("profile_summary"));
+    for (stage, time) in stages {
+        logger.send(format!("  {:<32} {:>6} {:>10} {:>10}", stage, time.calls, lang.format_duration(time.total), lang.format_duration(time.own)));
+    }
+    logger.send(lang.t_args("profile_trace_written", &[trace.display()]));
+}
+
+/// Prints every model with its download size, resolution, license and citation.
+fn list_models(lang: &LanguageManager, logger: &LogOutput) {
+    for model in ModelType::ALL.into_iter().filter(|&m| m != ModelType::Auto) {
+        let config = models::get_model_config(model);
+        let note = if config.non_commercial() { lang.t("cli_model_non_commercial") } else { String::new() };
+        logger.send(format!("{:<24} {:>4} MB {:>5}px  {}{}", config.name, config.size_mb, config.resolution, config.license, note));
+        logger.send(format!("    {}", config.citation));
+    }
+}
+
+fn execute(args: Args, lang: &LanguageManager, logger: &LogOutput) -> Result<ExitCode> {
+    if let Some(command) = args.command {
+        return match command {
+            Command::SelfUpdate => {
+                update::self_update(lang, logger)?;
+                Ok(ExitCode::SUCCESS)
+            }
+            Command::RunLast => Ok(cli::run_last(lang, logger)?.into()),
+            Command::Ipc => {
+                ipc::run(lang)?;
+                Ok(ExitCode::SUCCESS)
+            }
+            Command::Clean { output_dir, all, recursive, dry_run } => {
+                let opts = temp::CleanOptions {
+                    older_than: if all { None } else { temp::CleanOptions::default().older_than },
+                    recursive,
+                    dry_run,
+                };
+                let removed = temp::clean(&output_dir, &opts)?;
+                for path in &removed {
+                    let line = lang.t_args("cli_temp_removed", &[lang.format_file_name(path)]);
+                    // The list is the point of a dry run
+                    if dry_run { logger.send(line) } else { logger.verbose(line) }
+                }
+                let key = if dry_run { "cli_temp_would_clean" } else { "cli_temp_cleaned" };
+                logger.send(lang.t_args(key, &[removed.len()]));
+                Ok(ExitCode::SUCCESS)
+            }
+            Command::Diff { a, b, output, min_ssim } => {
+                let result = diff::compare(&a, &b, &output)?;
+                logger.send(lang.t_args("diff_ssim", &[lang.format_number(result.ssim, 4), lang.format_number(result.alpha_ssim, 4)]));
+                logger.send(lang.t_args("diff_stats", &[lang.format_number(result.mean_abs_diff, 2), lang.format_number(result.changed_percent, 2)]));
+                logger.verbose(lang.t_args("diff_heatmaps", &[result.heatmap.display(), result.alpha_heatmap.display()]));
+                match min_ssim {
+                    Some(min) if result.ssim < min => {
+                        logger.error(lang.t_args("diff_below", &[lang.format_number(result.ssim, 4), lang.format_number(min, 4)]));
+                        Ok(ExitCode::FAILURE)
+                    }
+                    _ => Ok(ExitCode::SUCCESS),
+                }
+            }
+        };
+    }
+
+    if args.list_models {
+        list_models(lang, logger);
+        return Ok(ExitCode::SUCCESS);
+    }
 
     match (args.input, args.output) {
         (Some(input), Some(output)) => {
-            cli::process_batch(&input, &output, &lang, &logger)?;
+            let watermark = match (&args.watermark, args.no_watermark) {
+                (_, true) => None,
+                (Some(path), false) => Some(WatermarkOptions::load(path)?),
+                (None, false) => Settings::load().watermark,
+            };
+            let mut params = Params::default();
+            if let Some([r, g, b]) = args.color_pop_tint {
+                params.set("colorpop.tint".to_string(), ParamValue::Text(format!("#{:02x}{:02x}{:02x}", r, g, b)));
+            }
+            if args.color_pop_jpeg {
+                params.set("colorpop.format".to_string(), ParamValue::Text("jpg".to_string()));
+            }
+            for generator in ["thumb", "colorpop"] {
+                if let Some(format) = &args.raster_format {
+                    params.set(format!("{}.format", generator), ParamValue::Text(format.clone()));
+                }
+                if let Some(quality) = args.raster_quality {
+                    params.set(format!("{}.quality", generator), ParamValue::Number(quality as f64));
+                }
+            }
+            for (key, value) in args.set {
+                params.set(key, value);
+            }
+            let mut generators = args.only.unwrap_or_else(|| OutputKind::ALL.to_vec());
+            if args.color_pop && !generators.contains(&OutputKind::ColorPop) {
+                generators.push(OutputKind::ColorPop);
+            }
+            let opts = cli::BatchOptions {
+                dry_run: args.dry_run,
+                resume: args.resume,
+                dedup: args.dedup,
+                dedup_distance: args.dedup_distance,
+                pipeline_depth: args.pipeline_depth,
+                model: args.model,
+                alpha: AlphaOptions {
+                    max_megapixels: (args.max_megapixels > 0.0).then_some(args.max_megapixels),
+                    upscale_mask: args.upscale_mask,
+                    allow_downscale_retry: args.allow_downscale_retry,
+                    watermark: watermark.clone(),
+                    mask: {
+                        let [black, white, gamma] = args.mask_levels.unwrap_or([0.0, 1.0, 1.0]);
+                        MaskAdjust {
+                            black,
+                            white,
+                            gamma,
+                            strength: args.mask_strength,
+                            threshold: args.mask_threshold,
+                            softness: args.mask_softness,
+                            invert: args.invert_mask,
+                            fill: args.hole_fill,
+                        }
+                    },
+                    preprocess: PreprocessOptions {
+                        auto_levels: args.auto_levels,
+                        white_balance: args.white_balance,
+                        exposure_ev: args.exposure,
+                        deblock: args.deblock,
+                        denoise: args.denoise,
+                        transform: Transform {
+                            quarter_turns: args.rotate,
+                            flip_horizontal: args.flip_h,
+                            flip_vertical: args.flip_v,
+                            straighten: args.straighten,
+                        },
+                    },
+                    ensemble: args.model_ensemble.map(|models| Ensemble { models, fusion: args.ensemble_fusion }),
+                    roi: args.roi,
+                },
+                svg: SvgOptions {
+                    max_working_px: (args.max_vector_px > 0).then_some(args.max_vector_px),
+                    unit: args.svg_unit,
+                    dpi: args.dpi,
+                    preserve_aspect_ratio: args.preserve_aspect_ratio,
+                    separate_layers: args.separate_layers,
+                    cmyk: args.cmyk,
+                    embed_raster: args.embed_raster,
+                    hpgl: args.hpgl.then_some(HpglOptions {
+                        units_per_mm: args.hpgl_units_per_mm,
+                        pen: args.hpgl_pen,
+                    }),
+                    embroidery: args.embroidery.then_some(EmbroideryOptions {
+                        row_spacing_mm: args.stitch_spacing,
+                        max_stitch_mm: args.max_stitch,
+                        satin_max_width_mm: args.satin_width,
+                        format: args.embroidery_format,
+                    }),
+                    metadata: None,
+                    provenance: None,
+                    compress: args.svgz,
+                    // Chosen per vector output with --set <generator>.background=...
+                    background: None,
+                    min_region_area: args.min_region_area,
+                    gradients: args.gradients,
+                    text: args.text_regions,
+                    text_regions: Vec::new(),
+                    #[cfg(feature = "ocr")]
+                    ocr: args.ocr,
+                    #[cfg(not(feature = "ocr"))]
+                    ocr: None,
+                    ocr_lines: Vec::new(),
+                },
+                rasterize: args.rasterize_svg,
+                path_json: args.path_json,
+                skip_suspect: args.skip_suspect_svg,
+                fidelity: args.fidelity,
+                pause_on_battery: args.pause_on_battery,
+                vectorize_original: args.vectorize_original,
+                confidence: args.confidence,
+                desktop_thumbnails: args.desktop_thumbnails,
+                bokeh: args.bokeh.then_some(BokehOptions {
+                    radius: args.bokeh_radius,
+                    feather: args.bokeh_feather,
+                    quality: args.raster_quality.unwrap_or(encode::DEFAULT_QUALITY),
+                }),
+                generators,
+                params,
+                watermark,
+                upscale: args.upscale,
+                denoise_for: args.denoise_for,
+                summary: args.summary,
+                name_template: args.name_template,
+                organize: args.organize.as_deref().map(OrganizeRules::load).transpose()?,
+                upload: args.upload,
+                metadata_map: args.metadata_map.as_deref().map(MetadataMapping::load).transpose()?.unwrap_or_default(),
+                upload_jobs: args.upload_jobs,
+            };
+            let status = cli::process_batch(&input, &output, &opts, lang, logger)?;
+            let missing = lang.missing_keys();
+            if !missing.is_empty() {
+                logger.debug(lang.t_args("cli_missing_translations", &[missing.len().to_string(), lang.current_lang(), missing.join(", ")]));
+            }
+            Ok(status.into())
         }
         _ => {
             println!("{}", lang.t("log_gui_starting"));
-            gui::run_gui()?;
+            gui::run_gui(args.files, lang)?;
+            Ok(ExitCode::SUCCESS)
         }
     }
-
-    Ok(())
 }
@@ -0,0 +1,190 @@
+//! Output file naming shared by the CLI and the GUI.
+//!
+//! Names are built from a template where `{stem}` is the input file stem (or the
+//! user-provided base name) and `{suffix}` identifies the output kind.
+
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_TEMPLATE: &str = "{stem}_{suffix}";
+
+/// Kinds of files produced by the pipeline, serialized by their suffix.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputKind {
+    Alpha,
+    Gray,
+    Halftone,
+    Lineart,
+    Logo,
+    Illustration,
+    #[serde(rename = "thumb")]
+    Thumbnail,
+    /// Mask confidence heatmap.
+    Confidence,
+    /// Original photo with the background blurred.
+    Bokeh,
+    /// Subject in color over a grayscale background.
+    ColorPop,
+}
+
+impl OutputKind {
+    /// Outputs of a full run; the optional extras (confidence, bokeh, color pop) are requested separately.
+    pub const ALL: [OutputKind; 7] = [
+        OutputKind::Alpha,
+        OutputKind::Gray,
+        OutputKind::Halftone,
+        OutputKind::Lineart,
+        OutputKind::Logo,
+        OutputKind::Illustration,
+        OutputKind::Thumbnail,
+    ];
+
+    /// Every kind, the optional extras included.
+    pub const EVERY: [OutputKind; 10] = [
+        OutputKind::Alpha,
+        OutputKind::Gray,
+        OutputKind::Halftone,
+        OutputKind::Lineart,
+        OutputKind::Logo,
+        OutputKind::Illustration,
+        OutputKind::Thumbnail,
+        OutputKind::Confidence,
+        OutputKind::Bokeh,
+        OutputKind::ColorPop,
+    ];
+
+    /// Suffix substituted for `{suffix}` in the template.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            OutputKind::Alpha => "alpha",
+            OutputKind::Gray => "gray",
+            OutputKind::Halftone => "halftone",
+            OutputKind::Lineart => "lineart",
+            OutputKind::Logo => "logo",
+            OutputKind::Illustration => "illustration",
+            OutputKind::Thumbnail => "thumb",
+            OutputKind::Confidence => "confidence",
+            OutputKind::Bokeh => "bokeh",
+            OutputKind::ColorPop => "colorpop",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputKind::Alpha | OutputKind::Thumbnail | OutputKind::Confidence | OutputKind::ColorPop => "png",
+            OutputKind::Bokeh => "jpg",
+            _ => "svg",
+        }
+    }
+}
+
+/// Builds output paths for one input image.
+pub struct OutputNaming {
+    stem: String,
+    template: String,
+}
+
+impl OutputNaming {
+    /// Creates the naming for `input`. A non-empty `base_name` replaces the input stem.
+    pub fn new(input: &Path, base_name: Option<&str>, template: &str) -> Result<Self> {
+        if !template.contains("{stem}") || !template.contains("{suffix}") {
+            return Err(anyhow!("Name template must contain {{stem}} and {{suffix}}: {}", template));
+        }
+
+        let stem = match base_name.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(base) => base.to_string(),
+            None => input.file_stem().context("No filename")?
+                .to_str().context("Decodification error")?
+                .to_string(),
+        };
+
+        Ok(Self { stem, template: template.to_string() })
+    }
+
+    /// File name (with extension) for an output kind.
+    pub fn file_name(&self, kind: OutputKind) -> String {
+        format!("{}.{}", self.base_name(kind), kind.extension())
+    }
+
+    /// File name without the extension for an output kind.
+    fn base_name(&self, kind: OutputKind) -> String {
+        self.template
+            .replace("{stem}", &self.stem)
+            .replace("{suffix}", kind.suffix())
+    }
+
+    /// Name under `other` of `file_name`, an output named by `self`, keeping
+    /// its extension (e.g. `.svgz`). `None` when it is not one of `self`'s outputs.
+    pub fn rename(&self, file_name: &str, other: &OutputNaming) -> Option<String> {
+        OutputKind::EVERY.iter().find_map(|&kind| {
+            let extension = file_name.strip_prefix(&self.base_name(kind))?.strip_prefix('.')?;
+            Some(format!("{}.{}", other.base_name(kind), extension))
+        })
+    }
+
+    /// Full path of an output kind inside `output_dir`.
+    pub fn path(&self, output_dir: &Path, kind: OutputKind) -> PathBuf {
+        output_dir.join(self.file_name(kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::generators::ModelType;
+    use crate::gui::processing::{self, ProcessingConfig};
+
+    fn gui_config(input: &Path, output: &Path) -> ProcessingConfig {
+        ProcessingConfig {
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            custom_filename: String::new(),
+            generators: OutputKind::ALL.to_vec(),
+            params: Default::default(),
+            profiles: Vec::new(),
+            selected_model: ModelType::default(),
+            preprocess: Default::default(),
+            upscale: None,
+            roi: None,
+            watermark: None,
+            vectorize_original: false,
+            mask: Default::default(),
+        }
+    }
+
+    #[test]
+    fn default_template_names() {
+        let naming = OutputNaming::new(Path::new("/in/photo.jpg"), None, DEFAULT_TEMPLATE).unwrap();
+        assert_eq!(naming.file_name(OutputKind::Alpha), "photo_alpha.png");
+        assert_eq!(naming.file_name(OutputKind::Logo), "photo_logo.svg");
+        assert_eq!(naming.file_name(OutputKind::Thumbnail), "photo_thumb.png");
+    }
+
+    #[test]
+    fn base_name_replaces_stem() {
+        let naming = OutputNaming::new(Path::new("photo.jpg"), Some("  brand "), DEFAULT_TEMPLATE).unwrap();
+        assert_eq!(naming.file_name(OutputKind::Gray), "brand_gray.svg");
+    }
+
+    #[test]
+    fn template_requires_placeholders() {
+        assert!(OutputNaming::new(Path::new("photo.jpg"), None, "{stem}").is_err());
+    }
+
+    #[test]
+    fn cli_and_gui_produce_identical_file_sets() {
+        let input = Path::new("/in/photo.jpg");
+        let output = Path::new("/out");
+
+        let cli: HashSet<PathBuf> = crate::cli::output_paths(input, output, DEFAULT_TEMPLATE, &OutputKind::ALL)
+            .unwrap().into_iter().map(|(_, p)| p).collect();
+        let gui: HashSet<PathBuf> = processing::output_paths(&gui_config(input, output))
+            .unwrap().into_iter().map(|(_, p)| p).collect();
+
+        assert_eq!(cli.len(), OutputKind::ALL.len());
+        assert_eq!(cli, gui);
+    }
+}
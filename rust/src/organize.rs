@@ -0,0 +1,112 @@
+//! File organization rules applied after each CLI image is processed.
+//!
+//! Loaded from a JSON job file with `--organize`, e.g.
+//!
+//! ```json
+//! { "move_originals": "done", "prefix_job_id": true, "date_folders": true }
+//! ```
+//!
+//! Outputs are renamed and moved before the journal, the report and the
+//! desktop thumbnails see them, so those always record the final paths.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::history;
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrganizeRules {
+    /// Folder processed inputs are moved to, relative to the input's folder.
+    pub move_originals: Option<PathBuf>,
+    /// Prefix every output file name with `{job_id}_`.
+    pub prefix_job_id: bool,
+    /// Write outputs into a `YYYY-MM-DD` subfolder of the output directory.
+    pub date_folders: bool,
+    /// Identifies the batch; the start time (`YYYYMMDD-HHMMSS`, UTC) when not set.
+    #[serde(default = "new_job_id")]
+    pub job_id: String,
+}
+
+impl Default for OrganizeRules {
+    fn default() -> Self {
+        Self { move_originals: None, prefix_job_id: false, date_folders: false, job_id: new_job_id() }
+    }
+}
+
+impl OrganizeRules {
+    /// Loads the rules from a JSON job file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read organize file {:?}", path))?;
+        let rules: Self = serde_json::from_str(&content).with_context(|| format!("Invalid organize file {:?}", path))?;
+        if rules.prefix_job_id && rules.job_id.contains(['/', '\\']) {
+            bail!("Invalid job ID {:?}: it must not contain path separators", rules.job_id);
+        }
+        Ok(rules)
+    }
+
+    /// Moves `outputs` of `output_dir` to their organized paths, returning them.
+    pub fn place_outputs(&self, outputs: &[PathBuf], output_dir: &Path) -> Result<Vec<PathBuf>> {
+        if !self.prefix_job_id && !self.date_folders {
+            return Ok(outputs.to_vec());
+        }
+        let dir = if self.date_folders {
+            let (year, month, day) = history::civil_date(unix_now());
+            output_dir.join(format!("{:04}-{:02}-{:02}", year, month, day))
+        } else {
+            output_dir.to_path_buf()
+        };
+        fs::create_dir_all(&dir)?;
+
+        outputs.iter()
+            .map(|path| {
+                let name = path.file_name().context("Output without a file name")?.to_string_lossy();
+                let target = if self.prefix_job_id {
+                    dir.join(format!("{}_{}", self.job_id, name))
+                } else {
+                    dir.join(&*name)
+                };
+                move_file(path, &target)?;
+                Ok(target)
+            })
+            .collect()
+    }
+
+    /// Moves a processed input into the originals folder, returning its new path,
+    /// or `None` when originals stay in place. An existing file is never replaced.
+    pub fn archive_input(&self, input: &Path) -> Result<Option<PathBuf>> {
+        let Some(folder) = &self.move_originals else { return Ok(None) };
+        let dir = input.parent().unwrap_or(Path::new(".")).join(folder);
+        fs::create_dir_all(&dir)?;
+        let target = dir.join(input.file_name().context("Input without a file name")?);
+        if target.exists() {
+            bail!("{} already exists", target.display());
+        }
+        move_file(input, &target)?;
+        Ok(Some(target))
+    }
+}
+
+fn new_job_id() -> String {
+    let now = unix_now();
+    let (year, month, day) = history::civil_date(now);
+    let secs = now % 86_400;
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Renames `from` to `to`, copying across file systems.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    fs::remove_file(from)?;
+    Ok(())
+}
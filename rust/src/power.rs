@@ -0,0 +1,134 @@
+//! Pausing batches on battery power or thermal throttling (`--pause-on-battery`).
+//!
+//! Before each image, the batch asks the operating system whether the machine
+//! runs on battery or is throttling its processor for heat, and waits until it
+//! no longer does, checking every [`config::POWER_POLL_SECS`] seconds. Where the
+//! system does not tell, nothing pauses:
+//!
+//! - Linux: a discharging battery in `/sys/class/power_supply`, and a thermal
+//!   zone at or above its passive trip point in `/sys/class/thermal`.
+//! - macOS: `pmset -g batt` drawing from the battery, and a CPU speed limit
+//!   below 100 in `pmset -g therm`.
+//! - Windows: a discharging battery in `Win32_Battery`; throttling is not exposed.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::generators::LogOutput;
+use crate::lang::LanguageManager;
+
+/// Why a batch waits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Constraint {
+    Battery,
+    Thermal,
+}
+
+impl Constraint {
+    fn label_key(self) -> &'static str {
+        match self {
+            Constraint::Battery => "power_battery",
+            Constraint::Thermal => "power_thermal",
+        }
+    }
+}
+
+/// Blocks while the machine runs on battery or is throttling, logging when the
+/// batch pauses and resumes.
+pub fn wait_while_constrained(lang: &LanguageManager, logger: &LogOutput) {
+    let Some(first) = constraint() else { return };
+    logger.send(lang.t_args("log_power_paused", &[lang.t(first.label_key())]));
+    let paused = Instant::now();
+    let mut current = first;
+    loop {
+        thread::sleep(Duration::from_secs(config::POWER_POLL_SECS));
+        match constraint() {
+            None => break,
+            Some(constraint) if constraint != current => {
+                logger.verbose(lang.t_args("log_power_paused", &[lang.t(constraint.label_key())]));
+                current = constraint;
+            }
+            Some(_) => {}
+        }
+    }
+    logger.send(lang.t_args("log_power_resumed", &[lang.format_duration(paused.elapsed())]));
+}
+
+/// What the machine is constrained by right now, battery first.
+pub fn constraint() -> Option<Constraint> {
+    if on_battery() {
+        Some(Constraint::Battery)
+    } else if throttling() {
+        Some(Constraint::Thermal)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    use std::fs;
+    let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else { return false };
+    supplies.flatten().any(|supply| {
+        let read = |name: &str| fs::read_to_string(supply.path().join(name)).map(|v| v.trim().to_string()).unwrap_or_default();
+        // Peripherals such as mice report batteries with a scope of "Device"
+        read("type") == "Battery" && read("scope") != "Device" && read("status") == "Discharging"
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn throttling() -> bool {
+    use std::fs;
+    let Ok(zones) = fs::read_dir("/sys/class/thermal") else { return false };
+    zones.flatten()
+        .filter(|zone| zone.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .any(|zone| {
+            let read = |name: String| fs::read_to_string(zone.path().join(name)).ok().map(|v| v.trim().to_string());
+            let Some(temp) = read("temp".into()).and_then(|t| t.parse::<i64>().ok()) else { return false };
+            // Trip points are numbered from 0 until the first missing one
+            (0..).map_while(|i| Some((read(format!("trip_point_{}_type", i))?, read(format!("trip_point_{}_temp", i))?)))
+                .any(|(kind, trip)| kind == "passive" && trip.parse::<i64>().is_ok_and(|trip| trip > 0 && temp >= trip))
+        })
+}
+
+#[cfg(target_os = "macos")]
+fn on_battery() -> bool {
+    pmset("batt").is_some_and(|out| out.contains("'Battery Power'"))
+}
+
+#[cfg(target_os = "macos")]
+fn throttling() -> bool {
+    pmset("therm").is_some_and(|out| out.lines()
+        .filter_map(|line| line.trim().strip_prefix("CPU_Speed_Limit"))
+        .filter_map(|rest| rest.trim_start_matches([' ', '=']).trim().parse::<u32>().ok())
+        .any(|limit| limit < 100))
+}
+
+#[cfg(target_os = "macos")]
+fn pmset(topic: &str) -> Option<String> {
+    let output = std::process::Command::new("pmset").args(["-g", topic]).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(windows)]
+fn on_battery() -> bool {
+    use std::os::windows::process::CommandExt;
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", "(Get-CimInstance Win32_Battery).BatteryStatus"])
+        // CREATE_NO_WINDOW: no console flashing up between images
+        .creation_flags(0x0800_0000)
+        .output();
+    // Status 1 is "the battery is discharging"
+    output.is_ok_and(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).lines().any(|line| line.trim() == "1"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn throttling() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn on_battery() -> bool {
+    false
+}
@@ -0,0 +1,81 @@
+//! Background mode: `--nice`/`--low-priority` and the GUI toggle.
+//!
+//! Long batches otherwise keep every core busy at normal priority. In
+//! background mode the process asks the scheduler for a lower priority, which
+//! the threads and the potrace and tesseract processes started afterwards
+//! inherit, and the model sessions run on at most half the cores. The priority
+//! is changed with the platform tools: `renice` on Linux and macOS, and the
+//! process priority class through PowerShell on Windows.
+
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use anyhow::{Context, Result, bail};
+
+use crate::generators::ai;
+
+/// Nice level of `--nice` without a value and of the GUI background mode.
+pub const DEFAULT_NICE: i32 = 10;
+
+/// Threads a model session may use; 0 leaves the choice to ONNX Runtime.
+static WORKER_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// Enters background mode at `nice` (1 to 19), or leaves it with 0.
+///
+/// The worker limit always follows; the priority may fail to change, most
+/// often back up, which unprivileged processes are not allowed on Linux and
+/// macOS: it then stays low until the program is restarted.
+pub fn set_background(nice: i32) -> Result<()> {
+    let limit = if nice > 0 {
+        thread::available_parallelism().map_or(1, |n| (n.get() / 2).max(1))
+    } else {
+        0
+    };
+    if WORKER_LIMIT.swap(limit, Ordering::Relaxed) != limit {
+        // Loaded sessions keep the thread pool they were built with
+        ai::clear_sessions();
+    }
+    set_priority(nice.clamp(0, 19))
+}
+
+/// Threads a model session may use in background mode.
+pub fn worker_limit() -> Option<usize> {
+    match WORKER_LIMIT.load(Ordering::Relaxed) {
+        0 => None,
+        limit => Some(limit),
+    }
+}
+
+#[cfg(windows)]
+fn set_priority(nice: i32) -> Result<()> {
+    let class = match nice {
+        0 => "Normal",
+        15.. => "Idle",
+        _ => "BelowNormal",
+    };
+    let script = format!("(Get-Process -Id {}).PriorityClass = '{}'", std::process::id(), class);
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW: no console flashing up over the GUI
+        command.creation_flags(0x0800_0000);
+    }
+    run(command)
+}
+
+#[cfg(not(windows))]
+fn set_priority(nice: i32) -> Result<()> {
+    // The priority as the first argument is absolute with both util-linux and BSD renice
+    let mut command = Command::new("renice");
+    command.args([&nice.to_string(), "-p", &std::process::id().to_string()]);
+    run(command)
+}
+
+fn run(mut command: Command) -> Result<()> {
+    let output = command.output().context("Could not change the process priority")?;
+    if !output.status.success() {
+        bail!("Could not change the process priority: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
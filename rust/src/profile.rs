@@ -0,0 +1,130 @@
+//! Per-stage timing for `--profile`.
+//!
+//! The pipeline stages and generators open `tracing` spans. Without a
+//! subscriber they cost next to nothing; [`Profiler::start`] installs one that
+//! sums the time spent in each kind of span and writes every span to a
+//! Chrome trace file (chrome://tracing, Perfetto).
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{Context as _, Result};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Time spent in one kind of span.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTime {
+    pub calls: usize,
+    pub total: Duration,
+    /// `total` minus the time spent in nested stages.
+    pub own: Duration,
+}
+
+type Stages = Arc<Mutex<BTreeMap<String, StageTime>>>;
+
+/// Collects the timings while installed as the global subscriber.
+pub struct Profiler {
+    stages: Stages,
+    trace: PathBuf,
+    flush: FlushGuard,
+}
+
+impl Profiler {
+    /// Installs the subscriber, writing the Chrome trace to `trace`.
+    pub fn start(trace: &Path) -> Result<Self> {
+        let file = File::create(trace).with_context(|| format!("Could not create trace file {}", trace.display()))?;
+        let (chrome, flush) = ChromeLayerBuilder::new().writer(file).include_args(true).build();
+        let stages = Stages::default();
+        tracing_subscriber::registry()
+            .with(SummaryLayer { stages: stages.clone() })
+            .with(chrome)
+            .try_init()?;
+        Ok(Self { stages, trace: trace.to_path_buf(), flush })
+    }
+
+    /// Completes the trace file and returns its path with the stages, slowest first.
+    pub fn finish(self) -> (PathBuf, Vec<(String, StageTime)>) {
+        self.flush.flush();
+        let mut stages: Vec<_> = self.stages.lock().unwrap().clone().into_iter().collect();
+        stages.sort_by_key(|(_, time)| Reverse(time.total));
+        (self.trace, stages)
+    }
+}
+
+/// Timing of an open span, kept in its extensions.
+struct SpanTiming {
+    stage: String,
+    entered: Option<Instant>,
+    busy: Duration,
+    children: Duration,
+}
+
+struct SummaryLayer {
+    stages: Stages,
+}
+
+impl<S> Layer<S> for SummaryLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut stage = StageName(attrs.metadata().name().to_string());
+        attrs.record(&mut stage);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming { stage: stage.0, entered: None, busy: Duration::ZERO, children: Duration::ZERO });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>()
+        {
+            timing.entered = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>()
+            && let Some(entered) = timing.entered.take()
+        {
+            timing.busy += entered.elapsed();
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else { return };
+        if let Some(parent) = span.parent()
+            && let Some(parent_timing) = parent.extensions_mut().get_mut::<SpanTiming>()
+        {
+            parent_timing.children += timing.busy;
+        }
+        let mut stages = self.stages.lock().unwrap();
+        let stage = stages.entry(timing.stage).or_default();
+        stage.calls += 1;
+        stage.total += timing.busy;
+        stage.own += timing.busy.saturating_sub(timing.children);
+    }
+}
+
+/// Span name, qualified by its `kind` field when it has one, e.g. `generate(Logo)`.
+struct StageName(String);
+
+impl Visit for StageName {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "kind" {
+            self.0 = format!("{}({:?})", self.0, value);
+        }
+    }
+}
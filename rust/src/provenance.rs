@@ -0,0 +1,78 @@
+//! How a result was produced: tool version, models and their checksums,
+//! parameter values, platform and execution provider.
+//!
+//! Written to the batch report and the `<metadata>` of every SVG so an output
+//! can be reproduced exactly later on, with the same models and settings.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::generators::ai;
+use crate::update::CURRENT_VERSION;
+
+/// SHA-256 of the model files hashed so far; models are large, so each is read once per process.
+static CHECKSUMS: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Provenance {
+    pub version: String,
+    /// Operating system and architecture, e.g. `linux x86_64`.
+    pub os: String,
+    pub execution_provider: String,
+    /// Models loaded by the run so far.
+    pub models: Vec<ModelFile>,
+    /// Command-line arguments of the run; empty for GUI jobs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+    /// Effective parameter values.
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ModelFile {
+    pub name: String,
+    pub file: String,
+    /// `None` when the file could not be read.
+    pub sha256: Option<String>,
+    /// SPDX identifier of the license of the weights.
+    pub license: String,
+}
+
+impl Provenance {
+    /// Describes the current process with the given arguments and parameters.
+    pub fn collect(arguments: Vec<String>, parameters: serde_json::Value) -> Self {
+        let models = ai::loaded_model_files().into_iter()
+            .map(|model| ModelFile {
+                file: model.path.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+                sha256: checksum(&model.path),
+                name: model.name,
+                license: model.license.to_string(),
+            })
+            .collect();
+        Self {
+            version: CURRENT_VERSION.to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            execution_provider: ai::EXECUTION_PROVIDER.to_string(),
+            models,
+            arguments,
+            parameters,
+        }
+    }
+}
+
+fn checksum(path: &Path) -> Option<String> {
+    let mut checksums = CHECKSUMS.lock().ok()?;
+    if let Some(sum) = checksums.get(path) {
+        return Some(sum.clone());
+    }
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path).ok()?, &mut hasher).ok()?;
+    let sum = format!("{:x}", hasher.finalize());
+    checksums.insert(path.to_path_buf(), sum.clone());
+    Some(sum)
+}
@@ -0,0 +1,272 @@
+//! Batch report.
+//!
+//! Summarizes a CLI batch run as JSON in the output directory: processed images,
+//! failures and quarantined inputs that could not be read.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use image::{DynamicImage, GrayImage, Luma, imageops::FilterType};
+use imageproc::region_labelling::{Connectivity, connected_components};
+use serde::Serialize;
+use crate::config;
+use crate::generators::confidence::ConfidenceStats;
+use crate::generators::fidelity::Fidelity;
+use crate::generators::metadata::DocumentMetadata;
+use crate::generators::ocr::OcrLine;
+use crate::generators::text_regions::TextRegion;
+use crate::dedup::DuplicateGroup;
+use crate::lang::LanguageManager;
+use crate::naming::OutputKind;
+use crate::provenance::Provenance;
+use crate::upload::RemoteFile;
+
+pub const REPORT_FILE: &str = "alphasvg_report.json";
+pub const SUMMARY_CSV_FILE: &str = "alphasvg_summary.csv";
+
+/// How the end-of-batch summary is emitted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum SummaryFormat {
+    /// Aligned table on stdout.
+    #[default]
+    Table,
+    /// The table plus the same rows written as CSV to the output directory.
+    Csv,
+}
+
+/// An input that was processed successfully.
+#[derive(Serialize, Clone, Debug)]
+pub struct ImageReport {
+    pub input: PathBuf,
+    /// Model(s) that computed the mask; absent when a cached mask was reused.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Lower model resolution used after inference ran out of memory at the model's own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downscale_retry: Option<u32>,
+    pub inference_ms: u64,
+    pub duration_ms: u64,
+    pub outputs: Vec<PathBuf>,
+    /// Combined size of the outputs.
+    pub output_bytes: u64,
+    /// Mask confidence, when the diagnostic heatmap was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<ConfidenceStats>,
+    pub coverage: Coverage,
+    pub quality: QualityCheck,
+    /// Input metadata carried over to the SVG outputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<DocumentMetadata>,
+    /// Lines of text found by `--text-regions`, in pixels of the vectorized
+    /// image; they may need OCR or retypesetting.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub text_regions: Vec<TextRegion>,
+    /// Lines of text recognized by `--ocr`, in pixels of the vectorized image.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ocr: Vec<OcrLine>,
+    /// How closely each vector output matches its source, with `--fidelity`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fidelity: Vec<Fidelity>,
+}
+
+/// Where the subject sits in the cut-out, for downstream layout automation.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Coverage {
+    /// Share of non-transparent pixels, in percent.
+    pub percent: f32,
+    /// Bounding box of the non-transparent pixels as `[x, y, width, height]`.
+    pub bbox: Option<[u32; 4]>,
+    /// Alpha-weighted center of the subject as `[x, y]` in pixels.
+    pub centroid: Option<[f32; 2]>,
+}
+
+impl Coverage {
+    pub fn measure(img: &DynamicImage) -> Self {
+        let rgba = img.to_rgba8();
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+        let (mut weight, mut cx, mut cy) = (0f64, 0f64, 0f64);
+        let mut opaque = 0usize;
+        for (x, y, p) in rgba.enumerate_pixels() {
+            let a = p[3];
+            if a == 0 {
+                continue;
+            }
+            opaque += 1;
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+            let w = a as f64 / 255.0;
+            weight += w;
+            cx += x as f64 * w;
+            cy += y as f64 * w;
+        }
+
+        let total = (rgba.width() as usize * rgba.height() as usize).max(1);
+        Self {
+            percent: opaque as f32 * 100.0 / total as f32,
+            bbox: (opaque > 0).then(|| [min_x, min_y, max_x - min_x + 1, max_y - min_y + 1]),
+            centroid: (weight > 0.0).then(|| [(cx / weight) as f32, (cy / weight) as f32]),
+        }
+    }
+}
+
+/// Why a cut-out was flagged as suspect.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssue {
+    /// Almost nothing was kept: likely a blank cut-out.
+    NearlyEmpty,
+    /// Almost nothing was removed: the background was likely not detected.
+    NearlyFull,
+    /// The mask is scattered into many pieces with no dominant subject.
+    Fragmented,
+}
+
+impl QualityIssue {
+    /// Locale key of the issue description.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            QualityIssue::NearlyEmpty => "qa_nearly_empty",
+            QualityIssue::NearlyFull => "qa_nearly_full",
+            QualityIssue::Fragmented => "qa_fragmented",
+        }
+    }
+}
+
+/// Sanity check of a cut-out's mask, run before the SVG generators.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct QualityCheck {
+    pub suspect: bool,
+    pub issues: Vec<QualityIssue>,
+    /// Connected pieces of the mask (8-connectivity, measured at up to 512 px).
+    pub fragments: usize,
+    /// Share of the mask in its largest piece, in percent.
+    pub largest_fragment_percent: f32,
+}
+
+impl QualityCheck {
+    /// Largest edge the mask is downscaled to before counting fragments.
+    const MAX_PX: u32 = 512;
+
+    pub fn measure(img: &DynamicImage, coverage: &Coverage) -> Self {
+        let mut issues = Vec::new();
+        if coverage.percent < config::QA_MIN_COVERAGE {
+            issues.push(QualityIssue::NearlyEmpty);
+        } else if coverage.percent > config::QA_MAX_COVERAGE {
+            issues.push(QualityIssue::NearlyFull);
+        }
+
+        let small = img.resize(Self::MAX_PX, Self::MAX_PX, FilterType::Triangle).to_rgba8();
+        let mask = GrayImage::from_fn(small.width(), small.height(), |x, y| {
+            Luma([if small.get_pixel(x, y)[3] >= 128 { 255 } else { 0 }])
+        });
+        let labels = connected_components(&mask, Connectivity::Eight, Luma([0u8]));
+        let mut sizes: Vec<usize> = Vec::new();
+        for label in labels.pixels().map(|p| p[0] as usize).filter(|&l| l > 0) {
+            if sizes.len() < label {
+                sizes.resize(label, 0);
+            }
+            sizes[label - 1] += 1;
+        }
+        let total: usize = sizes.iter().sum();
+        let largest = sizes.iter().copied().max().unwrap_or(0);
+        let largest_fragment_percent = if total > 0 { largest as f32 * 100.0 / total as f32 } else { 0.0 };
+        if sizes.len() > config::QA_MAX_FRAGMENTS && largest_fragment_percent < config::QA_MIN_LARGEST_FRAGMENT {
+            issues.push(QualityIssue::Fragmented);
+        }
+
+        Self { suspect: !issues.is_empty(), issues, fragments: sizes.len(), largest_fragment_percent }
+    }
+}
+
+/// An input that was skipped or failed, with the reason.
+#[derive(Serialize, Clone, Debug)]
+pub struct FileIssue {
+    pub input: PathBuf,
+    pub reason: String,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct BatchReport {
+    pub model: String,
+    /// Outputs requested for every image.
+    pub generators: Vec<OutputKind>,
+    pub total: usize,
+    pub skipped: usize,
+    pub processed: Vec<ImageReport>,
+    pub failed: Vec<FileIssue>,
+    /// Inputs rejected by the preflight check (unreadable or corrupt headers).
+    pub quarantine: Vec<FileIssue>,
+    /// Near-duplicate inputs left out with `--dedup`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Manifest of the outputs uploaded with `--upload`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uploads: Vec<RemoteFile>,
+    /// Outputs that could not be uploaded.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub upload_failed: Vec<FileIssue>,
+    /// Tool version, models, parameters and platform of the run.
+    pub provenance: Provenance,
+}
+
+const SUMMARY_HEADER: [&str; 6] = ["file", "outputs", "model", "inference_ms", "total_ms", "output_kb"];
+
+impl BatchReport {
+    fn summary_rows(&self) -> Vec<[String; 6]> {
+        self.processed.iter().map(|image| [
+            image.input.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+            image.outputs.len().to_string(),
+            match (&image.model, image.downscale_retry) {
+                (Some(model), Some(res)) => format!("{} @{}px", model, res),
+                (Some(model), None) => model.clone(),
+                (None, _) => "cached".to_string(),
+            },
+            image.inference_ms.to_string(),
+            image.duration_ms.to_string(),
+            image.output_bytes.div_ceil(1024).to_string(),
+        ]).collect()
+    }
+
+    /// Formats the processed images as an aligned table with translated headers; numbers are right-aligned.
+    pub fn summary_table(&self, lang: &LanguageManager) -> String {
+        let rows = self.summary_rows();
+        let header = SUMMARY_HEADER.map(|column| lang.t(&format!("summary_col_{}", column)));
+        let mut widths = header.each_ref().map(|h| h.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let format_row = |cells: [&str; 6]| {
+            let line: Vec<String> = cells.iter().zip(widths).enumerate().map(|(i, (cell, width))| {
+                if i == 0 || i == 2 { format!("{:<width$}", cell) } else { format!("{:>width$}", cell) }
+            }).collect();
+            line.join("  ").trim_end().to_string()
+        };
+
+        let mut table = vec![format_row(header.each_ref().map(String::as_str))];
+        table.push(widths.iter().map(|&w| "-".repeat(w)).collect::<Vec<_>>().join("  "));
+        table.extend(rows.iter().map(|row| format_row(row.each_ref().map(String::as_str))));
+        table.join("\n")
+    }
+
+    /// Writes the summary rows as CSV to `output_dir`, returning its path.
+    pub fn write_summary_csv(&self, output_dir: &Path) -> Result<PathBuf> {
+        let escape = |cell: &str| if cell.contains([',', '"', '\n']) { format!("\"{}\"", cell.replace('"', "\"\"")) } else { cell.to_string() };
+        let mut csv = SUMMARY_HEADER.join(",") + "\n";
+        for row in self.summary_rows() {
+            csv += &row.iter().map(|c| escape(c)).collect::<Vec<_>>().join(",");
+            csv += "\n";
+        }
+        let path = output_dir.join(SUMMARY_CSV_FILE);
+        fs::write(&path, csv)?;
+        Ok(path)
+    }
+
+    /// Writes the report to `output_dir`, returning its path.
+    pub fn write(&self, output_dir: &Path) -> Result<PathBuf> {
+        let path = output_dir.join(REPORT_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,90 @@
+//! Persistent user settings.
+//!
+//! Stored as JSON in the platform config directory (e.g. `~/.config/alphasvg/settings.json`).
+//! Missing or unreadable files fall back to defaults so a corrupt file never blocks startup.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::generators::watermark::WatermarkOptions;
+use crate::gui::backdrop::Backdrop;
+use crate::gui::processing::ProcessingConfig;
+use crate::upload::UploadCredentials;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Language code selected in the GUI.
+    pub language: Option<String>,
+    /// Last job started from the GUI, replayed by `run-last`.
+    pub last_job: Option<ProcessingConfig>,
+    /// Watermark stamped on raster outputs of GUI jobs and CLI batches.
+    pub watermark: Option<WatermarkOptions>,
+    /// Credentials for `--upload`, see [`crate::upload`].
+    pub upload: Option<UploadCredentials>,
+    /// Background drawn behind the previews.
+    pub backdrop: Backdrop,
+    /// Font file used for the GUI text before the bundled and script fonts.
+    pub ui_font: Option<PathBuf>,
+    /// Skips the check for a newer release when the GUI starts.
+    pub disable_update_check: bool,
+    /// Turns off GUI animations even when the desktop does not ask for reduced motion.
+    pub disable_animations: bool,
+    /// Runs jobs at a lower priority on half the cores, see [`crate::priority`].
+    pub background_mode: bool,
+    /// Shows the tray icon, in builds with the `tray` feature.
+    pub tray_icon: bool,
+    /// Opt-in local usage statistics, see [`crate::stats`].
+    pub usage_stats: bool,
+}
+
+/// Writes `contents` to `path` readable and writable by the user only, for
+/// files holding secrets; Windows keeps the default per-user ACL.
+pub(crate) fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path).with_context(|| format!("Failed to write {}", path.display()))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+impl Settings {
+    /// Directory holding the settings and other per-user state.
+    pub fn config_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("alphasvg"))
+    }
+
+    pub fn load() -> Self {
+        Self::config_dir()
+            .and_then(|dir| fs::read_to_string(dir.join(SETTINGS_FILE)).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let dir = Self::config_dir().context("Could not find config directory")?;
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(SETTINGS_FILE);
+        let contents = serde_json::to_string_pretty(self)?;
+        if self.upload.is_some() {
+            // Upload passwords and secret keys are stored in plain text
+            write_private(&path, &contents)
+        } else {
+            fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+        }
+    }
+}
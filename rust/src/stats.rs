@@ -0,0 +1,116 @@
+//! Local usage statistics shown in the GUI "Statistics" window.
+//!
+//! Opt-in with `usage_stats` in the settings. While it is on, every output
+//! folder a CLI batch writes to is remembered in `stats_folders.json` in the
+//! config directory, and GUI jobs are recorded in `stats_jobs.jsonl` next to
+//! it; the statistics are computed from the journals of those folders and the
+//! GUI jobs. GUI jobs stay out of the folder journals, which `--resume` reads
+//! to skip inputs. Nothing is uploaded.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Context, Result};
+
+use crate::journal::{self, JOURNAL_FILE, JournalEntry};
+use crate::settings::Settings;
+
+const FOLDERS_FILE: &str = "stats_folders.json";
+const JOBS_FILE: &str = "stats_jobs.jsonl";
+
+/// Runs of one model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelUsage {
+    pub images: usize,
+    pub total_ms: u64,
+}
+
+impl ModelUsage {
+    pub fn average(&self) -> Duration {
+        Duration::from_millis(self.total_ms / self.images.max(1) as u64)
+    }
+}
+
+/// Totals over the journals of the remembered output folders.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageStats {
+    pub folders: usize,
+    pub images: usize,
+    pub total_ms: u64,
+    /// Size of the outputs still on disk.
+    pub output_bytes: u64,
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl UsageStats {
+    /// Reads the journals of the remembered output folders and the GUI jobs.
+    pub fn load() -> Self {
+        let mut stats = Self::default();
+        let mut seen = BTreeSet::new();
+        for dir in folders() {
+            let Ok(entries) = journal::read_entries(&dir.join(JOURNAL_FILE)) else { continue };
+            for entry in &entries {
+                stats.add(entry);
+            }
+            seen.insert(dir);
+        }
+        let gui_jobs = Settings::config_dir().and_then(|dir| journal::read_entries(&dir.join(JOBS_FILE)).ok()).unwrap_or_default();
+        for entry in &gui_jobs {
+            stats.add(entry);
+            if let Some(dir) = entry.outputs.first().and_then(|p| p.parent()) {
+                seen.insert(fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf()));
+            }
+        }
+        stats.folders = seen.len();
+        stats
+    }
+
+    fn add(&mut self, entry: &JournalEntry) {
+        self.images += 1;
+        self.total_ms += entry.duration_ms;
+        self.output_bytes += entry.outputs.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum::<u64>();
+        let model = self.models.entry(entry.model.clone()).or_default();
+        model.images += 1;
+        model.total_ms += entry.duration_ms;
+    }
+
+    pub fn average(&self) -> Duration {
+        Duration::from_millis(self.total_ms / self.images.max(1) as u64)
+    }
+}
+
+/// Output folders remembered so far.
+fn folders() -> Vec<PathBuf> {
+    Settings::config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(FOLDERS_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Remembers the output folder of a CLI batch for the statistics when they are on.
+pub fn remember_folder(output_dir: &Path) -> Result<()> {
+    if !Settings::load().usage_stats {
+        return Ok(());
+    }
+    let dir = fs::canonicalize(output_dir)?;
+    let mut folders = folders();
+    if !folders.contains(&dir) {
+        folders.push(dir);
+        let config = Settings::config_dir().context("Could not find config directory")?;
+        fs::create_dir_all(&config)?;
+        fs::write(config.join(FOLDERS_FILE), serde_json::to_string_pretty(&folders)?)?;
+    }
+    Ok(())
+}
+
+/// Records a finished GUI job when the statistics are on.
+pub fn record_job(input: &Path, model: &str, elapsed: Duration, outputs: Vec<PathBuf>) -> Result<()> {
+    if !Settings::load().usage_stats {
+        return Ok(());
+    }
+    let entry = JournalEntry::new(input, model, elapsed.as_millis() as u64, outputs)?;
+    let config = Settings::config_dir().context("Could not find config directory")?;
+    fs::create_dir_all(&config)?;
+    journal::append_entry(&config.join(JOBS_FILE), &entry)
+}
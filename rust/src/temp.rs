@@ -0,0 +1,125 @@
+//! Naming and cleanup of temporary files.
+//!
+//! Intermediate files (potrace bitmaps and traces, partial SVGs) are written
+//! next to the output they belong to, named `<output name>.temp.<random>.<ext>`
+//! with six random letters or digits, so an interrupted run leaves them where
+//! they are easy to find and the batch walker never mistakes them for inputs.
+//! The `<output name>.temp.bmp`/`.svg` and `.vtrace_temp.` files of the Python
+//! version are recognized as well. Nothing else is taken for a temporary file,
+//! so a user's `logo.temp.png` is left alone.
+//!
+//! Each file is removed when its handle is dropped; what a killed process
+//! leaves behind is removed, once stale, when the next batch starts, when the
+//! GUI starts (in the last job's output folder) or by `alphasvg clean`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use anyhow::Result;
+use tempfile::{Builder, NamedTempFile, TempPath};
+use walkdir::WalkDir;
+
+use crate::config;
+use crate::error::AlphasvgError;
+
+/// Marker in the name of every temporary file.
+pub const TEMP_MARKER: &str = ".temp.";
+/// Marker of the temporary files of the Python version's vtrace tracer.
+pub const VTRACE_TEMP_MARKER: &str = ".vtrace_temp.";
+/// Length of the random part of a temporary file name.
+const RANDOM_LEN: usize = 6;
+
+/// Whether a file name is that of a temporary file.
+pub fn is_temp(name: &str) -> bool {
+    let alphanumeric = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric());
+    // `<output>.<ext>.temp.bmp` of the Python version, and its vtrace equivalent
+    let python = |output: &str, ext: &str| output.contains('.') && matches!(ext, "bmp" | "svg");
+    if let Some((output, rest)) = name.rsplit_once(TEMP_MARKER) {
+        let ours = rest.split_once('.')
+            .is_some_and(|(random, ext)| random.len() == RANDOM_LEN && alphanumeric(random) && alphanumeric(ext));
+        return !output.is_empty() && (ours || python(output, rest));
+    }
+    name.rsplit_once(VTRACE_TEMP_MARKER).is_some_and(|(output, ext)| python(output, ext))
+}
+
+/// What [`clean`] removes.
+pub struct CleanOptions {
+    /// Only files last modified longer ago than this; `None` removes them all.
+    pub older_than: Option<Duration>,
+    /// Look in the subfolders too.
+    pub recursive: bool,
+    /// List the files without removing them.
+    pub dry_run: bool,
+}
+
+impl Default for CleanOptions {
+    fn default() -> Self {
+        Self {
+            older_than: Some(Duration::from_secs(config::TEMP_STALE_SECS)),
+            recursive: false,
+            dry_run: false,
+        }
+    }
+}
+
+/// Creates an empty temporary file next to `output`, with `extension`, and
+/// returns its path. The file is removed when the path is dropped; it is closed,
+/// so external tools such as potrace can overwrite it.
+pub fn path_beside(output: &Path, extension: &str) -> Result<TempPath> {
+    Ok(file_beside(output, extension)?.into_temp_path())
+}
+
+/// Creates a temporary file next to `output`, with `extension`, open for writing.
+/// It is removed when dropped unless persisted.
+pub fn file_beside(output: &Path, extension: &str) -> Result<NamedTempFile> {
+    let dir = output.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = output.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let file = Builder::new()
+        .prefix(&format!("{}{}", name, TEMP_MARKER))
+        .rand_bytes(RANDOM_LEN)
+        .suffix(&format!(".{}", extension))
+        .tempfile_in(dir)
+        .map_err(AlphasvgError::io(dir))?;
+    Ok(file)
+}
+
+/// Removes the temporary files in `dir` as `opts` say. Returns the paths
+/// removed, or those that would be with [`CleanOptions::dry_run`].
+pub fn clean(dir: &Path, opts: &CleanOptions) -> Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    let walker = WalkDir::new(dir).max_depth(if opts.recursive { usize::MAX } else { 1 });
+    for entry in walker.into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() || !is_temp(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        if let Some(age) = opts.older_than {
+            let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+            if modified.and_then(|m| now.duration_since(m).ok()).is_none_or(|elapsed| elapsed < age) {
+                continue;
+            }
+        }
+        if !opts.dry_run {
+            fs::remove_file(entry.path()).map_err(AlphasvgError::io(entry.path()))?;
+        }
+        removed.push(entry.path().to_path_buf());
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_generated_names_are_temporary() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = file_beside(&dir.path().join("photo_lineart.svg"), "bmp").unwrap();
+        assert!(is_temp(&file.path().file_name().unwrap().to_string_lossy()));
+        assert!(is_temp("photo_lineart.svg.temp.bmp"));
+        assert!(is_temp("photo_color.svg.vtrace_temp.svg"));
+        assert!(!is_temp("logo.temp.png"));
+        assert!(!is_temp("logo.temp.final.png"));
+        assert!(!is_temp(".temp.abc123.png"));
+    }
+}
@@ -0,0 +1,177 @@
+//! Self-update from GitHub releases.
+//!
+//! The release workflow publishes each executable as
+//! `transparente_rust-{os}-{arch}[.exe]`, with a `.sha256` checksum and a
+//! `.minisig` minisign signature next to it. The checksum only catches broken
+//! downloads; the signature is what authenticates the executable, against the
+//! public key the workflow builds in through `ALPHASVG_UPDATE_PUBKEY`. Builds
+//! without that key, such as local ones, refuse to update themselves.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use minisign_verify::{PublicKey, Signature};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::generators::LogOutput;
+use crate::lang::LanguageManager;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/danloi2/alphasvg/releases/latest";
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Base64 minisign public key the release executables are signed with.
+const UPDATE_PUBLIC_KEY: Option<&str> = option_env!("ALPHASVG_UPDATE_PUBKEY");
+
+#[derive(Deserialize, Debug)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// Version without the leading `v` of the tag.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+fn client() -> Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
+        .user_agent(concat!("alphasvg/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+/// Fetches the latest published release.
+pub fn latest_release() -> Result<Release> {
+    let response = client()?.get(RELEASES_URL).send()?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to query releases: HTTP {}", response.status()));
+    }
+    Ok(serde_json::from_str(&response.text()?)?)
+}
+
+/// Returns the latest release if it is newer than the running version.
+pub fn check_for_update() -> Result<Option<Release>> {
+    let release = latest_release()?;
+    Ok(is_newer(release.version(), CURRENT_VERSION).then_some(release))
+}
+
+/// Compares dotted numeric versions (`1.2.10` > `1.2.9`); pre-release suffixes are ignored.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parts(v: &str) -> Vec<u64> {
+        v.split(['-', '+']).next().unwrap_or("")
+            .split('.')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    }
+    let (a, b) = (parts(candidate), parts(current));
+    let len = a.len().max(b.len());
+    for i in 0..len {
+        let (x, y) = (a.get(i).copied().unwrap_or(0), b.get(i).copied().unwrap_or(0));
+        if x != y {
+            return x > y;
+        }
+    }
+    false
+}
+
+/// Name of the release asset for the running platform.
+fn platform_asset_name() -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "macos",
+        "windows" => "windows",
+        _ => "linux",
+    };
+    let ext = if cfg!(windows) { ".exe" } else { "" };
+    format!("transparente_rust-{}-{}{}", os, std::env::consts::ARCH, ext)
+}
+
+/// Downloads the latest release for this platform, verifies its checksum and
+/// signature and replaces the running executable. Returns `false` when already
+/// up to date.
+pub fn self_update(lang: &LanguageManager, logger: &LogOutput) -> Result<bool> {
+    let public_key = UPDATE_PUBLIC_KEY.filter(|key| !key.is_empty())
+        .context("This build has no update signing key; download new versions from the releases page")?;
+    logger.send(lang.t_args("update_checking", &[CURRENT_VERSION]));
+    let Some(release) = check_for_update()? else {
+        logger.send(lang.t("update_up_to_date"));
+        return Ok(false);
+    };
+
+    let asset_name = platform_asset_name();
+    let asset = release.asset(&asset_name)
+        .with_context(|| format!("Release {} has no asset {}", release.tag_name, asset_name))?;
+    let checksum_asset = release.asset(&format!("{}.sha256", asset_name))
+        .with_context(|| format!("Release {} has no checksum for {}", release.tag_name, asset_name))?;
+    let signature_asset = release.asset(&format!("{}.minisig", asset_name))
+        .with_context(|| format!("Release {} has no signature for {}", release.tag_name, asset_name))?;
+
+    logger.send(lang.t_args("update_downloading", &[&asset_name, &release.tag_name]));
+    let client = client()?;
+    let mut bytes = Vec::new();
+    client.get(&asset.browser_download_url).send()?.error_for_status()?.read_to_end(&mut bytes)?;
+    let expected = client.get(&checksum_asset.browser_download_url).send()?.error_for_status()?.text()?;
+    let signature = client.get(&signature_asset.browser_download_url).send()?.error_for_status()?.text()?;
+
+    verify_checksum(&bytes, &expected)?;
+    verify_signature(&bytes, &signature, public_key)?;
+    logger.verbose(lang.t("update_verified"));
+
+    let current_exe = std::env::current_exe()?;
+    replace_executable(&current_exe, &bytes)?;
+    logger.send(lang.t_args("update_done", &[&release.tag_name]));
+    Ok(true)
+}
+
+/// Checks `bytes` against a `sha256sum`-style line (`<hex>  <file>`).
+fn verify_checksum(bytes: &[u8], expected: &str) -> Result<()> {
+    let expected = expected.split_whitespace().next().unwrap_or("").to_lowercase();
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if expected != actual {
+        return Err(anyhow!("Checksum mismatch: expected {}, got {}", expected, actual));
+    }
+    Ok(())
+}
+
+/// Checks the minisign `signature` of `bytes` against `public_key`.
+fn verify_signature(bytes: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let key = PublicKey::from_base64(public_key.trim()).context("Invalid update signing key")?;
+    let signature = Signature::decode(signature).context("Invalid update signature")?;
+    key.verify(bytes, &signature, false).context("The update is not signed by the release key")?;
+    Ok(())
+}
+
+/// Writes the new binary next to the current one and swaps them.
+/// The previous executable is kept as `.old` until the next update.
+fn replace_executable(current_exe: &Path, bytes: &[u8]) -> Result<()> {
+    let new_path = current_exe.with_extension("new");
+    let old_path = current_exe.with_extension("old");
+
+    fs::write(&new_path, bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    let _ = fs::remove_file(&old_path);
+    fs::rename(current_exe, &old_path).context("Failed to move the current executable aside")?;
+    if let Err(e) = fs::rename(&new_path, current_exe) {
+        let _ = fs::rename(&old_path, current_exe);
+        return Err(anyhow!("Failed to install the new executable: {}", e));
+    }
+    Ok(())
+}
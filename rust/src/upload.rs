@@ -0,0 +1,327 @@
+//! Remote output destinations for CLI batches.
+//!
+//! With `--upload`, the outputs of every processed image are uploaded after the
+//! batch to an S3 bucket (`s3://bucket/prefix`, signed with AWS Signature V4)
+//! or a WebDAV collection (`https://host/path/`), keeping their paths relative
+//! to the output directory. The remote URLs are listed in the batch report.
+//!
+//! Credentials come from the `upload` section of the settings file, overridden
+//! by the usual environment variables: `AWS_ACCESS_KEY_ID`,
+//! `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`, `AWS_REGION` and
+//! `ALPHASVG_S3_ENDPOINT` (for S3-compatible services, path-style), or
+//! `ALPHASVG_WEBDAV_USER` and `ALPHASVG_WEBDAV_PASSWORD`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::Url;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::history;
+use crate::settings::Settings;
+
+/// Parallel uploads when `--upload-jobs` is not given.
+pub const DEFAULT_JOBS: usize = 4;
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Where the outputs are uploaded.
+#[derive(Clone, Debug)]
+pub enum Destination {
+    S3 { bucket: String, prefix: String },
+    WebDav(Url),
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    /// Parses `s3://bucket/prefix` or an `http(s)://` WebDAV collection URL.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = value.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(format!("missing bucket name: {}", value));
+            }
+            return Ok(Destination::S3 { bucket: bucket.to_string(), prefix: prefix.trim_matches('/').to_string() });
+        }
+        let url = Url::parse(value).map_err(|e| format!("invalid upload URL {}: {}", value, e))?;
+        match url.scheme() {
+            "http" | "https" => Ok(Destination::WebDav(url)),
+            scheme => Err(format!("unsupported upload scheme {}: use s3://, http:// or https://", scheme)),
+        }
+    }
+}
+
+/// Upload credentials stored in the settings; environment variables take precedence.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct UploadCredentials {
+    pub s3_access_key_id: Option<String>,
+    pub s3_secret_access_key: Option<String>,
+    pub s3_session_token: Option<String>,
+    pub s3_region: Option<String>,
+    /// Endpoint of an S3-compatible service, e.g. `https://minio.local:9000`.
+    pub s3_endpoint: Option<String>,
+    pub webdav_user: Option<String>,
+    pub webdav_password: Option<String>,
+}
+
+impl UploadCredentials {
+    /// Credentials from the settings, overridden by the environment.
+    pub fn resolve() -> Self {
+        let mut creds = Settings::load().upload.unwrap_or_default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        for (field, names) in [
+            (&mut creds.s3_access_key_id, &["AWS_ACCESS_KEY_ID"][..]),
+            (&mut creds.s3_secret_access_key, &["AWS_SECRET_ACCESS_KEY"]),
+            (&mut creds.s3_session_token, &["AWS_SESSION_TOKEN"]),
+            (&mut creds.s3_region, &["AWS_REGION", "AWS_DEFAULT_REGION"]),
+            (&mut creds.s3_endpoint, &["ALPHASVG_S3_ENDPOINT"]),
+            (&mut creds.webdav_user, &["ALPHASVG_WEBDAV_USER"]),
+            (&mut creds.webdav_password, &["ALPHASVG_WEBDAV_PASSWORD"]),
+        ] {
+            if let Some(value) = names.iter().find_map(|name| env(name)) {
+                *field = Some(value);
+            }
+        }
+        creds
+    }
+}
+
+/// An uploaded output, as listed in the report manifest.
+#[derive(Serialize, Clone, Debug)]
+pub struct RemoteFile {
+    pub local: PathBuf,
+    pub url: String,
+}
+
+/// Uploads `files`, which must lie inside `root`, with `jobs` parallel uploads.
+/// Returns the uploaded files and the failures with their reasons.
+pub fn upload_all(files: &[PathBuf], root: &Path, destination: &Destination, jobs: usize) -> (Vec<RemoteFile>, Vec<(PathBuf, String)>) {
+    let creds = UploadCredentials::resolve();
+    let mut failed = Vec::new();
+    let mut pending = Vec::new();
+    for file in files {
+        match relative_key(file, root) {
+            Ok(key) => pending.push((file.clone(), key)),
+            Err(e) => failed.push((file.clone(), format!("{:#}", e))),
+        }
+    }
+
+    // WebDAV needs the collections to exist; create them once, before the parallel uploads.
+    // Blocking clients are only used on worker threads, outside the async runtime of `main`.
+    if let Destination::WebDav(base) = destination {
+        let dirs: BTreeSet<String> = pending.iter()
+            .flat_map(|(_, key)| key.match_indices('/').map(|(i, _)| key[..i].to_string()).collect::<Vec<_>>())
+            .collect();
+        let created = std::thread::scope(|scope| {
+            scope.spawn(|| client().and_then(|client| dirs.iter().try_for_each(|dir| webdav_mkcol(&client, base, dir, &creds))))
+                .join()
+                .expect("upload thread panicked")
+        });
+        if let Err(e) = created {
+            failed.extend(pending.drain(..).map(|(file, _)| (file, format!("{:#}", e))));
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(pending.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, pending.len().max(1)) {
+            scope.spawn(|| {
+                let client = client();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((file, key)) = pending.get(i) else { break };
+                    let result = match &client {
+                        Ok(client) => upload_file(client, file, key, destination, &creds),
+                        Err(e) => Err(anyhow!("{:#}", e)),
+                    };
+                    results.lock().expect("upload results lock").push((i, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().expect("upload results lock");
+    results.sort_by_key(|(i, _)| *i);
+    let mut uploaded = Vec::new();
+    for (i, result) in results {
+        let file = pending[i].0.clone();
+        match result {
+            Ok(url) => uploaded.push(RemoteFile { local: file, url }),
+            Err(e) => failed.push((file, format!("{:#}", e))),
+        }
+    }
+    (uploaded, failed)
+}
+
+fn client() -> Result<Client> {
+    Ok(Client::builder()
+        .user_agent(concat!("alphasvg/", env!("CARGO_PKG_VERSION")))
+        .build()?)
+}
+
+/// Path of `file` below `root` with `/` separators.
+fn relative_key(file: &Path, root: &Path) -> Result<String> {
+    let relative = file.strip_prefix(root).with_context(|| format!("{} is outside the output directory", file.display()))?;
+    let parts: Vec<String> = relative.components()
+        .map(|c| match c {
+            Component::Normal(part) => Ok(part.to_string_lossy().into_owned()),
+            _ => Err(anyhow!("Unsupported path {}", relative.display())),
+        })
+        .collect::<Result<_>>()?;
+    Ok(parts.join("/"))
+}
+
+/// Uploads one file and returns its remote URL.
+fn upload_file(client: &Client, file: &Path, key: &str, destination: &Destination, creds: &UploadCredentials) -> Result<String> {
+    let body = fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    match destination {
+        Destination::S3 { bucket, prefix } => {
+            let key = if prefix.is_empty() { key.to_string() } else { format!("{}/{}", prefix, key) };
+            s3_put(client, bucket, &key, body, content_type(file), creds)
+        }
+        Destination::WebDav(base) => {
+            let url = webdav_url(base, key);
+            let mut request = client.put(url.clone()).header("Content-Type", content_type(file)).body(body);
+            if let Some(user) = &creds.webdav_user {
+                request = request.basic_auth(user, creds.webdav_password.as_ref());
+            }
+            check(request.send()?)?;
+            Ok(url.to_string())
+        }
+    }
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("json") => "application/json",
+        Some("plt" | "hpgl") => "application/vnd.hp-hpgl",
+        _ => "application/octet-stream",
+    }
+}
+
+fn check(response: reqwest::blocking::Response) -> Result<()> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let detail = response.text().unwrap_or_default();
+    bail!("HTTP {}: {}", status, detail.trim().chars().take(200).collect::<String>())
+}
+
+fn webdav_url(base: &Url, key: &str) -> Url {
+    let mut url = base.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().extend(key.split('/'));
+    }
+    url
+}
+
+/// Creates the collection `dir` below `base`; an existing one is fine.
+fn webdav_mkcol(client: &Client, base: &Url, dir: &str, creds: &UploadCredentials) -> Result<()> {
+    let mut url = webdav_url(base, dir);
+    url.set_path(&format!("{}/", url.path()));
+    let method = reqwest::Method::from_bytes(b"MKCOL").expect("valid method");
+    let mut request = client.request(method, url);
+    if let Some(user) = &creds.webdav_user {
+        request = request.basic_auth(user, creds.webdav_password.as_ref());
+    }
+    let response = request.send()?;
+    // 405 Method Not Allowed: the collection already exists
+    if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        return Ok(());
+    }
+    check(response)
+}
+
+/// Uploads `body` to `bucket/key` with a Signature V4 signed PUT.
+fn s3_put(client: &Client, bucket: &str, key: &str, body: Vec<u8>, content_type: &str, creds: &UploadCredentials) -> Result<String> {
+    let access_key = creds.s3_access_key_id.as_deref().context("Missing S3 credentials: set AWS_ACCESS_KEY_ID")?;
+    let secret = creds.s3_secret_access_key.as_deref().context("Missing S3 credentials: set AWS_SECRET_ACCESS_KEY")?;
+    let region = creds.s3_region.as_deref().unwrap_or(DEFAULT_REGION);
+
+    let encoded_key: Vec<String> = key.split('/').map(uri_encode).collect();
+    let url = match &creds.s3_endpoint {
+        Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, encoded_key.join("/")),
+        None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, encoded_key.join("/")),
+    };
+    let url = Url::parse(&url)?;
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = history::civil_date(now);
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let secs = now % 86_400;
+    let amz_date = format!("{}T{:02}{:02}{:02}Z", date, secs / 3600, secs / 60 % 60, secs % 60);
+    let payload_hash = format!("{:x}", Sha256::digest(&body));
+
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &creds.s3_session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!("PUT\n{}\n\n{}\n{}\n{}", url.path(), canonical_headers, signed_headers, payload_hash);
+
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{:x}", amz_date, scope, Sha256::digest(canonical_request.as_bytes()));
+    let mut signing_key = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    for part in [region, "s3", "aws4_request"] {
+        signing_key = hmac_sha256(&signing_key, part.as_bytes());
+    }
+    let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, scope, signed_headers, signature
+    );
+
+    let mut request = client.put(url.clone())
+        .header("Authorization", authorization)
+        .header("Content-Type", content_type)
+        .body(body);
+    for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+        request = request.header(name, value);
+    }
+    check(request.send()?)?;
+    Ok(url.to_string())
+}
+
+/// Percent-encodes everything but the unreserved characters, as Signature V4 requires.
+fn uri_encode(segment: &str) -> String {
+    segment.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
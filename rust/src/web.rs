@@ -0,0 +1,293 @@
+//! Browser build: removes the background of one picked image on a WebGL canvas.
+//!
+//! Only the small models (`u2netp`, `silueta`) are offered. tract runs them
+//! in place of ONNX Runtime, and their weights are fetched from `models/`
+//! next to the page (see `web/index.html`). Vectorization, batches, settings
+//! and the job journal need the native build.
+
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+
+use anyhow::{Context, Result, anyhow};
+use eframe::egui;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, RgbaImage, imageops::{self, FilterType}};
+use tract_onnx::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::generators::ModelType;
+use crate::generators::models::get_model_config;
+use crate::generators::tensor::{fill_planar_tensor, transform_output};
+use crate::lang::LanguageManager;
+
+/// Models small enough to download and run in a browser tab.
+const WEB_MODELS: [ModelType; 2] = [ModelType::U2NetP, ModelType::Silueta];
+/// Id of the canvas eframe draws on.
+const CANVAS_ID: &str = "alphasvg_canvas";
+/// Directory, relative to the page, holding the `.onnx` weights.
+const MODELS_URL: &str = "models";
+
+type Plan = TypedRunnableModel<TypedModel>;
+
+thread_local! {
+    // Optimized models, kept so a second image skips the download
+    static PLANS: RefCell<Vec<(ModelType, Rc<Plan>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Entry point called by the `wasm-bindgen` glue once the module is loaded.
+#[wasm_bindgen(start)]
+pub fn start() {
+    wasm_bindgen_futures::spawn_local(async {
+        let Some(canvas) = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(CANVAS_ID))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+        else {
+            web_sys::console::error_1(&format!("No <canvas id=\"{}\"> in the page", CANVAS_ID).into());
+            return;
+        };
+
+        let started = eframe::WebRunner::new()
+            .start(canvas, eframe::WebOptions::default(), Box::new(|cc| Ok(Box::new(WebApp::new(cc)))))
+            .await;
+        if let Err(err) = started {
+            web_sys::console::error_1(&err);
+        }
+    });
+}
+
+/// A finished cutout and its preview.
+struct Cutout {
+    file_name: String,
+    image: RgbaImage,
+    texture: egui::TextureHandle,
+}
+
+/// What a background task hands back to the next frame.
+enum Outcome {
+    Cutout { file_name: String, image: RgbaImage },
+    Cancelled,
+    Failed(String),
+}
+
+struct WebApp {
+    lang: LanguageManager,
+    model: ModelType,
+    busy: bool,
+    status: String,
+    cutout: Option<Cutout>,
+    pending: Rc<RefCell<Option<Outcome>>>,
+}
+
+impl WebApp {
+    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        let mut lang = LanguageManager::default();
+        // Start in the browser language when a locale for it is embedded
+        if let Some(code) = web_sys::window().and_then(|window| window.navigator().language())
+            && lang.supports(&code)
+        {
+            lang.load_language(&code);
+        }
+        Self {
+            lang,
+            model: WEB_MODELS[0],
+            busy: false,
+            status: String::new(),
+            cutout: None,
+            pending: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Asks for an image and cuts it out in the background.
+    fn open(&mut self, ctx: &egui::Context) {
+        self.busy = true;
+        self.status = self.lang.t("web_working");
+        let model = self.model;
+        let pending = Rc::clone(&self.pending);
+        let ctx = ctx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let outcome = match cut_out_picked(model).await {
+                Ok(Some((file_name, image))) => Outcome::Cutout { file_name, image },
+                Ok(None) => Outcome::Cancelled,
+                Err(err) => Outcome::Failed(format!("{:#}", err)),
+            };
+            *pending.borrow_mut() = Some(outcome);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Hands the cutout to the browser as a download.
+    fn save(&self) {
+        let Some(cutout) = &self.cutout else { return };
+        let mut png = Vec::new();
+        if let Err(err) = cutout.image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png) {
+            web_sys::console::error_1(&err.to_string().into());
+            return;
+        }
+        let dialog = rfd::AsyncFileDialog::new().set_file_name(&cutout.file_name);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(handle) = dialog.save_file().await
+                && let Err(err) = handle.write(&png).await
+            {
+                web_sys::console::error_1(&err.to_string().into());
+            }
+        });
+    }
+
+    fn take_outcome(&mut self, ctx: &egui::Context) {
+        let Some(outcome) = self.pending.borrow_mut().take() else { return };
+        self.busy = false;
+        match outcome {
+            Outcome::Cutout { file_name, image } => {
+                let size = [image.width() as usize, image.height() as usize];
+                let texture = ctx.load_texture("web_cutout", egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()), egui::TextureOptions::LINEAR);
+                self.status = self.lang.t_args("web_done", &[&file_name]);
+                self.cutout = Some(Cutout { file_name, image, texture });
+            }
+            Outcome::Cancelled => self.status.clear(),
+            Outcome::Failed(err) => self.status = self.lang.t_args("web_failed", &[err]),
+        }
+    }
+}
+
+impl eframe::App for WebApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.take_outcome(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(self.lang.t("web_title"));
+            ui.label(self.lang.t("web_limits"));
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label(self.lang.t("menu_lang"));
+                let current = self.lang.current_lang();
+                let languages = self.lang.available_languages();
+                let selected = languages.iter().find(|(code, _)| *code == current).map_or(current.clone(), |(_, name)| name.clone());
+                egui::ComboBox::from_id_salt("web_language")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (code, name) in &languages {
+                            if ui.selectable_label(*code == current, name).clicked() {
+                                self.lang.load_language(code);
+                            }
+                        }
+                    });
+
+                ui.label(self.lang.t("web_model"));
+                egui::ComboBox::from_id_salt("web_model")
+                    .selected_text(self.model.name())
+                    .show_ui(ui, |ui| {
+                        for model in WEB_MODELS {
+                            ui.selectable_value(&mut self.model, model, model.name());
+                        }
+                    });
+            });
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!self.busy, egui::Button::new(self.lang.t("web_open"))).clicked() {
+                    self.open(ui.ctx());
+                }
+                if ui.add_enabled(!self.busy && self.cutout.is_some(), egui::Button::new(self.lang.t("web_save"))).clicked() {
+                    self.save();
+                }
+                if self.busy {
+                    ui.spinner();
+                }
+                ui.label(&self.status);
+            });
+
+            if let Some(cutout) = &self.cutout {
+                ui.add(egui::Image::new(&cutout.texture).shrink_to_fit());
+            }
+        });
+    }
+}
+
+/// Picks an image and removes its background with `model`; `None` when the picker is cancelled.
+async fn cut_out_picked(model: ModelType) -> Result<Option<(String, RgbaImage)>> {
+    let Some(file) = rfd::AsyncFileDialog::new()
+        .add_filter("Images", &["png", "jpg", "jpeg", "webp", "bmp", "gif", "tiff"])
+        .pick_file()
+        .await
+    else {
+        return Ok(None);
+    };
+    let img = image::load_from_memory(&file.read().await).context("Unsupported image")?;
+    let plan = load_plan(model).await?;
+    let mask = infer_mask(&img, model, &plan)?;
+
+    let mut rgba = img.to_rgba8();
+    for (px, m) in rgba.pixels_mut().zip(mask.pixels()) {
+        px[3] = (px[3] as u16 * m[0] as u16 / 255) as u8;
+    }
+
+    let name = file.file_name();
+    let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+    Ok(Some((format!("{}_alpha.png", stem), rgba)))
+}
+
+/// Returns the optimized model, fetching its weights on first use.
+async fn load_plan(model: ModelType) -> Result<Rc<Plan>> {
+    if let Some(plan) = PLANS.with_borrow(|plans| plans.iter().find(|(m, _)| *m == model).map(|(_, plan)| Rc::clone(plan))) {
+        return Ok(plan);
+    }
+
+    let config = get_model_config(model);
+    let bytes = fetch(&format!("{}/{}", MODELS_URL, config.filename)).await?;
+    let res = config.resolution as usize;
+    let plan = tract_onnx::onnx()
+        .model_for_read(&mut Cursor::new(bytes))?
+        .with_input_fact(0, f32::fact([1, 3, res, res]).into())?
+        .into_optimized()?
+        .into_runnable()?;
+
+    let plan = Rc::new(plan);
+    PLANS.with_borrow_mut(|plans| plans.push((model, Rc::clone(&plan))));
+    Ok(plan)
+}
+
+/// Downloads `url` relative to the page.
+async fn fetch(url: &str) -> Result<Vec<u8>> {
+    let window = web_sys::window().context("No browser window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|err| anyhow!("Failed to fetch {}: {:?}", url, err))?
+        .dyn_into()
+        .map_err(|_| anyhow!("Failed to fetch {}", url))?;
+    if !response.ok() {
+        return Err(anyhow!("Failed to fetch {}: HTTP {}", url, response.status()));
+    }
+    let buffer = response.array_buffer().map_err(|err| anyhow!("Failed to read {}: {:?}", url, err))?;
+    let buffer = JsFuture::from(buffer).await.map_err(|err| anyhow!("Failed to read {}: {:?}", url, err))?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Runs `plan` on `img` and returns the mask at the image size, like [`crate::generators::ai`].
+fn infer_mask(img: &DynamicImage, model: ModelType, plan: &Plan) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>> {
+    let config = get_model_config(model);
+    let res = config.resolution;
+    let (width, height) = img.dimensions();
+
+    let mut input = Vec::new();
+    fill_planar_tensor(&img.resize_exact(res, res, FilterType::Lanczos3), &config.normalization, &mut input);
+    let input = Tensor::from_shape(&[1, 3, res as usize, res as usize], &input)?;
+    let outputs = plan.run(tvec!(input.into()))?;
+
+    let output = outputs[0].to_array_view::<f32>()?;
+    let raw = output.as_slice().context("Model output is not contiguous")?;
+    let plane = (res * res) as usize;
+    // Outputs are [1, C, H, W] or [1, H, W]; anything else is treated as one channel
+    let channels = if output.ndim() == 4 { output.shape()[1].max(1) } else { 1 };
+    if raw.len() < plane * channels {
+        return Err(anyhow!("Model output is smaller than the expected mask"));
+    }
+
+    let mut mask = Vec::new();
+    transform_output(raw, channels, plane, &config.output, &mut mask);
+    let mask = ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(res, res, mask)
+        .context("Model output is smaller than the expected mask")?;
+    Ok(imageops::resize(&mask, width, height, FilterType::Lanczos3))
+}